@@ -0,0 +1,1428 @@
+use crate::resolver::GENERATED_LABEL_PREFIX;
+use crate::tacky;
+use crate::type_checker::{StaticInit, SymbolTable};
+use crate::types::{Constant, FunctionType, Type};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reg {
+    AX,
+    CX,
+    DX,
+    DI,
+    SI,
+    R8,
+    R9,
+    R10,
+    R11,
+    XMM0,
+    XMM1,
+    XMM2,
+    XMM3,
+    XMM4,
+    XMM5,
+    XMM6,
+    XMM7,
+    // Scratch registers, 15 for fixing up operands and 14 for the longer sequences we generate ourselves
+    XMM14,
+    XMM15,
+}
+
+// How wide an operand is, which decides the suffix on the instruction
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsmType {
+    Byte,
+    Word,
+    Longword,
+    Quadword,
+    Float,
+    Double,
+}
+
+// The first six integer arguments go in registers, in this order
+const ARGUMENT_REGISTERS: [Reg; 6] = [Reg::DI, Reg::SI, Reg::DX, Reg::CX, Reg::R8, Reg::R9];
+// And the first eight floats and doubles go in these
+const DOUBLE_ARGUMENT_REGISTERS: [Reg; 8] = [
+    Reg::XMM0, Reg::XMM1, Reg::XMM2, Reg::XMM3, Reg::XMM4, Reg::XMM5, Reg::XMM6, Reg::XMM7,
+];
+// Where a function that returns a structure in memory keeps the address its caller gave it
+// Nothing from the source can have this name, return is a keyword
+const RETURN_POINTER: &str = "return.pointer";
+// Where a function with a `...` keeps every argument register, for va_arg to find the ones it wasn't given names for.
+// The general purpose registers come first, then the xmm ones sixteen bytes apart
+const REGISTER_SAVE_AREA: &str = "register.save_area";
+const SAVE_AREA_SIZE: i64 = 176;
+const SAVE_AREA_XMM_START: i64 = 48;
+// 2^63, the first value that doesn't fit in a long
+const LONG_LIMIT: f64 = 9223372036854775808.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Imm(i64),
+    Register(Reg),
+    // Every TACKY variable starts out as a pseudo register until it's given a spot on the stack
+    Pseudo(String),
+    // A spot inside a variable that's too big for a register, offset bytes from its start
+    PseudoMem(String, i64),
+    Stack(i64),
+    // Variables with static storage, addressed relative to %rip, offset bytes into them
+    Data(String, i64),
+    // The global offset table entry holding the address of a function defined somewhere else
+    Got(String),
+    // Whatever is at the address in the register plus the offset
+    Memory(Reg, i64),
+    // base + index * scale, scale being 1, 2, 4 or 8
+    Indexed(Reg, Reg, i64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CondCode {
+    E,
+    NE,
+    G,
+    GE,
+    L,
+    LE,
+    // Unsigned comparisons look at the carry flag instead of the sign flag
+    A,
+    AE,
+    B,
+    BE,
+    // comisd sets the parity flag when either side is NaN
+    P,
+    NP,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsmUnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsmBinaryOp {
+    Add,
+    Sub,
+    Mult,
+    And,
+    Or,
+    Xor,
+    Sal,
+    Sar,
+    Shr,
+    // Integer division goes through idiv and div, floats and doubles have a normal two operand instruction
+    DivDouble,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Mov(AsmType, Operand, Operand),
+    // Widening moves carry the type they're coming from and the type they're going to
+    Movsx(AsmType, AsmType, Operand, Operand),
+    MovZeroExtend(AsmType, AsmType, Operand, Operand),
+    // Signed conversions from the integer type to the floating type and back, in the order the mnemonic names them
+    Cvtsi2fp(AsmType, AsmType, Operand, Operand),
+    Cvttfp2si(AsmType, AsmType, Operand, Operand),
+    // Between float and double, cvtss2sd and cvtsd2ss
+    Cvtfp2fp(AsmType, AsmType, Operand, Operand),
+    // Loads the address of the source rather than what's stored there, the destination has to be a register
+    Lea(Operand, Operand),
+    Unary(AsmUnaryOp, AsmType, Operand),
+    Binary(AsmBinaryOp, AsmType, Operand, Operand),
+    Cmp(AsmType, Operand, Operand),
+    Idiv(AsmType, Operand),
+    Div(AsmType, Operand),
+    // Sign extends %eax into %edx, or %rax into %rdx for quadwords
+    Cdq(AsmType),
+    Jmp(String),
+    JmpCC(CondCode, String),
+    SetCC(CondCode, Operand),
+    Label(String),
+    // Indirect jump through a table in .rodata, the index has to be in %eax
+    JmpTable(String, Vec<String>),
+    AllocateStack(i64),
+    DeallocateStack(i64),
+    Push(Operand),
+    // Functions that aren't defined in this file have to go through the PLT
+    Call { name: String, plt: bool },
+    // Calls the function at the address in the operand
+    CallIndirect(Operand),
+    Ret,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub global: bool,
+    pub instructions: Vec<Instruction>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticVariable {
+    pub name: String,
+    pub global: bool,
+    pub alignment: usize,
+    pub init: Vec<StaticInit>,
+}
+
+// A read only value the code refers to, either a string literal or the bits of a float or a double
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticConstant {
+    pub name: String,
+    pub alignment: usize,
+    pub init: Vec<StaticInit>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub functions: Vec<Function>,
+    pub static_variables: Vec<StaticVariable>,
+    pub static_constants: Vec<StaticConstant>,
+}
+
+pub fn generate(program: &tacky::Program, symbols: &SymbolTable) -> Program {
+    let mut static_constants: Vec<StaticConstant> = program.static_constants
+        .iter()
+        .map(|constant| StaticConstant {
+            name: constant.name.clone(),
+            alignment: symbols.alignment_of(&constant.ty),
+            init: constant.init.clone(),
+        })
+        .collect();
+    let functions = program.functions
+        .iter()
+        .map(|function| {
+            let instructions = generate_function(function, symbols);
+            let instructions = lift_floating_constants(instructions, &mut static_constants);
+            let (instructions, stack_size) = replace_pseudos(instructions, symbols);
+            Function {
+                name: function.name.clone(),
+                global: function.global,
+                instructions: fix_up(instructions, stack_size),
+            }
+        })
+        .collect();
+    let static_variables = program.static_variables
+        .iter()
+        .map(|variable| StaticVariable {
+            name: variable.name.clone(),
+            global: variable.global,
+            alignment: variable_alignment(&variable.ty, symbols),
+            init: variable.init.clone(),
+        })
+        .collect();
+    Program {
+        functions,
+        static_variables,
+        static_constants,
+    }
+}
+
+// The System V ABI wants array variables of 16 bytes or more on a 16 byte boundary, whatever they're made of,
+// and code compiled elsewhere is free to rely on it
+fn variable_alignment(ty: &Type, symbols: &SymbolTable) -> usize {
+    let alignment = symbols.alignment_of(ty);
+    if ty.is_array() && symbols.size_of(ty) >= 16 {
+        alignment.max(16)
+    } else {
+        alignment
+    }
+}
+
+fn to_operand(val: &tacky::Val) -> Operand {
+    match val {
+        tacky::Val::Constant(value) => Operand::Imm(*value),
+        tacky::Val::Var(name) => Operand::Pseudo(name.clone()),
+    }
+}
+
+impl AsmType {
+    pub fn from_type(ty: &Type) -> AsmType {
+        match ty.unqualified() {
+            Type::Float => return AsmType::Float,
+            Type::Double => return AsmType::Double,
+            _ => {}
+        }
+        match ty.size() {
+            1 => AsmType::Byte,
+            2 => AsmType::Word,
+            4 => AsmType::Longword,
+            8 => AsmType::Quadword,
+            _ => panic!("Values of type '{}' aren't supported by codegen yet", ty),
+        }
+    }
+
+    pub fn size(&self) -> u8 {
+        match self {
+            AsmType::Byte => 1,
+            AsmType::Word => 2,
+            AsmType::Longword | AsmType::Float => 4,
+            AsmType::Quadword | AsmType::Double => 8,
+        }
+    }
+
+    // Whether it lives in an xmm register
+    pub fn is_floating(&self) -> bool {
+        matches!(self, AsmType::Float | AsmType::Double)
+    }
+
+    // The sign bit on its own, xor flips it to negate a float or a double
+    fn negative_zero(&self) -> i64 {
+        match self {
+            AsmType::Float => Constant::float(-0.0).value,
+            _ => Constant::double(-0.0).value,
+        }
+    }
+}
+
+// Constants take on the type of whatever they're used with, so we go by the first variable we find
+fn asm_type(vals: &[&tacky::Val], symbols: &SymbolTable) -> AsmType {
+    vals.iter()
+        .find_map(|val| match val {
+            tacky::Val::Var(name) => Some(AsmType::from_type(symbols.type_of(name))),
+            tacky::Val::Constant(_) => None,
+        })
+        .unwrap_or(AsmType::Longword)
+}
+
+// Same idea as asm_type, constants are assumed to be signed when there's nothing else to go by
+fn is_signed(vals: &[&tacky::Val], symbols: &SymbolTable) -> bool {
+    vals.iter()
+        .find_map(|val| match val {
+            tacky::Val::Var(name) => Some(symbols.type_of(name).is_signed()),
+            tacky::Val::Constant(_) => None,
+        })
+        .unwrap_or(true)
+}
+
+fn relational_code(op: &tacky::BinaryOp, signed: bool) -> Option<CondCode> {
+    use tacky::BinaryOp::*;
+    match (op, signed) {
+        (Equal, _) => Some(CondCode::E),
+        (NotEqual, _) => Some(CondCode::NE),
+        (LessThan, true) => Some(CondCode::L),
+        (LessThan, false) => Some(CondCode::B),
+        (LessOrEqual, true) => Some(CondCode::LE),
+        (LessOrEqual, false) => Some(CondCode::BE),
+        (GreaterThan, true) => Some(CondCode::G),
+        (GreaterThan, false) => Some(CondCode::A),
+        (GreaterOrEqual, true) => Some(CondCode::GE),
+        (GreaterOrEqual, false) => Some(CondCode::AE),
+        _ => None,
+    }
+}
+
+fn function_type<'a>(name: &str, symbols: &'a SymbolTable) -> &'a FunctionType {
+    match symbols.type_of(name).unqualified() {
+        Type::Function(function) => function,
+        _ => panic!("'{}' is not a function", name),
+    }
+}
+
+// What a pointer points to decides how wide the value going through it is
+fn referenced_type(ptr: &tacky::Val, symbols: &SymbolTable) -> Option<AsmType> {
+    match ptr {
+        tacky::Val::Var(name) => match symbols.type_of(name).unqualified() {
+            Type::Pointer(referenced) => Some(AsmType::from_type(referenced)),
+            ty => panic!("'{}' has type '{}' but is used as a pointer", name, ty),
+        },
+        tacky::Val::Constant(_) => None,
+    }
+}
+
+// Scales that fit straight into an indexed address, anything else has to be multiplied out first
+fn is_index_scale(scale: i64) -> bool {
+    matches!(scale, 1 | 2 | 4 | 8)
+}
+
+// Which kind of register each eightbyte of a value travels in
+#[derive(Debug, Clone, PartialEq)]
+enum Class {
+    Integer,
+    Sse,
+    // Too big for registers, it gets copied onto the stack instead
+    Memory,
+}
+
+// Every scalar inside the type along with where it sits, the members of a union all overlap
+fn flatten_scalars(ty: &Type, offset: usize, symbols: &SymbolTable, scalars: &mut Vec<(Type, usize)>) {
+    match ty.unqualified() {
+        Type::Struct(tag) | Type::Union(tag) => {
+            let definition = symbols.get_struct(tag).expect("Structures are complete by the time they're passed around");
+            for member in definition.members.iter() {
+                flatten_scalars(&member.ty, offset + member.offset, symbols, scalars);
+            }
+        }
+        Type::Array(element, count) => {
+            let size = symbols.size_of(element);
+            for index in 0..*count {
+                flatten_scalars(element, offset + index * size, symbols, scalars);
+            }
+        }
+        ty => scalars.push((ty.clone(), offset)),
+    }
+}
+
+// Anything over two eightbytes goes in memory, otherwise an eightbyte holding nothing but floats and doubles goes in an xmm register
+fn classify(ty: &Type, symbols: &SymbolTable) -> Vec<Class> {
+    if !ty.is_structure() {
+        return vec![if ty.is_floating() { Class::Sse } else { Class::Integer }];
+    }
+    let size = symbols.size_of(ty);
+    if size > 16 {
+        return vec![Class::Memory];
+    }
+    let mut scalars = Vec::new();
+    flatten_scalars(ty, 0, symbols, &mut scalars);
+    (0..size.div_ceil(8))
+        .map(|eightbyte| {
+            let all_floating = scalars
+                .iter()
+                .filter(|(_, offset)| offset / 8 == eightbyte)
+                .all(|(ty, _)| ty.is_floating());
+            if all_floating { Class::Sse } else { Class::Integer }
+        })
+        .collect()
+}
+
+// Integers fill up the general purpose registers and floating values the xmm ones independently of each other,
+// whatever doesn't fit goes on the stack in the order it came in. A structure only goes in registers if all of it fits.
+// When the return value goes in memory its address takes up the first integer register.
+fn classify_parameters(params: &[Type], return_in_memory: bool, symbols: &SymbolTable) -> Vec<Option<Vec<Reg>>> {
+    let mut integers = return_in_memory as usize;
+    let mut doubles = 0;
+    params
+        .iter()
+        .map(|param| {
+            let classes = classify(param, symbols);
+            let needed_integers = classes.iter().filter(|class| **class == Class::Integer).count();
+            let needed_doubles = classes.iter().filter(|class| **class == Class::Sse).count();
+            if classes.contains(&Class::Memory)
+                || integers + needed_integers > ARGUMENT_REGISTERS.len()
+                || doubles + needed_doubles > DOUBLE_ARGUMENT_REGISTERS.len() {
+                return None;
+            }
+            let registers = classes
+                .iter()
+                .map(|class| if *class == Class::Sse {
+                    doubles += 1;
+                    DOUBLE_ARGUMENT_REGISTERS[doubles - 1].clone()
+                } else {
+                    integers += 1;
+                    ARGUMENT_REGISTERS[integers - 1].clone()
+                })
+                .collect();
+            Some(registers)
+        })
+        .collect()
+}
+
+// Where a structure comes back from a call, None when the caller hands over memory for it instead
+fn structure_return_registers(ty: &Type, symbols: &SymbolTable) -> Option<Vec<Reg>> {
+    let classes = classify(ty, symbols);
+    if classes.contains(&Class::Memory) {
+        return None;
+    }
+    let mut integers = [Reg::AX, Reg::DX].into_iter();
+    let mut doubles = [Reg::XMM0, Reg::XMM1].into_iter();
+    Some(classes
+        .iter()
+        .map(|class| match class {
+            Class::Sse => doubles.next(),
+            _ => integers.next(),
+        })
+        .map(|reg| reg.expect("Two eightbytes never need more than two registers of a kind"))
+        .collect())
+}
+
+fn is_xmm(reg: &Reg) -> bool {
+    DOUBLE_ARGUMENT_REGISTERS.contains(reg)
+}
+
+// Structures and unions get copied a piece at a time, so anything that moves one needs to know how big it is
+fn aggregate_size(val: &tacky::Val, symbols: &SymbolTable) -> Option<usize> {
+    match val {
+        tacky::Val::Var(name) => {
+            let ty = symbols.type_of(name);
+            ty.is_structure().then(|| symbols.size_of(ty))
+        }
+        tacky::Val::Constant(_) => None,
+    }
+}
+
+// Copies size bytes in the biggest chunks that fit, src and dst give the operand at each offset
+fn copy_bytes(instructions: &mut Vec<Instruction>, size: usize, src: impl Fn(i64) -> Operand, dst: impl Fn(i64) -> Operand) {
+    let mut offset = 0;
+    while offset < size {
+        let ty = match size - offset {
+            8.. => AsmType::Quadword,
+            4..=7 => AsmType::Longword,
+            _ => AsmType::Byte,
+        };
+        let chunk = ty.size() as usize;
+        instructions.push(Instruction::Mov(ty, src(offset as i64), dst(offset as i64)));
+        offset += chunk;
+    }
+}
+
+// Gets the eightbyte at offset into reg, where size is how much of the value is left from there
+// A tail that isn't four or eight bytes long is built up a byte at a time from the top down, so we never read past the end.
+// An xmm register only ever gets a lone float at the end or a whole eightbyte.
+fn load_eightbyte(instructions: &mut Vec<Instruction>, src: &dyn Fn(i64) -> Operand, offset: i64, size: usize, reg: Reg) {
+    let register = Operand::Register(reg.clone());
+    match size {
+        4 if is_xmm(&reg) => instructions.push(Instruction::Mov(AsmType::Float, src(offset), register)),
+        _ if is_xmm(&reg) => instructions.push(Instruction::Mov(AsmType::Double, src(offset), register)),
+        8.. => instructions.push(Instruction::Mov(AsmType::Quadword, src(offset), register)),
+        4 => instructions.push(Instruction::Mov(AsmType::Longword, src(offset), register)),
+        _ => {
+            for byte in (0..size as i64).rev() {
+                instructions.push(Instruction::Mov(AsmType::Byte, src(offset + byte), register.clone()));
+                if byte != 0 {
+                    instructions.push(Instruction::Binary(AsmBinaryOp::Sal, AsmType::Quadword, Operand::Imm(8), register.clone()));
+                }
+            }
+        }
+    }
+}
+
+// The other way around, a partial eightbyte gets written out a byte at a time from the bottom up
+fn store_eightbyte(instructions: &mut Vec<Instruction>, reg: Reg, dst: &dyn Fn(i64) -> Operand, offset: i64, size: usize) {
+    let register = Operand::Register(reg.clone());
+    match size {
+        4 if is_xmm(&reg) => instructions.push(Instruction::Mov(AsmType::Float, register, dst(offset))),
+        _ if is_xmm(&reg) => instructions.push(Instruction::Mov(AsmType::Double, register, dst(offset))),
+        8.. => instructions.push(Instruction::Mov(AsmType::Quadword, register, dst(offset))),
+        4 => instructions.push(Instruction::Mov(AsmType::Longword, register, dst(offset))),
+        _ => {
+            for byte in 0..size as i64 {
+                instructions.push(Instruction::Mov(AsmType::Byte, register.clone(), dst(offset + byte)));
+                if byte != size as i64 - 1 {
+                    instructions.push(Instruction::Binary(AsmBinaryOp::Shr, AsmType::Quadword, Operand::Imm(8), register.clone()));
+                }
+            }
+        }
+    }
+}
+
+fn pseudo_mem(name: &str) -> impl Fn(i64) -> Operand + '_ {
+    move |offset| Operand::PseudoMem(name.to_string(), offset)
+}
+
+fn var_name(val: &tacky::Val) -> &str {
+    match val {
+        tacky::Val::Var(name) => name,
+        tacky::Val::Constant(_) => unreachable!("Structures are never constants"),
+    }
+}
+
+fn return_register(ty: &AsmType) -> Operand {
+    if ty.is_floating() {
+        Operand::Register(Reg::XMM0)
+    } else {
+        Operand::Register(Reg::AX)
+    }
+}
+
+fn make_label(purpose: &str, counter: &mut usize) -> String {
+    let label = format!("{}asm_{}_{}", GENERATED_LABEL_PREFIX, purpose, counter);
+    *counter += 1;
+    label
+}
+
+fn generate_function(function: &tacky::Function, symbols: &SymbolTable) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let ty = |vals: &[&tacky::Val]| asm_type(vals, symbols);
+    let mut labels = 0;
+    let signature = function_type(&function.name, symbols);
+    let returns_structure = signature.return_type.is_structure();
+    let return_registers = if returns_structure {
+        structure_return_registers(&signature.return_type, symbols)
+    } else {
+        None
+    };
+    let return_in_memory = returns_structure && return_registers.is_none();
+    if signature.variadic {
+        save_argument_registers(&mut instructions);
+    }
+    if return_in_memory {
+        instructions.push(Instruction::Mov(AsmType::Quadword, Operand::Register(Reg::DI), Operand::Pseudo(RETURN_POINTER.to_string())));
+    }
+
+    // Parameters that didn't get a register were pushed by the caller, they sit above the return address and saved %rbp
+    let mut stack_offset = 16;
+    let locations = classify_parameters(&signature.params, return_in_memory, symbols);
+    // va_start picks up where the named parameters left off, in the registers and on the stack
+    let (mut used_integers, used_doubles) = registers_used(&locations);
+    used_integers += return_in_memory as usize;
+    for (param, location) in function.params.iter().zip(locations) {
+        let ty = symbols.type_of(param);
+        if ty.is_structure() {
+            let size = symbols.size_of(ty);
+            match location {
+                Some(registers) => {
+                    for (index, reg) in registers.into_iter().enumerate() {
+                        let offset = index * 8;
+                        store_eightbyte(&mut instructions, reg, &pseudo_mem(param), offset as i64, size - offset);
+                    }
+                }
+                None => {
+                    let start = stack_offset;
+                    copy_bytes(&mut instructions, size, |offset| Operand::Stack(start + offset), pseudo_mem(param));
+                    stack_offset += 8 * size.div_ceil(8) as i64;
+                }
+            }
+            continue;
+        }
+        let src = match location {
+            Some(registers) => Operand::Register(registers[0].clone()),
+            None => {
+                stack_offset += 8;
+                Operand::Stack(stack_offset - 8)
+            }
+        };
+        instructions.push(Instruction::Mov(AsmType::from_type(ty), src, Operand::Pseudo(param.clone())));
+    }
+
+    for instruction in function.instructions.iter() {
+        match instruction {
+            tacky::Instruction::Return(None) => instructions.push(Instruction::Ret),
+            tacky::Instruction::Return(Some(val)) if returns_structure => {
+                let size = symbols.size_of(&signature.return_type);
+                let src = pseudo_mem(var_name(val));
+                match return_registers.as_ref() {
+                    Some(registers) => {
+                        for (index, reg) in registers.iter().enumerate() {
+                            let offset = index * 8;
+                            load_eightbyte(&mut instructions, &src, offset as i64, size - offset, reg.clone());
+                        }
+                    }
+                    // The caller gets the address it passed in back in %rax
+                    None => {
+                        instructions.push(Instruction::Mov(AsmType::Quadword, Operand::Pseudo(RETURN_POINTER.to_string()), Operand::Register(Reg::AX)));
+                        copy_bytes(&mut instructions, size, src, |offset| Operand::Memory(Reg::AX, offset));
+                    }
+                }
+                instructions.push(Instruction::Ret);
+            }
+            // A constant being returned doesn't know how wide it is, but the function does
+            tacky::Instruction::Return(Some(val)) => {
+                let return_type = AsmType::from_type(&signature.return_type);
+                instructions.push(Instruction::Mov(return_type.clone(), to_operand(val), return_register(&return_type)));
+                instructions.push(Instruction::Ret);
+            }
+            tacky::Instruction::Unary { op: tacky::UnaryOp::Not, src, dst } if ty(&[src]).is_floating() => {
+                instructions.extend(compare_with_zero(ty(&[src]), src));
+                instructions.push(Instruction::Mov(ty(&[dst]), Operand::Imm(0), to_operand(dst)));
+                set_double_equality(&mut instructions, CondCode::E, to_operand(dst));
+            }
+            tacky::Instruction::Unary { op: tacky::UnaryOp::Negate, src, dst } if ty(&[dst]).is_floating() => {
+                let ty = ty(&[dst]);
+                let sign = Operand::Imm(ty.negative_zero());
+                instructions.push(Instruction::Mov(ty.clone(), to_operand(src), to_operand(dst)));
+                instructions.push(Instruction::Binary(AsmBinaryOp::Xor, ty, sign, to_operand(dst)));
+            }
+            tacky::Instruction::Unary { op: tacky::UnaryOp::Not, src, dst } => {
+                instructions.push(Instruction::Cmp(ty(&[src]), Operand::Imm(0), to_operand(src)));
+                instructions.push(Instruction::Mov(ty(&[dst]), Operand::Imm(0), to_operand(dst)));
+                instructions.push(Instruction::SetCC(CondCode::E, to_operand(dst)));
+            }
+            tacky::Instruction::Unary { op, src, dst } => {
+                let op = match op {
+                    tacky::UnaryOp::Negate => AsmUnaryOp::Neg,
+                    _ => AsmUnaryOp::Not,
+                };
+                let ty = ty(&[dst]);
+                instructions.push(Instruction::Mov(ty.clone(), to_operand(src), to_operand(dst)));
+                instructions.push(Instruction::Unary(op, ty, to_operand(dst)));
+            }
+            tacky::Instruction::Binary { op, src1, src2, dst } => {
+                if let Some(code) = relational_code(op, is_signed(&[src1, src2], symbols)) {
+                    let operand_type = ty(&[src1, src2]);
+                    if operand_type.is_floating() {
+                        generate_double_comparison(&mut instructions, op, operand_type, src1, src2, ty(&[dst]), to_operand(dst));
+                        continue;
+                    }
+                    instructions.push(Instruction::Cmp(operand_type, to_operand(src2), to_operand(src1)));
+                    instructions.push(Instruction::Mov(ty(&[dst]), Operand::Imm(0), to_operand(dst)));
+                    instructions.push(Instruction::SetCC(code, to_operand(dst)));
+                    continue;
+                }
+
+                let ty = ty(&[dst]);
+                let signed = is_signed(&[dst], symbols);
+                let op = match op {
+                    tacky::BinaryOp::Divide if ty.is_floating() => AsmBinaryOp::DivDouble,
+                    tacky::BinaryOp::Divide | tacky::BinaryOp::Remainder => {
+                        // Division leaves the quotient in %eax and the remainder in %edx
+                        let result = match op {
+                            tacky::BinaryOp::Divide => Reg::AX,
+                            _ => Reg::DX,
+                        };
+                        instructions.push(Instruction::Mov(ty.clone(), to_operand(src1), Operand::Register(Reg::AX)));
+                        // The upper half of the dividend is the sign extension for idiv and zero for div
+                        if signed {
+                            instructions.push(Instruction::Cdq(ty.clone()));
+                            instructions.push(Instruction::Idiv(ty.clone(), to_operand(src2)));
+                        } else {
+                            instructions.push(Instruction::Mov(ty.clone(), Operand::Imm(0), Operand::Register(Reg::DX)));
+                            instructions.push(Instruction::Div(ty.clone(), to_operand(src2)));
+                        }
+                        instructions.push(Instruction::Mov(ty, Operand::Register(result), to_operand(dst)));
+                        continue;
+                    }
+                    tacky::BinaryOp::Add => AsmBinaryOp::Add,
+                    tacky::BinaryOp::Subtract => AsmBinaryOp::Sub,
+                    tacky::BinaryOp::Multiply => AsmBinaryOp::Mult,
+                    tacky::BinaryOp::BitwiseAnd => AsmBinaryOp::And,
+                    tacky::BinaryOp::BitwiseOr => AsmBinaryOp::Or,
+                    tacky::BinaryOp::BitwiseXor => AsmBinaryOp::Xor,
+                    tacky::BinaryOp::ShiftLeft => AsmBinaryOp::Sal,
+                    _ if signed => AsmBinaryOp::Sar,
+                    _ => AsmBinaryOp::Shr,
+                };
+                instructions.push(Instruction::Mov(ty.clone(), to_operand(src1), to_operand(dst)));
+                instructions.push(Instruction::Binary(op, ty, to_operand(src2), to_operand(dst)));
+            }
+            tacky::Instruction::Copy { src, dst } => match aggregate_size(dst, symbols) {
+                Some(size) => copy_bytes(&mut instructions, size, pseudo_mem(var_name(src)), pseudo_mem(var_name(dst))),
+                None => instructions.push(Instruction::Mov(ty(&[dst, src]), to_operand(src), to_operand(dst))),
+            },
+            tacky::Instruction::SignExtend { src, dst } => {
+                instructions.push(Instruction::Movsx(ty(&[src]), ty(&[dst]), to_operand(src), to_operand(dst)));
+            }
+            tacky::Instruction::ZeroExtend { src, dst } => {
+                instructions.push(Instruction::MovZeroExtend(ty(&[src]), ty(&[dst]), to_operand(src), to_operand(dst)));
+            }
+            // Reading the narrower type out of the wider one leaves just the low bytes
+            tacky::Instruction::Truncate { src, dst } => {
+                instructions.push(Instruction::Mov(ty(&[dst]), to_operand(src), to_operand(dst)));
+            }
+            // A constant source has already been folded, so both sides are variables
+            tacky::Instruction::IntToDouble { src, dst } => {
+                instructions.push(Instruction::Cvtsi2fp(ty(&[src]), ty(&[dst]), to_operand(src), to_operand(dst)));
+            }
+            tacky::Instruction::DoubleToInt { src, dst } => {
+                instructions.push(Instruction::Cvttfp2si(ty(&[src]), ty(&[dst]), to_operand(src), to_operand(dst)));
+            }
+            // An unsigned int always fits in a long, so it can be zero extended and converted as a signed value
+            tacky::Instruction::UIntToDouble { src, dst } if ty(&[src]) == AsmType::Longword => {
+                instructions.push(Instruction::MovZeroExtend(AsmType::Longword, AsmType::Quadword, to_operand(src), Operand::Register(Reg::AX)));
+                instructions.push(Instruction::Cvtsi2fp(AsmType::Quadword, ty(&[dst]), Operand::Register(Reg::AX), to_operand(dst)));
+            }
+            tacky::Instruction::UIntToDouble { src, dst } => {
+                generate_ulong_to_double(&mut instructions, to_operand(src), ty(&[dst]), to_operand(dst), &mut labels);
+            }
+            tacky::Instruction::DoubleToUInt { src, dst } if ty(&[dst]) == AsmType::Longword => {
+                instructions.push(Instruction::Cvttfp2si(ty(&[src]), AsmType::Quadword, to_operand(src), Operand::Register(Reg::AX)));
+                instructions.push(Instruction::Mov(AsmType::Longword, Operand::Register(Reg::AX), to_operand(dst)));
+            }
+            tacky::Instruction::DoubleToUInt { src, dst } => {
+                generate_double_to_ulong(&mut instructions, ty(&[src]), to_operand(src), to_operand(dst), &mut labels);
+            }
+            tacky::Instruction::FloatToDouble { src, dst } => {
+                instructions.push(Instruction::Cvtfp2fp(AsmType::Float, AsmType::Double, to_operand(src), to_operand(dst)));
+            }
+            tacky::Instruction::DoubleToFloat { src, dst } => {
+                instructions.push(Instruction::Cvtfp2fp(AsmType::Double, AsmType::Float, to_operand(src), to_operand(dst)));
+            }
+            tacky::Instruction::FunCall { name, args, dst } => {
+                generate_call(&mut instructions, Callee::Direct(name), args, dst.as_ref(), symbols);
+            }
+            tacky::Instruction::IndirectCall { function, args, dst } => {
+                generate_call(&mut instructions, Callee::Pointer(function), args, dst.as_ref(), symbols);
+            }
+            // A function isn't an object with a spot of its own, its address is where its code starts.
+            // One from another file could be anywhere, so the address comes out of the global offset table
+            tacky::Instruction::GetAddress { src: tacky::Val::Var(name), dst } if matches!(symbols.type_of(name), Type::Function(_)) => {
+                if symbols.is_defined(name) {
+                    instructions.push(Instruction::Lea(Operand::Data(name.clone(), 0), to_operand(dst)));
+                } else {
+                    instructions.push(Instruction::Mov(AsmType::Quadword, Operand::Got(name.clone()), to_operand(dst)));
+                }
+            }
+            tacky::Instruction::GetAddress { src, dst } => {
+                instructions.push(Instruction::Lea(to_operand(src), to_operand(dst)));
+            }
+            tacky::Instruction::Load { src_ptr, dst } => {
+                instructions.push(Instruction::Mov(AsmType::Quadword, to_operand(src_ptr), Operand::Register(Reg::AX)));
+                match aggregate_size(dst, symbols) {
+                    Some(size) => copy_bytes(&mut instructions, size, |offset| Operand::Memory(Reg::AX, offset), pseudo_mem(var_name(dst))),
+                    None => instructions.push(Instruction::Mov(ty(&[dst]), Operand::Memory(Reg::AX, 0), to_operand(dst))),
+                }
+            }
+            tacky::Instruction::Store { src, dst_ptr } => {
+                instructions.push(Instruction::Mov(AsmType::Quadword, to_operand(dst_ptr), Operand::Register(Reg::AX)));
+                match aggregate_size(src, symbols) {
+                    Some(size) => copy_bytes(&mut instructions, size, pseudo_mem(var_name(src)), |offset| Operand::Memory(Reg::AX, offset)),
+                    None => {
+                        let ty = referenced_type(dst_ptr, symbols).unwrap_or_else(|| ty(&[src]));
+                        instructions.push(Instruction::Mov(ty, to_operand(src), Operand::Memory(Reg::AX, 0)));
+                    }
+                }
+            }
+            // A constant doesn't know how wide it is, but the array it's going into does
+            tacky::Instruction::CopyToOffset { src, dst, offset } => {
+                if let Some(size) = aggregate_size(src, symbols) {
+                    let dst = pseudo_mem(dst);
+                    copy_bytes(&mut instructions, size, pseudo_mem(var_name(src)), |start| dst(offset + start));
+                    continue;
+                }
+                let ty = match src {
+                    tacky::Val::Var(_) => ty(&[src]),
+                    tacky::Val::Constant(_) => AsmType::from_type(symbols.type_of(dst).scalar_element()),
+                };
+                instructions.push(Instruction::Mov(ty, to_operand(src), Operand::PseudoMem(dst.clone(), *offset)));
+            }
+            tacky::Instruction::CopyFromOffset { src, offset, dst } => match aggregate_size(dst, symbols) {
+                Some(size) => {
+                    let src = pseudo_mem(src);
+                    copy_bytes(&mut instructions, size, |start| src(offset + start), pseudo_mem(var_name(dst)));
+                }
+                None => instructions.push(Instruction::Mov(ty(&[dst]), Operand::PseudoMem(src.clone(), *offset), to_operand(dst))),
+            },
+            tacky::Instruction::AddPtr { ptr, index, scale, dst } => {
+                generate_add_ptr(&mut instructions, to_operand(ptr), to_operand(index), *scale, to_operand(dst));
+            }
+            tacky::Instruction::Jump(label) => {
+                instructions.push(Instruction::Jmp(label.clone()));
+            }
+            // NaN isn't zero, but comisd says it's equal to everything
+            tacky::Instruction::JumpIfZero(val, label) if ty(&[val]).is_floating() => {
+                let not_a_number = make_label("nan", &mut labels);
+                instructions.extend(compare_with_zero(ty(&[val]), val));
+                instructions.push(Instruction::JmpCC(CondCode::P, not_a_number.clone()));
+                instructions.push(Instruction::JmpCC(CondCode::E, label.clone()));
+                instructions.push(Instruction::Label(not_a_number));
+            }
+            tacky::Instruction::JumpIfNotZero(val, label) if ty(&[val]).is_floating() => {
+                instructions.extend(compare_with_zero(ty(&[val]), val));
+                instructions.push(Instruction::JmpCC(CondCode::NE, label.clone()));
+                instructions.push(Instruction::JmpCC(CondCode::P, label.clone()));
+            }
+            tacky::Instruction::JumpIfZero(val, label) => {
+                instructions.push(Instruction::Cmp(ty(&[val]), Operand::Imm(0), to_operand(val)));
+                instructions.push(Instruction::JmpCC(CondCode::E, label.clone()));
+            }
+            tacky::Instruction::JumpIfNotZero(val, label) => {
+                instructions.push(Instruction::Cmp(ty(&[val]), Operand::Imm(0), to_operand(val)));
+                instructions.push(Instruction::JmpCC(CondCode::NE, label.clone()));
+            }
+            tacky::Instruction::JumpTable { index, table, targets } => {
+                instructions.push(Instruction::Mov(ty(&[index]), to_operand(index), Operand::Register(Reg::AX)));
+                instructions.push(Instruction::JmpTable(table.clone(), targets.clone()));
+            }
+            tacky::Instruction::Label(label) => {
+                instructions.push(Instruction::Label(label.clone()));
+            }
+            tacky::Instruction::VaStart { list } => {
+                let named = VaStartPosition {
+                    integers: used_integers,
+                    doubles: used_doubles,
+                    stack_offset,
+                };
+                generate_va_start(&mut instructions, list, named);
+            }
+            tacky::Instruction::VaArg { list, dst } => {
+                generate_va_arg(&mut instructions, list, dst, symbols, &mut labels);
+            }
+        }
+    }
+    instructions
+}
+
+// How many general purpose and xmm registers the arguments that got one take up
+fn registers_used(locations: &[Option<Vec<Reg>>]) -> (usize, usize) {
+    let registers: Vec<&Reg> = locations.iter().flatten().flatten().collect();
+    let doubles = registers.iter().filter(|reg| is_xmm(reg)).count();
+    (registers.len() - doubles, doubles)
+}
+
+// Stores every register an argument could have come in, named or not, before anything else gets the chance to touch them
+fn save_argument_registers(instructions: &mut Vec<Instruction>) {
+    let save_area = pseudo_mem(REGISTER_SAVE_AREA);
+    for (index, reg) in ARGUMENT_REGISTERS.iter().enumerate() {
+        instructions.push(Instruction::Mov(AsmType::Quadword, Operand::Register(reg.clone()), save_area(8 * index as i64)));
+    }
+    for (index, reg) in DOUBLE_ARGUMENT_REGISTERS.iter().enumerate() {
+        let offset = SAVE_AREA_XMM_START + 16 * index as i64;
+        instructions.push(Instruction::Mov(AsmType::Double, Operand::Register(reg.clone()), save_area(offset)));
+    }
+}
+
+// Where the named parameters of a variadic function stop, which is where its unnamed arguments start
+struct VaStartPosition {
+    integers: usize,
+    doubles: usize,
+    // The first argument the caller pushed that didn't have a name, relative to %rbp
+    stack_offset: i64,
+}
+
+// Fills in the four fields of the va_list: how far into the save area the next integer and the next floating
+// argument are, where the next one on the stack is, and where the save area is
+fn generate_va_start(instructions: &mut Vec<Instruction>, list: &tacky::Val, named: VaStartPosition) {
+    let ax = Reg::AX;
+    let dx = Operand::Register(Reg::DX);
+    let integer_offset = 8 * named.integers as i64;
+    let double_offset = SAVE_AREA_XMM_START + 16 * named.doubles as i64;
+    instructions.extend([
+        Instruction::Mov(AsmType::Quadword, to_operand(list), Operand::Register(ax.clone())),
+        Instruction::Mov(AsmType::Longword, Operand::Imm(integer_offset), Operand::Memory(ax.clone(), 0)),
+        Instruction::Mov(AsmType::Longword, Operand::Imm(double_offset), Operand::Memory(ax.clone(), 4)),
+        Instruction::Lea(Operand::Stack(named.stack_offset), dx.clone()),
+        Instruction::Mov(AsmType::Quadword, dx.clone(), Operand::Memory(ax.clone(), 8)),
+        Instruction::Lea(Operand::PseudoMem(REGISTER_SAVE_AREA.to_string(), 0), dx.clone()),
+        Instruction::Mov(AsmType::Quadword, dx, Operand::Memory(ax, 16)),
+    ]);
+}
+
+// The value comes out of the save area if every eightbyte of it still fits in the registers that are left,
+// otherwise it's the next thing on the stack. Either way the va_list is moved on past it.
+fn generate_va_arg(instructions: &mut Vec<Instruction>, list: &tacky::Val, dst: &tacky::Val, symbols: &SymbolTable, labels: &mut usize) {
+    let ty = symbols.type_of(var_name(dst));
+    let size = symbols.size_of(ty);
+    let classes = classify(ty, symbols);
+    let ax = Reg::AX;
+    let dx = Operand::Register(Reg::DX);
+    let dst = pseudo_mem(var_name(dst));
+    let on_stack = make_label("va_arg_stack", labels);
+    let end = make_label("va_arg_end", labels);
+    instructions.push(Instruction::Mov(AsmType::Quadword, to_operand(list), Operand::Register(ax.clone())));
+
+    if !classes.contains(&Class::Memory) {
+        let needed_integers = classes.iter().filter(|class| **class == Class::Integer).count() as i64;
+        let needed_doubles = classes.iter().filter(|class| **class == Class::Sse).count() as i64;
+        // The offsets only ever grow, so anything past the last one that leaves enough room means the registers ran out
+        if needed_integers > 0 {
+            let limit = SAVE_AREA_XMM_START - 8 * needed_integers;
+            instructions.push(Instruction::Cmp(AsmType::Longword, Operand::Imm(limit), Operand::Memory(ax.clone(), 0)));
+            instructions.push(Instruction::JmpCC(CondCode::A, on_stack.clone()));
+        }
+        if needed_doubles > 0 {
+            let limit = SAVE_AREA_SIZE - 16 * needed_doubles;
+            instructions.push(Instruction::Cmp(AsmType::Longword, Operand::Imm(limit), Operand::Memory(ax.clone(), 4)));
+            instructions.push(Instruction::JmpCC(CondCode::A, on_stack.clone()));
+        }
+        for (index, class) in classes.iter().enumerate() {
+            let (field, step) = match class {
+                Class::Sse => (4, 16),
+                _ => (0, 8),
+            };
+            let start = 8 * index as i64;
+            instructions.push(Instruction::Mov(AsmType::Longword, Operand::Memory(ax.clone(), field), dx.clone()));
+            instructions.push(Instruction::Binary(AsmBinaryOp::Add, AsmType::Quadword, Operand::Memory(ax.clone(), 16), dx.clone()));
+            copy_bytes(instructions, (size - 8 * index).min(8), |offset| Operand::Memory(Reg::DX, offset), |offset| dst(start + offset));
+            instructions.push(Instruction::Binary(AsmBinaryOp::Add, AsmType::Longword, Operand::Imm(step), Operand::Memory(ax.clone(), field)));
+        }
+        instructions.push(Instruction::Jmp(end.clone()));
+    }
+
+    // Everything on the stack takes up a whole number of eightbytes
+    instructions.push(Instruction::Label(on_stack));
+    instructions.push(Instruction::Mov(AsmType::Quadword, Operand::Memory(ax.clone(), 8), dx));
+    copy_bytes(instructions, size, |offset| Operand::Memory(Reg::DX, offset), dst);
+    instructions.push(Instruction::Binary(AsmBinaryOp::Add, AsmType::Quadword, Operand::Imm(8 * size.div_ceil(8) as i64), Operand::Memory(ax, 8)));
+    instructions.push(Instruction::Label(end));
+}
+
+// System V calling convention, six arguments in registers and the rest pushed right to left
+// Structures are split into eightbytes that each go in a register of their own, unless they have to go on the stack whole
+// Where a call goes, straight to a function by its name or to wherever a function pointer points
+enum Callee<'a> {
+    Direct(&'a str),
+    Pointer(&'a tacky::Val),
+}
+
+fn generate_call(instructions: &mut Vec<Instruction>, callee: Callee, args: &[tacky::Val], dst: Option<&tacky::Val>, symbols: &SymbolTable) {
+    let signature = match callee {
+        Callee::Direct(name) => function_type(name, symbols),
+        Callee::Pointer(pointer) => match symbols.type_of(var_name(pointer)) {
+            Type::Pointer(referenced) if let Type::Function(function) = referenced.as_ref() => function,
+            ty => panic!("Call through '{}', which is not a function pointer", ty),
+        },
+    };
+    // The type checker already converted every argument to its parameter type, so that's how wide they are.
+    // Anything past the `...` is in a variable of the type it was promoted to
+    let params: Vec<Type> = args
+        .iter()
+        .enumerate()
+        .map(|(index, arg)| match signature.params.get(index) {
+            Some(param) => param.clone(),
+            None => symbols.type_of(var_name(arg)).clone(),
+        })
+        .collect();
+    let params = &params;
+    let return_registers = if signature.return_type.is_structure() {
+        structure_return_registers(&signature.return_type, symbols)
+    } else {
+        None
+    };
+    let return_in_memory = signature.return_type.is_structure() && return_registers.is_none();
+    // The callee writes a structure that doesn't fit in registers straight into our copy
+    if return_in_memory && let Some(dst) = dst {
+        instructions.push(Instruction::Lea(to_operand(dst), Operand::Register(Reg::DI)));
+    }
+
+    let mut stack_args = Vec::new();
+    let locations = classify_parameters(params, return_in_memory, symbols);
+    let (_, used_doubles) = registers_used(&locations);
+    for ((arg, param), location) in args.iter().zip(params).zip(locations) {
+        match location {
+            Some(registers) if param.is_structure() => {
+                let size = symbols.size_of(param);
+                for (index, reg) in registers.into_iter().enumerate() {
+                    let offset = index * 8;
+                    load_eightbyte(instructions, &pseudo_mem(var_name(arg)), offset as i64, size - offset, reg);
+                }
+            }
+            Some(registers) => instructions.push(Instruction::Mov(AsmType::from_type(param), to_operand(arg), Operand::Register(registers[0].clone()))),
+            None => stack_args.push((arg, param)),
+        }
+    }
+
+    // The stack has to be 16 byte aligned at the call, every pushed eightbyte takes up 8 bytes
+    let eightbytes: usize = stack_args
+        .iter()
+        .map(|(_, param)| if param.is_structure() { symbols.size_of(param).div_ceil(8) } else { 1 })
+        .sum();
+    let padding = if eightbytes % 2 == 1 { 8 } else { 0 };
+    if padding != 0 {
+        instructions.push(Instruction::AllocateStack(padding));
+    }
+
+    for (arg, param) in stack_args.iter().rev() {
+        if param.is_structure() {
+            // Last eightbyte first so the structure ends up in order, a partial one at the end goes through a register
+            let size = symbols.size_of(param);
+            let src = pseudo_mem(var_name(arg));
+            for index in (0..size.div_ceil(8)).rev() {
+                let offset = index * 8;
+                if size - offset >= 8 {
+                    instructions.push(Instruction::Push(src(offset as i64)));
+                } else {
+                    load_eightbyte(instructions, &src, offset as i64, size - offset, Reg::AX);
+                    instructions.push(Instruction::Push(Operand::Register(Reg::AX)));
+                }
+            }
+            continue;
+        }
+        let ty = AsmType::from_type(param);
+        match (to_operand(arg), &ty) {
+            // A floating constant is pushed as its bits
+            (Operand::Imm(value), _) => instructions.push(Instruction::Push(Operand::Imm(value))),
+            (operand, AsmType::Double) => instructions.push(Instruction::Push(operand)),
+            // The bits of a float can go through a general purpose register like any other four bytes
+            (operand, AsmType::Float) => {
+                instructions.push(Instruction::Mov(AsmType::Longword, operand, Operand::Register(Reg::AX)));
+                instructions.push(Instruction::Push(Operand::Register(Reg::AX)));
+            }
+            // push always moves 8 bytes, so narrower values go through a register first
+            (operand, ty) => {
+                instructions.push(Instruction::Mov(ty.clone(), operand, Operand::Register(Reg::AX)));
+                instructions.push(Instruction::Push(Operand::Register(Reg::AX)));
+            }
+        }
+    }
+
+    // A variadic function is told how many xmm registers hold arguments, in %al
+    if signature.variadic {
+        instructions.push(Instruction::Mov(AsmType::Longword, Operand::Imm(used_doubles as i64), Operand::Register(Reg::AX)));
+    }
+    match callee {
+        Callee::Direct(name) => instructions.push(Instruction::Call {
+            name: name.to_string(),
+            plt: !symbols.is_defined(name),
+        }),
+        // Nothing else needs %r11 by now, the arguments are all where they're going
+        Callee::Pointer(pointer) => {
+            instructions.push(Instruction::Mov(AsmType::Quadword, to_operand(pointer), Operand::Register(Reg::R11)));
+            instructions.push(Instruction::CallIndirect(Operand::Register(Reg::R11)));
+        }
+    }
+    let to_free = 8 * eightbytes as i64 + padding;
+    if to_free != 0 {
+        instructions.push(Instruction::DeallocateStack(to_free));
+    }
+
+    // A void function doesn't hand anything back
+    let Some(dst) = dst else { return };
+    if signature.return_type.is_structure() {
+        let size = symbols.size_of(&signature.return_type);
+        for (index, reg) in return_registers.into_iter().flatten().enumerate() {
+            let offset = index * 8;
+            store_eightbyte(instructions, reg, &pseudo_mem(var_name(dst)), offset as i64, size - offset);
+        }
+        return;
+    }
+    let ty = asm_type(&[dst], symbols);
+    instructions.push(Instruction::Mov(ty.clone(), return_register(&ty), to_operand(dst)));
+}
+
+// A constant index folds into the displacement, otherwise the scale goes into an indexed address if it can
+fn generate_add_ptr(instructions: &mut Vec<Instruction>, ptr: Operand, index: Operand, scale: i64, dst: Operand) {
+    instructions.push(Instruction::Mov(AsmType::Quadword, ptr, Operand::Register(Reg::AX)));
+    let offset = match index {
+        Operand::Imm(value) => value.checked_mul(scale).filter(|offset| i32::try_from(*offset).is_ok()),
+        _ => None,
+    };
+    let address = match offset {
+        Some(offset) => Operand::Memory(Reg::AX, offset),
+        None => {
+            instructions.push(Instruction::Mov(AsmType::Quadword, index, Operand::Register(Reg::DX)));
+            if is_index_scale(scale) {
+                Operand::Indexed(Reg::AX, Reg::DX, scale)
+            } else {
+                instructions.push(Instruction::Binary(AsmBinaryOp::Mult, AsmType::Quadword, Operand::Imm(scale), Operand::Register(Reg::DX)));
+                Operand::Indexed(Reg::AX, Reg::DX, 1)
+            }
+        }
+    };
+    instructions.push(Instruction::Lea(address, dst));
+}
+
+// Leaves the flags set by comparing the float or double in val against zero
+fn compare_with_zero(ty: AsmType, val: &tacky::Val) -> [Instruction; 2] {
+    let zero = Operand::Register(Reg::XMM14);
+    [
+        Instruction::Binary(AsmBinaryOp::Xor, ty.clone(), zero.clone(), zero.clone()),
+        Instruction::Cmp(ty, to_operand(val), zero),
+    ]
+}
+
+// comisd reports NaN as unordered through the parity flag, and unordered values are never equal
+fn set_double_equality(instructions: &mut Vec<Instruction>, code: CondCode, dst: Operand) {
+    let (parity, op) = match code {
+        CondCode::E => (CondCode::NP, AsmBinaryOp::And),
+        _ => (CondCode::P, AsmBinaryOp::Or),
+    };
+    instructions.push(Instruction::SetCC(code, dst.clone()));
+    instructions.push(Instruction::SetCC(parity, Operand::Register(Reg::R11)));
+    instructions.push(Instruction::Binary(op, AsmType::Byte, Operand::Register(Reg::R11), dst));
+}
+
+// comisd sets the flags like an unsigned compare, with NaN looking smaller than everything
+// So we only ask whether something is above, flipping the operands for less than, and NaN comes out false
+fn generate_double_comparison(instructions: &mut Vec<Instruction>, op: &tacky::BinaryOp, ty: AsmType, src1: &tacky::Val, src2: &tacky::Val, dst_type: AsmType, dst: Operand) {
+    use tacky::BinaryOp::*;
+    let (left, right, code) = match op {
+        LessThan => (src2, src1, CondCode::A),
+        LessOrEqual => (src2, src1, CondCode::AE),
+        GreaterThan => (src1, src2, CondCode::A),
+        GreaterOrEqual => (src1, src2, CondCode::AE),
+        Equal => (src1, src2, CondCode::E),
+        _ => (src1, src2, CondCode::NE),
+    };
+    instructions.push(Instruction::Cmp(ty, to_operand(right), to_operand(left)));
+    instructions.push(Instruction::Mov(dst_type, Operand::Imm(0), dst.clone()));
+    match code {
+        CondCode::E | CondCode::NE => set_double_equality(instructions, code, dst),
+        _ => instructions.push(Instruction::SetCC(code, dst)),
+    }
+}
+
+// cvtsi2sd only knows signed values, anything with the top bit set gets halved first and doubled afterwards
+// The bit shifted out is or'd back in so the result still rounds correctly, to a float just as much as to a double
+fn generate_ulong_to_double(instructions: &mut Vec<Instruction>, src: Operand, ty: AsmType, dst: Operand, labels: &mut usize) {
+    let out_of_range = make_label("ulong_out_of_range", labels);
+    let end = make_label("ulong_end", labels);
+    let ax = Operand::Register(Reg::AX);
+    let dx = Operand::Register(Reg::DX);
+    instructions.extend([
+        Instruction::Cmp(AsmType::Quadword, Operand::Imm(0), src.clone()),
+        Instruction::JmpCC(CondCode::L, out_of_range.clone()),
+        Instruction::Cvtsi2fp(AsmType::Quadword, ty.clone(), src.clone(), dst.clone()),
+        Instruction::Jmp(end.clone()),
+        Instruction::Label(out_of_range),
+        Instruction::Mov(AsmType::Quadword, src, ax.clone()),
+        Instruction::Mov(AsmType::Quadword, ax.clone(), dx.clone()),
+        Instruction::Binary(AsmBinaryOp::Shr, AsmType::Quadword, Operand::Imm(1), dx.clone()),
+        Instruction::Binary(AsmBinaryOp::And, AsmType::Quadword, Operand::Imm(1), ax.clone()),
+        Instruction::Binary(AsmBinaryOp::Or, AsmType::Quadword, ax, dx.clone()),
+        Instruction::Cvtsi2fp(AsmType::Quadword, ty.clone(), dx, dst.clone()),
+        Instruction::Binary(AsmBinaryOp::Add, ty, dst.clone(), dst),
+        Instruction::Label(end),
+    ]);
+}
+
+// Values from 2^63 up don't fit in a signed long, so we take 2^63 off before converting and add it back on afterwards
+fn generate_double_to_ulong(instructions: &mut Vec<Instruction>, ty: AsmType, src: Operand, dst: Operand, labels: &mut usize) {
+    let out_of_range = make_label("double_out_of_range", labels);
+    let end = make_label("double_end", labels);
+    let limit = match ty {
+        AsmType::Float => Constant::float(LONG_LIMIT as f32),
+        _ => Constant::double(LONG_LIMIT),
+    };
+    let limit = Operand::Imm(limit.value);
+    let scratch = Operand::Register(Reg::XMM14);
+    instructions.extend([
+        Instruction::Cmp(ty.clone(), limit.clone(), src.clone()),
+        Instruction::JmpCC(CondCode::AE, out_of_range.clone()),
+        Instruction::Cvttfp2si(ty.clone(), AsmType::Quadword, src.clone(), dst.clone()),
+        Instruction::Jmp(end.clone()),
+        Instruction::Label(out_of_range),
+        Instruction::Mov(ty.clone(), src, scratch.clone()),
+        Instruction::Binary(AsmBinaryOp::Sub, ty.clone(), limit, scratch.clone()),
+        Instruction::Cvttfp2si(ty, AsmType::Quadword, scratch, dst.clone()),
+        Instruction::Binary(AsmBinaryOp::Add, AsmType::Quadword, Operand::Imm(i64::MIN), dst),
+        Instruction::Label(end),
+    ]);
+}
+
+// SSE instructions can't take immediates, so every floating constant gets a label in .rodata instead
+fn lift_floating_constants(instructions: Vec<Instruction>, constants: &mut Vec<StaticConstant>) -> Vec<Instruction> {
+    let mut lift = |operand: Operand, ty: &AsmType, alignment: usize| match operand {
+        Operand::Imm(bits) => Operand::Data(constant_label(constants, bits, ty, alignment.max(ty.size() as usize)), 0),
+        other => other,
+    };
+    instructions
+        .into_iter()
+        .map(|instruction| match instruction {
+            Instruction::Mov(ty, src, dst) if ty.is_floating() => {
+                let src = lift(src, &ty, 0);
+                Instruction::Mov(ty, src, dst)
+            }
+            // xorpd reads 16 bytes from memory, and they have to be aligned
+            Instruction::Binary(AsmBinaryOp::Xor, ty, src, dst) if ty.is_floating() => {
+                let src = lift(src, &ty, 16);
+                Instruction::Binary(AsmBinaryOp::Xor, ty, src, dst)
+            }
+            Instruction::Binary(op, ty, src, dst) if ty.is_floating() => {
+                let src = lift(src, &ty, 0);
+                Instruction::Binary(op, ty, src, dst)
+            }
+            Instruction::Cmp(ty, left, right) if ty.is_floating() => {
+                let (left, right) = (lift(left, &ty, 0), lift(right, &ty, 0));
+                Instruction::Cmp(ty, left, right)
+            }
+            Instruction::Cvttfp2si(src_ty, dst_ty, src, dst) => {
+                let src = lift(src, &src_ty, 0);
+                Instruction::Cvttfp2si(src_ty, dst_ty, src, dst)
+            }
+            Instruction::Cvtfp2fp(src_ty, dst_ty, src, dst) => {
+                let src = lift(src, &src_ty, 0);
+                Instruction::Cvtfp2fp(src_ty, dst_ty, src, dst)
+            }
+            other => other,
+        })
+        .collect()
+}
+
+// Every use of the same value shares one constant
+fn constant_label(constants: &mut Vec<StaticConstant>, bits: i64, ty: &AsmType, alignment: usize) -> String {
+    let (kind, ty) = match ty {
+        AsmType::Float => ("float", Type::Float),
+        _ => ("double", Type::Double),
+    };
+    // Built by hand so the bits go in as they are
+    let init = vec![StaticInit::Scalar(Constant { value: bits, ty })];
+    if let Some(constant) = constants.iter_mut().find(|constant| constant.init == init) {
+        constant.alignment = constant.alignment.max(alignment);
+        return constant.name.clone();
+    }
+    let name = format!(".L{}.{}", kind, constants.len());
+    constants.push(StaticConstant {
+        name: name.clone(),
+        alignment,
+        init,
+    });
+    name
+}
+
+// Hands every pseudo register a slot on the stack that's big enough for its type and suitably aligned,
+// except for static variables which already have a home in the data section
+fn replace_pseudos(instructions: Vec<Instruction>, symbols: &SymbolTable) -> (Vec<Instruction>, i64) {
+    let mut offsets: HashMap<String, i64> = HashMap::new();
+    let mut stack_size = 0;
+    let mut slot = |name: String| -> i64 {
+        *offsets.entry(name).or_insert_with_key(|name| {
+            let (size, alignment) = if name == RETURN_POINTER {
+                (8, 8)
+            } else if name == REGISTER_SAVE_AREA {
+                (SAVE_AREA_SIZE, 16)
+            } else {
+                let ty = symbols.type_of(name);
+                (symbols.size_of(ty) as i64, variable_alignment(ty, symbols) as i64)
+            };
+            stack_size = (stack_size + size + alignment - 1) / alignment * alignment;
+            -stack_size
+        })
+    };
+    let mut replace = |operand: Operand| -> Operand {
+        match operand {
+            Operand::Pseudo(name) if symbols.is_static(&name) => Operand::Data(name, 0),
+            Operand::Pseudo(name) => Operand::Stack(slot(name)),
+            Operand::PseudoMem(name, offset) if symbols.is_static(&name) => Operand::Data(name, offset),
+            Operand::PseudoMem(name, offset) => Operand::Stack(slot(name) + offset),
+            other => other,
+        }
+    };
+
+    let replaced = instructions
+        .into_iter()
+        .map(|instruction| match instruction {
+            Instruction::Mov(ty, src, dst) => Instruction::Mov(ty, replace(src), replace(dst)),
+            Instruction::Movsx(src_ty, dst_ty, src, dst) => Instruction::Movsx(src_ty, dst_ty, replace(src), replace(dst)),
+            Instruction::MovZeroExtend(src_ty, dst_ty, src, dst) => Instruction::MovZeroExtend(src_ty, dst_ty, replace(src), replace(dst)),
+            Instruction::Cvtsi2fp(src_ty, dst_ty, src, dst) => Instruction::Cvtsi2fp(src_ty, dst_ty, replace(src), replace(dst)),
+            Instruction::Cvttfp2si(src_ty, dst_ty, src, dst) => Instruction::Cvttfp2si(src_ty, dst_ty, replace(src), replace(dst)),
+            Instruction::Cvtfp2fp(src_ty, dst_ty, src, dst) => Instruction::Cvtfp2fp(src_ty, dst_ty, replace(src), replace(dst)),
+            Instruction::Lea(src, dst) => Instruction::Lea(replace(src), replace(dst)),
+            Instruction::Unary(op, ty, operand) => Instruction::Unary(op, ty, replace(operand)),
+            Instruction::Binary(op, ty, src, dst) => Instruction::Binary(op, ty, replace(src), replace(dst)),
+            Instruction::Cmp(ty, left, right) => Instruction::Cmp(ty, replace(left), replace(right)),
+            Instruction::Idiv(ty, operand) => Instruction::Idiv(ty, replace(operand)),
+            Instruction::Div(ty, operand) => Instruction::Div(ty, replace(operand)),
+            Instruction::SetCC(code, operand) => Instruction::SetCC(code, replace(operand)),
+            Instruction::Push(operand) => Instruction::Push(replace(operand)),
+            other => other,
+        })
+        .collect();
+    (replaced, stack_size)
+}
+
+fn is_memory(operand: &Operand) -> bool {
+    matches!(operand, Operand::Stack(_) | Operand::Data(..) | Operand::Got(_) | Operand::Memory(..) | Operand::Indexed(..))
+}
+
+fn is_register(operand: &Operand) -> bool {
+    matches!(operand, Operand::Register(_))
+}
+
+// x86 won't take two memory operands at once, amongst other things, so we route them through scratch registers
+fn fix_up(instructions: Vec<Instruction>, stack_size: i64) -> Vec<Instruction> {
+    let r10 = Operand::Register(Reg::R10);
+    let r11 = Operand::Register(Reg::R11);
+    let xmm15 = Operand::Register(Reg::XMM15);
+    // The stack has to stay 16 byte aligned
+    let mut fixed = vec![Instruction::AllocateStack((stack_size + 15) / 16 * 16)];
+
+    for instruction in instructions {
+        let instruction = fix_large_immediate(&mut fixed, instruction);
+        match instruction {
+            // SSE instructions want their destination, and comisd its right operand, in an xmm register
+            Instruction::Mov(ty, src, dst) if ty.is_floating() && is_memory(&src) && is_memory(&dst) => {
+                fixed.push(Instruction::Mov(ty.clone(), src, xmm15.clone()));
+                fixed.push(Instruction::Mov(ty, xmm15.clone(), dst));
+            }
+            Instruction::Binary(op, ty, src, dst) if ty.is_floating() && !is_register(&dst) => {
+                fixed.push(Instruction::Mov(ty.clone(), dst.clone(), xmm15.clone()));
+                fixed.push(Instruction::Binary(op, ty.clone(), src, xmm15.clone()));
+                fixed.push(Instruction::Mov(ty, xmm15.clone(), dst));
+            }
+            Instruction::Cmp(ty, left, right) if ty.is_floating() && !is_register(&right) => {
+                fixed.push(Instruction::Mov(ty.clone(), right, xmm15.clone()));
+                fixed.push(Instruction::Cmp(ty, left, xmm15.clone()));
+            }
+            Instruction::Cvtsi2fp(src_ty, dst_ty, src, dst) => {
+                let src = immediate_to_register(&mut fixed, &src_ty, src);
+                if is_register(&dst) {
+                    fixed.push(Instruction::Cvtsi2fp(src_ty, dst_ty, src, dst));
+                } else {
+                    fixed.push(Instruction::Cvtsi2fp(src_ty, dst_ty.clone(), src, xmm15.clone()));
+                    fixed.push(Instruction::Mov(dst_ty, xmm15.clone(), dst));
+                }
+            }
+            Instruction::Cvttfp2si(src_ty, dst_ty, src, dst) if !is_register(&dst) => {
+                fixed.push(Instruction::Cvttfp2si(src_ty, dst_ty.clone(), src, r11.clone()));
+                fixed.push(Instruction::Mov(dst_ty, r11.clone(), dst));
+            }
+            Instruction::Cvtfp2fp(src_ty, dst_ty, src, dst) if !is_register(&dst) => {
+                fixed.push(Instruction::Cvtfp2fp(src_ty, dst_ty.clone(), src, xmm15.clone()));
+                fixed.push(Instruction::Mov(dst_ty, xmm15.clone(), dst));
+            }
+            Instruction::Lea(src, dst) if !is_register(&dst) => {
+                fixed.push(Instruction::Lea(src, r11.clone()));
+                fixed.push(Instruction::Mov(AsmType::Quadword, r11.clone(), dst));
+            }
+            Instruction::Mov(ty, src, dst) if is_memory(&src) && is_memory(&dst) => {
+                fixed.push(Instruction::Mov(ty.clone(), src, r10.clone()));
+                fixed.push(Instruction::Mov(ty, r10.clone(), dst));
+            }
+            // Widening moves can't take an immediate and have to land in a register
+            Instruction::Movsx(src_ty, dst_ty, src, dst) => {
+                let src = immediate_to_register(&mut fixed, &src_ty, src);
+                fixed.push(Instruction::Movsx(src_ty, dst_ty.clone(), src, r11.clone()));
+                fixed.push(Instruction::Mov(dst_ty, r11.clone(), dst));
+            }
+            // Any move into a 32 bit register clears the upper half, so there's no instruction for this one
+            Instruction::MovZeroExtend(AsmType::Longword, AsmType::Quadword, src, dst) => {
+                if let Operand::Register(_) = dst {
+                    fixed.push(Instruction::Mov(AsmType::Longword, src, dst));
+                } else {
+                    fixed.push(Instruction::Mov(AsmType::Longword, src, r11.clone()));
+                    fixed.push(Instruction::Mov(AsmType::Quadword, r11.clone(), dst));
+                }
+            }
+            Instruction::MovZeroExtend(src_ty, dst_ty, src, dst) => {
+                let src = immediate_to_register(&mut fixed, &src_ty, src);
+                fixed.push(Instruction::MovZeroExtend(src_ty, dst_ty.clone(), src, r11.clone()));
+                fixed.push(Instruction::Mov(dst_ty, r11.clone(), dst));
+            }
+            Instruction::Idiv(ty, Operand::Imm(value)) => {
+                fixed.push(Instruction::Mov(ty.clone(), Operand::Imm(value), r10.clone()));
+                fixed.push(Instruction::Idiv(ty, r10.clone()));
+            }
+            Instruction::Div(ty, Operand::Imm(value)) => {
+                fixed.push(Instruction::Mov(ty.clone(), Operand::Imm(value), r10.clone()));
+                fixed.push(Instruction::Div(ty, r10.clone()));
+            }
+            Instruction::Binary(AsmBinaryOp::Mult, ty, src, dst) if is_memory(&dst) => {
+                fixed.push(Instruction::Mov(ty.clone(), dst.clone(), r11.clone()));
+                fixed.push(Instruction::Binary(AsmBinaryOp::Mult, ty.clone(), src, r11.clone()));
+                fixed.push(Instruction::Mov(ty, r11.clone(), dst));
+            }
+            // Shifting by a variable amount only works with the count in %cl
+            Instruction::Binary(op @ (AsmBinaryOp::Sal | AsmBinaryOp::Sar | AsmBinaryOp::Shr), ty, src, dst) if !matches!(src, Operand::Imm(_)) => {
+                fixed.push(Instruction::Mov(AsmType::Longword, src, Operand::Register(Reg::CX)));
+                fixed.push(Instruction::Binary(op, ty, Operand::Register(Reg::CX), dst));
+            }
+            Instruction::Binary(op, ty, src, dst) if is_memory(&src) && is_memory(&dst) => {
+                fixed.push(Instruction::Mov(ty.clone(), src, r10.clone()));
+                fixed.push(Instruction::Binary(op, ty, r10.clone(), dst));
+            }
+            Instruction::Cmp(ty, left, right) if is_memory(&left) && is_memory(&right) => {
+                fixed.push(Instruction::Mov(ty.clone(), left, r10.clone()));
+                fixed.push(Instruction::Cmp(ty, r10.clone(), right));
+            }
+            Instruction::Cmp(ty, left, Operand::Imm(value)) => {
+                fixed.push(Instruction::Mov(ty.clone(), Operand::Imm(value), r11.clone()));
+                fixed.push(Instruction::Cmp(ty, left, r11.clone()));
+            }
+            other => fixed.push(other),
+        }
+    }
+    fixed
+}
+
+fn is_large_immediate(operand: &Operand) -> bool {
+    matches!(operand, Operand::Imm(value) if i32::try_from(*value).is_err())
+}
+
+// Only mov can take a 64 bit immediate, and only into a register
+fn fix_large_immediate(fixed: &mut Vec<Instruction>, instruction: Instruction) -> Instruction {
+    let mut hoist = |operand: Operand| {
+        if is_large_immediate(&operand) {
+            fixed.push(Instruction::Mov(AsmType::Quadword, operand, Operand::Register(Reg::R10)));
+            Operand::Register(Reg::R10)
+        } else {
+            operand
+        }
+    };
+    match instruction {
+        Instruction::Mov(AsmType::Quadword, src, dst) if is_memory(&dst) => Instruction::Mov(AsmType::Quadword, hoist(src), dst),
+        Instruction::Binary(op, AsmType::Quadword, src, dst) => Instruction::Binary(op, AsmType::Quadword, hoist(src), dst),
+        Instruction::Cmp(AsmType::Quadword, left, right) => Instruction::Cmp(AsmType::Quadword, hoist(left), right),
+        Instruction::Push(operand) => Instruction::Push(hoist(operand)),
+        other => other,
+    }
+}
+
+fn immediate_to_register(fixed: &mut Vec<Instruction>, ty: &AsmType, operand: Operand) -> Operand {
+    match operand {
+        Operand::Imm(value) => {
+            fixed.push(Instruction::Mov(ty.clone(), Operand::Imm(value), Operand::Register(Reg::R10)));
+            Operand::Register(Reg::R10)
+        }
+        operand => operand,
+    }
+}
@@ -0,0 +1,303 @@
+use crate::assembly::*;
+use crate::type_checker::StaticInit;
+use std::fmt::Write;
+
+// Turns the assembly tree into AT&T syntax that the system assembler understands
+pub fn emit(program: &Program) -> String {
+    let mut out = String::new();
+    for function in program.functions.iter() {
+        emit_function(&mut out, function);
+    }
+    for variable in program.static_variables.iter() {
+        emit_static_variable(&mut out, variable);
+    }
+    for constant in program.static_constants.iter() {
+        emit_static_constant(&mut out, constant);
+    }
+    // Without this the linker assumes we need an executable stack
+    out.push_str("    .section .note.GNU-stack,\"\",@progbits\n");
+    out
+}
+
+// Labels from the source are only unique within their function, so every label carries its function's name
+fn local_label(function: &str, label: &str) -> String {
+    format!(".L{}.{}", function, label)
+}
+
+fn register(reg: &Reg, size: u8) -> &'static str {
+    // xmm registers go by the same name whatever we're using them for
+    let xmm = match reg {
+        Reg::XMM0 => Some("%xmm0"),
+        Reg::XMM1 => Some("%xmm1"),
+        Reg::XMM2 => Some("%xmm2"),
+        Reg::XMM3 => Some("%xmm3"),
+        Reg::XMM4 => Some("%xmm4"),
+        Reg::XMM5 => Some("%xmm5"),
+        Reg::XMM6 => Some("%xmm6"),
+        Reg::XMM7 => Some("%xmm7"),
+        Reg::XMM14 => Some("%xmm14"),
+        Reg::XMM15 => Some("%xmm15"),
+        _ => None,
+    };
+    if let Some(name) = xmm {
+        return name;
+    }
+
+    // Names for the 1, 2, 4 and 8 byte views of every register
+    let names = match reg {
+        Reg::AX => ["%al", "%ax", "%eax", "%rax"],
+        Reg::CX => ["%cl", "%cx", "%ecx", "%rcx"],
+        Reg::DX => ["%dl", "%dx", "%edx", "%rdx"],
+        Reg::DI => ["%dil", "%di", "%edi", "%rdi"],
+        Reg::SI => ["%sil", "%si", "%esi", "%rsi"],
+        Reg::R8 => ["%r8b", "%r8w", "%r8d", "%r8"],
+        Reg::R9 => ["%r9b", "%r9w", "%r9d", "%r9"],
+        Reg::R10 => ["%r10b", "%r10w", "%r10d", "%r10"],
+        Reg::R11 => ["%r11b", "%r11w", "%r11d", "%r11"],
+        _ => unreachable!(),
+    };
+    match size {
+        1 => names[0],
+        2 => names[1],
+        4 => names[2],
+        _ => names[3],
+    }
+}
+
+fn operand(operand: &Operand, size: u8) -> String {
+    match operand {
+        Operand::Imm(value) => format!("${}", value),
+        Operand::Register(reg) => register(reg, size).to_string(),
+        Operand::Stack(offset) => format!("{}(%rbp)", offset),
+        Operand::Data(name, 0) => format!("{}(%rip)", name),
+        Operand::Data(name, offset) => format!("{}{:+}(%rip)", name, offset),
+        Operand::Got(name) => format!("{}@GOTPCREL(%rip)", name),
+        // Addresses always use the full width of their registers
+        Operand::Memory(base, offset) => format!("{}({})", offset, register(base, 8)),
+        Operand::Indexed(base, index, scale) => format!("({},{},{})", register(base, 8), register(index, 8), scale),
+        Operand::Pseudo(name) | Operand::PseudoMem(name, _) => panic!("Pseudo register {} survived until emission", name),
+    }
+}
+
+fn suffix(ty: &AsmType) -> &'static str {
+    match ty {
+        AsmType::Byte => "b",
+        AsmType::Word => "w",
+        AsmType::Longword => "l",
+        AsmType::Quadword => "q",
+        AsmType::Float => "ss",
+        AsmType::Double => "sd",
+    }
+}
+
+fn condition(code: &CondCode) -> &'static str {
+    match code {
+        CondCode::E => "e",
+        CondCode::NE => "ne",
+        CondCode::G => "g",
+        CondCode::GE => "ge",
+        CondCode::L => "l",
+        CondCode::LE => "le",
+        CondCode::A => "a",
+        CondCode::AE => "ae",
+        CondCode::B => "b",
+        CondCode::BE => "be",
+        CondCode::P => "p",
+        CondCode::NP => "np",
+    }
+}
+
+fn emit_function(out: &mut String, function: &Function) {
+    if function.global {
+        let _ = writeln!(out, "    .globl {}", function.name);
+    }
+    let _ = writeln!(out, "    .text");
+    let _ = writeln!(out, "{}:", function.name);
+    let _ = writeln!(out, "    pushq %rbp");
+    let _ = writeln!(out, "    movq %rsp, %rbp");
+    for instruction in function.instructions.iter() {
+        emit_instruction(out, &function.name, instruction);
+    }
+}
+
+fn init_size(init: &StaticInit) -> usize {
+    match init {
+        StaticInit::Scalar(constant) => constant.ty.size(),
+        StaticInit::Zero(size) => *size,
+        StaticInit::Address(..) => 8,
+        StaticInit::String(value) => value.len(),
+    }
+}
+
+// Zero initialized variables go in .bss so they don't take up space in the object file
+fn emit_static_variable(out: &mut String, variable: &StaticVariable) {
+    if variable.global {
+        let _ = writeln!(out, "    .globl {}", variable.name);
+    }
+    let all_zero = variable.init.iter().all(|init| match init {
+        StaticInit::Scalar(constant) => constant.value == 0,
+        StaticInit::Zero(_) => true,
+        StaticInit::Address(..) => false,
+        StaticInit::String(value) => value.iter().all(|byte| *byte == 0),
+    });
+    if all_zero {
+        let _ = writeln!(out, "    .bss");
+    } else {
+        let _ = writeln!(out, "    .data");
+    }
+    let _ = writeln!(out, "    .balign {}", variable.alignment);
+    let _ = writeln!(out, "{}:", variable.name);
+    if all_zero {
+        let _ = writeln!(out, "    .zero {}", variable.init.iter().map(init_size).sum::<usize>());
+        return;
+    }
+    emit_inits(out, &variable.init);
+}
+
+fn emit_static_constant(out: &mut String, constant: &StaticConstant) {
+    let _ = writeln!(out, "    .section .rodata");
+    let _ = writeln!(out, "    .balign {}", constant.alignment);
+    let _ = writeln!(out, "{}:", constant.name);
+    emit_inits(out, &constant.init);
+}
+
+fn emit_inits(out: &mut String, inits: &[StaticInit]) {
+    for init in inits.iter() {
+        let _ = match init {
+            StaticInit::Zero(size) => writeln!(out, "    .zero {}", size),
+            StaticInit::Address(symbol, 0) => writeln!(out, "    .quad {}", symbol),
+            StaticInit::Address(symbol, offset) => writeln!(out, "    .quad {}{:+}", symbol, offset),
+            StaticInit::Scalar(constant) => match constant.ty.size() {
+                1 => writeln!(out, "    .byte {}", constant.value),
+                2 => writeln!(out, "    .short {}", constant.value),
+                // Floats and doubles are written out as their bits so they come back exactly as they went in
+                4 => writeln!(out, "    .long {}", constant.value),
+                _ => writeln!(out, "    .quad {}", constant.value),
+            },
+            StaticInit::String(value) => writeln!(out, "    .ascii \"{}\"", escape(value)),
+        };
+    }
+}
+
+// The assembler reads strings the way C does, anything that isn't plain text is safest as an octal escape
+fn escape(value: &[u8]) -> String {
+    value
+        .iter()
+        .map(|byte| match byte {
+            b'"' | b'\\' => format!("\\{}", *byte as char),
+            b' '..=b'~' => (*byte as char).to_string(),
+            _ => format!("\\{:03o}", byte),
+        })
+        .collect()
+}
+
+fn emit_instruction(out: &mut String, function: &str, instruction: &Instruction) {
+    let local_label = |label: &str| local_label(function, label);
+    let _ = match instruction {
+        Instruction::Mov(ty, src, dst) => {
+            writeln!(out, "    mov{} {}, {}", suffix(ty), operand(src, ty.size()), operand(dst, ty.size()))
+        }
+        Instruction::Movsx(src_ty, dst_ty, src, dst) => writeln!(out, "    movs{}{} {}, {}",
+            suffix(src_ty), suffix(dst_ty), operand(src, src_ty.size()), operand(dst, dst_ty.size())),
+        Instruction::MovZeroExtend(src_ty, dst_ty, src, dst) => writeln!(out, "    movz{}{} {}, {}",
+            suffix(src_ty), suffix(dst_ty), operand(src, src_ty.size()), operand(dst, dst_ty.size())),
+        Instruction::Cvtsi2fp(src_ty, dst_ty, src, dst) => writeln!(out, "    cvtsi2{}{} {}, {}",
+            suffix(dst_ty), suffix(src_ty), operand(src, src_ty.size()), operand(dst, 8)),
+        Instruction::Cvttfp2si(src_ty, dst_ty, src, dst) => writeln!(out, "    cvtt{}2si{} {}, {}",
+            suffix(src_ty), suffix(dst_ty), operand(src, 8), operand(dst, dst_ty.size())),
+        Instruction::Cvtfp2fp(src_ty, dst_ty, src, dst) => writeln!(out, "    cvt{}2{} {}, {}",
+            suffix(src_ty), suffix(dst_ty), operand(src, 8), operand(dst, 8)),
+        Instruction::Lea(src, dst) => writeln!(out, "    leaq {}, {}", operand(src, 8), operand(dst, 8)),
+        Instruction::Unary(op, ty, dst) => {
+            let name = match op {
+                AsmUnaryOp::Neg => "neg",
+                AsmUnaryOp::Not => "not",
+            };
+            writeln!(out, "    {}{} {}", name, suffix(ty), operand(dst, ty.size()))
+        }
+        // The packed forms of the bitwise operators are the only ones SSE has
+        Instruction::Binary(op, ty @ (AsmType::Float | AsmType::Double), src, dst) => {
+            let name = match op {
+                AsmBinaryOp::Add => "add",
+                AsmBinaryOp::Sub => "sub",
+                AsmBinaryOp::Mult => "mul",
+                AsmBinaryOp::DivDouble => "div",
+                AsmBinaryOp::And => "and",
+                AsmBinaryOp::Or => "or",
+                AsmBinaryOp::Xor => "xor",
+                _ => panic!("{:?} doesn't work on {:?}", op, ty),
+            };
+            let suffix = match (op, ty) {
+                (AsmBinaryOp::And | AsmBinaryOp::Or | AsmBinaryOp::Xor, AsmType::Float) => "ps",
+                (AsmBinaryOp::And | AsmBinaryOp::Or | AsmBinaryOp::Xor, _) => "pd",
+                _ => suffix(ty),
+            };
+            writeln!(out, "    {}{} {}, {}", name, suffix, operand(src, 8), operand(dst, 8))
+        }
+        Instruction::Binary(op, ty, src, dst) => {
+            let name = match op {
+                AsmBinaryOp::Add => "add",
+                AsmBinaryOp::Sub => "sub",
+                AsmBinaryOp::Mult => "imul",
+                AsmBinaryOp::And => "and",
+                AsmBinaryOp::Or => "or",
+                AsmBinaryOp::Xor => "xor",
+                AsmBinaryOp::Sal => "sal",
+                AsmBinaryOp::Sar => "sar",
+                AsmBinaryOp::Shr => "shr",
+                AsmBinaryOp::DivDouble => panic!("divsd only works on doubles"),
+            };
+            // The shift count always lives in a byte register
+            let src_size = match op {
+                AsmBinaryOp::Sal | AsmBinaryOp::Sar | AsmBinaryOp::Shr => 1,
+                _ => ty.size(),
+            };
+            writeln!(out, "    {}{} {}, {}", name, suffix(ty), operand(src, src_size), operand(dst, ty.size()))
+        }
+        Instruction::Cmp(ty @ (AsmType::Float | AsmType::Double), left, right) => {
+            writeln!(out, "    comi{} {}, {}", suffix(ty), operand(left, 8), operand(right, 8))
+        }
+        Instruction::Cmp(ty, left, right) => {
+            writeln!(out, "    cmp{} {}, {}", suffix(ty), operand(left, ty.size()), operand(right, ty.size()))
+        }
+        Instruction::Idiv(ty, divisor) => writeln!(out, "    idiv{} {}", suffix(ty), operand(divisor, ty.size())),
+        Instruction::Div(ty, divisor) => writeln!(out, "    div{} {}", suffix(ty), operand(divisor, ty.size())),
+        Instruction::Cdq(AsmType::Quadword) => writeln!(out, "    cqo"),
+        Instruction::Cdq(_) => writeln!(out, "    cdq"),
+        Instruction::Jmp(label) => writeln!(out, "    jmp {}", local_label(label)),
+        Instruction::JmpCC(code, label) => writeln!(out, "    j{} {}", condition(code), local_label(label)),
+        Instruction::SetCC(code, dst) => writeln!(out, "    set{} {}", condition(code), operand(dst, 1)),
+        Instruction::Label(label) => writeln!(out, "{}:", local_label(label)),
+        Instruction::JmpTable(table, targets) => {
+            // The table holds offsets from its own start so it doesn't need relocating in a PIE
+            let table = local_label(table);
+            let _ = writeln!(out, "    leaq {}(%rip), %rcx", table);
+            let _ = writeln!(out, "    movslq (%rcx,%rax,4), %rdx");
+            let _ = writeln!(out, "    addq %rcx, %rdx");
+            let _ = writeln!(out, "    jmp *%rdx");
+            let _ = writeln!(out, "    .section .rodata");
+            let _ = writeln!(out, "    .balign 4");
+            let _ = writeln!(out, "{}:", table);
+            for target in targets.iter() {
+                let _ = writeln!(out, "    .long {} - {}", local_label(target), table);
+            }
+            writeln!(out, "    .text")
+        }
+        Instruction::AllocateStack(size) => writeln!(out, "    subq ${}, %rsp", size),
+        Instruction::DeallocateStack(size) => writeln!(out, "    addq ${}, %rsp", size),
+        Instruction::Push(value) => writeln!(out, "    pushq {}", operand(value, 8)),
+        Instruction::Call { name, plt } => {
+            if *plt {
+                writeln!(out, "    call {}@PLT", name)
+            } else {
+                writeln!(out, "    call {}", name)
+            }
+        }
+        Instruction::CallIndirect(function) => writeln!(out, "    call *{}", operand(function, 8)),
+        Instruction::Ret => {
+            let _ = writeln!(out, "    movq %rbp, %rsp");
+            let _ = writeln!(out, "    popq %rbp");
+            writeln!(out, "    ret")
+        }
+    };
+}
@@ -0,0 +1,179 @@
+use crate::assembly;
+use crate::emitter;
+use crate::lexer::LexerToken;
+use crate::parsing::Parser;
+use crate::preprocessor::Preprocessor;
+use crate::resolver::Resolver;
+use crate::tacky::{Program, TackyGenerator};
+use crate::target::Target;
+use crate::type_checker::{SymbolTable, TypeChecker};
+use std::collections::LinkedList;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Runs everything up to and including TACKY generation, on source that isn't in a file
+pub fn compile_to_tacky(source: &str) -> (Program, SymbolTable) {
+    let target = Target::default();
+    let mut preprocessor = Preprocessor::new(Vec::new(), &target);
+    preprocessor.preprocess(source);
+    tokens_to_tacky(preprocessor.tokens, target)
+}
+
+// The preprocessor and the type checker are handed the same target, so they agree on how big every type is
+fn tokens_to_tacky(tokens: LinkedList<LexerToken>, target: Target) -> (Program, SymbolTable) {
+    let mut parser = Parser::new(tokens);
+    parser.start_parsing();
+
+    let mut resolver = Resolver::new();
+    resolver.resolve_program(&mut parser.parsed_tokens);
+
+    let mut checker = TypeChecker::for_target(target);
+    checker.check_program(&mut parser.parsed_tokens);
+
+    let (program, symbols) = TackyGenerator::new(checker.symbols).generate(&parser.parsed_tokens);
+    for warning in &program.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    (program, symbols)
+}
+
+pub fn compile(source: &str) -> String {
+    let (tacky, symbols) = compile_to_tacky(source);
+    let asm = assembly::generate(&tacky, &symbols);
+    emitter::emit(&asm)
+}
+
+// Locations in diagnostics name the file, and quoted includes are looked for next to it first
+pub fn compile_file(path: &Path, include_paths: &[PathBuf]) -> String {
+    let target = Target::default();
+    let mut preprocessor = Preprocessor::new(include_paths.to_vec(), &target);
+    preprocessor.preprocess_file(path);
+    let (tacky, symbols) = tokens_to_tacky(preprocessor.tokens, target);
+    let asm = assembly::generate(&tacky, &symbols);
+    emitter::emit(&asm)
+}
+
+// We leave assembling and linking up to the system toolchain
+pub fn assemble(assembly: &Path, object: &Path) {
+    let status = Command::new("as")
+        .arg(assembly)
+        .arg("-o")
+        .arg(object)
+        .status()
+        .expect("Unable to run as");
+    if !status.success() {
+        panic!("Unable to assemble {}", assembly.display())
+    }
+}
+
+// gcc brings in the C library and the start-up code along with the objects
+pub fn link(objects: &[PathBuf], output: &Path) {
+    let status = Command::new("gcc")
+        .args(objects)
+        .arg("-o")
+        .arg(output)
+        .status()
+        .expect("Unable to run gcc");
+    if !status.success() {
+        panic!("Unable to link {}", output.display())
+    }
+}
+
+// Where to stop, everything up to it is done for every input that needs it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    // -S, assembly for every source file
+    Assembly,
+    // -c, an object file for every source and assembly file
+    Object,
+    Executable,
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    // .c files are compiled, .s files assembled and .o files only linked
+    pub inputs: Vec<PathBuf>,
+    pub include_paths: Vec<PathBuf>,
+    pub stage: Stage,
+    // Without -o what comes out goes next to the input, an executable is named after the first one
+    pub output: Option<PathBuf>,
+}
+
+// Keeps the scratch files of builds running at the same time apart
+static BUILDS: AtomicUsize = AtomicUsize::new(0);
+
+// Files in between stages, removed once the build is over whether it got to the end or not
+struct Scratch(Vec<PathBuf>);
+
+impl Scratch {
+    // Taken down before anything is written to it, so a half written file goes too
+    fn add(&mut self, file: PathBuf) -> PathBuf {
+        self.0.push(file.clone());
+        file
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        for file in &self.0 {
+            let _ = fs::remove_file(file);
+        }
+    }
+}
+
+// Every source file is a translation unit of its own and is compiled without knowing about the others,
+// so objects from anywhere can be linked with ours. Files in between stages go in the temporary directory
+pub fn build(options: &Options) {
+    if options.output.is_some() && options.stage != Stage::Executable && options.inputs.len() > 1 {
+        panic!("-o can't be used with -c or -S when there is more than one input file")
+    }
+    let build = BUILDS.fetch_add(1, Ordering::SeqCst);
+    let mut objects = Vec::new();
+    let mut scratch = Scratch(Vec::new());
+    for (index, input) in options.inputs.iter().enumerate() {
+        let stem = input.file_stem().map_or("input".into(), |stem| stem.to_string_lossy());
+        let scratch_file = |extension: &str| {
+            std::env::temp_dir().join(format!("compiler_{}_{}_{}_{}.{}", std::process::id(), build, index, stem, extension))
+        };
+        let kept = |extension: &str| options.output.clone().unwrap_or_else(|| input.with_extension(extension));
+
+        let assembly = match input.extension().and_then(|extension| extension.to_str()) {
+            Some("c") => {
+                let text = compile_file(input, &options.include_paths);
+                let assembly = if options.stage == Stage::Assembly { kept("s") } else { scratch.add(scratch_file("s")) };
+                fs::write(&assembly, text).unwrap_or_else(|_| panic!("Unable to write {}", assembly.display()));
+                if options.stage == Stage::Assembly {
+                    continue;
+                }
+                assembly
+            }
+            Some("s") if options.stage == Stage::Assembly => continue,
+            Some("s") => input.clone(),
+            // Object files only matter to the linker
+            Some("o") => {
+                if options.stage == Stage::Executable {
+                    objects.push(input.clone());
+                }
+                continue;
+            }
+            _ => panic!("Don't know what to do with input file {}", input.display()),
+        };
+        if options.stage == Stage::Object {
+            assemble(&assembly, &kept("o"));
+            continue;
+        }
+        let object = scratch.add(scratch_file("o"));
+        assemble(&assembly, &object);
+        objects.push(object);
+    }
+
+    if options.stage == Stage::Executable {
+        let output = match options.output.as_ref() {
+            Some(output) => output.clone(),
+            None => options.inputs.first().expect("Nothing to build").with_extension(""),
+        };
+        link(&objects, &output);
+    }
+}
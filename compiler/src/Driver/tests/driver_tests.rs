@@ -0,0 +1,1452 @@
+#[cfg(test)]
+mod driver_tests {
+    use crate::driver::{build, compile, Options, Stage};
+    use std::fs;
+    use std::ops::Deref;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    // A directory of its own for every test that builds from files, removed with everything in it
+    // whether the test passes or not
+    struct TempDir(PathBuf);
+
+    impl Deref for TempDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn directory(name: &str) -> TempDir {
+        let dir = std::env::temp_dir().join(format!("compiler_driver_tests_{}_{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    // Compiles the program all the way to an executable, runs it and hands back the exit code
+    fn run(str: &str) -> i32 {
+        let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+        let dir = directory(&format!("run_{}", id));
+        let source = dir.join("test.c");
+        let executable = dir.join("test");
+
+        fs::write(&source, str).unwrap();
+        build(&Options {
+            inputs: vec![source.clone()],
+            include_paths: Vec::new(),
+            stage: Stage::Executable,
+            output: Some(executable.clone()),
+        });
+        let status = Command::new(&executable).status().unwrap();
+        status.code().unwrap()
+    }
+
+    #[test]
+    fn return_constant() {
+        assert_eq!(run("int main(void) { return 42; }"), 42);
+    }
+
+    #[test]
+    fn expressions_and_locals() {
+        let str = r#"
+        int main(void) {
+            int a = 7;
+            int b = a * 3 - -2;
+            int c;
+            c = b % 5 + (b / 5 << 2);
+            return (c ^ 1) | (~a & 8) + (a >= 7) + !b;
+        }
+        "#;
+        assert_eq!(run(str), (((23 % 5) + ((23 / 5) << 2)) ^ 1) | ((!7 & 8) + 1));
+    }
+
+    #[test]
+    fn compound_assignment() {
+        let str = r#"
+        int main(void) {
+            int a = 10;
+            int b = 3;
+            a += b;
+            a -= 1;
+            a *= 4;
+            a /= 5;
+            a %= 7;
+            int c = 12;
+            c &= 10;
+            c |= 5;
+            c ^= 3;
+            c <<= 2;
+            c >>= 1;
+            int d = 1;
+            int e = (d += 4) * 2;
+            a = b += 2;
+            return (a == 5) + (b == 5) * 2 + (c == 28) * 4 + (d == 5) * 8 + (e == 10) * 16;
+        }
+        "#;
+        assert_eq!(run(str), 31);
+    }
+
+    #[test]
+    fn increment_and_decrement() {
+        let str = r#"
+        int main(void) {
+            int x = 5;
+            int a = x++;
+            int b = ++x;
+            int c = x--;
+            int d = --x;
+            int e = -x++;
+            return (a == 5) + (b == 7) * 2 + (c == 7) * 4 + (d == 5) * 8 + (e == -5) * 16 + (x == 6) * 32;
+        }
+        "#;
+        assert_eq!(run(str), 63);
+    }
+
+    #[test]
+    fn logical_operators() {
+        let str = r#"
+        int main(void) {
+            int a = (5 && 7) == 1;
+            int b = (0 || -3) == 1;
+            int c = (0 && 1) == 0;
+            int d = (0 || 0) == 0;
+            int e = (2 > 1 && 3 > 2 || 0) == 1;
+            return a + b * 2 + c * 4 + d * 8 + e * 16;
+        }
+        "#;
+        assert_eq!(run(str), 31);
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        // The right operand only runs when the left operand doesn't decide the result
+        let str = r#"
+        int main(void) {
+            int x = 0;
+            0 && (x = 1);
+            1 || (x += 2);
+            1 && (x += 4);
+            0 || (x += 8);
+            int y = 0;
+            int z = (y++ && y++) + (y++ || y++);
+            return x + y * 16 + z * 64;
+        }
+        "#;
+        assert_eq!(run(str), 12 + 2 * 16 + 64);
+    }
+
+    #[test]
+    fn switch_fallthrough_and_default() {
+        let str = r#"
+        int main(void) {
+            int x = 1;
+            int result = 0;
+            switch (x) {
+                case 0: result = result + 1;
+                case 1: result = result + 10;
+                case 2: result = result + 100;
+                    break;
+                default: result = result + 1000;
+            }
+            switch (x + 5) {
+                case 0: result = 0;
+                default: result = result + 1;
+                case 1: result = result + 2;
+            }
+            return result;
+        }
+        "#;
+        assert_eq!(run(str), 113);
+    }
+
+    #[test]
+    fn switch_without_matching_case() {
+        let str = r#"
+        int main(void) {
+            int x = 9;
+            switch (x) {
+                case 1: return 1;
+                case 2: return 2;
+            }
+            switch (x) ;
+            return 5;
+        }
+        "#;
+        assert_eq!(run(str), 5);
+    }
+
+    #[test]
+    fn switch_jump_table() {
+        // Dense enough for a jump table, including negative values, holes and values out of range either side
+        let program = |x: i32| format!(r#"
+        int main(void) {{
+            int x = {};
+            switch (x) {{
+                case -2: return 1;
+                case -1: return 2;
+                case 0: return 3;
+                case 2: return 4;
+                case 3: {{
+                    int y = 5;
+                    return y;
+                }}
+                default: return 6;
+            }}
+        }}
+        "#, x);
+        assert_eq!(run(&program(-2)), 1);
+        assert_eq!(run(&program(-1)), 2);
+        assert_eq!(run(&program(0)), 3);
+        assert_eq!(run(&program(1)), 6);
+        assert_eq!(run(&program(2)), 4);
+        assert_eq!(run(&program(3)), 5);
+        assert_eq!(run(&program(-3)), 6);
+        assert_eq!(run(&program(4)), 6);
+    }
+
+    #[test]
+    fn goto_loop() {
+        // Sums 1 through 10 with a backwards goto, using a switch as the loop condition
+        let str = r#"
+        int main(void) {
+            int i = 0;
+            int sum = 0;
+        loop:
+            i = i + 1;
+            sum = sum + i;
+            switch (i) {
+                case 10: goto done;
+            }
+            goto loop;
+        done:
+            return sum;
+        }
+        "#;
+        assert_eq!(run(str), 55);
+    }
+
+    #[test]
+    fn goto_forward_into_switch() {
+        let str = r#"
+        int helper(void) {
+            goto end;
+        end:
+            return 1;
+        }
+        int main(void) {
+            int x = 0;
+            goto inside;
+            switch (x) {
+                case 0: x = 100;
+                inside:
+                case 1: x = x + 3;
+            }
+            goto end;
+            x = 50;
+        end:
+            return x;
+        }
+        "#;
+        assert_eq!(run(str), 3);
+    }
+
+    #[test]
+    fn nested_switch() {
+        let str = r#"
+        int main(void) {
+            int state = 2;
+            int steps = 0;
+            switch (state) {
+                case 1: steps = 100;
+                case 2:
+                    switch (steps) {
+                        case 0: steps = 7; break;
+                        case 7: steps = 99;
+                    }
+                    steps = steps * 2;
+                    break;
+                case 3: steps = 50;
+            }
+            return steps;
+        }
+        "#;
+        assert_eq!(run(str), 14);
+    }
+
+    #[test]
+    fn function_calls() {
+        // More arguments than there are registers, so some of them go on the stack
+        let str = r#"
+        int add(int a, int b);
+
+        int weigh(int a, int b, int c, int d, int e, int f, int g, int h) {
+            return a + 2 * b + 3 * c + 4 * d + 5 * e + 6 * f + 7 * g + 8 * h;
+        }
+
+        int main(void) {
+            int first = weigh(1, 1, 1, 1, 1, 1, 1, 2);
+            int second = weigh(1, 0, 0, 0, 0, 0, 3, 0);
+            return add(first, second);
+        }
+
+        int add(int a, int b) {
+            return a + b;
+        }
+        "#;
+        assert_eq!(run(str), 44 + 22);
+    }
+
+    #[test]
+    fn library_call() {
+        assert_eq!(run("int putchar(int c); int main(void) { return putchar(65) == 65; }"), 1);
+    }
+
+    #[test]
+    fn char_conversions() {
+        // Storing into a char truncates, reading it back sign extends
+        let str = r#"
+        char narrow(int x) {
+            return x;
+        }
+
+        int main(void) {
+            char c = 300;
+            int big = c;
+            char n = narrow(255);
+            char step = 127;
+            step++;
+            return (big == 44) + ((n == -1) << 1) + ((step < 0) << 2) + ((c * 2 == 88) << 3);
+        }
+        "#;
+        assert_eq!(run(str), 15);
+    }
+
+    #[test]
+    fn file_scope_variables() {
+        let str = r#"
+        int counter;
+        static int step = 3;
+        char small = 200;
+        extern int counter;
+
+        static int bump(void) {
+            counter += step;
+            return counter;
+        }
+
+        int main(void) {
+            bump();
+            bump();
+            extern int later;
+            return counter + later + (small == -56);
+        }
+
+        int later = 100;
+        "#;
+        assert_eq!(run(str), 107);
+    }
+
+    #[test]
+    fn static_data_sections() {
+        let assembly = compile("int zero; static int five = 5; extern int other; int main(void) { return five + other; }");
+        assert!(assembly.contains("    .globl zero\n    .bss\n    .balign 4\nzero:\n    .zero 4\n"));
+        assert!(assembly.contains("    .data\n    .balign 4\nfive:\n    .long 5\n"));
+        assert!(!assembly.contains(".globl five"));
+        assert!(!assembly.contains("other:"));
+        assert!(assembly.contains("five(%rip)"));
+    }
+
+    #[test]
+    fn static_locals() {
+        // Each static keeps its value between calls, even when they share a name
+        let str = r#"
+        int count(void) {
+            static int calls = 10;
+            calls += 1;
+            return calls;
+        }
+
+        int other(void) {
+            static int calls;
+            {
+                static char inner = 1;
+                inner *= 2;
+                calls += inner;
+            }
+            return calls;
+        }
+
+        int main(void) {
+            count();
+            count();
+            other();
+            other();
+            return count() + other();
+        }
+        "#;
+        assert_eq!(run(str), 13 + 2 + 4 + 8);
+
+        let assembly = compile("int main(void) { static int hidden = 4; return hidden; }");
+        assert!(!assembly.contains(".globl hidden"));
+    }
+
+    #[test]
+    fn long_arithmetic() {
+        let str = r#"
+        long big = 4294967296L;
+
+        long sum(long a, int b, long c, int d, long e, int f, long g, int h) {
+            return a + b + c + d + e + f + g + h;
+        }
+
+        int main(void) {
+            long x = big * 3;
+            long y = x / -2;
+            int truncated = x + 5;
+            long widened = -7;
+            long shifted = 1L << 40;
+            int checks = (x == 12884901888L)
+                + ((y == -6442450944L) << 1)
+                + ((truncated == 5) << 2)
+                + ((widened * big == -30064771072L) << 3)
+                + (((shifted >> 38) == 4) << 4)
+                + ((sum(big, 1, big, 1, big, 1, big, 1) == 17179869188L) << 5);
+            return checks;
+        }
+        "#;
+        assert_eq!(run(str), 63);
+    }
+
+    #[test]
+    fn unsigned_arithmetic() {
+        let str = r#"
+        int main(void) {
+            unsigned int u = 4294967295u;
+            unsigned long ul = 18446744073709551615UL;
+            int negative = -1;
+            signed int s = 7;
+            unsigned char byte = 255;
+            long int from_unsigned = u;
+            int checks = (u / 2 == 2147483647u)
+                + ((u > 1) << 1)
+                + ((negative > 0u) << 2)
+                + ((ul % 10 == 5) << 3)
+                + (((ul >> 60) == 15) << 4)
+                + ((from_unsigned == 4294967295L) << 5)
+                + ((byte + s == 262) << 6)
+                + ((u + 1 == 0) << 7);
+            return checks;
+        }
+        "#;
+        assert_eq!(run(str), 255);
+    }
+
+    #[test]
+    fn long_switch_and_statics() {
+        let str = r#"
+        unsigned long table = 18446744073709551615UL;
+
+        int classify(long x) {
+            switch (x) {
+                case 8589934592L: return 1;
+                case 2: return 2;
+                case 3: return 3;
+                case 4: return 4;
+                case 5: return 5;
+                default: return 0;
+            }
+        }
+
+        int main(void) {
+            return classify(8589934592L) + classify(4) * 2 + (table == -1) * 16 + classify(-4294967294L) * 32;
+        }
+        "#;
+        assert_eq!(run(str), 1 + 8 + 16);
+    }
+
+    #[test]
+    fn double_arithmetic_and_calls() {
+        // More doubles than there are xmm registers, mixed in with integers, so both kinds end up on the stack
+        let str = r#"
+        double half(double x) {
+            return x / 2;
+        }
+
+        double mix(int a, double b, long c, double d, unsigned e, double f, double g,
+                   double h, double i, double j, double k, double l, int m) {
+            return a + b + c + d + e + f + g + h + i + j + k + l + m;
+        }
+
+        double big = 1e300;
+        static double negative = -2.5;
+
+        double accumulate(double step) {
+            static double total = 0.5;
+            total += step;
+            return total;
+        }
+
+        int main(void) {
+            double x = 3.5;
+            x++;
+            x -= 1.5;
+            x *= 4;
+            int checks = (half(x) == 6.0)
+                + ((-x == -12.0) << 1)
+                + ((mix(1, 2.0, 3, 4.0, 5u, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0, 13) == 91.0) << 2)
+                + ((negative * big < -2e300) << 3)
+                + ((accumulate(1.0) + accumulate(2.0) == 5.0) << 4)
+                + ((.5 + 1e1 == 10.5) << 5);
+            return checks;
+        }
+        "#;
+        assert_eq!(run(str), 63);
+    }
+
+    #[test]
+    fn double_conversions() {
+        let str = r#"
+        unsigned long huge = 1e19;
+        long truncated = -2.9;
+
+        int main(void) {
+            double d = 11.75;
+            char c = d;
+            int i = -d;
+            unsigned char byte = 200.7;
+            unsigned int ui = 4294967295u;
+            unsigned long ul = 18446744073709551615UL;
+            double from_ul = ul;
+            unsigned long back = 18446744073709549568.0;
+            double from_negative = -5l;
+            int checks = (c == 11)
+                + ((i == -11) << 1)
+                + ((byte == 200) << 2)
+                + ((ui == 4294967295.0) << 3)
+                + ((from_ul == 18446744073709551616.0) << 4)
+                + ((back == 18446744073709549568UL) << 5)
+                + ((huge == 10000000000000000000UL) << 6)
+                + ((truncated == -2) << 7)
+                + ((from_negative == -5.0) << 8)
+                + ((d < ul) << 9);
+            return checks == 1023;
+        }
+        "#;
+        assert_eq!(run(str), 1);
+    }
+
+    #[test]
+    fn double_nan_comparisons() {
+        // Every comparison with NaN is false except for !=, and NaN counts as true
+        let str = r#"
+        int main(void) {
+            double zero = 0.0;
+            double nan = zero / zero;
+            int checks = (nan != nan)
+                + (!(nan == nan) << 1)
+                + (!(nan < 1.0) << 2)
+                + (!(nan <= 1.0) << 3)
+                + (!(nan > 1.0) << 4)
+                + (!(nan >= 1.0) << 5)
+                + ((nan && 1) << 6)
+                + (!(-0.0 || zero) << 7)
+                + (!!nan << 8);
+            return checks == 511;
+        }
+        "#;
+        assert_eq!(run(str), 1);
+
+        let assembly = compile("double f(double x) { return x + 0.25; } int main(void) { return 0; }");
+        assert!(assembly.contains("    .section .rodata\n    .balign 8\n.Ldouble.0:\n    .quad 4598175219545276416\n"));
+        assert!(assembly.contains("addsd .Ldouble.0(%rip)"));
+    }
+
+    #[test]
+    fn pointers_swap_and_write_through() {
+        let str = r#"
+        int swap(int *a, int *b) {
+            int tmp = *a;
+            *a = *b;
+            *b = tmp;
+            return 0;
+        }
+        long counter;
+        int main(void) {
+            int x = 3;
+            int y = 40;
+            swap(&x, &y);
+            long *p = &counter;
+            *p = 4294967296;
+            *p += 2;
+            (*p)++;
+            double d = 1.5;
+            double *dp = &d;
+            *dp = *dp * 2.0;
+            int *q = &*&x;
+            return x == 40 && y == 3 && counter == 4294967299 && d == 3.0 && *q == 40;
+        }
+        "#;
+        assert_eq!(run(str), 1);
+    }
+
+    #[test]
+    fn pointer_arithmetic_and_comparisons() {
+        let str = r#"
+        int main(void) {
+            long a = 1;
+            long b = 2;
+            long *p = &a;
+            long *q = p + 3;
+            long *r = 2 + q;
+            r -= 4;
+            r++;
+            --r;
+            int *null = 0;
+            int checks = (q - p == 3)
+                + ((r - q == -2) << 1)
+                + ((p != 0) << 2)
+                + ((null == 0) << 3)
+                + (!null << 4)
+                + ((p < q) << 5)
+                + ((q >= p + 3) << 6)
+                + ((&b != &a) << 7);
+            return checks == 255;
+        }
+        "#;
+        assert_eq!(run(str), 1);
+    }
+
+    #[test]
+    fn arrays_and_subscripts() {
+        let str = r#"
+        long table[2][3] = {{1, 2, 3}, {4}};
+        static int zeroes[5];
+        double scale[3] = {0.5, 2.0};
+
+        int sum(int values[4], int count) {
+            int total = 0;
+            int i = 0;
+            switch (count) {
+                case 4: total += values[3];
+                case 3: total += 2[values];
+                case 2: total += *(values + 1);
+                case 1: total += values[0];
+            }
+            return total;
+        }
+
+        int main(void) {
+            int values[4] = {1, 2, 3};
+            int grid[3][3] = {{1}, {0, 5}, {7, 8, 9}};
+            values[3] = 10;
+            grid[0][2] = grid[1][1] + grid[2][0];
+            int *p = grid[2];
+            p[1]++;
+            zeroes[4] = table[1][0];
+            scale[2] = scale[0] * scale[1];
+            int checks = (sum(values, 4) == 16)
+                + ((grid[0][2] == 12) << 1)
+                + ((grid[2][1] == 9) << 2)
+                + ((sizeof grid == 36) << 3)
+                + ((sizeof grid[1] / sizeof grid[1][0] == 3) << 4)
+                + ((table[0][2] + table[1][2] == 3) << 5)
+                + ((zeroes[4] + zeroes[0] == 4) << 6)
+                + ((scale[2] == 1.0) << 7)
+                + ((&grid[1][0] - &grid[0][0] == 3) << 8);
+            return checks == 511;
+        }
+        "#;
+        assert_eq!(run(str), 1);
+
+        // Arrays of 16 bytes or more are aligned to 16, smaller ones to their elements
+        let assembly = compile("long table[2][3] = {{1, 2, 3}, {4}}; int zeroes[5]; int small[3]; int main(void) { return 0; }");
+        assert!(assembly.contains("    .balign 16\ntable:\n    .quad 1\n    .quad 2\n    .quad 3\n    .quad 4\n    .zero 16\n"));
+        assert!(assembly.contains("    .bss\n    .balign 16\nzeroes:\n    .zero 20\n"));
+        assert!(assembly.contains("    .bss\n    .balign 4\nsmall:\n    .zero 12\n"));
+        assert_eq!(run("int main(void) { char c = 1; int a[5]; char d = 2; return ((long)a % 16 == 0) + c + d; }"), 4);
+    }
+
+    #[test]
+    fn pointers_to_arrays_and_unsized_parameters() {
+        let str = r#"
+        int total(int (*rows)[3], int count) {
+            return rows[0][0] + rows[0][2] + rows[count - 1][1];
+        }
+
+        long first(long *values[]) {
+            return *values[0] + *values[1];
+        }
+
+        int main(void) {
+            int grid[2][3] = {{1, 2, 3}, {4, 5, 6}};
+            int (*row)[3] = grid + 1;
+            long a = 30;
+            long b = 12;
+            long *pair[2] = {&a, &b};
+            return total(grid, 2) + (*row)[2] + first(pair);
+        }
+        "#;
+        assert_eq!(run(str), 9 + 6 + 42);
+    }
+
+    #[test]
+    fn structures_and_member_access() {
+        let str = r#"
+        struct point { int x; int y; };
+        struct shape { char tag; struct point corners[2]; struct shape *next; };
+        union bits { long whole; char bytes[8]; };
+        struct point origin = {3, 4};
+        static struct shape saved;
+
+        int area(struct shape *shape) {
+            return (shape->corners[1].x - shape->corners[0].x) * (shape->corners[1].y - shape->corners[0].y);
+        }
+
+        int main(void) {
+            struct shape box = {98, {{1, 2}, {4, 6}}, 0};
+            struct shape other;
+            union bits value;
+            struct point *corner = &box.corners[1];
+            int checks = 0;
+            other = box;
+            other.next = &box;
+            other.corners[0] = origin;
+            saved = other;
+            value.whole = 258;
+            corner->y = corner->y + 1;
+            checks = (area(&box) == 15)
+                + ((other.next->corners[1].y == 7) << 1)
+                + ((saved.corners[0].x + saved.corners[0].y == 7) << 2)
+                + ((value.bytes[0] + value.bytes[1] == 3) << 3)
+                + ((sizeof box == 32) << 4)
+                + ((sizeof value == 8) << 5)
+                + ((saved.tag == 98) << 6)
+                + ((&box.corners[1].y - &box.corners[0].x == 3) << 7);
+            return checks == 255;
+        }
+        "#;
+        assert_eq!(run(str), 1);
+
+        let assembly = compile("struct s { char c; long l; }; struct s g = {1, 2}; int main(void) { return g.l; }");
+        assert!(assembly.contains("g:\n    .byte 1\n    .zero 7\n    .quad 2\n"));
+        assert!(assembly.contains("g+8(%rip)"));
+    }
+
+    #[test]
+    fn passing_and_returning_structures() {
+        let str = r#"
+        struct bytes { char a; char b; char c; };
+        struct mixed { double d; int i; };
+        struct doubles { double x; double y; };
+        struct big { long a; long b; long c; char d; };
+
+        struct bytes bump(struct bytes value) {
+            value.c = value.c + 1;
+            return value;
+        }
+
+        struct mixed combine(struct doubles pair, struct mixed extra) {
+            struct mixed result;
+            result.d = pair.x + pair.y + extra.d;
+            result.i = extra.i * 2;
+            return result;
+        }
+
+        struct big grow(struct big value, long amount) {
+            value.a = value.a + amount;
+            value.d = value.d + 1;
+            return value;
+        }
+
+        long crowded(long a, long b, long c, long d, long e, struct doubles pair, struct bytes small, struct big big) {
+            return a + e + small.c + big.c;
+        }
+
+        int main(void) {
+            struct bytes small = {1, 2, 3};
+            struct doubles pair = {1.5, 2.5};
+            struct mixed extra = {0.5, 21};
+            struct big big = {10, 20, 30, 40};
+            struct bytes bumped = bump(small);
+            struct mixed combined = combine(pair, extra);
+            struct big grown = grow(big, 5);
+            int checks = (bumped.c == 4 && bumped.a == 1 && small.c == 3)
+                + ((combined.d == 4.5 && combined.i == 42) << 1)
+                + ((grown.a == 15 && grown.c == 30 && grown.d == 41 && big.a == 10) << 2)
+                + ((crowded(1, 2, 3, 4, 5, pair, small, big) == 39) << 3);
+            return checks;
+        }
+        "#;
+        assert_eq!(run(str), 15);
+    }
+
+    #[test]
+    fn casts_and_sizeof() {
+        let str = r#"
+        struct s { char c; long l; };
+        int buffer[sizeof(long) * 2];
+
+        int main(void) {
+            long big = 4294967298;
+            double d = 3.9;
+            int checks = 0;
+            buffer[1] = 77;
+            checks = ((int)big == 2)
+                + (((unsigned char)300 == 44) << 1)
+                + (((int)d == 3) << 2)
+                + (((double)(int)d / 2 == 1.5) << 3)
+                + ((sizeof(struct s) == 16) << 4)
+                + ((sizeof(int[3]) == 12) << 5)
+                + ((sizeof buffer == 64) << 6)
+                + ((sizeof(char) + sizeof (d) == 9) << 7)
+                + (((long)&buffer[1] - (long)buffer == 4) << 8)
+                + ((*(int *)((char *)buffer + 4) == 77) << 9)
+                + ((sizeof(int) - 5 > 0) << 10);
+            return checks == 2047;
+        }
+        "#;
+        assert_eq!(run(str), 1);
+    }
+
+    #[test]
+    fn void_and_malloc() {
+        let str = r#"
+        void *malloc(unsigned long size);
+        void *calloc(unsigned long count, unsigned long size);
+        void free(void *ptr);
+
+        int total;
+
+        void add(int value) {
+            total = total + value;
+            return;
+        }
+
+        void fill(int *numbers, int count) {
+            int i = 0;
+        again:
+            numbers[i] = i + 1;
+            i = i + 1;
+            switch (i < count) {
+                case 1: goto again;
+            }
+        }
+
+        int main(void) {
+            int *numbers = malloc(4 * sizeof(int));
+            long *zeros = calloc(3, sizeof(long));
+            void *raw = numbers;
+            int *back = raw;
+            int checks;
+            fill(numbers, 4);
+            add(back[0]);
+            add(numbers[3]);
+            (void)add(100);
+            (void)(total + 1);
+            checks = (total == 105)
+                + ((raw == numbers) << 1)
+                + ((zeros[0] + zeros[1] + zeros[2] == 0) << 2)
+                + ((back[2] == 3) << 3);
+            free(numbers);
+            free(zeros);
+            return checks;
+        }
+        "#;
+        assert_eq!(run(str), 15);
+    }
+
+    #[test]
+    fn enums() {
+        let str = r#"
+        enum color { RED, GREEN = 5, BLUE, };
+        int table[BLUE + 1];
+        static int start = GREEN * 2;
+
+        struct shape { enum kind { SQUARE = -1, CIRCLE } kind; int size; };
+
+        int classify(enum color c) {
+            switch (c) {
+                case RED: return 1;
+                case GREEN: return 2;
+                case BLUE: return 3;
+            }
+            return 0;
+        }
+
+        int main(void) {
+            enum color c = BLUE;
+            struct shape s;
+            int RED = 40;
+            int checks;
+            s.kind = CIRCLE;
+            {
+                enum { RED = 100 };
+                table[6] = RED;
+            }
+            checks = (classify(c) == 3)
+                + ((classify(GREEN) == 2 && classify(0) == 1) << 1)
+                + ((sizeof table == 28 && sizeof(enum color) == 4) << 2)
+                + ((s.kind == 0 && SQUARE < 0) << 3)
+                + ((RED == 40 && table[6] == 100 && start == 10) << 4);
+            return checks;
+        }
+        "#;
+        assert_eq!(run(str), 31);
+    }
+
+    #[test]
+    fn typedefs() {
+        let str = r#"
+        typedef unsigned long size_t;
+        typedef int T;
+        typedef T *TP;
+        typedef struct point { int x; int y; } Point;
+        typedef struct { long a; } Anon;
+        typedef int Row[3];
+        void *malloc(size_t size);
+        void free(void *ptr);
+
+        T twice(T value) {
+            return value * 2;
+        }
+
+        int main(void) {
+            T a = 3;
+            TP p = &a;
+            Point *pt = malloc(sizeof(Point));
+            Anon an;
+            Row r;
+            T *q = p;
+            int checks;
+            pt->x = 4;
+            an.a = 5;
+            r[2] = 6;
+            {
+                int T = 7;
+                a = a + T * 2;
+            }
+            {
+                typedef long T;
+                T big = 8;
+                a = a + sizeof(T) + (T)big;
+            }
+            checks = (twice(*q) == 66)
+                + ((pt->x + an.a + r[2] == 15) << 1)
+                + ((sizeof(Row) == 12 && sizeof(size_t) == 8 && sizeof(Anon) == 8) << 2);
+            free(pt);
+            return checks;
+        }
+        "#;
+        assert_eq!(run(str), 7);
+    }
+
+    #[test]
+    fn running_qualifiers() {
+        let str = r#"
+        struct point { const int x; int y; };
+        int read(const int *p) {
+            return *p;
+        }
+        int main(void) {
+            const int a = 3;
+            int b = 4;
+            int *const pb = &b;
+            volatile int v = 5;
+            const int *pa = &a;
+            struct point s = {1, 2};
+            const double d = 2.5;
+            *pb = 7;
+            s.y = 10;
+            v = v + 1;
+            return (read(pa) + b == 10) + ((v == 6 && s.x + s.y == 11) << 1) + (((int)(d * 2) == 5) << 2);
+        }
+        "#;
+        assert_eq!(run(str), 7);
+    }
+
+    #[test]
+    fn running_arithmetic_types() {
+        let str = r#"
+        struct triple { float a; float b; float c; };
+        float half(float x) {
+            return x / 2.0f;
+        }
+        double widen(float x) {
+            return x;
+        }
+        float sum(float a, float b, float c, float d, float e, float f, float g, float h, float i, float j) {
+            return a + b + c + d + e + f + g + h + i + j;
+        }
+        struct triple make(float x) {
+            struct triple t = {x, x * 2, x * 3};
+            return t;
+        }
+        static float scale = 1.5f;
+        static short offset = -3;
+        int main(void) {
+            short s = -5;
+            unsigned short us = 65535;
+            long long ll = 1LL << 40;
+            unsigned long long ull = 18446744073709551615ULL;
+            _Bool b = 42;
+            _Bool z = 0.0;
+            _Bool p = &s;
+            float f = 3.0f;
+            float g = f * scale + 0.25f;
+            struct triple t = make(1.5f);
+            unsigned long big = 18446744073709551615UL;
+            float rounded = big;
+            int checks = (s + us == 65530)
+                + ((ll == 1099511627776 && ull + 1 == 0 && offset == -3) << 1)
+                + ((b == 1 && z == 0 && p == 1) << 2)
+                + ((g == 4.75f && widen(g) == 4.75 && half(f) == 1.5f && -f == -3.0f) << 3)
+                + ((sum(1, 2, 3, 4, 5, 6, 7, 8, 9, 10) == 55.0f && t.a + t.b + t.c == 9.0f) << 4)
+                + ((rounded == 18446744073709551616.0f && (unsigned long)1e19f == 9999999980506447872UL) << 5);
+            b++;
+            b++;
+            s = 40000;
+            return checks + ((b == 1 && s == -25536 && sizeof(short) + sizeof(float) + sizeof(_Bool) == 7) << 6);
+        }
+        "#;
+        assert_eq!(run(str), 127);
+    }
+
+    #[test]
+    fn running_variadic_functions() {
+        let str = r#"
+        #include <stdarg.h>
+        int snprintf(char *buf, unsigned long size, char *format, ...);
+        struct pair { long a; double b; };
+        struct big { long x; long y; long z; };
+        long sum(int count, ...) {
+            va_list ap;
+            long total = 0;
+            int i = 0;
+            va_start(ap, count);
+            loop:
+            switch (i < count) {
+                case 1:
+                    total = total + va_arg(ap, int);
+                    i++;
+                    goto loop;
+            }
+            va_end(ap);
+            return total;
+        }
+        double dsum(int count, va_list ap) {
+            double total = 0;
+            int i = 0;
+            loop:
+            switch (i < count) {
+                case 1:
+                    total = total + va_arg(ap, double);
+                    i++;
+                    goto loop;
+            }
+            return total;
+        }
+        double mixed(int count, ...) {
+            va_list ap;
+            va_list copy;
+            double result;
+            va_start(ap, count);
+            va_copy(copy, ap);
+            result = dsum(count, ap) * 2 + dsum(count, copy);
+            va_end(ap);
+            va_end(copy);
+            return result;
+        }
+        long structs(int first, ...) {
+            va_list ap;
+            struct pair p;
+            struct big b;
+            struct pair q;
+            va_start(ap, first);
+            p = va_arg(ap, struct pair);
+            b = va_arg(ap, struct big);
+            q = va_arg(ap, struct pair);
+            va_end(ap);
+            return p.a + (long)p.b + b.x + b.y + b.z + q.a + (long)q.b;
+        }
+        int main(void) {
+            char buf[16];
+            struct pair p = {1, 2.0};
+            struct big b = {3, 4, 5};
+            struct pair q = {6, 7.0};
+            float f = 1.25f;
+            int checks = sum(3, 1, 2, 3) == 6;
+            checks = checks + ((sum(10, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10) == 55) << 1);
+            checks = checks + ((mixed(10, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, f) == 138.75) << 2);
+            checks = checks + ((structs(0, p, b, q, p, p, p) == 28) << 3);
+            snprintf(buf, 16, "%.1f", 2.5);
+            checks = checks + ((buf[0] == 50 && buf[1] == 46 && buf[2] == 53 && buf[3] == 0) << 4);
+            return checks;
+        }
+        "#;
+        assert_eq!(run(str), 31);
+    }
+
+    #[test]
+    fn running_designated_initializers() {
+        let str = r#"
+        struct point { int x; int y; };
+        struct shape { struct point corners[3]; long tag; union { int i; double d; } value; };
+        static int primes[6] = {[4] = 11, [1] = 3, 5, [0] = 2};
+        static struct shape global = {.tag = 9, .corners[1].y = 4, .corners[2] = {7, 8}, .value.d = 2.5};
+        int length(struct point p) {
+            return p.x * p.x + p.y * p.y;
+        }
+        int sum(int *values, int count) {
+            int total = 0;
+            int i = 0;
+            loop:
+            switch (i < count) {
+                case 1:
+                    total = total + values[i];
+                    i++;
+                    goto loop;
+            }
+            return total;
+        }
+        int main(void) {
+            struct shape local = {.value = {.d = 1.5}, .corners = {[2].x = 6, [0] = {.y = 1}}};
+            int grid[2][3] = {[1] = {[2] = 9}, [0][1] = 4};
+            struct point *p = &(struct point){.y = 3};
+            int checks = (primes[0] == 2 && primes[1] == 3 && primes[2] == 5 && primes[3] == 0 && primes[4] == 11);
+            checks = checks + ((global.tag == 9 && global.corners[1].y == 4 && global.corners[1].x == 0 && global.corners[2].y == 8 && global.value.d == 2.5) << 1);
+            checks = checks + ((local.value.d == 1.5 && local.corners[2].x == 6 && local.corners[0].y == 1 && local.corners[1].y == 0 && local.tag == 0) << 2);
+            checks = checks + ((grid[1][2] == 9 && grid[0][1] == 4 && grid[1][0] == 0) << 3);
+            checks = checks + ((length((struct point){3, 4}) == 25 && sum((int[4]){1, 2, 3, 4}, 4) == 10) << 4);
+            p->x = 2;
+            checks = checks + ((p->x + p->y == 5 && (struct point){.x = 8}.x == 8 && sizeof (int[5]){0} == 20) << 5);
+            return checks;
+        }
+        "#;
+        assert_eq!(run(str), 63);
+    }
+
+    #[test]
+    fn running_static_addresses() {
+        let str = r#"
+        struct pt { int x; int y; };
+        struct two { struct pt a; struct pt b; };
+        int counter;
+        long table[4] = {1, 2, 3, 4};
+        int *p = &counter;
+        long *third = table + 2;
+        long *last = &table[3];
+        int *gp = (int[2]){5, 6};
+        struct pt *origin = &(struct pt){7, 8};
+        int *oy = &(struct pt){9, 10}.y;
+        char *name = "hello" + 1;
+        int get(void) { return *p; }
+        int (*getter)(void) = get;
+        struct two t = {.b.y = 4, .a = {1, 2}, 5};
+        int main(void) {
+            static int *local = &counter;
+            struct two l = {.b.y = 4, .a = {1, 2}, 5};
+            counter = 3;
+            int checks = (*p == 3 && getter() == 3 && *local == 3);
+            checks = checks + ((*third == 3 && last[0] == 4 && third[-1] == 2) << 1);
+            checks = checks + ((gp[0] + gp[1] == 11 && origin->y == 8 && *oy == 10) << 2);
+            checks = checks + ((name[0] == 101 && name[4] == 0) << 3);
+            checks = checks + ((t.b.x == 5 && t.b.y == 4 && t.a.y == 2 && l.b.x == 5 && l.b.y == 4 && l.a.x == 1) << 4);
+            return checks;
+        }
+        "#;
+        assert_eq!(run(str), 31);
+
+        let assembly = compile("long table[4]; long *last = &table[3]; int main(void) { return 0; }");
+        assert!(assembly.contains("last:\n    .quad table+24\n"));
+    }
+
+    #[test]
+    fn running_declarator_lists_and_arrays_without_size() {
+        let str = r#"
+        extern int table[];
+        int first(void), last(void);
+        int table[] = {1, 2, 3, 4}, count = 4;
+        int spare[];
+        struct pair { long x, y; };
+        typedef struct pair pair, *pair_ptr;
+        int first(void) {
+            return table[0];
+        }
+        int last(void) {
+            return table[count - 1];
+        }
+        int main(void) {
+            int a = 1, *p = &a, b[] = {5, 6};
+            pair q = {7, 8};
+            pair_ptr r = &q;
+            int checks = (first() == 1 && last() == 4 && sizeof table == 16);
+            checks = checks + ((*p == 1 && sizeof b == 8 && b[1] == 6) << 1);
+            checks = checks + ((r->x + r->y == 15 && sizeof(pair) == 16) << 2);
+            spare[0] = 9;
+            checks = checks + ((spare[0] == 9) << 3);
+            return checks;
+        }
+        "#;
+        assert_eq!(run(str), 15);
+    }
+
+    #[test]
+    fn running_string_literals() {
+        let str = r#"
+        int strcmp(char *a, char *b);
+        unsigned long strlen(char *s);
+        char greeting[] = "hello";
+        char padded[8] = "ab";
+        char words[2][4] = {"ab", "cde"};
+        char *first(void) { return "first" " second"; }
+        int main(void) {
+            char local[] = {"hi"};
+            char exact[3] = "abc";
+            char *p = "tab\there\n";
+            static char kept[4] = "x\101";
+            int checks = (sizeof "abc" == 4 && "abc"[1] == 98 && sizeof greeting == 6 && greeting[5] == 0);
+            checks = checks + ((padded[1] == 98 && padded[2] == 0 && padded[7] == 0) << 1);
+            checks = checks + ((words[1][2] == 101 && words[0][3] == 0 && strcmp(words[0], "ab") == 0) << 2);
+            checks = checks + ((strcmp(first(), "first second") == 0 && strlen(first()) == 12) << 3);
+            checks = checks + ((sizeof local == 3 && local[1] == 105 && exact[2] == 99) << 4);
+            checks = checks + ((p[3] == 9 && p[8] == 10 && p[9] == 0 && kept[1] == 65 && kept[2] == 0) << 5);
+            checks = checks + ((sizeof "\x41\0" == 3 && "\x41\0"[1] == 0 && *"\"" == 34) << 6);
+            return checks;
+        }
+        "#;
+        assert_eq!(run(str), 127);
+
+        let assembly = compile("char *p(void) { return \"a\\\"b\\n\"; } char s[] = \"hi\"; int main(void) { return 0; }");
+        assert!(assembly.contains("    .section .rodata\n    .balign 1\nstring."));
+        assert!(assembly.contains("    .ascii \"a\\\"b\\012\\000\"\n"));
+        assert!(assembly.contains("s:\n    .ascii \"hi\"\n    .zero 1\n"));
+    }
+
+    #[test]
+    fn running_initializers_without_inner_braces() {
+        let str = r#"
+        struct point { int x; int y; };
+        struct line { struct point a; struct point b; int width; };
+        int squares[] = {0, 1, 4, 9, 16};
+        int main(void) {
+            int m[2][2] = {1, 2, 3, 4};
+            struct line l = {1, 2, 3, 4, 5};
+            struct point origin = {0, 0};
+            struct point ends[] = {origin, 7, 8, [3] = 9};
+            int checks = (m[0][0] == 1 && m[0][1] == 2 && m[1][0] == 3 && m[1][1] == 4);
+            checks = checks + ((l.a.x == 1 && l.a.y == 2 && l.b.x == 3 && l.b.y == 4 && l.width == 5) << 1);
+            checks = checks + ((sizeof squares == 20 && squares[4] == 16) << 2);
+            checks = checks + ((sizeof ends == 32 && ends[1].x == 7 && ends[1].y == 8 && ends[3].x == 9 && ends[2].y == 0) << 3);
+            return checks;
+        }
+        "#;
+        assert_eq!(run(str), 15);
+    }
+
+    #[test]
+    fn running_function_pointers() {
+        let str = r#"
+        int abs(int value);
+        int add(int a, int b) {
+            return a + b;
+        }
+        int sub(int a, int b) {
+            return a - b;
+        }
+        static int twice(int value) {
+            return value * 2;
+        }
+        struct handler { int (*apply)(int); long tag; };
+        int (*global_ops[2])(int, int) = {add, &sub};
+        static struct handler handlers[2] = {{twice, 1}, {abs, 2}};
+        int (*pick(int which))(int, int) {
+            return global_ops[which];
+        }
+        int fold(int *values, int count, int (*combine)(int, int), int start) {
+            int total = start;
+            int i = 0;
+            loop:
+            switch (i < count) {
+                case 1:
+                    total = combine(total, values[i]);
+                    i++;
+                    goto loop;
+            }
+            return total;
+        }
+        double scale(double x, double factor) {
+            return x * factor;
+        }
+        int main(void) {
+            int values[3] = {1, 2, 3};
+            int (*local_ops[2])(int, int) = {sub, add};
+            double (*fp)(double, double) = scale;
+            int (*op)(int, int) = pick(0);
+            int checks = (op(2, 3) == 5 && (*op)(4, 4) == 8 && pick(1)(9, 4) == 5);
+            checks = checks + ((fold(values, 3, add, 10) == 16 && fold(values, 3, local_ops[0], 0) == -6) << 1);
+            checks = checks + ((handlers[0].apply(21) == 42 && handlers[1].apply(-7) == 7 && handlers[1].tag == 2) << 2);
+            checks = checks + ((fp(1.5, 4.0) == 6.0 && (**fp)(2.0, 0.5) == 1.0) << 3);
+            checks = checks + ((op == add && op != sub && global_ops[1] == sub && local_ops[1] == &add) << 4);
+            return checks;
+        }
+        "#;
+        assert_eq!(run(str), 31);
+    }
+
+    #[test]
+    fn running_preprocessed_programs() {
+        let str = r#"
+        #define SQUARE(x) ((x) * (x))
+        #define CALL(f, ...) f(__VA_ARGS__)
+        #define GETTER(name) int get_##name(struct pair *p) { return p->name; }
+        // Comments are just whitespace
+        struct pair {
+            int first;
+            int second;
+        };
+        GETTER(first)
+        GETTER(second)
+        #if defined(SQUARE) && SQUARE(3) == 9
+        #define RESULT 1
+        #else
+        #define RESULT 0
+        #endif
+        int main(void) {
+            struct pair p = {SQUARE(2 + 1), /* nine */ 4};
+            return CALL(get_first, &p) + get_second(&p) * 10 + RESULT * 100;
+        }
+        "#;
+        assert_eq!(run(str), 149);
+    }
+
+    #[test]
+    #[should_panic(expected = "at 2, 23 in expansion of 'VALUE' at 4, 21")]
+    fn diagnostics_inside_macros() {
+        let str = "
+        #define VALUE missing
+        int main(void) {
+            int x = VALUE;
+            return x;
+        }";
+        compile(str);
+    }
+
+    fn gcc(args: &[&str], dir: &Path) {
+        let status = Command::new("gcc").args(args).current_dir(dir).status().unwrap();
+        assert!(status.success());
+    }
+
+    // The structure and the arguments past the sixth cross between our code and gcc's both ways
+    const SHARED: &str = r#"
+        struct pair { long a; double b; };
+        struct pair make(long a, double b);
+        long sum8(long a, long b, long c, long d, long e, long f, long g, long h);
+        "#;
+
+    const GCC_SIDE: &str = r#"
+        struct pair make(long a, double b) { struct pair p = {a, b}; return p; }
+        long sum8(long a, long b, long c, long d, long e, long f, long g, long h) {
+            return a + b + c + d + e + f + g + h;
+        }
+        "#;
+
+    #[test]
+    fn separate_translation_units() {
+        let dir = directory("units");
+        let main = r#"
+        #include "shared.h"
+        int add(int a, int b);
+        extern int counter;
+        static int helper(void) { return 1; }
+        int main(void) {
+            struct pair p = make(5, 2.5);
+            counter = counter + helper();
+            return add(counter, 3) + (int)(p.a * p.b) + (int)sum8(1, 2, 3, 4, 5, 6, 7, 8);
+        }
+        "#;
+        // Each file has a helper of its own, static ones don't clash
+        let other = "int counter = 10; static int helper(void) { return 100; } int add(int a, int b) { return a + b; }";
+        fs::write(dir.join("shared.h"), SHARED).unwrap();
+        fs::write(dir.join("main.c"), main).unwrap();
+        fs::write(dir.join("other.c"), other).unwrap();
+        fs::write(dir.join("gcc_side.c"), format!("{}{}", SHARED, GCC_SIDE)).unwrap();
+        gcc(&["-c", "gcc_side.c", "-o", "gcc_side.o"], &dir);
+
+        let executable = dir.join("program");
+        build(&Options {
+            inputs: vec![dir.join("main.c"), dir.join("other.c"), dir.join("gcc_side.o")],
+            include_paths: Vec::new(),
+            stage: Stage::Executable,
+            output: Some(executable.clone()),
+        });
+        let status = Command::new(&executable).status().unwrap();
+        assert_eq!(status.code(), Some(11 + 3 + 12 + 36));
+    }
+
+    #[test]
+    fn objects_linked_by_gcc() {
+        let dir = directory("objects");
+        let ours = format!("{}{}", SHARED, "long total(void) { struct pair p = make(3, 1.5); return p.a + (long)p.b + sum8(1, 1, 1, 1, 1, 1, 1, 9); }");
+        let main = format!("{}{}{}", SHARED, GCC_SIDE, "long total(void); int main(void) { return total(); }");
+        fs::write(dir.join("ours.c"), ours).unwrap();
+        fs::write(dir.join("main.c"), main).unwrap();
+
+        // Without -o the object goes next to the source, -S leaves the assembly there the same way
+        for stage in [Stage::Object, Stage::Assembly] {
+            build(&Options {
+                inputs: vec![dir.join("ours.c")],
+                include_paths: Vec::new(),
+                stage,
+                output: None,
+            });
+        }
+        assert!(dir.join("ours.s").is_file());
+        gcc(&["main.c", "ours.o", "-o", "program"], &dir);
+        let status = Command::new(dir.join("program")).status().unwrap();
+        assert_eq!(status.code(), Some(3 + 1 + 16));
+    }
+
+    #[test]
+    fn scratch_files_removed_when_a_build_fails() {
+        let dir = directory("failing");
+        fs::write(dir.join("left_behind.c"), "int main(void) { return 0; }").unwrap();
+        fs::write(dir.join("broken.c"), "int main(void) { return missing; }").unwrap();
+        let options = Options {
+            inputs: vec![dir.join("left_behind.c"), dir.join("broken.c")],
+            include_paths: Vec::new(),
+            stage: Stage::Executable,
+            output: Some(dir.join("program")),
+        };
+        let result = std::panic::catch_unwind(|| build(&options));
+        assert!(result.is_err());
+
+        // The first file got as far as an object before the second one failed
+        let prefix = format!("compiler_{}_", std::process::id());
+        let left = fs::read_dir(std::env::temp_dir()).unwrap().filter_map(|entry| entry.ok()).any(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.starts_with(&prefix) && name.contains("_left_behind.")
+        });
+        assert!(!left);
+    }
+
+    #[test]
+    #[should_panic(expected = "-o can't be used with -c or -S when there is more than one input file")]
+    fn one_output_for_several_objects() {
+        build(&Options {
+            inputs: vec![PathBuf::from("a.c"), PathBuf::from("b.c")],
+            include_paths: Vec::new(),
+            stage: Stage::Object,
+            output: Some(PathBuf::from("both.o")),
+        });
+    }
+}
@@ -1,6 +1,5 @@
 use std::collections::LinkedList;
 use std::fmt;
-use regex::Regex;
 use crate::lexer::syntax_token::*;
 
 pub mod syntax_token;
@@ -44,6 +43,13 @@ impl Lexer {
 
     }
 
+    // For text that doesn't start at the top of the file, so locations give the line it's really on
+    pub fn starting_at(file_data: &str, line: usize) -> Self {
+        let mut lexer = Lexer::new(file_data);
+        lexer.current_line = line;
+        lexer
+    }
+
     pub fn lex(&mut self) {
         while self.idx < self.file.len() {
             let cur = self.current();
@@ -60,24 +66,43 @@ impl Lexer {
         self.file.get(self.idx + offset).cloned().unwrap_or_default()
     }
 
-    fn expect(&mut self, expected: SyntaxKind) -> bool {
-        let char = self.peek(0);
-        if let Some(actual) = SyntaxKind::from_char(char) {
-            self.idx += 1;
-            actual == expected
-        } else {
-            false
-        }
-    }
-
     fn current(&self) -> char {
         self.peek(0)
     }
 
     fn parse_char(&mut self, c: char) -> Option<LexerToken> {
-        let data = c.to_string();
+        // Comments and backslashes at the end of a line are gone before anything else gets to see the tokens
+        if c == '/' && self.peek(1) == '/' {
+            while self.idx < self.file.len() && self.current() != '\n' {
+                self.idx += 1;
+            }
+            return None;
+        }
+        if c == '/' && self.peek(1) == '*' {
+            self.skip_block_comment();
+            return None;
+        }
+        // Files written on Windows end their lines with \r\n
+        let splice = match self.peek(1) {
+            '\n' => 2,
+            '\r' if self.peek(2) == '\n' => 3,
+            _ => 0,
+        };
+        if c == '\\' && splice > 0 {
+            self.idx += splice;
+            self.current_line += 1;
+            self.current_column = 1;
+            return None;
+        }
+        if c == '"' || c == '\'' {
+            return Some(self.read_quoted(c));
+        }
+
+        let mut data = c.to_string();
 
-        let symbol = SyntaxKind::from_char(c);
+        // A floating constant can start with its decimal point, anywhere else a dot picks out a member
+        let starts_fraction = c == '.' && self.peek(1).is_ascii_digit();
+        let symbol = if starts_fraction { None } else { SyntaxKind::from_char(c) };
         if let Some(mut kind) = symbol {
             // Once we know that it's a single or max two digit character
             // We can safely increase the index & column
@@ -87,41 +112,47 @@ impl Lexer {
 
             self.current_location.set_line_and_column(self.current_line, self.current_column - 1);
 
-            match kind {
-                // Multiline chars that will match at one char
-                SyntaxKind::Equal => {
-                    if self.current() == '=' {
-                        kind = SyntaxKind::EqualEqual;
-                    }
-                }
-                SyntaxKind::NewLine => {
-                    self.current_line += 1;
-                    self.current_column = 1;
-                }
-                _ => {}
+            // Two dots on their own aren't a token, so an ellipsis is only taken when all three are there
+            if kind == SyntaxKind::Dot && self.current() == '.' && self.peek(1) == '.' {
+                kind = SyntaxKind::Ellipsis;
+                data.push_str("..");
+                self.idx += 2;
+                self.current_column += 2;
+            }
+
+            // Multi char symbols that will match at one char
+            while let Some(extended) = kind.extend(self.current()) {
+                kind = extended;
+                data.push(self.current());
+                self.idx += 1;
+                self.current_column += 1;
             }
 
+            if kind == SyntaxKind::NewLine {
+                self.current_line += 1;
+                self.current_column = 1;
+            }
+
+            let length = data.len();
             let result = LexerToken {
                 kind,
                 data,
                 location: self.current_location.clone(),
-                length: 1
+                length
             };
 
             Some(result)
 
         } else {
             // Could be a string literal or a number literal
-            let number_regex = Regex::new("[0-9]").unwrap();
-            let strs = Regex::new("[a-zA-Z]").unwrap();
-
-            if number_regex.captures(c.to_string().as_str()).is_some() {
+            if c.is_ascii_digit() || starts_fraction {
                 // We have numbers
-                Some(self.read_numbers(&number_regex))
+                Some(self.read_numbers())
 
-            } else if strs.captures(c.to_string().as_str()).is_some() {
+            } else if c.is_ascii_alphabetic() || c == '_' {
                 // We probably have characters
-                Some(self.read_chars(&strs))
+                // Past the first character identifiers are allowed to contain digits as well
+                Some(self.read_chars())
             } else if c.is_whitespace() {
                 // As we're dealing with whitespace, we can safely just skip it.
                 self.idx += 1;
@@ -138,14 +169,42 @@ impl Lexer {
                 //      * Or number
                 // If we ever end up here, we've exhausted our options.
                 //
-                panic!("Unable to lex character {0}", c)
+                panic!("Unable to lex character {} at {}", c, self.current_location)
             }
         }
     }
 
-    fn read_numbers(&mut self, num_regex: &Regex) -> LexerToken {
+    fn read_numbers(&mut self) -> LexerToken {
+
+        // Hexadecimal constants are only ever integers, and an e in one is a digit
+        if self.current() == '0' && matches!(self.peek(1), 'x' | 'X') {
+            self.idx += 2;
+            let mut st = self.vec_to_str(Vec::from(&self.file[self.idx - 2..self.idx]));
+            st.push_str(&self.read_while_match(|c| c.is_ascii_alphanumeric()));
+            return self.number_token(st);
+        }
+        let mut st = self.read_while_match(|c| c.is_ascii_digit());
+        // A fraction or an exponent makes it a floating constant, the parser tells the two apart by looking for them
+        if self.current() == '.' {
+            st.push('.');
+            self.idx += 1;
+            st.push_str(&self.read_while_match(|c| c.is_ascii_digit()));
+        }
+        if matches!(self.current(), 'e' | 'E') {
+            st.push(self.current());
+            self.idx += 1;
+            if matches!(self.current(), '+' | '-') {
+                st.push(self.current());
+                self.idx += 1;
+            }
+            st.push_str(&self.read_while_match(|c| c.is_ascii_digit()));
+        }
+        // The suffix decides the type of the literal, the parser works out whether it makes sense
+        st.push_str(&self.read_while_match(|c| matches!(c, 'u' | 'U' | 'l' | 'L' | 'f' | 'F')));
+        self.number_token(st)
+    }
 
-        let st = self.read_while_match(num_regex);
+    fn number_token(&mut self, st: String) -> LexerToken {
         let len = st.len();
         let result = LexerToken {
             kind: SyntaxKind::NumberLiteral,
@@ -157,8 +216,8 @@ impl Lexer {
         result
     }
 
-    fn read_chars(&mut self, str_regex: &Regex) -> LexerToken {
-        let st = self.read_while_match(str_regex);
+    fn read_chars(&mut self) -> LexerToken {
+        let st = self.read_while_match(|c| c.is_ascii_alphanumeric() || c == '_');
         let len = st.len();
 
         let mut kind = SyntaxKind::StringLiteral;
@@ -178,9 +237,60 @@ impl Lexer {
         result
     }
 
-    fn read_while_match(&mut self, reg: &Regex) -> String {
+    // A comment can go over several lines, which still count for the locations of whatever comes after it
+    fn skip_block_comment(&mut self) {
+        let start = self.current_location.clone();
+        self.idx += 2;
+        self.current_column += 2;
+        while !(self.current() == '*' && self.peek(1) == '/') {
+            if self.idx >= self.file.len() {
+                panic!("Unterminated comment at {}", start)
+            }
+            if self.current() == '\n' {
+                self.current_line += 1;
+                self.current_column = 1;
+            } else {
+                self.current_column += 1;
+            }
+            self.idx += 1;
+        }
+        self.idx += 2;
+        self.current_column += 2;
+    }
+
+    // Escapes are left as they are, an escaped quote just doesn't end the string or character constant
+    fn read_quoted(&mut self, quote: char) -> LexerToken {
+        let (kind, what) = match quote {
+            '"' => (SyntaxKind::QuotedString, "string"),
+            _ => (SyntaxKind::QuotedChar, "character constant"),
+        };
+        let start_index = self.idx;
+        self.idx += 1;
+        while self.current() != quote {
+            if self.idx >= self.file.len() || self.current() == '\n' {
+                panic!("Unterminated {} at {}", what, self.current_location)
+            }
+            if self.current() == '\\' {
+                self.idx += 1;
+            }
+            self.idx += 1;
+        }
+        self.idx += 1;
+        let st = self.vec_to_str(Vec::from(&self.file[start_index..self.idx]));
+        let len = self.idx - start_index;
+        let result = LexerToken {
+            kind,
+            data: st,
+            location: self.current_location.clone(),
+            length: len,
+        };
+        self.current_column += len;
+        result
+    }
+
+    fn read_while_match(&mut self, matches: impl Fn(char) -> bool) -> String {
         let start_index = self.idx;
-        while self.idx < self.file.len() &&  reg.captures(self.file[self.idx].to_string().as_str()).is_some() {
+        while self.idx < self.file.len() && matches(self.file[self.idx]) {
             self.idx += 1;
         }
         let slice = Vec::from(&self.file[start_index..self.idx]);
@@ -197,6 +307,104 @@ impl Lexer {
 
 }
 
+// An integer constant taken apart. Octal and hexadecimal constants can have unsigned types that a decimal
+// constant of the same value can't, so the base is kept along with the value
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntegerLiteral {
+    pub value: u64,
+    pub decimal: bool,
+    pub unsigned: bool,
+    // How many l's the suffix has
+    pub longs: usize,
+}
+
+impl IntegerLiteral {
+    // 0x starts a hexadecimal constant and any other leading zero an octal one
+    pub fn parse(token: &LexerToken) -> IntegerLiteral {
+        let text = token.data.as_str();
+        let (radix, digits) = match text.get(..2) {
+            Some("0x" | "0X") => (16, &text[2..]),
+            _ if text.len() > 1 && text.starts_with('0') => (8, &text[1..]),
+            _ => (10, text),
+        };
+        let end = digits.find(|c: char| !c.is_digit(radix)).unwrap_or(digits.len());
+        let (digits, suffix) = digits.split_at(end);
+        if suffix.contains(|c: char| !matches!(c, 'u' | 'U' | 'l' | 'L')) || (radix == 16 && digits.is_empty()) {
+            panic!("Invalid number '{}' at {}", token.data, token.location)
+        }
+        let (unsigned, longs) = match suffix.to_lowercase().as_str() {
+            "" => (false, 0),
+            "u" => (true, 0),
+            "l" => (false, 1),
+            "ul" | "lu" => (true, 1),
+            "ll" => (false, 2),
+            "ull" | "llu" => (true, 2),
+            _ => panic!("Invalid suffix on number '{}' at {}", token.data, token.location),
+        };
+        // The 0 on its own is all an octal zero has
+        let value = match digits {
+            "" => 0,
+            _ => u64::from_str_radix(digits, radix)
+                .unwrap_or_else(|_| panic!("Number '{}' at {} is too large for any integer type", token.data, token.location)),
+        };
+        IntegerLiteral { value, decimal: radix == 10, unsigned, longs }
+    }
+}
+
+// The bytes a quoted string or character constant stands for once its escapes are worked out, without the quotes or a terminating null
+pub fn unescape(token: &LexerToken) -> Vec<u8> {
+    let text = &token.data[1..token.data.len() - 1];
+    let mut bytes = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+        let escaped = chars.next().unwrap_or_else(|| panic!("Unfinished escape sequence in {} at {}", token.data, token.location));
+        let byte = match escaped {
+            'n' => b'\n',
+            't' => b'\t',
+            'r' => b'\r',
+            'a' => 0x07,
+            'b' => 0x08,
+            'f' => 0x0C,
+            'v' => 0x0B,
+            '\\' | '\'' | '"' | '?' => escaped as u8,
+            // Up to three octal digits, or as many hexadecimal ones as there are
+            '0'..='7' => {
+                let mut value = escaped.to_digit(8).unwrap();
+                for _ in 0..2 {
+                    match chars.peek().and_then(|c| c.to_digit(8)) {
+                        Some(digit) => value = value * 8 + digit,
+                        None => break,
+                    }
+                    chars.next();
+                }
+                if value > 0xFF {
+                    panic!("Octal escape sequence in {} at {} is out of range", token.data, token.location)
+                }
+                value as u8
+            }
+            'x' => {
+                let mut digits = String::new();
+                while let Some(digit) = chars.peek().filter(|c| c.is_ascii_hexdigit()) {
+                    digits.push(*digit);
+                    chars.next();
+                }
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(value) => value,
+                    Err(_) => panic!("Invalid hexadecimal escape sequence in {} at {}", token.data, token.location),
+                }
+            }
+            _ => panic!("Unknown escape sequence '\\{}' in {} at {}", escaped, token.data, token.location),
+        };
+        bytes.push(byte);
+    }
+    bytes
+}
+
 impl fmt::Display for LexerToken {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let _ = f.write_str("Token{ Data : ");
@@ -210,7 +418,7 @@ impl fmt::Display for LexerToken {
 impl Clone for LexerToken {
     fn clone(&self) -> Self {
         LexerToken {
-            kind: SyntaxKind::from_str(self.data.as_str()).unwrap(),
+            kind: self.kind.clone(),
             data: self.data.clone(),
             location: self.location.clone(),
             length: self.length,
@@ -1,27 +1,63 @@
 use std::fmt;
 use std::fmt::Debug;
+use std::rc::Rc;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum SyntaxKind {
 
     // KEYWORDS
     IntKeyword,
     CharKeyword,
+    LongKeyword,
+    ShortKeyword,
+    SignedKeyword,
+    UnsignedKeyword,
+    DoubleKeyword,
+    FloatKeyword,
+    BoolKeyword,
     ReturnKeyword,
     VoidKeyword,
+    SwitchKeyword,
+    CaseKeyword,
+    DefaultKeyword,
+    BreakKeyword,
+    GotoKeyword,
+    StaticKeyword,
+    ExternKeyword,
+    TypedefKeyword,
+    SizeofKeyword,
+    StructKeyword,
+    UnionKeyword,
+    EnumKeyword,
+    ConstKeyword,
+    VolatileKeyword,
 
     // LITERALS
     NumberLiteral,
     StringLiteral,
+    // Text in double quotes, quotes and escapes included. Only the preprocessor has a use for it so far
+    QuotedString,
+    // A character constant in single quotes, kept the same way. Only #if has a use for it so far
+    QuotedChar,
 
     // SYMBOLS
     OpenParen,
     CloseParen,
     OpenCurly,
     CloseCurly,
+    OpenBracket,
+    CloseBracket,
     Semicolon,
+    Colon,
+    Question,
     Comma,
+    Dot,
+    Ellipsis,
+    Arrow,
     Underscore,
+    // Only mean anything to the preprocessor
+    Hash,
+    HashHash,
 
     // MATH SYMBOLS
     Equal,
@@ -30,6 +66,35 @@ pub enum SyntaxKind {
     EqualEqual,
     Star,
     Slash,
+    Percent,
+    Tilde,
+    Bang,
+    BangEqual,
+    Less,
+    LessEqual,
+    LessLess,
+    Greater,
+    GreaterEqual,
+    GreaterGreater,
+    Ampersand,
+    Pipe,
+    Caret,
+    AmpersandAmpersand,
+    PipePipe,
+    PlusPlus,
+    MinusMinus,
+
+    // ASSIGNMENT SYMBOLS
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
+    AmpersandEqual,
+    PipeEqual,
+    CaretEqual,
+    LessLessEqual,
+    GreaterGreaterEqual,
 
     // WHITESPACE
     NewLine,
@@ -46,9 +111,14 @@ impl SyntaxKind {
             ')' => Some(CloseParen),
             '{' => Some(OpenCurly),
             '}' => Some(CloseCurly),
+            '[' => Some(OpenBracket),
+            ']' => Some(CloseBracket),
             ';' => Some(Semicolon),
+            ':' => Some(Colon),
+            '?' => Some(Question),
             ',' => Some(Comma),
-            '_' => Some(Underscore),
+            '.' => Some(Dot),
+            '#' => Some(Hash),
 
             // Math symbols
             '=' => Some(Equal),
@@ -56,6 +126,14 @@ impl SyntaxKind {
             '+' => Some(Plus),
             '*' => Some(Star),
             '/' => Some(Slash),
+            '%' => Some(Percent),
+            '~' => Some(Tilde),
+            '!' => Some(Bang),
+            '<' => Some(Less),
+            '>' => Some(Greater),
+            '&' => Some(Ampersand),
+            '|' => Some(Pipe),
+            '^' => Some(Caret),
 
 
             // Whitespace
@@ -67,6 +145,57 @@ impl SyntaxKind {
             }
         }
     }
+
+    // Symbols that are made up of more than one character start out as their first character
+    // The lexer keeps asking this function whether the next character extends the symbol
+    pub fn extend(&self, next: char) -> Option<SyntaxKind> {
+        use SyntaxKind::*;
+        match (self, next) {
+            (Equal, '=') => Some(EqualEqual),
+            (Hash, '#') => Some(HashHash),
+            (Bang, '=') => Some(BangEqual),
+            (Less, '=') => Some(LessEqual),
+            (Less, '<') => Some(LessLess),
+            (Greater, '=') => Some(GreaterEqual),
+            (Greater, '>') => Some(GreaterGreater),
+            (Ampersand, '&') => Some(AmpersandAmpersand),
+            (Pipe, '|') => Some(PipePipe),
+            (Plus, '+') => Some(PlusPlus),
+            (Minus, '-') => Some(MinusMinus),
+            (Minus, '>') => Some(Arrow),
+            (Plus, '=') => Some(PlusEqual),
+            (Minus, '=') => Some(MinusEqual),
+            (Star, '=') => Some(StarEqual),
+            (Slash, '=') => Some(SlashEqual),
+            (Percent, '=') => Some(PercentEqual),
+            (Ampersand, '=') => Some(AmpersandEqual),
+            (Pipe, '=') => Some(PipeEqual),
+            (Caret, '=') => Some(CaretEqual),
+            (LessLess, '=') => Some(LessLessEqual),
+            (GreaterGreater, '=') => Some(GreaterGreaterEqual),
+            _ => None
+        }
+    }
+
+    // The binary operator a compound assignment applies before it stores the result
+    pub fn compound_operator(s: &SyntaxKind) -> Option<SyntaxKind> {
+        use SyntaxKind::*;
+        match s {
+            PlusEqual => Some(Plus),
+            MinusEqual => Some(Minus),
+            StarEqual => Some(Star),
+            SlashEqual => Some(Slash),
+            PercentEqual => Some(Percent),
+            AmpersandEqual => Some(Ampersand),
+            PipeEqual => Some(Pipe),
+            CaretEqual => Some(Caret),
+            LessLessEqual => Some(LessLess),
+            GreaterGreaterEqual => Some(GreaterGreater),
+            _ => None
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<SyntaxKind> {
         use SyntaxKind::*;
         match s {
@@ -74,7 +203,28 @@ impl SyntaxKind {
             "==" => Some(EqualEqual),
             "return" => Some(ReturnKeyword),
             "char" => Some(CharKeyword),
+            "long" => Some(LongKeyword),
+            "short" => Some(ShortKeyword),
+            "signed" => Some(SignedKeyword),
+            "unsigned" => Some(UnsignedKeyword),
+            "double" => Some(DoubleKeyword),
+            "float" => Some(FloatKeyword),
+            "_Bool" => Some(BoolKeyword),
             "void" => Some(VoidKeyword),
+            "switch" => Some(SwitchKeyword),
+            "case" => Some(CaseKeyword),
+            "default" => Some(DefaultKeyword),
+            "break" => Some(BreakKeyword),
+            "goto" => Some(GotoKeyword),
+            "static" => Some(StaticKeyword),
+            "extern" => Some(ExternKeyword),
+            "typedef" => Some(TypedefKeyword),
+            "sizeof" => Some(SizeofKeyword),
+            "struct" => Some(StructKeyword),
+            "union" => Some(UnionKeyword),
+            "enum" => Some(EnumKeyword),
+            "const" => Some(ConstKeyword),
+            "volatile" => Some(VolatileKeyword),
             _ => Some(StringLiteral)
         }
     }
@@ -82,10 +232,57 @@ impl SyntaxKind {
     pub fn is_type_keyword(s: &SyntaxKind) -> bool {
         match s {
             SyntaxKind::IntKeyword |
-            SyntaxKind::CharKeyword => true,
+            SyntaxKind::CharKeyword |
+            SyntaxKind::LongKeyword |
+            SyntaxKind::ShortKeyword |
+            SyntaxKind::SignedKeyword |
+            SyntaxKind::UnsignedKeyword |
+            SyntaxKind::DoubleKeyword |
+            SyntaxKind::FloatKeyword |
+            SyntaxKind::BoolKeyword |
+            SyntaxKind::VoidKeyword |
+            SyntaxKind::StructKeyword |
+            SyntaxKind::UnionKeyword |
+            SyntaxKind::EnumKeyword |
+            SyntaxKind::ConstKeyword |
+            SyntaxKind::VolatileKeyword => true,
             &_ => false
         }
     }
+
+    pub fn is_type_qualifier(s: &SyntaxKind) -> bool {
+        matches!(s, SyntaxKind::ConstKeyword | SyntaxKind::VolatileKeyword)
+    }
+
+    // typedef isn't really a storage class, but it goes in the same place and can't be combined with one either
+    pub fn is_storage_class(s: &SyntaxKind) -> bool {
+        matches!(s, SyntaxKind::StaticKeyword | SyntaxKind::ExternKeyword | SyntaxKind::TypedefKeyword)
+    }
+
+    // Anything that can start a declaration
+    pub fn is_specifier(s: &SyntaxKind) -> bool {
+        SyntaxKind::is_type_keyword(s) || SyntaxKind::is_storage_class(s)
+    }
+
+    // Higher binds tighter, anything that isn't a binary operator has no precedence
+    pub fn binary_precedence(s: &SyntaxKind) -> Option<u8> {
+        use SyntaxKind::*;
+        match s {
+            Star | Slash | Percent => Some(50),
+            Plus | Minus => Some(45),
+            LessLess | GreaterGreater => Some(40),
+            Less | LessEqual | Greater | GreaterEqual => Some(35),
+            EqualEqual | BangEqual => Some(30),
+            Ampersand => Some(25),
+            Caret => Some(20),
+            Pipe => Some(15),
+            AmpersandAmpersand => Some(10),
+            PipePipe => Some(5),
+            Equal => Some(1),
+            _ if SyntaxKind::compound_operator(s).is_some() => Some(1),
+            _ => None
+        }
+    }
 }
 
 
@@ -93,6 +290,16 @@ impl SyntaxKind {
 pub struct Location {
     pub(crate) line: usize,
     pub(crate) column: usize,
+    // Only known when the source came out of a file rather than a string
+    pub(crate) file: Option<Rc<str>>,
+    // The use of a macro the token was written in the definition of, if it came out of one
+    pub(crate) expansion: Option<Rc<Expansion>>,
+}
+
+// Where a macro was used, which can itself be inside the expansion of another one
+pub struct Expansion {
+    pub name: String,
+    pub location: Location,
 }
 
 impl Location {
@@ -100,9 +307,19 @@ impl Location {
         Location {
             line,
             column,
+            file: None,
+            expansion: None,
         }
     }
 
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    pub fn expansion(&self) -> Option<&Expansion> {
+        self.expansion.as_deref()
+    }
+
     pub fn line(&self) -> usize {
         self.line
     }
@@ -132,12 +349,21 @@ impl Clone for Location {
         Location {
             line: self.line,
             column: self.column,
+            file: self.file.clone(),
+            expansion: self.expansion.clone(),
         }
     }
 }
 impl fmt::Display for Location {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}, {}", self.line, self.column)
+        if let Some(file) = self.file.as_ref() {
+            write!(f, "{}:", file)?;
+        }
+        write!(f, "{}, {}", self.line, self.column)?;
+        match self.expansion.as_ref() {
+            Some(expansion) => write!(f, " in expansion of '{}' at {}", expansion.name, expansion.location),
+            None => Ok(()),
+        }
     }
 }
 
@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod lexer_tests {
-    use crate::lexer::Lexer;
+    use crate::lexer::{unescape, Lexer};
     use crate::lexer::syntax_token::*;
 
     #[test]
@@ -58,10 +58,7 @@ mod lexer_tests {
         assert_eq!(lexer.file.len(), str.len());
         assert_eq!(lexer.tokens.len(), 1);
         let token = lexer.tokens.pop_front().unwrap();
-        assert_eq!(token.location, Location {
-            column: 1,
-            line: 1,
-        });
+        assert_eq!(token.location, Location::new(1, 1));
 
 
 
@@ -116,6 +113,88 @@ mod lexer_tests {
         assert_eq!(token.data, str);
     }
 
+    #[test]
+    fn lexing_hexadecimal_numbers() {
+        let mut lexer = Lexer::new("0x1Fu+0XabcLL-0x1e5");
+        lexer.lex();
+        let tokens: Vec<(SyntaxKind, String)> = lexer.tokens.into_iter().map(|token| (token.kind, token.data)).collect();
+        assert_eq!(tokens, vec![
+            (SyntaxKind::NumberLiteral, "0x1Fu".to_string()),
+            (SyntaxKind::Plus, "+".to_string()),
+            (SyntaxKind::NumberLiteral, "0XabcLL".to_string()),
+            (SyntaxKind::Minus, "-".to_string()),
+            (SyntaxKind::NumberLiteral, "0x1e5".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn lexing_floating_numbers() {
+        for str in ["1.5", ".25", "3.", "1e10", "2.5E-3", "6e+2"] {
+            let mut lexer = Lexer::new(str);
+            lexer.lex();
+
+            assert_eq!(lexer.tokens.len(), 1);
+            let token = lexer.tokens.pop_front().unwrap();
+            assert_eq!(token.kind, SyntaxKind::NumberLiteral);
+            assert_eq!(token.data, str);
+        }
+
+        let mut lexer = Lexer::new("1.5+x");
+        lexer.lex();
+        assert_eq!(lexer.tokens.len(), 3);
+        assert_eq!(lexer.tokens.back().unwrap().location, Location::new(1, 5));
+    }
+
+    #[test]
+    fn lexing_subscripts() {
+        let mut lexer = Lexer::new("sizeof a[12]");
+        lexer.lex();
+        let kinds: Vec<SyntaxKind> = lexer.tokens.iter().map(|token| token.kind.clone()).collect();
+        assert_eq!(kinds, vec![
+            SyntaxKind::SizeofKeyword,
+            SyntaxKind::StringLiteral,
+            SyntaxKind::OpenBracket,
+            SyntaxKind::NumberLiteral,
+            SyntaxKind::CloseBracket,
+        ]);
+    }
+
+    #[test]
+    fn lexing_member_access() {
+        let mut lexer = Lexer::new("struct s x; x.a->b-.5");
+        lexer.lex();
+        let kinds: Vec<SyntaxKind> = lexer.tokens.iter().map(|token| token.kind.clone()).collect();
+        assert_eq!(kinds, vec![
+            SyntaxKind::StructKeyword,
+            SyntaxKind::StringLiteral,
+            SyntaxKind::StringLiteral,
+            SyntaxKind::Semicolon,
+            SyntaxKind::StringLiteral,
+            SyntaxKind::Dot,
+            SyntaxKind::StringLiteral,
+            SyntaxKind::Arrow,
+            SyntaxKind::StringLiteral,
+            SyntaxKind::Minus,
+            SyntaxKind::NumberLiteral,
+        ]);
+    }
+
+    #[test]
+    fn lexing_enum() {
+        let mut lexer = Lexer::new("enum e { A = 1 };");
+        lexer.lex();
+        let kinds: Vec<SyntaxKind> = lexer.tokens.iter().map(|token| token.kind.clone()).collect();
+        assert_eq!(kinds, vec![
+            SyntaxKind::EnumKeyword,
+            SyntaxKind::StringLiteral,
+            SyntaxKind::OpenCurly,
+            SyntaxKind::StringLiteral,
+            SyntaxKind::Equal,
+            SyntaxKind::NumberLiteral,
+            SyntaxKind::CloseCurly,
+            SyntaxKind::Semicolon,
+        ]);
+    }
 
     // From this test, we should be able to conclude that all the locations are as expected!
     #[test]
@@ -129,81 +208,90 @@ mod lexer_tests {
         assert_eq!(lexer.file.len(), str.len());
         let mut token = lexer.tokens.pop_front().unwrap();
         assert_eq!(token.kind, SyntaxKind::Plus);
-        assert_eq!(token.location, Location {
-            line: 1,
-            column: 1
-        });
+        assert_eq!(token.location, Location::new(1, 1));
 
         token = lexer.tokens.pop_front().unwrap();
         assert_eq!(token.kind, SyntaxKind::Minus);
-        assert_eq!(token.location, Location {
-            line: 1,
-            column: 3
-        });
+        assert_eq!(token.location, Location::new(1, 3));
 
         token = lexer.tokens.pop_front().unwrap();
         assert_eq!(token.kind, SyntaxKind::Star);
-        assert_eq!(token.location, Location {
-            line: 1,
-            column: 5
-        });
+        assert_eq!(token.location, Location::new(1, 5));
 
         token = lexer.tokens.pop_front().unwrap();
         assert_eq!(token.kind, SyntaxKind::Slash);
-        assert_eq!(token.location, Location {
-            line: 1,
-            column: 7
-        });
+        assert_eq!(token.location, Location::new(1, 7));
 
         token = lexer.tokens.pop_front().unwrap();
         assert_eq!(token.kind, SyntaxKind::OpenParen);
-        assert_eq!(token.location, Location {
-            line: 1,
-            column: 9
-        });
+        assert_eq!(token.location, Location::new(1, 9));
 
         token = lexer.tokens.pop_front().unwrap();
         assert_eq!(token.kind, SyntaxKind::NewLine);
-        assert_eq!(token.location, Location {
-            line: 1,
-            column: 11
-        });
+        assert_eq!(token.location, Location::new(1, 11));
 
         token = lexer.tokens.pop_front().unwrap();
         assert_eq!(token.kind, SyntaxKind::CloseParen);
-        assert_eq!(token.location, Location {
-            line: 2,
-            column: 2
-        });
+        assert_eq!(token.location, Location::new(2, 2));
 
         token = lexer.tokens.pop_front().unwrap();
         assert_eq!(token.kind, SyntaxKind::OpenCurly);
-        assert_eq!(token.location, Location {
-            line: 2,
-            column: 4
-        });
+        assert_eq!(token.location, Location::new(2, 4));
 
         token = lexer.tokens.pop_front().unwrap();
         assert_eq!(token.kind, SyntaxKind::CloseCurly);
-        assert_eq!(token.location, Location {
-            line: 2,
-            column: 6
-        });
+        assert_eq!(token.location, Location::new(2, 6));
 
         token = lexer.tokens.pop_front().unwrap();
         assert_eq!(token.kind, SyntaxKind::Equal);
-        assert_eq!(token.location, Location {
-            line: 2,
-            column: 8
-        });
+        assert_eq!(token.location, Location::new(2, 8));
 
         token = lexer.tokens.pop_front().unwrap();
         assert_eq!(token.kind, SyntaxKind::EqualEqual);
-        assert_eq!(token.location, Location {
-            line: 2,
-            column: 10
-        });
+        assert_eq!(token.location, Location::new(2, 10));
+    }
+    #[test]
+    fn compound_symbols() {
+        let str = "+= -= *= /= %= &= |= ^= <<= >>= ++ -- << >> && || & |";
+        let mut lexer = Lexer::new(str);
+        lexer.lex();
+
+        let expected = [
+            SyntaxKind::PlusEqual,
+            SyntaxKind::MinusEqual,
+            SyntaxKind::StarEqual,
+            SyntaxKind::SlashEqual,
+            SyntaxKind::PercentEqual,
+            SyntaxKind::AmpersandEqual,
+            SyntaxKind::PipeEqual,
+            SyntaxKind::CaretEqual,
+            SyntaxKind::LessLessEqual,
+            SyntaxKind::GreaterGreaterEqual,
+            SyntaxKind::PlusPlus,
+            SyntaxKind::MinusMinus,
+            SyntaxKind::LessLess,
+            SyntaxKind::GreaterGreater,
+            SyntaxKind::AmpersandAmpersand,
+            SyntaxKind::PipePipe,
+            SyntaxKind::Ampersand,
+            SyntaxKind::Pipe,
+        ];
+        assert_eq!(lexer.tokens.len(), expected.len());
+        for kind in expected {
+            assert_eq!(lexer.tokens.pop_front().unwrap().kind, kind);
+        }
+
+        let str = "a+++b";
+        let mut lexer = Lexer::new(str);
+        lexer.lex();
+        assert_eq!(lexer.tokens.len(), 4);
+        let token = lexer.tokens.iter().nth(1).unwrap();
+        assert_eq!(token.kind, SyntaxKind::PlusPlus);
+        assert_eq!(token.data, "++");
+        assert_eq!(token.length, 2);
+        assert_eq!(lexer.tokens.iter().nth(2).unwrap().location, Location::new(1, 4));
     }
+
     #[test]
     fn basic_c_program() {
         let str = r#"
@@ -236,4 +324,98 @@ mod lexer_tests {
         assert_eq!(lexer.tokens.pop_front().unwrap().kind, SyntaxKind::CloseCurly);
         assert_eq!(lexer.tokens.pop_front().unwrap().kind, SyntaxKind::NewLine);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn lexing_arithmetic_types() {
+        let mut lexer = Lexer::new("short float _Bool 1.5f 3ULL");
+        lexer.lex();
+        let kinds: Vec<SyntaxKind> = lexer.tokens.iter().map(|token| token.kind.clone()).collect();
+        assert_eq!(kinds, vec![
+            SyntaxKind::ShortKeyword,
+            SyntaxKind::FloatKeyword,
+            SyntaxKind::BoolKeyword,
+            SyntaxKind::NumberLiteral,
+            SyntaxKind::NumberLiteral,
+        ]);
+        let literals: Vec<&str> = lexer.tokens.iter().skip(3).map(|token| token.data.as_str()).collect();
+        assert_eq!(literals, vec!["1.5f", "3ULL"]);
+    }
+
+    #[test]
+    fn lexing_ellipsis() {
+        let mut lexer = Lexer::new("int f(int a, ...); s.x");
+        lexer.lex();
+        let kinds: Vec<SyntaxKind> = lexer.tokens.iter().skip(6).map(|token| token.kind.clone()).collect();
+        assert_eq!(kinds, vec![
+            SyntaxKind::Ellipsis,
+            SyntaxKind::CloseParen,
+            SyntaxKind::Semicolon,
+            SyntaxKind::StringLiteral,
+            SyntaxKind::Dot,
+            SyntaxKind::StringLiteral,
+        ]);
+    }
+
+    #[test]
+    fn lexing_preprocessor_tokens() {
+        let mut lexer = Lexer::new("# ## \"a \\\"b\\\"\" // c\n /* d \n e */ x \\\n y");
+        lexer.lex();
+        let kinds: Vec<SyntaxKind> = lexer.tokens.iter().map(|token| token.kind.clone()).collect();
+        assert_eq!(kinds, vec![
+            SyntaxKind::Hash,
+            SyntaxKind::HashHash,
+            SyntaxKind::QuotedString,
+            SyntaxKind::NewLine,
+            SyntaxKind::StringLiteral,
+            SyntaxKind::StringLiteral,
+        ]);
+        assert_eq!(lexer.tokens.iter().nth(2).unwrap().data, "\"a \\\"b\\\"\"");
+        // Comments and spliced lines still count towards the lines
+        assert_eq!(lexer.tokens.back().unwrap().location, Location::new(4, 2));
+    }
+
+    #[test]
+    fn lexing_character_constants() {
+        let mut lexer = Lexer::new(r"'a' ? '\'' : '\\'");
+        lexer.lex();
+        let kinds: Vec<SyntaxKind> = lexer.tokens.iter().map(|token| token.kind.clone()).collect();
+        assert_eq!(kinds, vec![
+            SyntaxKind::QuotedChar,
+            SyntaxKind::Question,
+            SyntaxKind::QuotedChar,
+            SyntaxKind::Colon,
+            SyntaxKind::QuotedChar,
+        ]);
+        assert_eq!(unescape(lexer.tokens.iter().nth(2).unwrap()), b"'".to_vec());
+    }
+
+    #[test]
+    fn unescaping_strings() {
+        let mut lexer = Lexer::new(r#""a\tb\"\\\101\x42\0\?""#);
+        lexer.lex();
+        assert_eq!(unescape(lexer.tokens.front().unwrap()), b"a\tb\"\\AB\0?".to_vec());
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown escape sequence '\\q' in \"\\q\" at 1, 1")]
+    fn unescaping_unknown_sequence() {
+        let mut lexer = Lexer::new(r#""\q""#);
+        lexer.lex();
+        unescape(lexer.tokens.front().unwrap());
+    }
+
+    #[test]
+    fn lexing_splices_with_crlf() {
+        let mut lexer = Lexer::new("a \\\r\nb\r\n");
+        lexer.lex();
+        let tokens: Vec<(&str, usize)> = lexer.tokens.iter().map(|token| (token.data.as_str(), token.location.line())).collect();
+        assert_eq!(tokens, vec![("a", 1), ("b", 2), ("\n", 2)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unable to lex character @ at 2, 3")]
+    fn lexing_unknown_character() {
+        let mut lexer = Lexer::new("a\nb @");
+        lexer.lex();
+    }
+}
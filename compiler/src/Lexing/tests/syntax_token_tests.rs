@@ -7,6 +7,8 @@ mod syntax_token_tests {
         let mut location = Location {
             line : 0,
             column : 0,
+            file : None,
+            expansion : None,
         };
 
         assert_eq!(location.line, 0);
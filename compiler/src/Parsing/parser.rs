@@ -1,57 +1,517 @@
-use crate::lexer::syntax_token::SyntaxKind;
-use crate::lexer::LexerToken;
-use dyn_clone::{clone_box, DynClone};
-use std::collections::LinkedList;
+use crate::lexer::syntax_token::{Location, SyntaxKind};
+use crate::lexer::{unescape, IntegerLiteral, LexerToken};
+use crate::resolver::Resolvable;
+use crate::tacky::{LowerExpression, LowerStatement};
+use crate::type_checker::{SymbolTable, TypeCheckable};
+use crate::types::{Constant, Qualifiers, Type};
+use dyn_clone::DynClone;
+use std::collections::{HashMap, HashSet, LinkedList};
 
 
-pub trait Statement: Expression + DynClone {
-    fn get_expressions(&self) -> Option<Vec<Box<dyn Expression>>> ;
+pub trait Statement: Resolvable + TypeCheckable + LowerStatement + DynClone {
 }
 
 
-pub trait Expression: DynClone {
+pub trait Expression: Resolvable + TypeCheckable + LowerExpression + DynClone {
+    fn location(&self) -> Location;
 
+    // Only valid once the type checker has been over the expression
+    fn get_type(&self) -> Type;
+
+    // Only things that name a storage location can be assigned to
+    fn is_lvalue(&self) -> bool {
+        false
+    }
+
+    // Folds the expression down to a single value if it can be computed at compile time
+    fn constant_value(&self) -> Option<Constant> {
+        None
+    }
+
+    // The function an expression names when it's nothing but the name, which makes calling it a direct call
+    fn designated_function(&self) -> Option<&str> {
+        None
+    }
+
+    // The object or function with static storage an lvalue or function designator names, as the symbol it's in and how
+    // many bytes into it. Any of them can be pointed at before the program starts
+    fn static_object(&self, _symbols: &mut SymbolTable) -> Option<(String, i64)> {
+        None
+    }
+
+    // Where a pointer points when that's known before the program starts, the same way as for static_object.
+    // Besides null it's all a static pointer can start out as
+    fn static_address(&self, _symbols: &mut SymbolTable) -> Option<(String, i64)> {
+        None
+    }
+
+    // What a string literal holds, which is enough to initialize a character array on its own
+    fn string_literal(&self) -> Option<&[u8]> {
+        None
+    }
 }
+
+dyn_clone::clone_trait_object!(Statement);
+dyn_clone::clone_trait_object!(Expression);
+
 pub struct Parser {
     current: Option<LexerToken>,
     lexer_tokens: LinkedList<LexerToken>,
-    parsed_tokens: LinkedList<Box<dyn Statement>>
+    pub parsed_tokens: LinkedList<Box<dyn Statement>>,
+    // Structures and enumerations defined inside the specifiers of the declaration we're in the middle of
+    tag_definitions: Vec<TagDefinition>,
+    // Structures without a tag get a made up one, which nobody can write in the source
+    anonymous_structs: usize,
+    // Ordinary identifiers in scope, innermost at the back. Enumerators are replaced by their value
+    // as soon as they're used, so array sizes and case labels can be folded right away.
+    // Typedef names are what make `T * x;` a declaration rather than a multiplication
+    scopes: Vec<HashMap<String, OrdinaryName>>,
+    // Tags of the enumerations defined so far, pushed and popped along with the scopes above
+    enum_tags: Vec<HashSet<String>>,
+}
+
+// All the parser needs to know about a name, the resolver takes care of everything else
+#[derive(Clone)]
+enum OrdinaryName {
+    Object,
+    Enumerator(i64),
+    Typedef(Type),
+}
+
+enum TagDefinition {
+    Struct(StructDeclaration),
+    Enum(EnumDeclaration),
+}
+
+impl TagDefinition {
+    fn keyword(&self) -> &LexerToken {
+        match self {
+            TagDefinition::Struct(definition) => &definition.keyword,
+            TagDefinition::Enum(definition) => &definition.keyword,
+        }
+    }
+
+    fn into_statement(self) -> Box<dyn Statement> {
+        match self {
+            TagDefinition::Struct(definition) => Box::new(definition),
+            TagDefinition::Enum(definition) => Box::new(definition),
+        }
+    }
 }
 
 // Statement
+#[derive(Clone)]
 pub struct FunctionStatement {
-    func_type: LexerToken,
-    identifier: LexerToken,
+    // The type of the value the function returns
+    pub func_type: Type,
+    pub storage_class: Option<LexerToken>,
+    pub identifier: LexerToken,
+    pub open_paren: LexerToken,
+    pub parameters: Vec<Parameter>,
+    // Set when the parameter list ends in `...`
+    pub variadic: bool,
+    pub close_paren: LexerToken,
+    // Declarations without a definition don't have a body
+    pub func_body: Option<Body>,
+}
+
+#[derive(Clone)]
+pub struct Parameter {
+    pub param_type: Type,
+    // Parameters of a declaration don't need a name
+    pub identifier: Option<LexerToken>,
+}
+
+// The shape of a declaration around its name, `*(*fp)(int)` nests a pointer in a function in a pointer
+enum Declarator {
+    // Abstract declarators leave the name out
+    Name(Option<LexerToken>),
+    // The qualifiers after the star belong to the pointer itself
+    Pointer(Box<Declarator>, Qualifiers),
+    Array {
+        inner: Box<Declarator>,
+        open_bracket: LexerToken,
+        // Left out for `char *argv[]`
+        size: Option<usize>,
+    },
+    Function {
+        inner: Box<Declarator>,
+        parts: FunctionParts,
+    },
+}
+
+struct FunctionParts {
     open_paren: LexerToken,
-    pub parameters: Vec<Box<LexerToken>>,
+    parameters: Vec<Parameter>,
+    variadic: bool,
     close_paren: LexerToken,
-    func_body: Body,
 }
 
+// What a declarator turns into once it's been applied to the base type
+struct Declared {
+    identifier: Option<LexerToken>,
+    ty: Type,
+    // Set when the name is declared as a function, so the definition knows its parameters
+    function: Option<FunctionParts>,
+}
+
+#[derive(Clone)]
 pub struct Body {
-    open_curly: LexerToken,
+    pub open_curly: LexerToken,
     pub statements: Vec<Box<dyn Statement>>,
-    close_curly: LexerToken,
+    pub close_curly: LexerToken,
+}
+
+#[derive(Clone)]
+pub struct VariableDeclaration {
+    pub var_type: Type,
+    pub storage_class: Option<LexerToken>,
+    pub identifier: LexerToken,
+    pub initializer: Option<Initializer>,
+}
+
+// What a variable starts out holding, arrays get a braced list with one level of braces per dimension
+#[derive(Clone)]
+pub enum Initializer {
+    Single(Box<dyn Expression>),
+    Compound {
+        open_curly: LexerToken,
+        elements: Vec<Initializer>,
+    },
+    // `.x = 1` or `[3] = 7` in a braced list, the type checker puts every one of these in its place
+    // so nothing after it has to know about them
+    Designated {
+        designators: Vec<Designator>,
+        initializer: Box<Initializer>,
+    },
+}
+
+// One step of a designation, `.a[2].b` takes three
+#[derive(Clone)]
+pub enum Designator {
+    Member(LexerToken),
+    Index { open_bracket: LexerToken, index: usize },
+}
+
+// A structure or union on its own, either `struct s;` or a definition pulled out of the specifiers of a declaration
+#[derive(Clone)]
+pub struct StructDeclaration {
+    // Whether it's a struct or a union
+    pub keyword: LexerToken,
+    pub tag: LexerToken,
+    // Left out when the type is only declared, it stays incomplete until a definition turns up
+    pub members: Option<Vec<MemberDeclaration>>,
+}
+
+// An enumeration is only ever an int, the declaration is there so the resolver sees its tag and enumerators
+#[derive(Clone)]
+pub struct EnumDeclaration {
+    pub keyword: LexerToken,
+    pub tag: Option<LexerToken>,
+    pub enumerators: Vec<Enumerator>,
+}
+
+#[derive(Clone)]
+pub struct Enumerator {
+    pub identifier: LexerToken,
+    pub value: i64,
+}
+
+#[derive(Clone)]
+pub struct MemberDeclaration {
+    pub member_type: Type,
+    pub identifier: LexerToken,
+}
+
+#[derive(Clone)]
+pub struct ExpressionStatement {
+    pub expression: Box<dyn Expression>,
+    pub semicolon: LexerToken,
+}
+
+#[derive(Clone)]
+pub struct NullStatement {
+    pub semicolon: LexerToken,
+}
+
+// A case that has been attached to its switch, the value is None for the default case
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwitchCase {
+    pub value: Option<i64>,
+    pub label: String,
+    // Where the case was written, for telling it apart from another case with the same value
+    pub location: Location,
+}
+
+#[derive(Clone)]
+pub struct SwitchStatement {
+    pub switch_kw: LexerToken,
+    pub condition: Box<dyn Expression>,
+    pub body: Box<dyn Statement>,
+    // Both of these are filled in by the resolver
+    pub label: String,
+    pub cases: Vec<SwitchCase>,
+}
+
+#[derive(Clone)]
+pub struct CaseStatement {
+    pub case_kw: LexerToken,
+    pub value: Box<dyn Expression>,
+    pub colon: LexerToken,
+    pub statement: Box<dyn Statement>,
+    pub label: String,
+}
+
+#[derive(Clone)]
+pub struct DefaultStatement {
+    pub default_kw: LexerToken,
+    pub colon: LexerToken,
+    pub statement: Box<dyn Statement>,
+    pub label: String,
+}
+
+#[derive(Clone)]
+pub struct LabeledStatement {
+    pub label: LexerToken,
+    pub colon: LexerToken,
+    pub statement: Box<dyn Statement>,
+}
+
+#[derive(Clone)]
+pub struct GotoStatement {
+    pub goto_kw: LexerToken,
+    pub label: LexerToken,
+    pub semicolon: LexerToken,
+}
+
+#[derive(Clone)]
+pub struct BreakStatement {
+    pub break_kw: LexerToken,
+    pub semicolon: LexerToken,
+    // The label of the statement we're breaking out of, filled in by the resolver
+    pub label: String,
 }
 
 // Expressions
 #[derive(Clone)]
 pub struct ReturnExpr {
-    return_kw: LexerToken,
-    pub value: LexerToken,
-    semicolon: LexerToken,
+    pub return_kw: LexerToken,
+    pub value: Option<Box<dyn Expression>>,
+    pub semicolon: LexerToken,
 }
+
+#[derive(Clone)]
+pub struct ConstantExpr {
+    pub token: LexerToken,
+    pub value: i64,
+    // Unlike every other expression the parser knows this one, the literal spells out its type
+    pub ty: Option<Type>,
+}
+
+#[derive(Clone)]
+pub struct VariableExpr {
+    pub identifier: LexerToken,
+    // Filled in by the type checker
+    pub ty: Option<Type>,
+}
+
+#[derive(Clone)]
+pub struct UnaryExpr {
+    pub operator: LexerToken,
+    pub operand: Box<dyn Expression>,
+    // Filled in by the type checker
+    pub ty: Option<Type>,
+}
+
+#[derive(Clone)]
+pub struct BinaryExpr {
+    pub left: Box<dyn Expression>,
+    pub operator: LexerToken,
+    pub right: Box<dyn Expression>,
+    // Filled in by the type checker
+    pub ty: Option<Type>,
+}
+
+#[derive(Clone)]
+pub struct AssignmentExpr {
+    pub left: Box<dyn Expression>,
+    pub equal: LexerToken,
+    pub right: Box<dyn Expression>,
+    // Filled in by the type checker
+    pub ty: Option<Type>,
+}
+
+#[derive(Clone)]
+pub struct CompoundAssignmentExpr {
+    pub left: Box<dyn Expression>,
+    pub operator: LexerToken,
+    pub right: Box<dyn Expression>,
+    // Filled in by the type checker
+    pub ty: Option<Type>,
+}
+
+#[derive(Clone)]
+pub struct FunctionCallExpr {
+    // A function designator or a pointer to a function
+    pub function: Box<dyn Expression>,
+    pub arguments: Vec<Box<dyn Expression>>,
+    pub close_paren: LexerToken,
+    // Filled in by the type checker
+    pub ty: Option<Type>,
+}
+
+#[derive(Clone)]
+pub struct AddressOfExpr {
+    pub ampersand: LexerToken,
+    pub operand: Box<dyn Expression>,
+    // Filled in by the type checker
+    pub ty: Option<Type>,
+}
+
+#[derive(Clone)]
+pub struct DereferenceExpr {
+    pub star: LexerToken,
+    pub operand: Box<dyn Expression>,
+    // Filled in by the type checker
+    pub ty: Option<Type>,
+}
+
+// a[i] is the same as *(a + i), so the pointer can be on either side
+#[derive(Clone)]
+pub struct SubscriptExpr {
+    pub left: Box<dyn Expression>,
+    pub open_bracket: LexerToken,
+    pub index: Box<dyn Expression>,
+    // Filled in by the type checker
+    pub ty: Option<Type>,
+}
+
+// `sizeof(type)`, the other form of sizeof which takes a type name instead of an expression
+#[derive(Clone)]
+pub struct SizeofTypeExpr {
+    pub sizeof_kw: LexerToken,
+    pub target: Type,
+    // Filled in by the type checker
+    pub ty: Option<Type>,
+    pub size: Option<usize>,
+}
+
+// `s.member`, or `p->member` which is the same as `(*p).member`
+#[derive(Clone)]
+pub struct MemberExpr {
+    pub structure: Box<dyn Expression>,
+    // Either a dot or an arrow
+    pub operator: LexerToken,
+    pub member: LexerToken,
+    // Filled in by the type checker
+    pub ty: Option<Type>,
+}
+
+// The operand is never evaluated, only its type matters
+#[derive(Clone)]
+pub struct SizeofExpr {
+    pub sizeof_kw: LexerToken,
+    pub operand: Box<dyn Expression>,
+    // Filled in by the type checker, along with the size of the operand since structures need the symbol table for theirs
+    pub ty: Option<Type>,
+    pub size: Option<usize>,
+}
+
+// Either a `(type)expr` from the source or a conversion the type checker slots in between an expression and whoever uses it
+#[derive(Clone)]
+pub struct CastExpr {
+    pub target: Type,
+    pub expression: Box<dyn Expression>,
+    // Only casts written out in the source have one
+    pub open_paren: Option<LexerToken>,
+}
+
+// `(type){...}`, an unnamed object that's initialized every time the expression is evaluated.
+// It lives as long as the block it's in, or the whole program outside of a function
+#[derive(Clone)]
+pub struct CompoundLiteralExpr {
+    pub open_paren: LexerToken,
+    pub target: Type,
+    pub initializer: Initializer,
+    // The variable the object lives in, filled in by the resolver
+    pub name: String,
+}
+
+// `"..."`, an array of char with static storage that holds the characters and a null after them.
+// Literals next to each other are one literal, `"a" "b"` is `"ab"`
+#[derive(Clone)]
+pub struct StringLiteralExpr {
+    pub token: LexerToken,
+    pub value: Vec<u8>,
+    // The constant the array lives in, filled in by the resolver
+    pub name: String,
+}
+
+// __builtin_va_start, __builtin_va_arg, __builtin_va_end and __builtin_va_copy, which <stdarg.h> defines the usual
+// names as. They need the compiler's help to get at the arguments behind a `...`
+#[derive(Clone)]
+pub struct VaBuiltinExpr {
+    pub builtin: VaBuiltin,
+    pub identifier: LexerToken,
+    // The va_list always comes first, va_start also takes the last named parameter and va_copy the list to copy from
+    pub arguments: Vec<Box<dyn Expression>>,
+    // The type va_arg reads
+    pub target: Option<Type>,
+    // Filled in by the type checker
+    pub ty: Option<Type>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VaBuiltin {
+    Start,
+    Arg,
+    End,
+    Copy,
+}
+
+impl VaBuiltin {
+    // Only the reserved names are built in, the ones without the prefix are macros in <stdarg.h>
+    fn from_name(name: &str) -> Option<VaBuiltin> {
+        match name {
+            "__builtin_va_start" => Some(VaBuiltin::Start),
+            "__builtin_va_arg" => Some(VaBuiltin::Arg),
+            "__builtin_va_end" => Some(VaBuiltin::End),
+            "__builtin_va_copy" => Some(VaBuiltin::Copy),
+            _ => None,
+        }
+    }
+}
+
+// ++ and --, either in front of the operand or behind it
+#[derive(Clone)]
+pub struct IncrementExpr {
+    pub operator: LexerToken,
+    pub operand: Box<dyn Expression>,
+    pub postfix: bool,
+    // Filled in by the type checker
+    pub ty: Option<Type>,
+}
+
 impl Parser {
     pub fn new(lexed_tokens: LinkedList<LexerToken>) -> Self {
+        // Newlines only matter to the lexer for keeping track of locations
+        let lexer_tokens = lexed_tokens
+            .into_iter()
+            .filter(|token| token.kind != SyntaxKind::NewLine)
+            .collect();
         Parser {
             current: None,
-            lexer_tokens: lexed_tokens,
+            lexer_tokens,
             parsed_tokens: LinkedList::new(),
+            tag_definitions: Vec::new(),
+            anonymous_structs: 0,
+            scopes: vec![builtin_typedefs()],
+            enum_tags: vec![HashSet::new()],
         }
     }
 
     pub fn expect(&mut self, expected: SyntaxKind) -> bool{
-        if self.current.clone().unwrap().kind != expected {
+        if self.current_token().kind != expected {
             return false
         }
         self.current = self.lexer_tokens.pop_front();
@@ -62,87 +522,741 @@ impl Parser {
         self.current = self.lexer_tokens.pop_front();
     }
 
-    // We're expecting
-    /*
-    int main(void) {
-        return <const>;
+    fn current_token(&self) -> &LexerToken {
+        match self.current.as_ref() {
+            Some(token) => token,
+            None => panic!("Unexpected end of file"),
+        }
     }
-    */
+
+    fn current_kind(&self) -> Option<&SyntaxKind> {
+        self.current.as_ref().map(|token| &token.kind)
+    }
+
+    // The kind of the token straight after the current one
+    fn peek_kind(&self) -> Option<&SyntaxKind> {
+        self.lexer_tokens.front().map(|token| &token.kind)
+    }
+
+    // Same as expect, except we hand back the token and refuse to carry on if it isn't there
+    fn consume(&mut self, expected: SyntaxKind) -> LexerToken {
+        let token = self.current_token().clone();
+        if !self.expect(expected.clone()) {
+            panic!("Expected {:?} but found '{}' at {}", expected, token.data, token.location)
+        }
+        token
+    }
+
+    // A file is a list of declarations, functions and variables
     pub fn start_parsing(&mut self) {
         self.current = self.lexer_tokens.pop_front();
-        while self.current.is_some() {
-            self.parse_statement();
+        while let Some(token) = self.current.as_ref() {
+            if !self.starts_declaration() {
+                panic!("Expected a declaration but found '{}' at {}", token.data, token.location)
+            }
+            let declarations = self.parse_declaration();
+            self.parsed_tokens.extend(declarations);
         }
     }
 
-    fn parse_statement(& mut self) -> Box<dyn Statement> {
-        let cur = self.current.as_ref().unwrap();
-        // If we have a type keyword we try to parse a function, then an assignment
-        if SyntaxKind::is_type_keyword(&cur.kind) {
-
-            // I probably shouldn't be doing this however
-            // It makes sense to keep it as the same reference
-            // We don't really want it to be duped.
-            // Sure it limits ownership but that's kinda the whole point of rust
-            let func = Box::new(self.parse_func());
-            let pushback = (*func).clone();
-
-            self.parsed_tokens.push_back(func);
-            Box::new(pushback)
-        } else if cur.kind == SyntaxKind::ReturnKeyword {
-            Box::new(self.parse_return())
+    // A typedef name followed by a colon is a label, labels have a namespace of their own
+    fn starts_declaration(&self) -> bool {
+        let Some(token) = self.current.as_ref() else { return false };
+        SyntaxKind::is_specifier(&token.kind)
+            || (self.is_typedef_name(Some(token)) && self.peek_kind() != Some(&SyntaxKind::Colon))
+    }
+
+    fn parse_block_item(&mut self) -> Vec<Box<dyn Statement>> {
+        if self.starts_declaration() {
+            self.parse_declaration()
         } else {
-            // Error
-            panic!()
+            vec![self.parse_statement()]
+        }
+    }
+
+    fn parse_statement(& mut self) -> Box<dyn Statement> {
+        let cur = self.current_token();
+        match cur.kind {
+            SyntaxKind::ReturnKeyword => Box::new(self.parse_return()),
+            SyntaxKind::SwitchKeyword => Box::new(self.parse_switch()),
+            SyntaxKind::CaseKeyword => Box::new(self.parse_case()),
+            SyntaxKind::DefaultKeyword => Box::new(self.parse_default()),
+            SyntaxKind::BreakKeyword => Box::new(self.parse_break()),
+            SyntaxKind::GotoKeyword => Box::new(self.parse_goto()),
+            SyntaxKind::StringLiteral if self.peek_kind() == Some(&SyntaxKind::Colon) => {
+                Box::new(self.parse_labeled())
+            }
+            SyntaxKind::OpenCurly => Box::new(self.parse_body()),
+            SyntaxKind::Semicolon => {
+                let semicolon = self.consume(SyntaxKind::Semicolon);
+                Box::new(NullStatement { semicolon })
+            }
+            _ => {
+                let expression = self.parse_expression(0);
+                let semicolon = self.consume(SyntaxKind::Semicolon);
+                Box::new(ExpressionStatement {
+                    expression,
+                    semicolon,
+                })
+            }
+        }
+    }
+    // Type keywords and the storage class can come in any order, but there can only be one storage class
+    // A typedef name only counts as the type once nothing else has said what the type is, in `unsigned T;` it's the name
+    fn parse_specifiers(&mut self) -> (Type, Option<LexerToken>) {
+        let mut types: Vec<LexerToken> = Vec::new();
+        let mut named: Option<(LexerToken, Type)> = None;
+        let mut storage_class: Option<LexerToken> = None;
+        let mut qualifiers = Qualifiers::default();
+        while let Some(kind) = self.current_kind() {
+            let token = self.current_token().clone();
+            if SyntaxKind::is_type_qualifier(kind) {
+                qualifiers = qualifiers.union(self.parse_qualifiers());
+                continue;
+            }
+            if matches!(kind, SyntaxKind::StructKeyword | SyntaxKind::UnionKeyword | SyntaxKind::EnumKeyword) {
+                if named.is_some() {
+                    panic!("Invalid type specifiers at {}", token.location)
+                }
+                let ty = match kind {
+                    SyntaxKind::EnumKeyword => self.parse_enum_specifier(),
+                    _ => self.parse_struct_specifier(),
+                };
+                named = Some((token, ty));
+                continue;
+            }
+            if types.is_empty() && named.is_none() && let Some(OrdinaryName::Typedef(ty)) = self.lookup_name(&token.data) {
+                named = Some((token, ty.clone()));
+                self.eat_current_token();
+                continue;
+            }
+            if SyntaxKind::is_type_keyword(kind) {
+                types.push(token);
+            } else if SyntaxKind::is_storage_class(kind) {
+                if let Some(previous) = storage_class {
+                    panic!("Unexpected '{}' at {} after '{}'", token.data, token.location, previous.data)
+                }
+                storage_class = Some(token);
+            } else {
+                break;
+            }
+            self.eat_current_token();
+        }
+        // A structure, enumeration or typedef name is a whole type on its own, it can't be combined with any other keyword
+        let ty = match named {
+            Some((_, ty)) if types.is_empty() => ty,
+            Some((keyword, _)) => panic!("Invalid type specifiers at {}", keyword.location),
+            None => self.type_from_specifiers(&types),
+        };
+        (ty.qualified(qualifiers), storage_class)
+    }
+
+    // Saying the same qualifier twice is allowed, it means the same as saying it once
+    fn parse_qualifiers(&mut self) -> Qualifiers {
+        let mut qualifiers = Qualifiers::default();
+        loop {
+            match self.current_kind() {
+                Some(SyntaxKind::ConstKeyword) => qualifiers.constant = true,
+                Some(SyntaxKind::VolatileKeyword) => qualifiers.volatile = true,
+                _ => return qualifiers,
+            }
+            self.eat_current_token();
+        }
+    }
+
+    // The specifiers of anything that isn't a declaration, where a storage class makes no sense
+    fn parse_type(&mut self) -> Type {
+        let (ty, storage_class) = self.parse_specifiers();
+        if let Some(token) = storage_class {
+            panic!("Unexpected '{}' at {}", token.data, token.location)
         }
+        ty
     }
-    fn parse_func(&mut self) -> FunctionStatement {
-        // We know we already have a type keyword
-        let func_type = self.current.as_ref().unwrap().clone();
+
+    // `struct tag`, `struct tag { members }` or `struct { members }`
+    // Definitions get pulled out into a declaration of their own that comes before the one they were in
+    fn parse_struct_specifier(&mut self) -> Type {
+        let keyword = self.current_token().clone();
         self.eat_current_token();
-        let identifier = self.current.as_ref().unwrap().clone();
-        self.expect(SyntaxKind::StringLiteral);
+        let tag = if self.current_kind() == Some(&SyntaxKind::OpenCurly) {
+            let mut tag = keyword.clone();
+            tag.kind = SyntaxKind::StringLiteral;
+            tag.data = format!("<anonymous>.{}", self.anonymous_structs);
+            self.anonymous_structs += 1;
+            tag
+        } else {
+            self.consume(SyntaxKind::StringLiteral)
+        };
+
+        if self.expect(SyntaxKind::OpenCurly) {
+            let mut members = Vec::new();
+            while self.current_token().kind != SyntaxKind::CloseCurly {
+                members.extend(self.parse_members());
+            }
+            if members.is_empty() {
+                panic!("The {} at {} needs at least one member", keyword.data, keyword.location)
+            }
+            self.consume(SyntaxKind::CloseCurly);
+            self.tag_definitions.push(TagDefinition::Struct(StructDeclaration {
+                keyword: keyword.clone(),
+                tag: tag.clone(),
+                members: Some(members),
+            }));
+        }
+
+        match keyword.kind {
+            SyntaxKind::StructKeyword => Type::Struct(tag.data),
+            _ => Type::Union(tag.data),
+        }
+    }
+
+    // `enum tag`, `enum tag { enumerators }` or `enum { enumerators }`, all of which are just int
+    // An enumerator without a value is one more than the one before it, the first one is 0
+    fn parse_enum_specifier(&mut self) -> Type {
+        let keyword = self.consume(SyntaxKind::EnumKeyword);
+        let tag = if self.current_kind() == Some(&SyntaxKind::OpenCurly) {
+            None
+        } else {
+            Some(self.consume(SyntaxKind::StringLiteral))
+        };
+
+        if !self.expect(SyntaxKind::OpenCurly) {
+            let tag = tag.expect("An enum without a tag always has a list");
+            if !self.enum_tags.iter().any(|tags| tags.contains(&tag.data)) {
+                panic!("Use of undefined 'enum {}' at {}", tag.data, tag.location)
+            }
+            return Type::Int;
+        }
+        let mut enumerators = Vec::new();
+        let mut value: i64 = 0;
+        loop {
+            let identifier = self.consume(SyntaxKind::StringLiteral);
+            if self.expect(SyntaxKind::Equal) {
+                // Commas separate enumerators, so every value sits just above them
+                value = match self.parse_expression(1).constant_value() {
+                    Some(constant) if constant.ty.is_integer() => constant.value,
+                    _ => panic!("Value of enumerator '{}' at {} must be an integer constant", identifier.data, identifier.location),
+                };
+            }
+            if i32::try_from(value).is_err() {
+                panic!("Value of enumerator '{}' at {} doesn't fit in an int", identifier.data, identifier.location)
+            }
+            // Already in scope for the values of the enumerators after it
+            self.declare_name(&identifier, OrdinaryName::Enumerator(value));
+            enumerators.push(Enumerator {
+                identifier,
+                value,
+            });
+            value += 1;
+            // The last enumerator can have a comma after it
+            if !self.expect(SyntaxKind::Comma) || self.current_token().kind == SyntaxKind::CloseCurly {
+                break;
+            }
+        }
+        self.consume(SyntaxKind::CloseCurly);
 
-        let open_paren = self.current.as_ref().unwrap().clone();
-        if !self.expect(SyntaxKind::OpenParen) {
-            // Womp womp
+        if let Some(tag) = tag.as_ref() {
+            self.enum_tags
+                .last_mut()
+                .expect("The file scope is always there")
+                .insert(tag.data.clone());
         }
-        let mut parameters: Vec<Box<LexerToken>> = Vec::new();
-        while self.current.as_ref().unwrap().kind != SyntaxKind::CloseCurly {
-            // Right now we only support the word void
-            let param = Box::new(self.current.as_ref().unwrap().clone());
-            self.expect(SyntaxKind::VoidKeyword);
-            parameters.push(param);
+        self.tag_definitions.push(TagDefinition::Enum(EnumDeclaration {
+            keyword,
+            tag,
+            enumerators,
+        }));
+        Type::Int
+    }
+
+    fn enter_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+        self.enum_tags.push(HashSet::new());
+    }
+
+    fn exit_scope(&mut self) {
+        self.scopes.pop();
+        self.enum_tags.pop();
+    }
+
+    // The resolver catches every other clash, but it never sees typedef names
+    // Repeating a typedef with the same type is fine
+    fn declare_name(&mut self, identifier: &LexerToken, meaning: OrdinaryName) {
+        let scope = self.scopes.last_mut().expect("The file scope is always there");
+        match (scope.get(&identifier.data), &meaning) {
+            (Some(OrdinaryName::Typedef(old)), OrdinaryName::Typedef(ty)) if old != ty => {
+                panic!("Conflicting types for '{}' at {}, it was declared as '{}' before", identifier.data, identifier.location, old)
+            }
+            (Some(OrdinaryName::Typedef(_)), OrdinaryName::Typedef(_)) => {}
+            (Some(OrdinaryName::Typedef(_)), _) | (Some(_), OrdinaryName::Typedef(_)) => {
+                panic!("Redeclaration of '{}' at {}", identifier.data, identifier.location)
+            }
+            _ => {}
         }
+        scope.insert(identifier.data.clone(), meaning);
+    }
+
+    fn lookup_name(&self, name: &str) -> Option<&OrdinaryName> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn is_typedef_name(&self, token: Option<&LexerToken>) -> bool {
+        token.is_some_and(|token| {
+            token.kind == SyntaxKind::StringLiteral && matches!(self.lookup_name(&token.data), Some(OrdinaryName::Typedef(_)))
+        })
+    }
 
-        let close_paren = self.current.as_ref().unwrap().clone();
-        self.expect(SyntaxKind::CloseParen);
-        let body = self.parse_body();
+    // Members can share their type the same way variables can, `long a, b, *c;`
+    fn parse_members(&mut self) -> Vec<MemberDeclaration> {
+        let token = self.current_token();
+        if !self.starts_type_name(Some(token)) {
+            panic!("Expected a member type but found '{}' at {}", token.data, token.location)
+        }
+        let base = self.parse_type();
+        let mut members = Vec::new();
+        loop {
+            let declarator = self.parse_declarator(true);
+            let declared = self.apply_declarator(declarator, base.clone());
+            let identifier = declared.identifier.expect("Named declarators always have a name");
+            if let Type::Function(_) = declared.ty {
+                panic!("Member '{}' at {} can't be a function", identifier.data, identifier.location)
+            }
+            members.push(MemberDeclaration {
+                member_type: declared.ty,
+                identifier,
+            });
+            if !self.expect(SyntaxKind::Comma) {
+                break;
+            }
+        }
+        self.consume(SyntaxKind::Semicolon);
+        members
+    }
+
+    fn starts_type_name(&self, token: Option<&LexerToken>) -> bool {
+        token.is_some_and(|token| SyntaxKind::is_type_keyword(&token.kind)) || self.is_typedef_name(token)
+    }
+
+    // A type on its own, as in casts and sizeof, which is the specifiers followed by a declarator without a name
+    fn parse_type_name(&mut self) -> Type {
+        let base = self.parse_type();
+        let declarator = self.parse_declarator(false);
+        let ty = self.apply_declarator(declarator, base).ty;
+        // There'd be nowhere to put the definition, it has to come before the declaration it's used in
+        if let Some(definition) = self.tag_definitions.first() {
+            let keyword = definition.keyword();
+            panic!("Can't define a {} inside a type name at {}", keyword.data, keyword.location)
+        }
+        ty
+    }
+
+    // `struct tag;` declares the tag in the current scope, hiding any outer one with the same name
+    fn is_struct_declaration(&self) -> bool {
+        let kinds: Vec<&SyntaxKind> = self.current_kind()
+            .into_iter()
+            .chain(self.lexer_tokens.iter().take(2).map(|token| &token.kind))
+            .collect();
+        matches!(kinds.as_slice(),
+            [SyntaxKind::StructKeyword | SyntaxKind::UnionKeyword, SyntaxKind::StringLiteral, SyntaxKind::Semicolon])
+    }
+
+    // Works out which type a list of keywords like `unsigned long int` spells
+    fn type_from_specifiers(&self, types: &[LexerToken]) -> Type {
+        let first = match types.first() {
+            Some(first) => first,
+            None => {
+                let token = self.current_token();
+                panic!("Expected a type but found '{}' at {}", token.data, token.location)
+            }
+        };
+        let has = |kind: SyntaxKind| types.iter().any(|token| token.kind == kind);
+        // long is the only one that can be said twice, and only to make a long long
+        for (index, token) in types.iter().enumerate() {
+            let earlier = types[..index].iter().filter(|previous| previous.kind == token.kind).count();
+            if earlier > (token.kind == SyntaxKind::LongKeyword) as usize {
+                panic!("Duplicate '{}' at {}", token.data, token.location)
+            }
+        }
+        if has(SyntaxKind::SignedKeyword) && has(SyntaxKind::UnsignedKeyword) {
+            panic!("Type at {} can't be both signed and unsigned", first.location)
+        }
+
+        if has(SyntaxKind::DoubleKeyword) || has(SyntaxKind::FloatKeyword) || has(SyntaxKind::BoolKeyword) || has(SyntaxKind::VoidKeyword) {
+            if types.len() > 1 {
+                panic!("Invalid type specifiers at {}", first.location)
+            }
+            return match first.kind {
+                SyntaxKind::VoidKeyword => Type::Void,
+                SyntaxKind::BoolKeyword => Type::Bool,
+                SyntaxKind::FloatKeyword => Type::Float,
+                _ => Type::Double,
+            };
+        }
+
+        let unsigned = has(SyntaxKind::UnsignedKeyword);
+        let long = has(SyntaxKind::LongKeyword);
+        // Only one of char, short and long can say how big it is
+        let sizes = [SyntaxKind::CharKeyword, SyntaxKind::ShortKeyword, SyntaxKind::LongKeyword];
+        if sizes.into_iter().filter(|kind| has(kind.clone())).count() > 1 {
+            panic!("Invalid type specifiers at {}", first.location)
+        }
+        if has(SyntaxKind::ShortKeyword) {
+            return if unsigned { Type::UShort } else { Type::Short };
+        }
+        if has(SyntaxKind::CharKeyword) {
+            if has(SyntaxKind::IntKeyword) {
+                panic!("Invalid type specifiers at {}", first.location)
+            }
+            return if unsigned {
+                Type::UChar
+            } else if has(SyntaxKind::SignedKeyword) {
+                Type::SChar
+            } else {
+                Type::Char
+            };
+        }
+        let long_long = types.iter().filter(|token| token.kind == SyntaxKind::LongKeyword).count() == 2;
+        match (long_long, long, unsigned) {
+            (true, _, true) => Type::ULongLong,
+            (true, _, false) => Type::LongLong,
+            (false, true, true) => Type::ULong,
+            (false, true, false) => Type::Long,
+            (false, false, true) => Type::UInt,
+            (false, false, false) => Type::Int,
+        }
+    }
+
+    // We only know whether we've got a function or a variable once the whole declarator has been read
+    // Any structures defined along the way come out as declarations in front of it
+    fn parse_declaration(&mut self) -> Vec<Box<dyn Statement>> {
+        if self.is_struct_declaration() {
+            let keyword = self.current_token().clone();
+            self.eat_current_token();
+            let tag = self.consume(SyntaxKind::StringLiteral);
+            self.consume(SyntaxKind::Semicolon);
+            return vec![Box::new(StructDeclaration {
+                keyword,
+                tag,
+                members: None,
+            })];
+        }
+
+        let (base_type, storage_class) = self.parse_specifiers();
+        if !self.tag_definitions.is_empty() && self.expect(SyntaxKind::Semicolon) {
+            return self.take_tag_definitions();
+        }
+        let typedef = storage_class.as_ref().is_some_and(|token| token.kind == SyntaxKind::TypedefKeyword);
+        let mut statements = Vec::new();
+        let mut first = true;
+        // Every declarator in the list shares the specifiers, `int a, *b = &a;`
+        loop {
+            let declarator = self.parse_declarator(true);
+            let declared = self.apply_declarator(declarator, base_type.clone());
+            let identifier = declared.identifier.expect("Named declarators always have a name");
+            // Taken before a body is parsed, the declarations inside it have their own
+            statements.extend(self.take_tag_definitions());
+            // A typedef only gives the type a name, nothing gets declared
+            if typedef {
+                if self.current_kind() == Some(&SyntaxKind::Equal) {
+                    panic!("Typedef '{}' at {} can't be initialized", identifier.data, identifier.location)
+                }
+                self.declare_name(&identifier, OrdinaryName::Typedef(declared.ty));
+            } else if let (Type::Function(function), Some(parts)) = (&declared.ty, declared.function) {
+                self.declare_name(&identifier, OrdinaryName::Object);
+                let defined = self.current_kind() == Some(&SyntaxKind::OpenCurly);
+                if defined && !first {
+                    panic!("Function '{}' at {} can only be defined when it's declared on its own", identifier.data, identifier.location)
+                }
+                statements.push(Box::new(self.parse_func(*function.return_type.clone(), storage_class.clone(), identifier, parts)));
+                if defined {
+                    return statements;
+                }
+            } else {
+                // In scope from the end of its declarator, so it can hide an enumerator inside its own initializer
+                self.declare_name(&identifier, OrdinaryName::Object);
+                let mut initializer = None;
+                if self.expect(SyntaxKind::Equal) {
+                    initializer = Some(self.parse_initializer());
+                }
+                statements.push(Box::new(VariableDeclaration {
+                    var_type: declared.ty,
+                    storage_class: storage_class.clone(),
+                    identifier,
+                    initializer,
+                }));
+            }
+            if !self.expect(SyntaxKind::Comma) {
+                break;
+            }
+            first = false;
+        }
+        self.consume(SyntaxKind::Semicolon);
+        statements
+    }
+
+    fn take_tag_definitions(&mut self) -> Vec<Box<dyn Statement>> {
+        self.tag_definitions
+            .drain(..)
+            .map(TagDefinition::into_statement)
+            .collect()
+    }
+
+    // Stars come first and bind loosest, so `*a[3]` is an array of three pointers
+    fn parse_declarator(&mut self, named: bool) -> Declarator {
+        if self.expect(SyntaxKind::Star) {
+            let qualifiers = self.parse_qualifiers();
+            return Declarator::Pointer(Box::new(self.parse_declarator(named)), qualifiers);
+        }
+        let mut declarator = self.parse_direct_declarator(named);
+        loop {
+            match self.current_kind() {
+                Some(SyntaxKind::OpenBracket) => {
+                    let open_bracket = self.consume(SyntaxKind::OpenBracket);
+                    let size = if self.current_token().kind == SyntaxKind::CloseBracket {
+                        None
+                    } else {
+                        Some(self.parse_array_size(&open_bracket))
+                    };
+                    self.consume(SyntaxKind::CloseBracket);
+                    declarator = Declarator::Array {
+                        inner: Box::new(declarator),
+                        open_bracket,
+                        size,
+                    };
+                }
+                Some(SyntaxKind::OpenParen) => {
+                    let open_paren = self.consume(SyntaxKind::OpenParen);
+                    let (parameters, variadic) = self.parse_parameters();
+                    let close_paren = self.consume(SyntaxKind::CloseParen);
+                    declarator = Declarator::Function {
+                        inner: Box::new(declarator),
+                        parts: FunctionParts {
+                            open_paren,
+                            parameters,
+                            variadic,
+                            close_paren,
+                        },
+                    };
+                }
+                _ => break,
+            }
+        }
+        declarator
+    }
+
+    // Without a name a bracket could open a nested declarator or a parameter list, `(*)(int)` against `(int)`
+    fn parse_direct_declarator(&mut self, named: bool) -> Declarator {
+        let nested = self.current_kind() == Some(&SyntaxKind::OpenParen) && (named || {
+            let next = self.lexer_tokens.front();
+            !self.starts_type_name(next) && next.is_some_and(|token| token.kind != SyntaxKind::CloseParen)
+        });
+        if nested {
+            self.consume(SyntaxKind::OpenParen);
+            let declarator = self.parse_declarator(named);
+            self.consume(SyntaxKind::CloseParen);
+            return declarator;
+        }
+        if named || self.current_kind() == Some(&SyntaxKind::StringLiteral) {
+            return Declarator::Name(Some(self.consume(SyntaxKind::StringLiteral)));
+        }
+        Declarator::Name(None)
+    }
+
+    fn parse_array_size(&mut self, open_bracket: &LexerToken) -> usize {
+        let size = self.parse_expression(0);
+        match size.constant_value() {
+            Some(constant) if constant.ty.is_integer() && constant.value > 0 => constant.value as usize,
+            _ => panic!("Size of array at {} must be a positive integer constant", open_bracket.location),
+        }
+    }
+
+    // Unwraps the declarator from the outside in, which builds the type from the base outwards.
+    // An array can leave out its size, whatever it's declared for decides whether it needs one
+    fn apply_declarator(&self, declarator: Declarator, base: Type) -> Declared {
+        match declarator {
+            Declarator::Name(identifier) => Declared {
+                identifier,
+                ty: base,
+                function: None,
+            },
+            Declarator::Pointer(inner, qualifiers) => {
+                self.apply_declarator(*inner, Type::Pointer(Box::new(base)).qualified(qualifiers))
+            }
+            Declarator::Array { inner, open_bracket, size } => {
+                match base {
+                    Type::Function(_) => panic!("Array of functions at {}", open_bracket.location),
+                    Type::Void => panic!("Array of void at {}", open_bracket.location),
+                    Type::IncompleteArray(_) => panic!("Array at {} has elements without a size", open_bracket.location),
+                    _ => {}
+                }
+                let ty = match size {
+                    Some(size) => Type::Array(Box::new(base), size),
+                    None => Type::IncompleteArray(Box::new(base)),
+                };
+                self.apply_declarator(*inner, ty)
+            }
+            Declarator::Function { inner, parts } => {
+                if base.is_array() || matches!(base, Type::Function(_)) {
+                    panic!("Function at {} can't return '{}'", parts.open_paren.location, base)
+                }
+                let params = parts.parameters.iter().map(|parameter| parameter.param_type.clone()).collect();
+                let ty = if parts.variadic {
+                    Type::variadic_function(base, params)
+                } else {
+                    Type::function(base, params)
+                };
+                // The parameter names only matter when it's the declared name that is the function
+                let direct = matches!(*inner, Declarator::Name(_));
+                let mut declared = self.apply_declarator(*inner, ty);
+                if direct {
+                    declared.function = Some(parts);
+                }
+                declared
+            }
+        }
+    }
+
+    fn parse_initializer(&mut self) -> Initializer {
+        if self.current_token().kind != SyntaxKind::OpenCurly {
+            // Commas separate the elements of a list, so every value sits just above them
+            return Initializer::Single(self.parse_expression(1));
+        }
+        let open_curly = self.consume(SyntaxKind::OpenCurly);
+        let mut elements = vec![self.parse_list_element()];
+        // The last element can have a comma after it
+        while self.expect(SyntaxKind::Comma) && self.current_token().kind != SyntaxKind::CloseCurly {
+            elements.push(self.parse_list_element());
+        }
+        self.consume(SyntaxKind::CloseCurly);
+        Initializer::Compound {
+            open_curly,
+            elements,
+        }
+    }
+
+    // An element of a braced list, which can say which element it's for
+    fn parse_list_element(&mut self) -> Initializer {
+        let mut designators = Vec::new();
+        loop {
+            match self.current_kind() {
+                Some(SyntaxKind::Dot) => {
+                    self.eat_current_token();
+                    designators.push(Designator::Member(self.consume(SyntaxKind::StringLiteral)));
+                }
+                Some(SyntaxKind::OpenBracket) => {
+                    let open_bracket = self.consume(SyntaxKind::OpenBracket);
+                    let index = self.parse_expression(0);
+                    let index = match index.constant_value() {
+                        Some(constant) if constant.ty.is_integer() && constant.value >= 0 => constant.value as usize,
+                        _ => panic!("Index in designator at {} must be a non-negative integer constant", open_bracket.location),
+                    };
+                    self.consume(SyntaxKind::CloseBracket);
+                    designators.push(Designator::Index { open_bracket, index });
+                }
+                _ => break,
+            }
+        }
+        if designators.is_empty() {
+            return self.parse_initializer();
+        }
+        self.consume(SyntaxKind::Equal);
+        Initializer::Designated {
+            designators,
+            initializer: Box::new(self.parse_initializer()),
+        }
+    }
+
+    fn parse_func(&mut self, func_type: Type, storage_class: Option<LexerToken>, identifier: LexerToken, parts: FunctionParts) -> FunctionStatement {
+        // Without a body it's only a declaration, whatever ends the declaration is left for the caller
+        let body = if self.current_kind() != Some(&SyntaxKind::OpenCurly) {
+            None
+        } else {
+            // The parameters are in scope for the whole body
+            self.enter_scope();
+            for identifier in parts.parameters.iter().filter_map(|parameter| parameter.identifier.as_ref()) {
+                self.declare_name(identifier, OrdinaryName::Object);
+            }
+            let body = self.parse_body();
+            self.exit_scope();
+            Some(body)
+        };
+        if body.is_some() && parts.parameters.iter().any(|parameter| parameter.identifier.is_none()) {
+            panic!("Definition of '{}' at {} has a parameter without a name", identifier.data, identifier.location)
+        }
         FunctionStatement {
             func_type,
+            storage_class,
             identifier,
-            open_paren,
-            parameters,
-            close_paren,
+            open_paren: parts.open_paren,
+            parameters: parts.parameters,
+            variadic: parts.variadic,
+            close_paren: parts.close_paren,
             func_body: body,
         }
     }
 
+    // An empty list is treated the same as (void), the flag is set when the list ends in `...`
+    fn parse_parameters(&mut self) -> (Vec<Parameter>, bool) {
+        let mut parameters = Vec::new();
+        if self.current_token().kind == SyntaxKind::CloseParen {
+            return (parameters, false);
+        }
+        if self.current_token().kind == SyntaxKind::VoidKeyword && self.peek_kind() == Some(&SyntaxKind::CloseParen) {
+            self.eat_current_token();
+            return (parameters, false);
+        }
+
+        loop {
+            let token = self.current_token();
+            // va_start needs a named parameter to start from, so there's always one before the ellipsis
+            if token.kind == SyntaxKind::Ellipsis {
+                if parameters.is_empty() {
+                    panic!("Expected a parameter before '...' at {}", token.location)
+                }
+                self.eat_current_token();
+                return (parameters, true);
+            }
+            if !self.starts_type_name(Some(token)) {
+                panic!("Expected a parameter type but found '{}' at {}", token.data, token.location)
+            }
+            let location = token.location.clone();
+            let base = self.parse_type();
+            let declarator = self.parse_declarator(false);
+            let declared = self.apply_declarator(declarator, base);
+            // Arrays and functions are never passed by value, a parameter declared as one is really a pointer
+            let param_type = match declared.ty {
+                Type::Array(element, _) | Type::IncompleteArray(element) => Type::Pointer(element),
+                ty @ Type::Function(_) => Type::Pointer(Box::new(ty)),
+                Type::Void => panic!("Parameter at {} can't have type void", location),
+                ty => ty,
+            };
+            parameters.push(Parameter {
+                param_type,
+                identifier: declared.identifier,
+            });
+
+            if !self.expect(SyntaxKind::Comma) {
+                break;
+            }
+        }
+        (parameters, false)
+    }
+
     fn parse_body(&mut self) -> Body {
-        let open_curly = self.current.as_ref().unwrap().clone();
-        self.expect(SyntaxKind::OpenCurly);
+        let open_curly = self.consume(SyntaxKind::OpenCurly);
 
         // We need to parse body statements
         // Until we hit a closing curly bracket we don't care what's going on
         // It's great
         let mut statements: Vec<Box<dyn Statement>> = Vec::new();
-        while self.current.as_ref().unwrap().kind != SyntaxKind::CloseCurly {
-            let statement = self.parse_statement();
-            statements.push(statement)
+        self.enter_scope();
+        while self.current_token().kind != SyntaxKind::CloseCurly {
+            let items = self.parse_block_item();
+            statements.extend(items)
         }
+        self.exit_scope();
 
-        let close_curly = self.current.as_ref().unwrap().clone();
-        self.expect(SyntaxKind::CloseCurly);
+        let close_curly = self.consume(SyntaxKind::CloseCurly);
 
         Body {
             open_curly,
@@ -151,17 +1265,14 @@ impl Parser {
         }
 
     }
-    fn parse_return(&mut self) -> ReturnExpr {
-        // We're only supporting values right now
-        // Yippie!
-        let return_kw = self.current.as_ref().unwrap().clone();
-        self.expect(SyntaxKind::ReturnKeyword);
-
-        let value = self.current.as_ref().unwrap().clone();
-        self.eat_current_token();
 
-        let semicolon = self.current.as_ref().unwrap().clone();
-        self.expect(SyntaxKind::Semicolon);
+    fn parse_return(&mut self) -> ReturnExpr {
+        let return_kw = self.consume(SyntaxKind::ReturnKeyword);
+        let value = match self.current_kind() {
+            Some(SyntaxKind::Semicolon) => None,
+            _ => Some(self.parse_expression(0)),
+        };
+        let semicolon = self.consume(SyntaxKind::Semicolon);
 
         ReturnExpr {
             return_kw,
@@ -170,7 +1281,402 @@ impl Parser {
         }
     }
 
+    fn parse_switch(&mut self) -> SwitchStatement {
+        let switch_kw = self.consume(SyntaxKind::SwitchKeyword);
+        self.consume(SyntaxKind::OpenParen);
+        let condition = self.parse_expression(0);
+        self.consume(SyntaxKind::CloseParen);
+        let body = self.parse_statement();
+
+        SwitchStatement {
+            switch_kw,
+            condition,
+            body,
+            label: String::new(),
+            cases: Vec::new(),
+        }
+    }
+
+    fn parse_case(&mut self) -> CaseStatement {
+        let case_kw = self.consume(SyntaxKind::CaseKeyword);
+        let value = self.parse_expression(0);
+        let colon = self.consume(SyntaxKind::Colon);
+        let statement = self.parse_statement();
+
+        CaseStatement {
+            case_kw,
+            value,
+            colon,
+            statement,
+            label: String::new(),
+        }
+    }
+
+    fn parse_default(&mut self) -> DefaultStatement {
+        let default_kw = self.consume(SyntaxKind::DefaultKeyword);
+        let colon = self.consume(SyntaxKind::Colon);
+        let statement = self.parse_statement();
+
+        DefaultStatement {
+            default_kw,
+            colon,
+            statement,
+            label: String::new(),
+        }
+    }
+
+    fn parse_break(&mut self) -> BreakStatement {
+        let break_kw = self.consume(SyntaxKind::BreakKeyword);
+        let semicolon = self.consume(SyntaxKind::Semicolon);
+
+        BreakStatement {
+            break_kw,
+            semicolon,
+            label: String::new(),
+        }
+    }
+
+    fn parse_labeled(&mut self) -> LabeledStatement {
+        let label = self.consume(SyntaxKind::StringLiteral);
+        let colon = self.consume(SyntaxKind::Colon);
+        let statement = self.parse_statement();
+
+        LabeledStatement {
+            label,
+            colon,
+            statement,
+        }
+    }
+
+    fn parse_goto(&mut self) -> GotoStatement {
+        let goto_kw = self.consume(SyntaxKind::GotoKeyword);
+        let label = self.consume(SyntaxKind::StringLiteral);
+        let semicolon = self.consume(SyntaxKind::Semicolon);
+
+        GotoStatement {
+            goto_kw,
+            label,
+            semicolon,
+        }
+    }
+
+    // Precedence climbing, every operator to the right has to bind at least as tight as min_precedence
+    fn parse_expression(&mut self, min_precedence: u8) -> Box<dyn Expression> {
+        let mut left = self.parse_factor();
+
+        while let Some(precedence) = self.current_kind().and_then(SyntaxKind::binary_precedence) {
+            if precedence < min_precedence {
+                break;
+            }
+            let operator = self.current_token().clone();
+            self.eat_current_token();
+
+            if operator.kind == SyntaxKind::Equal {
+                // Assignment is right associative
+                let right = self.parse_expression(precedence);
+                left = Box::new(AssignmentExpr {
+                    left,
+                    equal: operator,
+                    right,
+                    ty: None,
+                });
+            } else if SyntaxKind::compound_operator(&operator.kind).is_some() {
+                let right = self.parse_expression(precedence);
+                left = Box::new(CompoundAssignmentExpr {
+                    left,
+                    operator,
+                    right,
+                    ty: None,
+                });
+            } else {
+                let right = self.parse_expression(precedence + 1);
+                left = Box::new(BinaryExpr {
+                    left,
+                    operator,
+                    right,
+                    ty: None,
+                });
+            }
+        }
+        left
+    }
+
+    fn parse_factor(&mut self) -> Box<dyn Expression> {
+        let token = self.current_token().clone();
+        match token.kind {
+            SyntaxKind::Minus | SyntaxKind::Tilde | SyntaxKind::Bang => {
+                self.eat_current_token();
+                let operand = self.parse_factor();
+                Box::new(UnaryExpr {
+                    operator: token,
+                    operand,
+                    ty: None,
+                })
+            }
+            SyntaxKind::Ampersand => {
+                self.eat_current_token();
+                let operand = self.parse_factor();
+                Box::new(AddressOfExpr {
+                    ampersand: token,
+                    operand,
+                    ty: None,
+                })
+            }
+            SyntaxKind::Star => {
+                self.eat_current_token();
+                let operand = self.parse_factor();
+                Box::new(DereferenceExpr {
+                    star: token,
+                    operand,
+                    ty: None,
+                })
+            }
+            SyntaxKind::PlusPlus | SyntaxKind::MinusMinus => {
+                self.eat_current_token();
+                let operand = self.parse_factor();
+                Box::new(IncrementExpr {
+                    operator: token,
+                    operand,
+                    postfix: false,
+                    ty: None,
+                })
+            }
+            // A type name in parentheses makes it a cast, or a compound literal when a braced list follows,
+            // anything else in them is just a parenthesized expression
+            SyntaxKind::OpenParen if self.starts_type_name(self.lexer_tokens.front()) => {
+                self.eat_current_token();
+                let target = self.parse_type_name();
+                self.consume(SyntaxKind::CloseParen);
+                if self.current_kind() == Some(&SyntaxKind::OpenCurly) {
+                    let literal = self.parse_compound_literal(token, target);
+                    return self.parse_postfix(literal);
+                }
+                let expression = self.parse_factor();
+                // The value a cast gives is never qualified, `(const int)x` is just an int
+                Box::new(CastExpr {
+                    target: target.unqualified().clone(),
+                    expression,
+                    open_paren: Some(token),
+                })
+            }
+            SyntaxKind::SizeofKeyword => {
+                self.eat_current_token();
+                if self.current_kind() == Some(&SyntaxKind::OpenParen) && self.starts_type_name(self.lexer_tokens.front()) {
+                    let open_paren = self.consume(SyntaxKind::OpenParen);
+                    let target = self.parse_type_name();
+                    self.consume(SyntaxKind::CloseParen);
+                    if self.current_kind() == Some(&SyntaxKind::OpenCurly) {
+                        let literal = self.parse_compound_literal(open_paren, target);
+                        return Box::new(SizeofExpr {
+                            sizeof_kw: token,
+                            operand: self.parse_postfix(literal),
+                            ty: None,
+                            size: None,
+                        });
+                    }
+                    return Box::new(SizeofTypeExpr {
+                        sizeof_kw: token,
+                        target,
+                        ty: None,
+                        size: None,
+                    });
+                }
+                let operand = self.parse_factor();
+                Box::new(SizeofExpr {
+                    sizeof_kw: token,
+                    operand,
+                    ty: None,
+                    size: None,
+                })
+            }
+            _ => {
+                let primary = self.parse_primary();
+                self.parse_postfix(primary)
+            }
+        }
+    }
+
+    // Postfix operators bind tighter than anything in front of the operand
+    fn parse_postfix(&mut self, mut primary: Box<dyn Expression>) -> Box<dyn Expression> {
+        while let Some(operator) = self.current.clone() {
+            primary = match operator.kind {
+                SyntaxKind::PlusPlus | SyntaxKind::MinusMinus => {
+                    self.eat_current_token();
+                    Box::new(IncrementExpr {
+                        operator,
+                        operand: primary,
+                        postfix: true,
+                        ty: None,
+                    })
+                }
+                SyntaxKind::OpenBracket => {
+                    self.eat_current_token();
+                    let index = self.parse_expression(0);
+                    self.consume(SyntaxKind::CloseBracket);
+                    Box::new(SubscriptExpr {
+                        left: primary,
+                        open_bracket: operator,
+                        index,
+                        ty: None,
+                    })
+                }
+                SyntaxKind::OpenParen => {
+                    self.eat_current_token();
+                    let mut arguments = Vec::new();
+                    if self.current_token().kind != SyntaxKind::CloseParen {
+                        loop {
+                            // Commas separate arguments, so each argument sits just above them
+                            arguments.push(self.parse_expression(1));
+                            if !self.expect(SyntaxKind::Comma) {
+                                break;
+                            }
+                        }
+                    }
+                    let close_paren = self.consume(SyntaxKind::CloseParen);
+                    Box::new(FunctionCallExpr {
+                        function: primary,
+                        arguments,
+                        close_paren,
+                        ty: None,
+                    })
+                }
+                SyntaxKind::Dot | SyntaxKind::Arrow => {
+                    self.eat_current_token();
+                    let member = self.consume(SyntaxKind::StringLiteral);
+                    Box::new(MemberExpr {
+                        structure: primary,
+                        operator,
+                        member,
+                        ty: None,
+                    })
+                }
+                _ => break,
+            };
+        }
+        primary
+    }
+
+    fn parse_compound_literal(&mut self, open_paren: LexerToken, target: Type) -> Box<dyn Expression> {
+        if let Type::Function(_) = target {
+            panic!("Compound literal at {} can't have a function type", open_paren.location)
+        }
+        let initializer = self.parse_initializer();
+        Box::new(CompoundLiteralExpr {
+            open_paren,
+            target,
+            initializer,
+            name: String::new(),
+        })
+    }
+
+    fn parse_primary(&mut self) -> Box<dyn Expression> {
+        let token = self.current_token().clone();
+        match token.kind {
+            SyntaxKind::NumberLiteral => {
+                self.eat_current_token();
+                let (value, ty) = if is_floating_literal(&token) {
+                    parse_floating_literal(&token)
+                } else {
+                    parse_integer_literal(&token)
+                };
+                Box::new(ConstantExpr {
+                    token,
+                    value,
+                    ty: Some(ty),
+                })
+            }
+            // A declaration of the same name hides the builtin
+            SyntaxKind::StringLiteral if self.peek_kind() == Some(&SyntaxKind::OpenParen)
+                && self.lookup_name(&token.data).is_none()
+                && let Some(builtin) = VaBuiltin::from_name(&token.data) => {
+                self.parse_va_builtin(builtin)
+            }
+            SyntaxKind::StringLiteral if self.peek_kind() == Some(&SyntaxKind::OpenParen)
+                && let Some(OrdinaryName::Enumerator(_)) = self.lookup_name(&token.data) => {
+                panic!("'{}' at {} is not a function", token.data, token.location)
+            }
+            SyntaxKind::StringLiteral => {
+                self.eat_current_token();
+                if let Some(OrdinaryName::Enumerator(value)) = self.lookup_name(&token.data) {
+                    return Box::new(ConstantExpr {
+                        value: *value,
+                        token,
+                        ty: Some(Type::Int),
+                    });
+                }
+                Box::new(VariableExpr {
+                    identifier: token,
+                    ty: None,
+                })
+            }
+            SyntaxKind::OpenParen => {
+                self.eat_current_token();
+                let inner = self.parse_expression(0);
+                self.consume(SyntaxKind::CloseParen);
+                inner
+            }
+            SyntaxKind::QuotedString => {
+                let mut value = Vec::new();
+                while self.current_kind() == Some(&SyntaxKind::QuotedString) {
+                    value.extend(unescape(self.current_token()));
+                    self.eat_current_token();
+                }
+                Box::new(StringLiteralExpr {
+                    token,
+                    value,
+                    name: String::new(),
+                })
+            }
+            _ => panic!("Expected an expression but found '{}' at {}", token.data, token.location),
+        }
+    }
+
+    fn parse_va_builtin(&mut self, builtin: VaBuiltin) -> Box<dyn Expression> {
+        let identifier = self.consume(SyntaxKind::StringLiteral);
+        self.consume(SyntaxKind::OpenParen);
+        let count = match builtin {
+            VaBuiltin::Start | VaBuiltin::Copy => 2,
+            VaBuiltin::Arg | VaBuiltin::End => 1,
+        };
+        let mut arguments = vec![self.parse_expression(1)];
+        while arguments.len() < count {
+            self.consume(SyntaxKind::Comma);
+            arguments.push(self.parse_expression(1));
+        }
+        let target = (builtin == VaBuiltin::Arg).then(|| {
+            self.consume(SyntaxKind::Comma);
+            self.parse_type_name()
+        });
+        self.consume(SyntaxKind::CloseParen);
+        Box::new(VaBuiltinExpr {
+            builtin,
+            identifier,
+            arguments,
+            target,
+            ty: None,
+        })
+    }
+
+
+}
 
+impl Initializer {
+    pub fn location(&self) -> Location {
+        match self {
+            Initializer::Single(expression) => expression.location(),
+            Initializer::Compound { open_curly, .. } => open_curly.location.clone(),
+            Initializer::Designated { designators, .. } => designators[0].token().location.clone(),
+        }
+    }
+}
+
+impl Designator {
+    pub fn token(&self) -> &LexerToken {
+        match self {
+            Designator::Member(member) => member,
+            Designator::Index { open_bracket, .. } => open_bracket,
+        }
+    }
 }
 
 impl Body {
@@ -183,73 +1689,493 @@ impl Body {
     }
 }
 
-impl Expression for Body {}
+impl Statement for Body {}
 
-impl Statement for Body {
-    fn get_expressions(&self) -> Option<Vec<Box<dyn Expression>>>
-    {
-        None
+impl Statement for FunctionStatement {}
+
+impl Statement for VariableDeclaration {}
+
+impl Statement for StructDeclaration {}
+
+impl Statement for EnumDeclaration {}
+
+impl Statement for ExpressionStatement {}
+
+impl Statement for NullStatement {}
+
+impl Statement for SwitchStatement {}
+
+impl Statement for CaseStatement {}
+
+impl Statement for DefaultStatement {}
+
+impl Statement for LabeledStatement {}
+
+impl Statement for GotoStatement {}
+
+impl Statement for BreakStatement {}
+
+impl Statement for ReturnExpr {}
+
+// <stdarg.h> defines va_list as __builtin_va_list, which is the only one of its names that's reserved
+fn builtin_typedefs() -> HashMap<String, OrdinaryName> {
+    HashMap::from([("__builtin_va_list".to_string(), OrdinaryName::Typedef(Type::va_list()))])
+}
+
+// A literal takes the first of these types its value fits in. u leaves only the unsigned ones and l or ll start it
+// off at long or long long, and a decimal literal without a u only ever gets a signed type.
+// Long already holds every value long long can, so the only way to get a long long is to ask for one
+fn parse_integer_literal(token: &LexerToken) -> (i64, Type) {
+    let literal = IntegerLiteral::parse(token);
+    let candidates = match literal.longs {
+        0 => vec![Type::Int, Type::UInt, Type::Long, Type::ULong],
+        1 => vec![Type::Long, Type::ULong],
+        _ => vec![Type::LongLong, Type::ULongLong],
+    };
+    let ty = candidates
+        .into_iter()
+        .filter(|ty| if literal.unsigned { !ty.is_signed() } else { ty.is_signed() || !literal.decimal })
+        .find(|ty| {
+            let bits = 8 * ty.size() as u32 - ty.is_signed() as u32;
+            literal.value <= u64::MAX >> (64 - bits)
+        });
+    match ty {
+        Some(ty) => (literal.value as i64, ty),
+        None => panic!("Number '{}' at {} is too large for any integer type", token.data, token.location),
     }
 }
-impl Clone for Body {
-    fn clone(&self) -> Self {
-        let mut new_statements: Vec<Box<dyn Statement>>= Vec::new();
-        for item in self.statements.iter().clone() {
-            let item_as_ref = item.as_ref();
-            let cloned_box = clone_box(item_as_ref);
-            new_statements.push(cloned_box)
 
-        }
-        Body {
-            open_curly: self.open_curly.clone(),
-            statements: new_statements,
-            close_curly: self.close_curly.clone(),
+// A hexadecimal constant is always an integer, an e in one is just a digit
+fn is_floating_literal(token: &LexerToken) -> bool {
+    !token.data.starts_with("0x") && !token.data.starts_with("0X") && token.data.contains(['.', 'e', 'E'])
+}
+
+// Rust rounds to the nearest float or double the same way C does, the bits are what ends up in the constant
+// An f suffix makes it a float, which is parsed as one so the digits are only rounded once
+fn parse_floating_literal(token: &LexerToken) -> (i64, Type) {
+    let invalid = || panic!("Invalid floating constant '{}' at {}", token.data, token.location);
+    match token.data.strip_suffix(['f', 'F']) {
+        Some(digits) => digits.parse::<f32>().map(|value| (Constant::float(value).value, Type::Float)).unwrap_or_else(|_| invalid()),
+        None => token.data.parse::<f64>().map(|value| (value.to_bits() as i64, Type::Double)).unwrap_or_else(|_| invalid()),
+    }
+}
+
+// Constant folding follows the same conversions the type checker applies, so the results match the generated code
+fn fold_unary(kind: &SyntaxKind, operand: Constant) -> Option<Constant> {
+    if operand.ty.is_floating() {
+        return match kind {
+            SyntaxKind::Minus => Some(Constant::double(-operand.as_double()).convert(&operand.ty)),
+            SyntaxKind::Bang => Some(Constant::int(operand.is_zero() as i64)),
+            _ => None,
+        };
+    }
+    let ty = operand.ty.promote();
+    let operand = operand.convert(&ty).value;
+    let result = match kind {
+        SyntaxKind::Minus => operand.wrapping_neg(),
+        SyntaxKind::Tilde => !operand,
+        SyntaxKind::Bang => return Some(Constant::int((operand == 0) as i64)),
+        _ => return None,
+    };
+    Some(Constant::new(result, ty))
+}
+
+fn fold_binary(kind: &SyntaxKind, left: Constant, right: Constant) -> Option<Constant> {
+    let truth = |value: bool| Some(Constant::int(value as i64));
+    match kind {
+        SyntaxKind::AmpersandAmpersand => return truth(!left.is_zero() && !right.is_zero()),
+        SyntaxKind::PipePipe => return truth(!left.is_zero() || !right.is_zero()),
+        _ => {}
+    }
+    let common = Type::common_type(&left.ty, &right.ty);
+    if common.is_floating() {
+        return fold_floating_binary(kind, left.convert(&common).as_double(), right.convert(&common).as_double(), &common);
+    }
+
+    // Shifts only care about the type of the left operand, everything else works in the common type
+    let ty = match kind {
+        SyntaxKind::LessLess | SyntaxKind::GreaterGreater => left.ty.promote(),
+        _ => Type::common_type(&left.ty, &right.ty),
+    };
+    let left = left.convert(&ty).value;
+    let right = right.convert(&ty).value;
+    // Unsigned values are zero extended, so looking at them as u64 gets division and comparison right
+    let signed = ty.is_signed();
+    let (unsigned_left, unsigned_right) = (left as u64, right as u64);
+
+    let result = match kind {
+        SyntaxKind::Plus => left.wrapping_add(right),
+        SyntaxKind::Minus => left.wrapping_sub(right),
+        SyntaxKind::Star => left.wrapping_mul(right),
+        // Dividing by zero isn't a constant, it's undefined behaviour
+        SyntaxKind::Slash if signed => left.checked_div(right)?,
+        SyntaxKind::Slash => unsigned_left.checked_div(unsigned_right)? as i64,
+        SyntaxKind::Percent if signed => left.checked_rem(right)?,
+        SyntaxKind::Percent => unsigned_left.checked_rem(unsigned_right)? as i64,
+        SyntaxKind::Ampersand => left & right,
+        SyntaxKind::Pipe => left | right,
+        SyntaxKind::Caret => left ^ right,
+        SyntaxKind::LessLess => left.wrapping_shl(right as u32),
+        SyntaxKind::GreaterGreater if signed => left.wrapping_shr(right as u32),
+        SyntaxKind::GreaterGreater => unsigned_left.wrapping_shr(right as u32) as i64,
+        SyntaxKind::EqualEqual => return truth(left == right),
+        SyntaxKind::BangEqual => return truth(left != right),
+        SyntaxKind::Less if signed => return truth(left < right),
+        SyntaxKind::Less => return truth(unsigned_left < unsigned_right),
+        SyntaxKind::LessEqual if signed => return truth(left <= right),
+        SyntaxKind::LessEqual => return truth(unsigned_left <= unsigned_right),
+        SyntaxKind::Greater if signed => return truth(left > right),
+        SyntaxKind::Greater => return truth(unsigned_left > unsigned_right),
+        SyntaxKind::GreaterEqual if signed => return truth(left >= right),
+        SyntaxKind::GreaterEqual => return truth(unsigned_left >= unsigned_right),
+        _ => return None,
+    };
+    Some(Constant::new(result, ty))
+}
+
+// Comparisons involving NaN come out false, except for != which comes out true, same as the generated code
+// Doubles have more than twice the precision of floats, so doing float arithmetic in double and rounding once at the end
+// gives exactly what addss and friends would have
+fn fold_floating_binary(kind: &SyntaxKind, left: f64, right: f64, ty: &Type) -> Option<Constant> {
+    let truth = |value: bool| Some(Constant::int(value as i64));
+    let result = match kind {
+        SyntaxKind::Plus => left + right,
+        SyntaxKind::Minus => left - right,
+        SyntaxKind::Star => left * right,
+        SyntaxKind::Slash => left / right,
+        SyntaxKind::EqualEqual => return truth(left == right),
+        SyntaxKind::BangEqual => return truth(left != right),
+        SyntaxKind::Less => return truth(left < right),
+        SyntaxKind::LessEqual => return truth(left <= right),
+        SyntaxKind::Greater => return truth(left > right),
+        SyntaxKind::GreaterEqual => return truth(left >= right),
+        _ => return None,
+    };
+    Some(Constant::double(result).convert(ty))
+}
+
+// The type checker fills in the type of every expression before anyone asks for it
+fn checked_type(ty: &Option<Type>, location: Location) -> Type {
+    match ty {
+        Some(ty) => ty.clone(),
+        None => panic!("Expression at {} hasn't been type checked", location),
+    }
+}
+
+impl Expression for ConstantExpr {
+    fn location(&self) -> Location {
+        self.token.location.clone()
+    }
+
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
+    }
+
+    fn constant_value(&self) -> Option<Constant> {
+        Some(Constant::new(self.value, self.get_type()))
+    }
+}
+
+impl Expression for VariableExpr {
+    fn location(&self) -> Location {
+        self.identifier.location.clone()
+    }
+
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
+    }
+
+    fn is_lvalue(&self) -> bool {
+        true
+    }
+
+    fn designated_function(&self) -> Option<&str> {
+        matches!(self.ty, Some(Type::Function(_))).then_some(self.identifier.data.as_str())
+    }
+
+    fn static_object(&self, symbols: &mut SymbolTable) -> Option<(String, i64)> {
+        let name = &self.identifier.data;
+        (symbols.is_static(name) || self.designated_function().is_some()).then(|| (name.clone(), 0))
+    }
+}
+
+impl Expression for UnaryExpr {
+    fn location(&self) -> Location {
+        self.operator.location.clone()
+    }
+
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
+    }
+
+    fn constant_value(&self) -> Option<Constant> {
+        fold_unary(&self.operator.kind, self.operand.constant_value()?)
+    }
+}
+
+impl Expression for BinaryExpr {
+    fn location(&self) -> Location {
+        self.left.location()
+    }
+
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
+    }
+
+    fn constant_value(&self) -> Option<Constant> {
+        fold_binary(&self.operator.kind, self.left.constant_value()?, self.right.constant_value()?)
+    }
+
+    // A constant number of elements either side of a static object, `a + 1`
+    fn static_address(&self, symbols: &mut SymbolTable) -> Option<(String, i64)> {
+        let (pointer, count) = match self.operator.kind {
+            SyntaxKind::Plus if self.left.get_type().is_pointer() => (&self.left, self.right.constant_value()?.value),
+            SyntaxKind::Plus => (&self.right, self.left.constant_value()?.value),
+            SyntaxKind::Minus if !self.right.get_type().is_pointer() => (&self.left, -self.right.constant_value()?.value),
+            _ => return None,
+        };
+        let Type::Pointer(referenced) = pointer.get_type() else { return None };
+        let (symbol, offset) = pointer.static_address(symbols)?;
+        Some((symbol, offset + count * symbols.size_of(&referenced) as i64))
+    }
+}
+
+impl Expression for AssignmentExpr {
+    fn location(&self) -> Location {
+        self.left.location()
+    }
+
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
+    }
+}
+
+impl Expression for CompoundAssignmentExpr {
+    fn location(&self) -> Location {
+        self.left.location()
+    }
+
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
+    }
+}
+
+impl Expression for IncrementExpr {
+    fn location(&self) -> Location {
+        if self.postfix {
+            self.operand.location()
+        } else {
+            self.operator.location.clone()
         }
     }
+
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
+    }
 }
 
-impl Expression for FunctionStatement {}
+impl Expression for FunctionCallExpr {
+    fn location(&self) -> Location {
+        self.function.location()
+    }
 
-impl Statement for FunctionStatement {
-    fn get_expressions(&self) -> Option<Vec<Box<dyn Expression>>>
-    {
-        None
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
+    }
+}
+
+impl Expression for AddressOfExpr {
+    fn location(&self) -> Location {
+        self.ampersand.location.clone()
     }
 
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
+    }
+
+    fn static_address(&self, symbols: &mut SymbolTable) -> Option<(String, i64)> {
+        self.operand.static_object(symbols)
+    }
 }
 
+impl Expression for DereferenceExpr {
+    fn location(&self) -> Location {
+        self.star.location.clone()
+    }
+
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
+    }
 
-impl Clone for FunctionStatement {
-    fn clone(&self) -> Self {
-        FunctionStatement {
-            func_type: self.func_type.clone(),
-            identifier: self.identifier.clone(),
-            open_paren: self.open_paren.clone(),
-            parameters: self.parameters.clone(),
-            close_paren: self.close_paren.clone(),
-            func_body: self.func_body.clone(),
+    fn is_lvalue(&self) -> bool {
+        true
+    }
+
+    fn static_object(&self, symbols: &mut SymbolTable) -> Option<(String, i64)> {
+        self.operand.static_address(symbols)
+    }
+}
+
+impl Expression for SubscriptExpr {
+    fn location(&self) -> Location {
+        self.left.location()
+    }
+
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
+    }
+
+    fn is_lvalue(&self) -> bool {
+        true
+    }
+
+    fn static_object(&self, symbols: &mut SymbolTable) -> Option<(String, i64)> {
+        let (pointer, index) = if self.left.get_type().is_pointer() { (&self.left, &self.index) } else { (&self.index, &self.left) };
+        let index = index.constant_value()?.value;
+        let (symbol, offset) = pointer.static_address(symbols)?;
+        Some((symbol, offset + index * symbols.size_of(&self.get_type()) as i64))
+    }
+}
+
+impl Expression for MemberExpr {
+    fn location(&self) -> Location {
+        self.structure.location()
+    }
+
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
+    }
+
+    // A member of something that isn't an lvalue, like the result of a call, can't be assigned to either
+    fn is_lvalue(&self) -> bool {
+        self.operator.kind == SyntaxKind::Arrow || self.structure.is_lvalue()
+    }
+
+    fn static_object(&self, symbols: &mut SymbolTable) -> Option<(String, i64)> {
+        let (structure, (symbol, offset)) = match self.structure.get_type() {
+            Type::Pointer(referenced) if self.operator.kind == SyntaxKind::Arrow => (*referenced, self.structure.static_address(symbols)?),
+            structure => (structure, self.structure.static_object(symbols)?),
+        };
+        let (Type::Struct(tag) | Type::Union(tag)) = structure.unqualified() else { return None };
+        let member = symbols.get_struct(tag)?.member(&self.member.data)?.offset;
+        Some((symbol, offset + member as i64))
+    }
+}
+
+impl Expression for SizeofTypeExpr {
+    fn location(&self) -> Location {
+        self.sizeof_kw.location.clone()
+    }
+
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
+    }
+
+    // Scalars and arrays of them are already known here, so they can size an array.
+    // Anything with a structure in it has to wait for the type checker to lay it out.
+    fn constant_value(&self) -> Option<Constant> {
+        let size = match self.size {
+            Some(size) => size,
+            None if self.target.scalar_element().is_scalar() => self.target.size(),
+            None => return None,
+        };
+        Some(Constant::new(size as i64, Type::ULong))
+    }
+}
+
+impl Expression for SizeofExpr {
+    fn location(&self) -> Location {
+        self.sizeof_kw.location.clone()
+    }
+
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
+    }
+
+    // Only known once the type checker has worked out the type of the operand
+    fn constant_value(&self) -> Option<Constant> {
+        Some(Constant::new(self.size? as i64, Type::ULong))
+    }
+}
+
+impl Expression for CastExpr {
+    fn location(&self) -> Location {
+        match self.open_paren.as_ref() {
+            Some(open_paren) => open_paren.location.clone(),
+            None => self.expression.location(),
+        }
+    }
+
+    fn get_type(&self) -> Type {
+        self.target.clone()
+    }
+
+    fn constant_value(&self) -> Option<Constant> {
+        Some(self.expression.constant_value()?.convert(&self.target))
+    }
+
+    // Either an array or function decaying into a pointer or a cast of one pointer to another
+    fn static_address(&self, symbols: &mut SymbolTable) -> Option<(String, i64)> {
+        if !self.target.is_pointer() {
+            return None;
+        }
+        match self.expression.get_type() {
+            Type::Array(..) | Type::IncompleteArray(_) | Type::Function(_) => self.expression.static_object(symbols),
+            Type::Pointer(_) => self.expression.static_address(symbols),
+            _ => None,
         }
     }
 }
 
-// Usually using a polymorphic approach you'd make Expressions derive from statements
-// This should work?
-// Realistically the difference between a statement
-// and an expression are mostly semantic
-// They're used differently, but we only care about
-impl Statement for dyn Expression {
-    fn get_expressions(&self) -> Option<Vec<Box<dyn Expression>>>
-    {
-        // Should this return Option(Vec::new(Box::new(self)))?
-        None
+impl Expression for VaBuiltinExpr {
+    fn location(&self) -> Location {
+        self.identifier.location.clone()
+    }
+
+    fn get_type(&self) -> Type {
+        checked_type(&self.ty, self.location())
     }
 }
 
-impl Expression for ReturnExpr {
+impl Expression for StringLiteralExpr {
+    fn location(&self) -> Location {
+        self.token.location.clone()
+    }
+
+    fn get_type(&self) -> Type {
+        Type::Array(Box::new(Type::Char), self.value.len() + 1)
+    }
+
+    fn is_lvalue(&self) -> bool {
+        true
+    }
+
+    fn string_literal(&self) -> Option<&[u8]> {
+        Some(&self.value)
+    }
 
+    // It's only given its array once it's pointed at like this, or used as an array in a function
+    fn static_object(&self, symbols: &mut SymbolTable) -> Option<(String, i64)> {
+        symbols.insert_string(&self.name, &self.value);
+        Some((self.name.clone(), 0))
+    }
 }
-impl Statement for ReturnExpr {
-    fn get_expressions(&self) -> Option<Vec<Box<dyn Expression>>> {
-        None
+
+impl Expression for CompoundLiteralExpr {
+    fn location(&self) -> Location {
+        self.open_paren.location.clone()
     }
-}
\ No newline at end of file
+
+    fn get_type(&self) -> Type {
+        self.target.clone()
+    }
+
+    fn is_lvalue(&self) -> bool {
+        true
+    }
+
+    // Only one outside of a function has static storage
+    fn static_object(&self, symbols: &mut SymbolTable) -> Option<(String, i64)> {
+        symbols.is_static(&self.name).then(|| (self.name.clone(), 0))
+    }
+}
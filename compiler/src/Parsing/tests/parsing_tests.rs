@@ -1,4 +1,524 @@
-#[cfg(tests)]
+#[cfg(test)]
 mod parsing_tests {
-    
-}
\ No newline at end of file
+    use crate::lexer::Lexer;
+    use crate::parsing::Parser;
+
+    fn parse(str: &str) -> Parser {
+        let mut lexer = Lexer::new(str);
+        lexer.lex();
+        let mut parser = Parser::new(lexer.tokens);
+        parser.start_parsing();
+        parser
+    }
+
+    #[test]
+    fn parsing_basic_function() {
+        let parser = parse("int main(void) {\n    return 0;\n}\n");
+        assert_eq!(parser.parsed_tokens.len(), 1);
+
+        let parser = parse("int foo(void) { return 1; } int main(void) { return 2; }");
+        assert_eq!(parser.parsed_tokens.len(), 2);
+    }
+
+    #[test]
+    fn parsing_switch() {
+        let str = r#"
+        int main(void) {
+            int x = 3;
+            switch (x) {
+                case 1:
+                case 2 + 1:
+                    x = x * 2;
+                    break;
+                default: ;
+            }
+            return x;
+        }
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 1);
+    }
+
+    #[test]
+    fn parsing_labels_and_goto() {
+        let str = r#"
+        int main(void) {
+            int x = 0;
+            goto end;
+            x: x = 1;
+            end:
+            start: ;
+            return x;
+        }
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 1);
+    }
+
+    #[test]
+    fn parsing_compound_assignment_and_increments() {
+        let str = r#"
+        int main(void) {
+            int x = 1;
+            int y = x++ + ++x - -x-- * --x;
+            x += y -= 3;
+            x <<= y >>= 1;
+            return x;
+        }
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 1);
+    }
+
+    #[test]
+    fn parsing_functions_and_calls() {
+        let str = r#"
+        int add(int a, char b);
+        int none();
+        int main(void) {
+            int local(void);
+            return add(1, none() + 2) + add(3, 4);
+        }
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 3);
+    }
+
+    #[test]
+    fn parsing_integer_specifiers() {
+        let str = r#"
+        unsigned long a = 4294967296UL;
+        long int b = 5l;
+        signed c;
+        int unsigned long d(unsigned e, signed char f);
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 4);
+    }
+
+    #[test]
+    fn parsing_arithmetic_specifiers() {
+        let str = r#"
+        short a;
+        unsigned short int b;
+        long long c = 1LL;
+        unsigned long long int d = 2ull;
+        long signed long e;
+        float f = 1.5f;
+        _Bool g;
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate 'long' at 1, 11")]
+    fn parsing_long_long_long() {
+        parse("long long long x;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid type specifiers at 1, 1")]
+    fn parsing_short_long() {
+        parse("short long x;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid type specifiers at 1, 1")]
+    fn parsing_unsigned_float() {
+        parse("unsigned float x;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid type specifiers at 1, 1")]
+    fn parsing_long_double() {
+        parse("long double x;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid floating constant '1.5u' at 1, 12")]
+    fn parsing_floating_constant_with_suffix() {
+        parse("double x = 1.5u;");
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be both signed and unsigned")]
+    fn parsing_signed_unsigned() {
+        parse("signed unsigned int x;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate 'int' at 1, 5")]
+    fn parsing_duplicate_specifier() {
+        parse("int int x;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected a parameter type but found 'a' at 1, 7")]
+    fn parsing_parameter_without_type() {
+        parse("int f(a);");
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected StringLiteral but found '1'")]
+    fn parsing_goto_without_label() {
+        parse("int main(void) { goto 1; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected Colon but found 'return' at 1, 38")]
+    fn parsing_case_without_colon() {
+        parse("int main(void) { switch (1) { case 1 return 0; } }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected an expression but found ';'")]
+    fn parsing_switch_without_condition() {
+        parse("int main(void) { switch (;) {} }");
+    }
+
+    #[test]
+    fn parsing_pointer_declarators() {
+        let str = r#"
+        int **pp;
+        long *f(int *a, double **b);
+        int main(void) { int x = 1; int *p = &x; *p = **pp * *p; return *&x; }
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 3);
+    }
+
+    #[test]
+    fn parsing_arrays() {
+        let str = r#"
+        int grid[2][3] = {{1, 2, 3}, {4, 5,},};
+        long f(int values[4], char *names[2]);
+        int main(void) { return grid[1][sizeof grid[0] - 1] + (grid)[0][0]++; }
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Size of array at 1, 6 must be a positive integer constant")]
+    fn parsing_zero_length_array() {
+        parse("int a[2 - 2];");
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected CloseBracket but found ';' at 1, 8")]
+    fn parsing_unclosed_subscript() {
+        parse("int a[3;");
+    }
+
+    #[test]
+    fn parsing_nested_declarators() {
+        let str = r#"
+        int *(*fp)(int, long);
+        int (*rows)[3];
+        int (*pick(int which))(long);
+        int main(int argc, char *argv[]) { return 0; }
+        long apply(long (*)(long), double [], int (*[2]));
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Function at 1, 6 can't return 'int[3]'")]
+    fn parsing_function_returning_array() {
+        parse("int f(void)[3];");
+    }
+
+    #[test]
+    #[should_panic(expected = "Array of functions at 1, 6")]
+    fn parsing_array_of_functions() {
+        parse("int f[2](void);");
+    }
+
+    #[test]
+    fn parsing_array_without_size() {
+        let parser = parse("int a[]; extern int b[][2]; int c[] = {1, 2}; void f(int d[], int (*e)[]);");
+        assert_eq!(parser.parsed_tokens.len(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "Array at 1, 6 has elements without a size")]
+    fn parsing_array_of_arrays_without_size() {
+        parse("int a[][];");
+    }
+
+    #[test]
+    fn parsing_declarator_lists() {
+        let str = r#"
+        int a, *b;
+        int g1, g2 = 3, g3[2] = {g2};
+        typedef int T, *P;
+        T t;
+        P p = &t;
+        struct s { long a, b, *c; int d; };
+        int f(void), g, h(int);
+        int main(void) {
+            int x = 1, y = x + 1;
+            return y;
+        }
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 12);
+    }
+
+    #[test]
+    #[should_panic(expected = "Function 'f' at 1, 8 can only be defined when it's declared on its own")]
+    fn parsing_function_body_in_declarator_list() {
+        parse("int a, f(void) { return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Definition of 'f' at 1, 5 has a parameter without a name")]
+    fn parsing_unnamed_parameter_in_definition() {
+        parse("int f(int) { return 0; }");
+    }
+
+    #[test]
+    fn parsing_structure_declarations() {
+        let str = r#"
+        struct point { int x; int y; };
+        struct node { struct node *next; union { long l; double d; } value; } head;
+        struct point;
+        int main(void) { struct point p; return p.x + head.next->value.l; }
+        "#;
+        let parser = parse(str);
+        // The anonymous union and struct node come out as declarations of their own, in front of head
+        assert_eq!(parser.parsed_tokens.len(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid type specifiers at 1, 5")]
+    fn parsing_structure_with_other_specifiers() {
+        parse("int struct s x;");
+    }
+
+    #[test]
+    #[should_panic(expected = "The struct at 1, 1 needs at least one member")]
+    fn parsing_empty_structure() {
+        parse("struct s {};");
+    }
+
+    #[test]
+    #[should_panic(expected = "Member 'f' at 1, 16 can't be a function")]
+    fn parsing_function_member() {
+        parse("struct s { int f(void); };");
+    }
+
+    #[test]
+    fn parsing_casts_and_sizeof() {
+        let str = r#"
+        int buffer[sizeof(long) * 2];
+        int main(void) {
+            long x = (long)3 + sizeof(int) + sizeof (x) + sizeof x + (x) * 2;
+            int *p = (int *)(long)0;
+            return (int)x + sizeof(int (*)[3]) + (unsigned char)-1;
+        }
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't define a struct inside a type name at 1, 32")]
+    fn parsing_structure_definition_in_type_name() {
+        parse("int main(void) { return sizeof(struct s { int a; }); }");
+    }
+
+    #[test]
+    fn parsing_void() {
+        let str = r#"
+        void *malloc(unsigned long size);
+        void reset(void **slot) {
+            *slot = (void *)0;
+            return;
+        }
+        int main(void) {
+            (void)sizeof(void *);
+            return 0;
+        }
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid type specifiers at 1, 1")]
+    fn parsing_void_with_other_specifiers() {
+        parse("void int x;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Parameter at 1, 14 can't have type void")]
+    fn parsing_void_parameter() {
+        parse("int f(int a, void);");
+    }
+
+    #[test]
+    #[should_panic(expected = "Array of void at 1, 17")]
+    fn parsing_array_of_void() {
+        parse("extern void (*p)[3];");
+    }
+
+    #[test]
+    fn parsing_enum() {
+        let str = r#"
+        enum color { RED, GREEN = 5, BLUE, };
+        int table[BLUE];
+        enum { BIG = BLUE * 2 } size = BIG;
+        int main(void) {
+            enum color c = GREEN;
+            return c + sizeof(enum color);
+        }
+        "#;
+        let parser = parse(str);
+        // Each definition comes out as a declaration of its own
+        assert_eq!(parser.parsed_tokens.len(), 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Value of enumerator 'B' at 1, 24 must be an integer constant")]
+    fn parsing_enumerator_with_variable_value() {
+        parse("int x; enum e { A = 1, B = x };");
+    }
+
+    #[test]
+    #[should_panic(expected = "Value of enumerator 'B' at 1, 26 doesn't fit in an int")]
+    fn parsing_enumerator_too_big() {
+        parse("enum e { A = 2147483647, B };");
+    }
+
+    #[test]
+    #[should_panic(expected = "Use of undefined 'enum e' at 1, 6")]
+    fn parsing_undefined_enum() {
+        parse("enum e x;");
+    }
+
+    #[test]
+    #[should_panic(expected = "'A' at 1, 43 is not a function")]
+    fn parsing_call_to_enumerator() {
+        parse("enum e { A }; int main(void) { return 1 + A(); }");
+    }
+
+    #[test]
+    fn parsing_typedef() {
+        let str = r#"
+        typedef int T;
+        typedef struct point { T x; } Point;
+        typedef T T;
+        int main(void) {
+            T * x;
+            Point p;
+            {
+                int T = 3;
+                return T * 2 + sizeof(Point) + (T);
+            }
+        }
+        "#;
+        let parser = parse(str);
+        // Only the structure definition and main are left, typedefs don't declare anything
+        assert_eq!(parser.parsed_tokens.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Redeclaration of 'T' at 1, 20")]
+    fn parsing_typedef_redeclared_as_variable() {
+        parse("typedef int T; int T;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Conflicting types for 'T' at 1, 29, it was declared as 'int' before")]
+    fn parsing_conflicting_typedefs() {
+        parse("typedef int T; typedef long T;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Typedef 'T' at 1, 13 can't be initialized")]
+    fn parsing_initialized_typedef() {
+        parse("typedef int T = 3;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid type specifiers at 1, 24")]
+    fn parsing_typedef_with_other_specifiers() {
+        parse("typedef int T; typedef T long U;");
+    }
+
+    #[test]
+    fn parsing_qualifiers() {
+        let str = r#"
+        const int limit = 3;
+        int main(void) {
+            volatile const long *const volatile p = 0;
+            int const x = (const int)limit;
+            return x + sizeof(const char *);
+        }
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 2);
+    }
+
+    #[test]
+    fn parsing_variadic_functions() {
+        let str = r#"
+        int printf(char *format, ...);
+        int sum(int count, ...) {
+            __builtin_va_list args;
+            int total;
+            __builtin_va_start(args, count);
+            total = __builtin_va_arg(args, int) + (int)__builtin_va_arg(args, double);
+            __builtin_va_end(args);
+            return total;
+        }
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected a parameter before '...' at 1, 7")]
+    fn parsing_ellipsis_on_its_own() {
+        parse("int f(...);");
+    }
+
+    #[test]
+    fn parsing_designated_initializers() {
+        let str = r#"
+        struct point { int x; int y; };
+        struct point origin = {.y = 1, .x = 2};
+        int values[4] = {[2] = 5, 6, [0] = 1};
+        int main(void) {
+            struct point corners[2] = {[1].x = 3, [0] = {.y = 4}};
+            return sizeof (int[2]){1, 2} + (struct point){.x = 1}.x;
+        }
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index in designator at 1, 16 must be a non-negative integer constant")]
+    fn parsing_negative_designator() {
+        parse("int a[3] = {1, [-1] = 2};");
+    }
+
+    #[test]
+    fn parsing_function_pointers() {
+        let str = r#"
+        struct ops { int (*apply)(int); int (*table[2])(int, int); };
+        int (*choose(int which))(int);
+        int main(void) {
+            int (*fp)(int) = choose(1);
+            struct ops o;
+            return fp(1) + (*fp)(2) + o.apply(3) + o.table[1](4, 5) + choose(0)(6);
+        }
+        "#;
+        let parser = parse(str);
+        assert_eq!(parser.parsed_tokens.len(), 3);
+    }
+}
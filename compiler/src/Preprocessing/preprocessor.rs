@@ -0,0 +1,1005 @@
+use crate::lexer::syntax_token::{Expansion, Location, SyntaxKind};
+use crate::lexer::{unescape, IntegerLiteral, Lexer, LexerToken};
+use crate::target::Target;
+use std::collections::{HashMap, HashSet, LinkedList, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Anything nested deeper than this is taken to be a file including itself forever
+const MAX_INCLUDE_DEPTH: usize = 200;
+
+// The directives that are still followed in a group that's skipped, so they can be matched up
+const CONDITIONALS: [&str; 6] = ["if", "ifdef", "ifndef", "elif", "else", "endif"];
+
+// Macros that expand to something different wherever they're used, so they can't be defined the usual way
+const BUILTIN_MACROS: [&str; 2] = ["__FILE__", "__LINE__"];
+
+// Headers that come with the compiler rather than the C library, since only the compiler knows how to write them.
+// They're used for <...> when none of the directories given with -I has one of the same name
+const BUILTIN_HEADERS: [(&str, &str); 1] = [(
+    "stdarg.h",
+    "#ifndef _STDARG_H
+#define _STDARG_H
+typedef __builtin_va_list va_list;
+#define va_start(ap, last) __builtin_va_start(ap, last)
+#define va_arg(ap, type) __builtin_va_arg(ap, type)
+#define va_end(ap) __builtin_va_end(ap)
+#define va_copy(dest, src) __builtin_va_copy(dest, src)
+#endif
+",
+)];
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+// Runs between the lexer and the parser. Every line that's kept gets lexed as it is, then directives are carried out
+// and macros expanded, and what's left is handed on to the parser as if it had been written out in full.
+pub struct Preprocessor {
+    // Searched in order for includes, after the directory of the including file for the quoted ones. There are no
+    // system directories, the C library's headers are written for compilers with GNU extensions. Apart from
+    // `<stdarg.h>`, which is built in, `<stddef.h>` and the like only work if a directory given with -I has them
+    include_paths: Vec<PathBuf>,
+    macros: HashMap<String, Macro>,
+    // Files that said #pragma once, including them again does nothing
+    included_once: HashSet<PathBuf>,
+    include_depth: usize,
+    // Whether a character constant in #if can come out negative
+    char_is_signed: bool,
+    pub tokens: LinkedList<LexerToken>,
+}
+
+#[derive(Clone, PartialEq)]
+struct Macro {
+    // An object-like macro doesn't have a parameter list at all
+    parameters: Option<Vec<String>>,
+    // The arguments behind the last named parameter all go into __VA_ARGS__
+    variadic: bool,
+    body: Vec<Token>,
+}
+
+// A token on its way through the preprocessor
+#[derive(Clone)]
+struct Token {
+    token: LexerToken,
+    // Whether there was whitespace in front of it, which only matters to #
+    space: bool,
+    // The macros this token came out of, which can't expand it a second time
+    hidden: HashSet<String>,
+}
+
+// Two definitions of a macro are the same if they're spelled the same, wherever they were written
+impl PartialEq for Token {
+    fn eq(&self, other: &Token) -> bool {
+        self.token.data == other.token.data && self.space == other.space
+    }
+}
+
+// The file we're in the middle of, a new one for every include
+struct Source {
+    // Where the file is on disk, for finding the files it includes and for #pragma once
+    path: Option<PathBuf>,
+    // What locations call the file, #line can change it
+    name: Option<Rc<str>>,
+    // Added to every line number after a #line
+    line_offset: isize,
+    conditionals: Vec<Conditional>,
+}
+
+struct Conditional {
+    location: Location,
+    // Whether the lines in the branch we're in are kept
+    active: bool,
+    // Once a branch is taken the ones after it are all skipped. A conditional inside a skipped branch counts as
+    // taken from the start, so none of its branches are ever kept
+    taken: bool,
+    seen_else: bool,
+}
+
+impl Preprocessor {
+    // Starts out with the macros of the standard and the ones that describe the target
+    pub fn new(include_paths: Vec<PathBuf>, target: &Target) -> Self {
+        target.check_supported();
+        let mut preprocessor = Preprocessor {
+            include_paths,
+            macros: HashMap::new(),
+            included_once: HashSet::new(),
+            include_depth: 0,
+            char_is_signed: target.char_is_signed,
+            tokens: LinkedList::new(),
+        };
+        let (date, time) = date_and_time(build_time());
+        let standard = [
+            "__STDC__ 1".to_string(),
+            "__STDC_VERSION__ 201710L".to_string(),
+            "__STDC_HOSTED__ 1".to_string(),
+            format!("__DATE__ \"{}\"", date),
+            format!("__TIME__ \"{}\"", time),
+        ];
+        for definition in standard.into_iter().chain(target.predefined_macros()) {
+            preprocessor.define(&definition);
+        }
+        preprocessor
+    }
+
+    // For source that didn't come out of a file, quoted includes are looked for from the current directory
+    pub fn preprocess(&mut self, source: &str) {
+        self.process(source, Source {
+            path: None,
+            name: None,
+            line_offset: 0,
+            conditionals: Vec::new(),
+        });
+    }
+
+    pub fn preprocess_file(&mut self, path: &Path) {
+        let text = fs::read_to_string(path).unwrap_or_else(|_| panic!("Cannot read from file: {}", path.display()));
+        self.process(&text, Source {
+            path: Some(path.to_path_buf()),
+            name: Some(Rc::from(path.display().to_string())),
+            line_offset: 0,
+            conditionals: Vec::new(),
+        });
+    }
+
+    // Takes whatever would follow #define, `NAME body` or `NAME(parameters) body`
+    pub fn define(&mut self, definition: &str) {
+        let line = lex_line(definition, 1);
+        self.define_macro(line, &Location::new(0, 0));
+    }
+
+    fn process(&mut self, text: &str, mut source: Source) {
+        // Lines in between directives are expanded together, a macro's arguments can go over several of them
+        let mut pending: Vec<Token> = Vec::new();
+        for (number, text) in split_lines(text) {
+            // A group that's skipped only has to be split into lines, whatever's in it doesn't have to lex. Only the
+            // conditionals in it are looked at, and only an #elif's expression can matter
+            let mut line = match directive_name(&text) {
+                _ if !is_skipping(&source.conditionals) => lex_line(&text, number),
+                Some(("elif", _)) => lex_line(&text, number),
+                Some((name, end)) if CONDITIONALS.contains(&name) => lex_line(&text[..end], number),
+                _ => continue,
+            };
+            if line.is_empty() {
+                continue;
+            }
+            for token in line.iter_mut() {
+                let location = &mut token.token.location;
+                location.line = (location.line as isize + source.line_offset) as usize;
+                location.file = source.name.clone();
+            }
+            if line[0].token.kind == SyntaxKind::Hash {
+                let expanded = self.expand(std::mem::take(&mut pending));
+                self.tokens.extend(expanded.into_iter().map(|token| token.token));
+                self.directive(line, &mut source);
+            } else if !is_skipping(&source.conditionals) {
+                pending.extend(line);
+            }
+        }
+        let expanded = self.expand(pending);
+        self.tokens.extend(expanded.into_iter().map(|token| token.token));
+
+        if let Some(conditional) = source.conditionals.last() {
+            panic!("Conditional at {} is missing its #endif", conditional.location)
+        }
+    }
+
+    fn directive(&mut self, line: Vec<Token>, source: &mut Source) {
+        // A # on its own does nothing
+        let Some(name) = line.get(1) else { return };
+        let location = name.token.location.clone();
+        let rest: Vec<Token> = line[2..].to_vec();
+        let skipping = is_skipping(&source.conditionals);
+        match name.token.data.as_str() {
+            "if" | "ifdef" | "ifndef" => {
+                let active = !skipping && match name.token.data.as_str() {
+                    "if" => self.evaluate(rest, &location),
+                    "ifdef" => self.is_defined(&macro_name(&rest, &location)),
+                    _ => !self.is_defined(&macro_name(&rest, &location)),
+                };
+                source.conditionals.push(Conditional {
+                    location,
+                    active,
+                    taken: active || skipping,
+                    seen_else: false,
+                });
+            }
+            "elif" | "else" => {
+                let count = source.conditionals.len();
+                if count == 0 {
+                    panic!("#{} at {} without #if", name.token.data, location)
+                }
+                let outer_skipping = is_skipping(&source.conditionals[..count - 1]);
+                let conditional = &source.conditionals[count - 1];
+                if conditional.seen_else {
+                    panic!("#{} at {} after #else", name.token.data, location)
+                }
+                let taken = conditional.taken;
+                let active = !taken && !outer_skipping && (name.token.data == "else" || self.evaluate(rest, &location));
+                let conditional = &mut source.conditionals[count - 1];
+                conditional.active = active;
+                conditional.taken = taken || active;
+                conditional.seen_else = name.token.data == "else";
+            }
+            "endif" => {
+                if source.conditionals.pop().is_none() {
+                    panic!("#endif at {} without #if", location)
+                }
+            }
+            _ if skipping => {}
+            "define" => self.define_macro(rest, &location),
+            "undef" => {
+                let name = macro_name(&rest, &location);
+                if BUILTIN_MACROS.contains(&name.as_str()) {
+                    panic!("Can't undefine built-in macro '{}' at {}", name, location)
+                }
+                self.macros.remove(&name);
+            }
+            "include" => self.include(rest, &location, source),
+            "error" => panic!("#error {} at {}", spell(&rest), location),
+            // Any other pragma is for some other compiler
+            "pragma" => {
+                if rest.first().is_some_and(|token| token.token.data == "once") && let Some(path) = source.path.as_ref() {
+                    self.included_once.insert(canonical(path));
+                }
+            }
+            "line" => self.line_directive(rest, &location, source),
+            directive => panic!("Unknown directive '#{}' at {}", directive, location),
+        }
+    }
+
+    // A function-like macro has its parameter list right up against the name, with a space it's part of the body
+    fn define_macro(&mut self, line: Vec<Token>, location: &Location) {
+        let name = macro_name(&line, location);
+        if name == "defined" {
+            panic!("'defined' at {} can't be defined as a macro", location)
+        }
+        if BUILTIN_MACROS.contains(&name.as_str()) {
+            panic!("Redefinition of built-in macro '{}' at {}", name, location)
+        }
+        let mut position = 1;
+        let mut parameters = None;
+        let mut variadic = false;
+        if line.get(1).is_some_and(|token| token.token.kind == SyntaxKind::OpenParen && !token.space) {
+            let mut names: Vec<String> = Vec::new();
+            position = 2;
+            loop {
+                let Some(token) = line.get(position) else {
+                    panic!("Unterminated parameter list of macro '{}' at {}", name, location)
+                };
+                position += 1;
+                match token.token.kind {
+                    SyntaxKind::CloseParen if names.is_empty() => break,
+                    SyntaxKind::Ellipsis => {
+                        variadic = true;
+                        names.push("__VA_ARGS__".to_string());
+                    }
+                    _ if is_identifier(&token.token) => {
+                        if names.contains(&token.token.data) {
+                            panic!("Duplicate parameter '{}' of macro '{}' at {}", token.token.data, name, token.token.location)
+                        }
+                        names.push(token.token.data.clone());
+                    }
+                    _ => panic!("Expected a parameter of macro '{}' but found '{}' at {}", name, token.token.data, token.token.location),
+                }
+                let Some(next) = line.get(position) else {
+                    panic!("Unterminated parameter list of macro '{}' at {}", name, location)
+                };
+                position += 1;
+                match next.token.kind {
+                    SyntaxKind::CloseParen => break,
+                    SyntaxKind::Comma if !variadic => {}
+                    _ => panic!("Expected ',' or ')' in the parameters of macro '{}' but found '{}' at {}", name, next.token.data, next.token.location),
+                }
+            }
+            parameters = Some(names);
+        }
+
+        let body: Vec<Token> = line[position.min(line.len())..].to_vec();
+        if let Some(token) = [body.first(), body.last()].into_iter().flatten().find(|token| token.token.kind == SyntaxKind::HashHash) {
+            panic!("'##' at {} can't be at either end of a macro", token.token.location)
+        }
+        if let Some(names) = parameters.as_ref() {
+            for (index, token) in body.iter().enumerate() {
+                let stringified = body.get(index + 1).is_some_and(|next| names.contains(&next.token.data));
+                if token.token.kind == SyntaxKind::Hash && !stringified {
+                    panic!("'#' at {} is not followed by a macro parameter", token.token.location)
+                }
+            }
+        }
+
+        let definition = Macro { parameters, variadic, body };
+        if let Some(existing) = self.macros.get(&name) && *existing != definition {
+            panic!("Redefinition of macro '{}' at {}", name, location)
+        }
+        self.macros.insert(name, definition);
+    }
+
+    fn include(&mut self, tokens: Vec<Token>, location: &Location, source: &Source) {
+        // The file name can come out of a macro, as long as it doesn't start out as one of the two forms
+        let tokens = match tokens.first() {
+            Some(token) if is_identifier(&token.token) => self.expand(tokens),
+            _ => tokens,
+        };
+        let (name, quoted) = match tokens.first().map(|token| &token.token.kind) {
+            Some(SyntaxKind::QuotedString) => {
+                let data = &tokens[0].token.data;
+                (data[1..data.len() - 1].to_string(), true)
+            }
+            // The lexer split the name up, so we put it back together. Spaces inside the brackets are lost
+            Some(SyntaxKind::Less) => {
+                let end = tokens
+                    .iter()
+                    .position(|token| token.token.kind == SyntaxKind::Greater)
+                    .unwrap_or_else(|| panic!("Missing '>' after the file name in #include at {}", location));
+                (spell_together(&tokens[1..end]), false)
+            }
+            _ => panic!("Expected a file name after #include at {}", location),
+        };
+
+        let builtin = BUILTIN_HEADERS.iter().find(|(header, _)| !quoted && *header == name);
+        let (text, path) = match self.find_include(&name, quoted, source) {
+            Some(path) => {
+                if self.included_once.contains(&canonical(&path)) {
+                    return;
+                }
+                let text = fs::read_to_string(&path).unwrap_or_else(|_| panic!("Can't read '{}' included at {}", name, location));
+                (text, Some(path))
+            }
+            // A built-in header guards itself, it doesn't have a path for #pragma once
+            None if let Some((_, text)) = builtin => (text.to_string(), None),
+            None if quoted => panic!("Can't find '{}' included at {}", name, location),
+            None => panic!("Can't find '{}' included at {}, only the directories given with -I are searched for <...>", name, location),
+        };
+        if self.include_depth == MAX_INCLUDE_DEPTH {
+            panic!("Includes nested too deeply at {}", location)
+        }
+        self.include_depth += 1;
+        let shown = path.as_ref().map_or_else(|| format!("<{}>", name), |path| path.display().to_string());
+        self.process(&text, Source {
+            name: Some(Rc::from(shown)),
+            path,
+            line_offset: 0,
+            conditionals: Vec::new(),
+        });
+        self.include_depth -= 1;
+    }
+
+    // Quoted names are looked for next to the file that includes them first
+    fn find_include(&self, name: &str, quoted: bool, source: &Source) -> Option<PathBuf> {
+        let here = match source.path.as_ref().and_then(|path| path.parent()) {
+            Some(directory) => directory.to_path_buf(),
+            None => PathBuf::from("."),
+        };
+        let local = quoted.then_some(here);
+        local
+            .into_iter()
+            .chain(self.include_paths.iter().cloned())
+            .map(|directory| directory.join(name))
+            .find(|path| path.is_file())
+    }
+
+    // The line after `#line 10` is line 10, and a file name after the number renames the file as well
+    fn line_directive(&mut self, tokens: Vec<Token>, location: &Location, source: &mut Source) {
+        let tokens = self.expand(tokens);
+        let line = match tokens.first() {
+            Some(token) if token.token.kind == SyntaxKind::NumberLiteral => token.token.data.parse::<isize>().ok(),
+            _ => None,
+        };
+        let Some(line) = line.filter(|line| *line > 0) else {
+            panic!("#line at {} needs a positive line number", location)
+        };
+        let last = tokens.last().map_or(location.line, |token| token.token.location.line);
+        source.line_offset += line - (last as isize + 1);
+        match tokens.get(1) {
+            Some(token) if token.token.kind == SyntaxKind::QuotedString => {
+                let data = &token.token.data;
+                source.name = Some(Rc::from(&data[1..data.len() - 1]));
+            }
+            Some(token) => panic!("Expected a file name after the line number but found '{}' at {}", token.token.data, token.token.location),
+            None => {}
+        }
+    }
+
+    // Expands every macro in tokens, then rescans what it expanded to for more. A macro's own name isn't expanded
+    // again anywhere in what came out of it, which keeps a macro that mentions itself from going on forever
+    fn expand(&self, tokens: Vec<Token>) -> Vec<Token> {
+        let mut input: VecDeque<Token> = tokens.into();
+        let mut output = Vec::new();
+        while let Some(token) = input.pop_front() {
+            if let Some(builtin) = builtin(&token) {
+                output.push(builtin);
+                continue;
+            }
+            let name = &token.token.data;
+            let definition = match self.macros.get(name) {
+                Some(definition) if is_identifier(&token.token) && !token.hidden.contains(name) => definition,
+                _ => {
+                    output.push(token);
+                    continue;
+                }
+            };
+            let mut hidden = token.hidden.clone();
+            let expanded = match definition.parameters.as_ref() {
+                None => {
+                    hidden.insert(name.clone());
+                    self.substitute(definition, &[], &token)
+                }
+                // The name of a function-like macro without arguments is just a name
+                Some(_) if input.front().is_none_or(|next| next.token.kind != SyntaxKind::OpenParen) => {
+                    output.push(token);
+                    continue;
+                }
+                Some(parameters) => {
+                    let (arguments, close_paren) = collect_arguments(&mut input, &token, parameters.len(), definition.variadic);
+                    // Only what's hidden from both the name and the closing bracket stays hidden
+                    hidden.retain(|name| close_paren.hidden.contains(name));
+                    hidden.insert(name.clone());
+                    self.substitute(definition, &arguments, &token)
+                }
+            };
+            for mut expanded in expanded.into_iter().rev() {
+                expanded.hidden.extend(hidden.iter().cloned());
+                input.push_front(expanded);
+            }
+        }
+        output
+    }
+
+    // Fills the arguments into the body. An argument next to # or ## goes in as it was written,
+    // anywhere else it's fully expanded first
+    fn substitute(&self, definition: &Macro, arguments: &[Vec<Token>], usage: &Token) -> Vec<Token> {
+        let parameters = definition.parameters.as_deref().unwrap_or_default();
+        let argument = |token: &Token| argument_of(parameters, arguments, token);
+        let is_paste = |index: usize| definition.body.get(index).is_some_and(|token| token.token.kind == SyntaxKind::HashHash);
+        let expansion = Rc::new(Expansion {
+            name: usage.token.data.clone(),
+            location: usage.token.location.clone(),
+        });
+        // Tokens from the body point at where they were written in the definition, and at the use they came out of
+        let from_body = |token: &Token| {
+            let mut token = token.clone();
+            token.token.location.expansion = Some(expansion.clone());
+            token
+        };
+
+        let mut output: Vec<Token> = Vec::new();
+        let mut index = 0;
+        while index < definition.body.len() {
+            let token = &definition.body[index];
+            if token.token.kind == SyntaxKind::Hash && definition.parameters.is_some() {
+                let mut quoted = from_body(token);
+                let written = argument(&definition.body[index + 1]).expect("Checked when the macro was defined");
+                quoted.token.kind = SyntaxKind::QuotedString;
+                quoted.token.data = stringify(written);
+                quoted.token.length = quoted.token.data.len();
+                output.push(quoted);
+                index += 2;
+                continue;
+            }
+            // `, ## __VA_ARGS__` drops the comma when there are no variadic arguments
+            if token.token.kind == SyntaxKind::Comma && is_paste(index + 1) && definition.variadic
+                && definition.body.get(index + 2).is_some_and(|next| next.token.data == "__VA_ARGS__")
+            {
+                let rest = &arguments[parameters.len() - 1];
+                if !rest.is_empty() {
+                    output.push(from_body(token));
+                    output.extend(rest.iter().cloned());
+                }
+                index += 3;
+                continue;
+            }
+            if token.token.kind == SyntaxKind::HashHash {
+                let right = &definition.body[index + 1];
+                let pieces = match argument(right) {
+                    Some(argument) => argument.clone(),
+                    None => vec![from_body(right)],
+                };
+                // An empty argument on either side leaves the other side as it is
+                let mut pieces = pieces.into_iter();
+                if let Some(first) = pieces.next() {
+                    match output.pop() {
+                        Some(left) => output.push(paste(left, first)),
+                        None => output.push(first),
+                    }
+                    output.extend(pieces);
+                }
+                index += 2;
+                continue;
+            }
+            match argument(token) {
+                // An empty argument on the left of ## means the right side goes in as it is
+                Some(written) if written.is_empty() && is_paste(index + 1) => {
+                    let right = &definition.body[index + 2];
+                    match argument(right) {
+                        Some(written) => output.extend(written.iter().cloned()),
+                        None => output.push(from_body(right)),
+                    }
+                    index += 3;
+                    continue;
+                }
+                Some(written) if is_paste(index + 1) => output.extend(written.iter().cloned()),
+                Some(written) => output.extend(self.expand(written.clone())),
+                None => output.push(from_body(token)),
+            }
+            index += 1;
+        }
+        // Whatever the macro turned into sits where its name was
+        if let Some(first) = output.first_mut() {
+            first.space = usage.space;
+        }
+        output
+    }
+
+    fn is_defined(&self, name: &str) -> bool {
+        self.macros.contains_key(name) || BUILTIN_MACROS.contains(&name)
+    }
+
+    // `defined NAME` and `defined(NAME)` are dealt with before anything is expanded, names that are left afterwards
+    // aren't macros and count as zero
+    fn evaluate(&self, tokens: Vec<Token>, location: &Location) -> bool {
+        let mut replaced = Vec::new();
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            if token.token.data != "defined" {
+                replaced.push(token);
+                continue;
+            }
+            let parenthesized = tokens.next_if(|next| next.token.kind == SyntaxKind::OpenParen).is_some();
+            let name = match tokens.next() {
+                Some(name) if is_identifier(&name.token) => name.token.data,
+                _ => panic!("Expected a macro name after 'defined' at {}", token.token.location),
+            };
+            if parenthesized && tokens.next().is_none_or(|close| close.token.kind != SyntaxKind::CloseParen) {
+                panic!("Expected ')' after 'defined({}' at {}", name, token.token.location)
+            }
+            replaced.push(number(&token, self.is_defined(&name) as i64));
+        }
+        let expanded: Vec<Token> = self
+            .expand(replaced)
+            .into_iter()
+            .map(|token| if is_identifier(&token.token) { number(&token, 0) } else { token })
+            .collect();
+        if expanded.is_empty() {
+            panic!("#if at {} needs an expression", location)
+        }
+
+        let mut evaluator = Evaluator {
+            tokens: &expanded,
+            position: 0,
+            location,
+            char_is_signed: self.char_is_signed,
+            evaluating: true,
+        };
+        let value = evaluator.expression(0);
+        if let Some(token) = expanded.get(evaluator.position) {
+            panic!("Unexpected '{}' in #if at {}", token.token.data, token.token.location)
+        }
+        value.bits != 0
+    }
+}
+
+// Splits the source into the lines directives go by, each with the number of the line it starts on. A backslash at the
+// end of a line carries it on to the next, and so does a comment that isn't closed yet. Quotes are followed so that
+// nothing inside them starts a comment
+fn split_lines(text: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut start = 1;
+    let mut number = 1;
+    let mut quote: Option<char> = None;
+    let (mut block_comment, mut line_comment) = (false, false);
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            number += 1;
+            if !block_comment {
+                lines.push((start, std::mem::take(&mut line)));
+                start = number;
+                quote = None;
+                line_comment = false;
+                continue;
+            }
+        }
+        line.push(c);
+        let next = chars.peek().copied();
+        let crlf = next == Some('\r') && chars.clone().nth(1) == Some('\n');
+        if c == '\\' && (next == Some('\n') || crlf) {
+            number += 1;
+            if crlf {
+                line.push(chars.next().unwrap());
+            }
+            line.push(chars.next().unwrap());
+        } else if block_comment {
+            if c == '*' && next == Some('/') {
+                line.push(chars.next().unwrap());
+                block_comment = false;
+            }
+        } else if line_comment {
+            // Nothing in it counts until the line ends
+        } else if let Some(open) = quote {
+            if c == '\\' && let Some(escaped) = chars.next() {
+                line.push(escaped);
+            } else if c == open {
+                quote = None;
+            }
+        } else if c == '"' || c == '\'' {
+            quote = Some(c);
+        } else if c == '/' && next == Some('*') {
+            line.push(chars.next().unwrap());
+            block_comment = true;
+        } else if c == '/' && next == Some('/') {
+            line_comment = true;
+        }
+    }
+    lines.push((start, line));
+    lines
+}
+
+// The name of the directive on a line and where it ends, without lexing anything
+fn directive_name(line: &str) -> Option<(&str, usize)> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let length = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+    let end = line.len() - rest.len() + length;
+    Some((&rest[..length], end))
+}
+
+// Lexes a line that starts on line number, a line with nothing on it gives no tokens
+fn lex_line(text: &str, number: usize) -> Vec<Token> {
+    if text.is_empty() {
+        return Vec::new();
+    }
+    let mut lexer = Lexer::starting_at(text, number);
+    lexer.lex();
+    let mut line: Vec<Token> = Vec::new();
+    for token in lexer.tokens.into_iter().filter(|token| token.kind != SyntaxKind::NewLine) {
+        // The lexer throws whitespace away, the gap it leaves between the tokens is what tells us it was there
+        let space = line.last().is_none_or(|previous: &Token| {
+            let previous = &previous.token;
+            previous.location.line != token.location.line || previous.location.column + previous.length < token.location.column
+        });
+        line.push(Token {
+            token,
+            space,
+            hidden: HashSet::new(),
+        });
+    }
+    line
+}
+
+fn is_skipping(conditionals: &[Conditional]) -> bool {
+    conditionals.iter().any(|conditional| !conditional.active)
+}
+
+// Keywords are names as far as the preprocessor is concerned, `#define const` is fine
+fn is_identifier(token: &LexerToken) -> bool {
+    token.kind != SyntaxKind::QuotedString && token.data.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+}
+
+fn macro_name(tokens: &[Token], location: &Location) -> String {
+    match tokens.first() {
+        Some(token) if is_identifier(&token.token) => token.token.data.clone(),
+        _ => panic!("Expected a macro name at {}", location),
+    }
+}
+
+fn argument_of<'a>(parameters: &[String], arguments: &'a [Vec<Token>], token: &Token) -> Option<&'a Vec<Token>> {
+    parameters.iter().position(|name| *name == token.token.data).map(|index| &arguments[index])
+}
+
+// Splits the arguments at the commas that aren't inside brackets, a variadic macro takes everything past its
+// named parameters as one last argument
+fn collect_arguments(input: &mut VecDeque<Token>, name: &Token, count: usize, variadic: bool) -> (Vec<Vec<Token>>, Token) {
+    input.pop_front();
+    let mut arguments: Vec<Vec<Token>> = vec![Vec::new()];
+    let mut depth = 0;
+    let close_paren = loop {
+        let Some(token) = input.pop_front() else {
+            panic!("Unterminated arguments of macro '{}' at {}", name.token.data, name.token.location)
+        };
+        match token.token.kind {
+            SyntaxKind::CloseParen if depth == 0 => break token,
+            SyntaxKind::Comma if depth == 0 && !(variadic && arguments.len() == count) => {
+                arguments.push(Vec::new());
+                continue;
+            }
+            SyntaxKind::OpenParen => depth += 1,
+            SyntaxKind::CloseParen => depth -= 1,
+            _ => {}
+        }
+        arguments.last_mut().unwrap().push(token);
+    };
+
+    // `F()` passes one empty argument, which is no arguments at all for a macro without parameters
+    if count == 0 && arguments.len() == 1 && arguments[0].is_empty() {
+        arguments.clear();
+    }
+    // Leaving out the variadic arguments altogether is the same as passing none
+    if variadic && arguments.len() == count - 1 {
+        arguments.push(Vec::new());
+    }
+    if arguments.len() != count {
+        let at_least = if variadic { "at least " } else { "" };
+        let count = if variadic { count - 1 } else { count };
+        panic!("Macro '{}' at {} takes {}{} arguments but was given {}", name.token.data, name.token.location, at_least, count, arguments.len())
+    }
+    (arguments, close_paren)
+}
+
+// Spaces in between tokens become a single space, quotes and backslashes inside strings are escaped
+fn stringify(tokens: &[Token]) -> String {
+    let mut text = String::from("\"");
+    for (index, token) in tokens.iter().enumerate() {
+        if index > 0 && token.space {
+            text.push(' ');
+        }
+        if matches!(token.token.kind, SyntaxKind::QuotedString | SyntaxKind::QuotedChar) {
+            text.push_str(&token.token.data.replace('\\', "\\\\").replace('"', "\\\""));
+        } else {
+            text.push_str(&token.token.data);
+        }
+    }
+    text.push('"');
+    text
+}
+
+// The two tokens written next to each other have to lex as exactly one token
+fn paste(left: Token, right: Token) -> Token {
+    let text = format!("{}{}", left.token.data, right.token.data);
+    let mut lexer = Lexer::new(&text);
+    lexer.lex();
+    if lexer.tokens.len() != 1 {
+        panic!("Pasting '{}' and '{}' at {} doesn't give a valid token", left.token.data, right.token.data, left.token.location)
+    }
+    let pasted = lexer.tokens.pop_front().unwrap();
+    let mut token = left;
+    token.token.kind = pasted.kind;
+    token.token.data = pasted.data;
+    token.token.length = pasted.length;
+    token.hidden.extend(right.hidden);
+    token
+}
+
+fn spell(tokens: &[Token]) -> String {
+    let quoted = stringify(tokens);
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+fn spell_together(tokens: &[Token]) -> String {
+    tokens.iter().map(|token| token.token.data.as_str()).collect()
+}
+
+fn number(token: &Token, value: i64) -> Token {
+    let mut token = token.clone();
+    token.token.kind = SyntaxKind::NumberLiteral;
+    token.token.data = value.to_string();
+    token
+}
+
+// What __FILE__ and __LINE__ expand to. Inside a macro that's the file and line the outermost macro was used on
+fn builtin(token: &Token) -> Option<Token> {
+    if !is_identifier(&token.token) || !BUILTIN_MACROS.contains(&token.token.data.as_str()) {
+        return None;
+    }
+    let mut location = &token.token.location;
+    while let Some(expansion) = location.expansion() {
+        location = &expansion.location;
+    }
+    if token.token.data == "__LINE__" {
+        return Some(number(token, location.line() as i64));
+    }
+    let name = location.file().unwrap_or("<stdin>");
+    let mut file = token.clone();
+    file.token.kind = SyntaxKind::QuotedString;
+    file.token.data = format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""));
+    file.token.length = file.token.data.len();
+    Some(file)
+}
+
+// Seconds since the epoch, SOURCE_DATE_EPOCH pins it down so builds can be reproduced
+fn build_time() -> i64 {
+    match std::env::var("SOURCE_DATE_EPOCH").ok().and_then(|seconds| seconds.parse().ok()) {
+        Some(seconds) => seconds,
+        None => SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs() as i64),
+    }
+}
+
+// __DATE__ and __TIME__ in UTC, "Jan  1 1970" and "00:00:00". Days are turned into a date
+// by counting in 400 year eras that start in March, so leap days come at the end of a year
+fn date_and_time(seconds: i64) -> (String, String) {
+    let days = seconds.div_euclid(86400);
+    let time = seconds.rem_euclid(86400);
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 2 } else { shifted_month - 10 };
+    let year = era * 400 + year_of_era + (month < 2) as i64;
+    (
+        format!("{} {:>2} {}", MONTHS[month as usize], day, year),
+        format!("{:02}:{:02}:{:02}", time / 3600, time / 60 % 60, time % 60),
+    )
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+// Evaluates the expression of an #if. Everything is done in 64 bits, the way intmax_t and uintmax_t would, and a value
+// is unsigned once an unsigned one has gone into it. Operators bind the way they do in the language, only without the
+// assignments and the comma
+struct Evaluator<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    location: &'a Location,
+    char_is_signed: bool,
+    // False in an operand that's never evaluated, the right of `0 && x` or the branch ?: doesn't take. It still has
+    // to parse, but dividing by zero in it is fine
+    evaluating: bool,
+}
+
+#[derive(Clone, Copy)]
+struct Value {
+    bits: i64,
+    unsigned: bool,
+}
+
+impl Value {
+    fn signed(bits: i64) -> Value {
+        Value { bits, unsigned: false }
+    }
+
+    fn truth(condition: bool) -> Value {
+        Value::signed(condition as i64)
+    }
+}
+
+// Binds looser than || and goes right to left, `a ? b : c ? d : e` is `a ? b : (c ? d : e)`
+const CONDITIONAL_PRECEDENCE: u8 = 3;
+
+impl Evaluator<'_> {
+    fn next(&mut self) -> &Token {
+        let Some(token) = self.tokens.get(self.position) else {
+            panic!("Unexpected end of the expression in #if at {}", self.location)
+        };
+        self.position += 1;
+        token
+    }
+
+    fn expression(&mut self, min_precedence: u8) -> Value {
+        let mut left = self.factor();
+        while let Some(operator) = self.tokens.get(self.position) {
+            if operator.token.kind == SyntaxKind::Question && min_precedence <= CONDITIONAL_PRECEDENCE {
+                self.position += 1;
+                let then = self.operand(0, left.bits == 0);
+                if self.next().token.kind != SyntaxKind::Colon {
+                    panic!("Expected ':' in #if at {}", self.location)
+                }
+                let otherwise = self.operand(CONDITIONAL_PRECEDENCE, left.bits != 0);
+                let unsigned = then.unsigned || otherwise.unsigned;
+                let chosen = if left.bits != 0 { then } else { otherwise };
+                left = Value { bits: chosen.bits, unsigned };
+                continue;
+            }
+            let precedence = match SyntaxKind::binary_precedence(&operator.token.kind) {
+                Some(precedence) if precedence > 1 && precedence >= min_precedence => precedence,
+                _ => break,
+            };
+            let operator = operator.clone();
+            self.position += 1;
+            let skipped = match operator.token.kind {
+                SyntaxKind::AmpersandAmpersand => left.bits == 0,
+                SyntaxKind::PipePipe => left.bits != 0,
+                _ => false,
+            };
+            let right = self.operand(precedence + 1, skipped);
+            left = self.binary(&operator, left, right);
+        }
+        left
+    }
+
+    fn operand(&mut self, min_precedence: u8, skipped: bool) -> Value {
+        let evaluating = self.evaluating;
+        self.evaluating = evaluating && !skipped;
+        let value = self.expression(min_precedence);
+        self.evaluating = evaluating;
+        value
+    }
+
+    fn factor(&mut self) -> Value {
+        let token = self.next().clone();
+        match token.token.kind {
+            SyntaxKind::NumberLiteral => parse_number(&token.token),
+            SyntaxKind::QuotedChar => parse_character(&token.token, self.char_is_signed),
+            SyntaxKind::Minus => {
+                let value = self.factor();
+                Value { bits: value.bits.wrapping_neg(), ..value }
+            }
+            SyntaxKind::Plus => self.factor(),
+            SyntaxKind::Tilde => {
+                let value = self.factor();
+                Value { bits: !value.bits, ..value }
+            }
+            SyntaxKind::Bang => Value::truth(self.factor().bits == 0),
+            SyntaxKind::OpenParen => {
+                let value = self.expression(0);
+                if self.next().token.kind != SyntaxKind::CloseParen {
+                    panic!("Expected ')' in #if at {}", self.location)
+                }
+                value
+            }
+            _ => panic!("Unexpected '{}' in #if at {}", token.token.data, token.token.location),
+        }
+    }
+
+    fn binary(&self, operator: &Token, left: Value, right: Value) -> Value {
+        use SyntaxKind::*;
+        let kind = &operator.token.kind;
+        // A shift keeps the type of its left operand, the logical operators only look at whether they're zero
+        let unsigned = match kind {
+            LessLess | GreaterGreater => left.unsigned,
+            AmpersandAmpersand | PipePipe => false,
+            _ => left.unsigned || right.unsigned,
+        };
+        if matches!(kind, Slash | Percent) && right.bits == 0 {
+            if self.evaluating {
+                panic!("Division by zero in #if at {}", operator.token.location)
+            }
+            // The value is thrown away, only its type can matter
+            return Value { bits: 0, unsigned };
+        }
+        let (l, r) = (left.bits, right.bits);
+        let (ul, ur) = (l as u64, r as u64);
+        let bits = match kind {
+            Star => l.wrapping_mul(r),
+            Slash if unsigned => (ul / ur) as i64,
+            Slash => l.wrapping_div(r),
+            Percent if unsigned => (ul % ur) as i64,
+            Percent => l.wrapping_rem(r),
+            Plus => l.wrapping_add(r),
+            Minus => l.wrapping_sub(r),
+            LessLess => l.wrapping_shl(r as u32),
+            GreaterGreater if unsigned => ul.wrapping_shr(r as u32) as i64,
+            GreaterGreater => l.wrapping_shr(r as u32),
+            Less | LessEqual | Greater | GreaterEqual => {
+                let ordering = if unsigned { ul.cmp(&ur) } else { l.cmp(&r) };
+                let holds = match kind {
+                    Less => ordering.is_lt(),
+                    LessEqual => ordering.is_le(),
+                    Greater => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                };
+                return Value::truth(holds);
+            }
+            EqualEqual => return Value::truth(l == r),
+            BangEqual => return Value::truth(l != r),
+            Ampersand => l & r,
+            Caret => l ^ r,
+            Pipe => l | r,
+            AmpersandAmpersand => return Value::truth(l != 0 && r != 0),
+            PipePipe => return Value::truth(l != 0 || r != 0),
+            _ => panic!("Unexpected '{}' in #if at {}", operator.token.data, operator.token.location),
+        };
+        Value { bits, unsigned }
+    }
+}
+
+// A u suffix makes it unsigned, and so does being too big for intmax_t. The other suffixes don't change anything
+fn parse_number(token: &LexerToken) -> Value {
+    let literal = IntegerLiteral::parse(token);
+    Value {
+        bits: literal.value as i64,
+        unsigned: literal.unsigned || literal.value > i64::MAX as u64,
+    }
+}
+
+// A character constant is an int holding the character, which is negative past 127 when char is signed
+fn parse_character(token: &LexerToken, char_is_signed: bool) -> Value {
+    let [byte] = unescape(token)[..] else {
+        panic!("Character constant {} at {} has to hold exactly one character", token.data, token.location)
+    };
+    Value::signed(if char_is_signed { byte as i8 as i64 } else { byte as i64 })
+}
@@ -0,0 +1,346 @@
+#[cfg(test)]
+mod preprocessor_tests {
+    use crate::preprocessor::Preprocessor;
+    use crate::target::Target;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn preprocess(str: &str) -> Preprocessor {
+        let mut preprocessor = Preprocessor::new(Vec::new(), &Target::default());
+        preprocessor.preprocess(str);
+        preprocessor
+    }
+
+    // The tokens that come out, with a space in between each of them
+    fn spelled(str: &str) -> String {
+        let tokens: Vec<String> = preprocess(str).tokens.iter().map(|token| token.data.clone()).collect();
+        tokens.join(" ")
+    }
+
+    // A directory of its own for every test that needs files
+    fn directory(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("compiler_preprocessor_tests_{}_{}", std::process::id(), name));
+        fs::create_dir_all(dir.join("include")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn object_like_macros() {
+        assert_eq!(spelled("#define N 4\nint a[N];"), "int a [ 4 ] ;");
+        assert_eq!(spelled("#define A B\n#define B 2\nA"), "2");
+        assert_eq!(spelled("#define N 4\n#undef N\nN"), "N");
+    }
+
+    #[test]
+    fn function_like_macros() {
+        assert_eq!(spelled("#define ADD(a, b) ((a) + (b))\nADD(1, ADD(2, 3))"), "( ( 1 ) + ( ( ( 2 ) + ( 3 ) ) ) )");
+        assert_eq!(spelled("#define F(x) x\nF + F(1)"), "F + 1");
+        assert_eq!(spelled("#define F() 3\nF()"), "3");
+        assert_eq!(spelled("#define F(x, y) x y\nF((1, 2),\n[3])"), "( 1 , 2 ) [ 3 ]");
+        // With a space before the bracket it's an object-like macro that starts with one
+        assert_eq!(spelled("#define F (x)\nF(1)"), "( x ) ( 1 )");
+    }
+
+    #[test]
+    fn macros_mentioning_themselves() {
+        assert_eq!(spelled("#define x x + 1\nx"), "x + 1");
+        assert_eq!(spelled("#define f(a) a + f(a)\nf(f(1))"), "1 + f ( 1 ) + f ( 1 + f ( 1 ) )");
+        assert_eq!(spelled("#define a b\n#define b a\na b"), "a b");
+    }
+
+    #[test]
+    fn stringification() {
+        assert_eq!(spelled("#define S(x) #x\nS(a  +   b) S(\"q\\n\") S()"), r#""a + b" "\"q\\n\"" """#);
+    }
+
+    #[test]
+    fn token_pasting() {
+        assert_eq!(spelled("#define CAT(a, b) a ## b\nCAT(foo, 12) CAT(, x) CAT(1, ) CAT(<, <=)"), "foo12 x 1 <<=");
+        assert_eq!(spelled("#define CAT(a, b) a ## b\n#define foo12 done\nCAT(foo, 12)"), "done");
+    }
+
+    #[test]
+    fn variadic_macros() {
+        let str = "#define CALL(f, ...) f(__VA_ARGS__)\n#define LOG(f, ...) f(0, ## __VA_ARGS__)\nCALL(g, 1, 2) CALL(g) LOG(h) LOG(h, 3, 4)";
+        assert_eq!(spelled(str), "g ( 1 , 2 ) g ( ) h ( 0 ) h ( 0 , 3 , 4 )");
+    }
+
+    #[test]
+    fn conditionals() {
+        let str = r#"
+        #define TWO 2
+        #if TWO * 3 == 6 && !defined(THREE)
+        a
+        #elif 1
+        b
+        #else
+        c
+        #endif
+        #ifdef THREE
+        d
+        #elif defined TWO
+        e
+        #endif
+        #ifndef TWO
+        f
+        #if 1
+        g
+        #else
+        h
+        #endif
+        #endif
+        #if UNDEFINED || (1 << 4) - 16 || 010 != 8 || 0x1F != 31
+        i
+        #else
+        j
+        #endif
+        "#;
+        assert_eq!(spelled(str), "a e j");
+    }
+
+    #[test]
+    fn skipped_lines_can_have_anything() {
+        assert_eq!(spelled("#if 0\n#error not here\n#unknown\n#if garbage (\n#endif\n#endif\nx"), "x");
+        assert_eq!(spelled("#if 0\nit's a @ `test` ?\n#if 'oops @\n#endif\n#else\ny\n#endif"), "y");
+        // A comment or backslash carries a line on, which decides what's a directive. Nothing in quotes starts a comment
+        assert_eq!(spelled("#if 0 /* one\n#else */\na\n#else\nb\n#endif"), "b");
+        assert_eq!(spelled("#define N \\\n 2\n#if 0\n#define X ' /*\n#endif\nN"), "2");
+    }
+
+    #[test]
+    fn if_expressions() {
+        let str = r#"
+        #if -1 > 0u && -1 / 2u != 0 && (0u - 1) >> 63 == 1 && -1 >> 63 == -1
+        a
+        #endif
+        #if (1 ? -1 : 0u) > 0 && (0 ? 1 : 2) == 2 && (1 ? 0 ? 3 : 4 : 5) == 4 && 1 || 0 ? 6 : 0
+        b
+        #endif
+        #if 0x10 == 16 && 0XffffffffffffffffU == -1 && 18446744073709551615 > 0
+        c
+        #endif
+        #if 'a' == 97 && '\n' == 10 && '\377' < 0 && '\'' == 39
+        d
+        #endif
+        "#;
+        assert_eq!(spelled(str), "a b c d");
+    }
+
+    // Operands that aren't evaluated can divide by zero
+    #[test]
+    fn if_short_circuits() {
+        let str = r#"
+        #if 0 && 1/0
+        a
+        #endif
+        #if (2 || 1/0)
+        b
+        #endif
+        #if 1 ? 2 : 1/0
+        c
+        #endif
+        #if 0 ? 1 % 0 : 0 || (1 ? 0 : 1/0)
+        d
+        #endif
+        #if defined(X) && 10 / X > 1
+        e
+        #endif
+        "#;
+        assert_eq!(spelled(str), "b c");
+    }
+
+    #[test]
+    #[should_panic(expected = "Division by zero in #if at 1, 12")]
+    fn if_divides_by_zero() {
+        preprocess("#if 1 && 1 / 0\n#endif");
+    }
+
+    #[test]
+    fn lines_carried_on() {
+        let preprocessor = preprocess("a /* one\ntwo */ b \\\nc\nd");
+        let lines: Vec<usize> = preprocessor.tokens.iter().map(|token| token.location.line()).collect();
+        assert_eq!(lines, vec![1, 2, 3, 4]);
+
+        // The same with the line endings Windows uses
+        assert_eq!(spelled("#define Y(a) \\\r\n    (a + 1)\r\nY(2)\r\n"), "( 2 + 1 )");
+    }
+
+    // Errors from the lexer give the line the character is really on
+    #[test]
+    #[should_panic(expected = "Unable to lex character @ at 3, 3")]
+    fn unknown_character() {
+        preprocess("a\nb\nc @");
+    }
+
+    #[test]
+    #[should_panic(expected = "Character constant 'ab' at 1, 5 has to hold exactly one character")]
+    fn character_constant_with_two_characters() {
+        preprocess("#if 'ab'\n#endif");
+    }
+
+    #[test]
+    fn line_directive() {
+        let preprocessor = preprocess("#line 40 \"other.c\"\nx\ny");
+        let lines: Vec<usize> = preprocessor.tokens.iter().map(|token| token.location.line()).collect();
+        assert_eq!(lines, vec![40, 41]);
+        assert_eq!(preprocessor.tokens.back().unwrap().location.file(), Some("other.c"));
+    }
+
+    #[test]
+    fn expansion_history() {
+        let preprocessor = preprocess("#define ONE 1\n#define F(x) x + ONE\nint a = F(2);");
+        let one = preprocessor.tokens.iter().find(|token| token.data == "1").unwrap();
+        assert_eq!(one.location.line_and_column(), (1, 13));
+        let expansion = one.location.expansion().unwrap();
+        assert_eq!(expansion.name, "ONE");
+        let outer = expansion.location.expansion().unwrap();
+        assert_eq!(outer.name, "F");
+        assert_eq!(outer.location.line_and_column(), (3, 9));
+        assert_eq!(one.location.to_string(), "1, 13 in expansion of 'ONE' at 2, 18 in expansion of 'F' at 3, 9");
+
+        // Arguments were written where the macro was used, so they keep their own locations
+        let two = preprocessor.tokens.iter().find(|token| token.data == "2").unwrap();
+        assert_eq!(two.location.to_string(), "3, 11");
+    }
+
+    #[test]
+    fn includes() {
+        let dir = directory("includes");
+        fs::write(dir.join("local.h"), "#pragma once\nint local;\n#include <system.h>\n").unwrap();
+        fs::write(dir.join("include").join("system.h"), "#define SYSTEM 1\nint system_header;\n").unwrap();
+        fs::write(dir.join("main.c"), "#include \"local.h\"\n#include \"local.h\"\n#define HEADER \"local.h\"\n#include HEADER\nint x = SYSTEM;\n").unwrap();
+
+        let mut preprocessor = Preprocessor::new(vec![dir.join("include")], &Target::default());
+        preprocessor.preprocess_file(&dir.join("main.c"));
+        let tokens: Vec<String> = preprocessor.tokens.iter().map(|token| token.data.clone()).collect();
+        assert_eq!(tokens.join(" "), "int local ; int system_header ; int x = 1 ;");
+        let header = preprocessor.tokens.front().unwrap();
+        assert_eq!(header.location.to_string(), format!("{}:2, 1", dir.join("local.h").display()));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't find 'missing.h' included at 1, 2")]
+    fn missing_include() {
+        preprocess("#include \"missing.h\"");
+    }
+
+    // The C library's headers aren't searched, even when they're there
+    #[test]
+    #[should_panic(expected = "Can't find 'stddef.h' included at 1, 2, only the directories given with -I are searched for <...>")]
+    fn no_system_headers() {
+        preprocess("#include <stddef.h>");
+    }
+
+    // <stdarg.h> comes with the compiler, and including it twice is fine
+    #[test]
+    fn builtin_stdarg() {
+        let str = "#include <stdarg.h>\n#include <stdarg.h>\nva_list ap; va_arg(ap, int); va_copy(a, b);";
+        let expected = "typedef __builtin_va_list va_list ; va_list ap ; __builtin_va_arg ( ap , int ) ; __builtin_va_copy ( a , b ) ;";
+        assert_eq!(spelled(str), expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "#error no good at 2, 2")]
+    fn error_directive() {
+        preprocess("#if 1\n#error no good\n#endif");
+    }
+
+    #[test]
+    #[should_panic(expected = "Conditional at 1, 2 is missing its #endif")]
+    fn unterminated_conditional() {
+        preprocess("#ifdef X\nint x;");
+    }
+
+    #[test]
+    #[should_panic(expected = "#else at 3, 2 after #else")]
+    fn else_after_else() {
+        preprocess("#if 1\n#else\n#else\n#endif");
+    }
+
+    #[test]
+    #[should_panic(expected = "Macro 'F' at 2, 1 takes 2 arguments but was given 1")]
+    fn wrong_number_of_macro_arguments() {
+        preprocess("#define F(a, b) a\nF(1)");
+    }
+
+    #[test]
+    #[should_panic(expected = "Macro 'F' at 2, 1 takes at least 2 arguments but was given 1")]
+    fn too_few_variadic_macro_arguments() {
+        preprocess("#define F(a, b, ...) a\nF(1)");
+    }
+
+    #[test]
+    #[should_panic(expected = "Pasting '+' and '-' at 2, 5 doesn't give a valid token")]
+    fn pasting_into_two_tokens() {
+        preprocess("#define CAT(a, b) a ## b\nCAT(+, -)");
+    }
+
+    #[test]
+    #[should_panic(expected = "Redefinition of macro 'N' at 2, 2")]
+    fn conflicting_macro_definitions() {
+        preprocess("#define N 1\n#define N 2");
+    }
+
+    #[test]
+    #[should_panic(expected = "'#' at 1, 14 is not followed by a macro parameter")]
+    fn stringifying_something_else() {
+        preprocess("#define S(x) #y");
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown directive '#inclde' at 1, 2")]
+    fn unknown_directive() {
+        preprocess("#inclde <x.h>");
+    }
+
+    #[test]
+    fn predefined_macros() {
+        let str = "__STDC__ __STDC_VERSION__ __x86_64__ __linux__ __unix__ __LP64__ __SIZEOF_LONG__ __SIZEOF_POINTER__ __INT_MAX__";
+        assert_eq!(spelled(str), "1 201710L 1 1 1 1 8 8 2147483647");
+        let str = "#if __SIZEOF_INT__ == 4 && __LONG_MAX__ == 9223372036854775807L && __BYTE_ORDER__ == __ORDER_LITTLE_ENDIAN__\nyes\n#endif";
+        assert_eq!(spelled(str), "yes");
+    }
+
+    #[test]
+    fn macros_follow_the_target() {
+        let target = Target { long_size: 4, pointer_size: 4, char_is_signed: false, ..Target::X86_64_LINUX };
+        let macros = target.predefined_macros();
+        for definition in ["__SIZEOF_LONG__ 4", "__SIZEOF_POINTER__ 4", "__LONG_MAX__ 2147483647L", "__CHAR_UNSIGNED__ 1"] {
+            assert!(macros.contains(&definition.to_string()), "{}", definition);
+        }
+        assert!(!macros.contains(&"__LP64__ 1".to_string()));
+    }
+
+    // Code is only ever generated for x86_64 Linux, so the macros can't describe anything else
+    #[test]
+    #[should_panic(expected = "Can't compile for x86_64-linux with these sizes, only the x86_64 Linux target is supported")]
+    fn other_targets() {
+        let target = Target { long_size: 4, pointer_size: 4, ..Target::X86_64_LINUX };
+        Preprocessor::new(Vec::new(), &target);
+    }
+
+    #[test]
+    fn file_and_line() {
+        assert_eq!(spelled("a __LINE__\n#define HERE __LINE__ __FILE__\n\nHERE"), "a 1 4 \"<stdin>\"");
+        assert_eq!(spelled("#line 40 \"other.c\"\n__FILE__ __LINE__"), "\"other.c\" 40");
+        assert_eq!(spelled("#if defined(__FILE__) && __LINE__ == 1\nyes\n#endif"), "yes");
+    }
+
+    #[test]
+    fn date_and_time() {
+        let preprocessor = preprocess("__DATE__ __TIME__");
+        let date = &preprocessor.tokens.front().unwrap().data;
+        let time = &preprocessor.tokens.back().unwrap().data;
+        // "Jan  1 1970" and "00:00:00", with their quotes
+        assert_eq!(date.len(), 13);
+        assert_eq!(time.len(), 10);
+        assert_eq!(time.matches(':').count(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Redefinition of built-in macro '__LINE__' at 1, 2")]
+    fn redefining_a_builtin_macro() {
+        preprocess("#define __LINE__ 3");
+    }
+}
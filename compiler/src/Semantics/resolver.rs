@@ -0,0 +1,616 @@
+use crate::lexer::syntax_token::{Location, SyntaxKind};
+use crate::lexer::LexerToken;
+use crate::parsing::*;
+use crate::types::{Type, VA_LIST_TAG};
+use std::collections::{HashMap, HashSet, LinkedList};
+
+// Every node in the tree knows how to resolve itself
+// Resolving renames every variable to something unique to the whole program
+// and ties cases and breaks to the statement that owns them
+pub trait Resolvable {
+    fn resolve(&mut self, resolver: &mut Resolver);
+}
+
+// Every label the compiler makes up starts with this, C reserves these names for the implementation
+pub const GENERATED_LABEL_PREFIX: &str = "__";
+
+// Undoes the renaming for error messages, unique names are the source name with a counter after a dot
+pub fn original_name(unique: &str) -> &str {
+    unique.split('.').next().unwrap_or(unique)
+}
+
+// What a name in scope resolves to
+#[derive(Clone)]
+struct ScopeEntry {
+    unique: String,
+    // Names with linkage refer to the same thing wherever they're declared, so they're never renamed
+    has_linkage: bool,
+}
+
+// Tags of structures, unions and enumerations live in a namespace of their own, but are scoped just like variables
+#[derive(Clone)]
+struct TagEntry {
+    unique: String,
+    // The struct, union or enum keyword it was declared with
+    keyword: SyntaxKind,
+}
+
+struct SwitchContext {
+    label: String,
+    cases: Vec<SwitchCase>,
+}
+
+pub struct Resolver {
+    // Innermost scope is at the back, the file scope is at the front
+    scopes: Vec<HashMap<String, ScopeEntry>>,
+    // Pushed and popped along with the scopes above
+    tag_scopes: Vec<HashMap<String, TagEntry>>,
+    switches: Vec<SwitchContext>,
+    // Every statement a break could jump out of, innermost at the back
+    break_labels: Vec<String>,
+    // Labels are scoped to the whole function, so gotos are only checked once we've seen all of it
+    labels: HashSet<String>,
+    gotos: Vec<LexerToken>,
+    counter: usize,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Resolver {
+            scopes: Vec::new(),
+            tag_scopes: Vec::new(),
+            switches: Vec::new(),
+            break_labels: Vec::new(),
+            labels: HashSet::new(),
+            gotos: Vec::new(),
+            counter: 0,
+        }
+    }
+
+    pub fn resolve_program(&mut self, program: &mut LinkedList<Box<dyn Statement>>) {
+        self.enter_scope();
+        // The structure behind va_list is built in, and keeps its name so the symbol table can have it from the start
+        self.tag_scopes[0].insert(VA_LIST_TAG.to_string(), TagEntry {
+            unique: VA_LIST_TAG.to_string(),
+            keyword: SyntaxKind::StructKeyword,
+        });
+        for statement in program.iter_mut() {
+            statement.resolve(self);
+        }
+        self.exit_scope();
+    }
+
+    fn unique_name(&mut self, name: &str) -> String {
+        let unique = format!("{}.{}", name, self.counter);
+        self.counter += 1;
+        unique
+    }
+
+    pub fn make_label(&mut self, purpose: &str) -> String {
+        let label = format!("{}{}_{}", GENERATED_LABEL_PREFIX, purpose, self.counter);
+        self.counter += 1;
+        label
+    }
+
+    pub fn enter_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+        self.tag_scopes.push(HashMap::new());
+    }
+
+    pub fn exit_scope(&mut self) {
+        self.scopes.pop();
+        self.tag_scopes.pop();
+    }
+
+    fn current_scope(&mut self) -> &mut HashMap<String, ScopeEntry> {
+        self.scopes
+            .last_mut()
+            .expect("Names can only be declared inside a scope")
+    }
+
+    pub fn at_file_scope(&self) -> bool {
+        self.scopes.len() == 1
+    }
+
+    pub fn declare_variable(&mut self, identifier: &LexerToken) -> String {
+        let name = identifier.data.clone();
+        if self.current_scope().contains_key(&name) {
+            panic!("Redeclaration of '{}' at {}", name, identifier.location)
+        }
+
+        let unique = self.unique_name(&name);
+        self.current_scope().insert(name, ScopeEntry {
+            unique: unique.clone(),
+            has_linkage: false,
+        });
+        unique
+    }
+
+    // Every declaration of something with linkage refers to the same thing, only a local variable in the way is a problem
+    pub fn declare_with_linkage(&mut self, identifier: &LexerToken) {
+        let name = identifier.data.clone();
+        let clashes = self.current_scope()
+            .get(&name)
+            .is_some_and(|entry| !entry.has_linkage);
+        if clashes {
+            panic!("Redeclaration of '{}' at {}", name, identifier.location)
+        }
+
+        self.current_scope().insert(name.clone(), ScopeEntry {
+            unique: name,
+            has_linkage: true,
+        });
+    }
+
+    pub fn resolve_variable(&self, identifier: &LexerToken) -> String {
+        for scope in self.scopes.iter().rev() {
+            if let Some(entry) = scope.get(&identifier.data) {
+                return entry.unique.clone();
+            }
+        }
+        panic!("Use of undeclared identifier '{}' at {}", identifier.data, identifier.location)
+    }
+
+    fn check_tag_kind(entry: &TagEntry, keyword: &SyntaxKind, tag: &str, location: &Location) {
+        if entry.keyword != *keyword {
+            let kind = match entry.keyword {
+                SyntaxKind::StructKeyword => "struct",
+                SyntaxKind::UnionKeyword => "union",
+                _ => "enum",
+            };
+            panic!("'{}' at {} was declared as a {} before", tag, location, kind)
+        }
+    }
+
+    // A definition or `struct s;` always refers to a tag of the current scope, hiding any outer one
+    pub fn declare_tag(&mut self, keyword: &LexerToken, tag: &LexerToken) -> String {
+        let scope = self.tag_scopes.last().expect("Tags can only be declared inside a scope");
+        if let Some(entry) = scope.get(&tag.data) {
+            Self::check_tag_kind(entry, &keyword.kind, &tag.data, &tag.location);
+            return entry.unique.clone();
+        }
+        self.add_tag(&tag.data, keyword.kind.clone())
+    }
+
+    // An enumeration can't be declared ahead of its definition, so finding its tag already here means it's defined twice
+    pub fn define_enum_tag(&mut self, keyword: &LexerToken, tag: &LexerToken) -> String {
+        let defined = self.tag_scopes
+            .last()
+            .expect("Tags can only be declared inside a scope")
+            .contains_key(&tag.data);
+        let unique = self.declare_tag(keyword, tag);
+        if defined {
+            panic!("Redefinition of 'enum {}' at {}", tag.data, tag.location)
+        }
+        unique
+    }
+
+    fn add_tag(&mut self, tag: &str, keyword: SyntaxKind) -> String {
+        let unique = self.unique_name(tag);
+        self.tag_scopes
+            .last_mut()
+            .expect("Tags can only be declared inside a scope")
+            .insert(tag.to_string(), TagEntry {
+                unique: unique.clone(),
+                keyword,
+            });
+        unique
+    }
+
+    // Any other mention refers to the innermost tag with that name, one nobody has seen yet gets declared right here
+    fn resolve_tag(&mut self, tag: &str, keyword: SyntaxKind, location: &Location) -> String {
+        for scope in self.tag_scopes.iter().rev() {
+            if let Some(entry) = scope.get(tag) {
+                Self::check_tag_kind(entry, &keyword, tag, location);
+                return entry.unique.clone();
+            }
+        }
+        self.add_tag(tag, keyword)
+    }
+
+    // Renames the tags of every structure the type is built from
+    pub fn resolve_type(&mut self, ty: &mut Type, location: &Location) {
+        match ty {
+            Type::Struct(tag) => *tag = self.resolve_tag(tag, SyntaxKind::StructKeyword, location),
+            Type::Union(tag) => *tag = self.resolve_tag(tag, SyntaxKind::UnionKeyword, location),
+            Type::Pointer(inner) | Type::Array(inner, _) | Type::IncompleteArray(inner) | Type::Qualified(inner, _) => self.resolve_type(inner, location),
+            Type::Function(function) => {
+                self.resolve_type(&mut function.return_type, location);
+                for param in function.params.iter_mut() {
+                    self.resolve_type(param, location);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn enter_function(&mut self) {
+        self.labels.clear();
+        self.gotos.clear();
+        self.enter_scope();
+    }
+
+    pub fn exit_function(&mut self) {
+        self.exit_scope();
+        for goto in self.gotos.iter() {
+            if !self.labels.contains(&goto.data) {
+                panic!("Use of undeclared label '{}' at {}", goto.data, goto.location)
+            }
+        }
+    }
+
+    pub fn declare_label(&mut self, label: &LexerToken) {
+        if label.data.starts_with(GENERATED_LABEL_PREFIX) {
+            panic!("Label '{}' at {} is reserved for compiler generated labels", label.data, label.location)
+        }
+        if !self.labels.insert(label.data.clone()) {
+            panic!("Duplicate label '{}' at {}", label.data, label.location)
+        }
+    }
+
+    pub fn add_goto(&mut self, label: &LexerToken) {
+        self.gotos.push(label.clone());
+    }
+
+    pub fn enter_switch(&mut self) -> String {
+        let label = self.make_label("switch");
+        self.switches.push(SwitchContext {
+            label: label.clone(),
+            cases: Vec::new(),
+        });
+        self.break_labels.push(label.clone());
+        label
+    }
+
+    pub fn exit_switch(&mut self) -> Vec<SwitchCase> {
+        self.break_labels.pop();
+        self.switches.pop().expect("exit_switch without enter_switch").cases
+    }
+
+    // Hands back the label the case will live at, value is None for the default case. Case values can only be
+    // compared with each other once the type checker has converted them to the type of the switch
+    pub fn add_case(&mut self, value: Option<i64>, token: &LexerToken) -> String {
+        let switch = match self.switches.last_mut() {
+            Some(switch) => switch,
+            None => panic!("'{}' label not within a switch statement at {}", token.data, token.location),
+        };
+
+        if value.is_none() && switch.cases.iter().any(|case| case.value.is_none()) {
+            panic!("Multiple default labels in one switch at {}", token.location)
+        }
+
+        let label = match value {
+            Some(value) => format!("{}_case_{}", switch.label, value).replace('-', "m"),
+            None => format!("{}_default", switch.label),
+        };
+        switch.cases.push(SwitchCase {
+            value,
+            label: label.clone(),
+            location: token.location.clone(),
+        });
+        label
+    }
+
+    pub fn break_label(&self, token: &LexerToken) -> String {
+        match self.break_labels.last() {
+            Some(label) => label.clone(),
+            None => panic!("Break statement not within a loop or switch at {}", token.location),
+        }
+    }
+}
+
+impl Resolvable for FunctionStatement {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        if !resolver.at_file_scope() {
+            if self.func_body.is_some() {
+                panic!("Function '{}' can't be defined inside another function at {}", self.identifier.data, self.identifier.location)
+            }
+            if self.storage_class.as_ref().is_some_and(|token| token.kind == SyntaxKind::StaticKeyword) {
+                panic!("Function '{}' declared inside a block at {} can't be static", self.identifier.data, self.identifier.location)
+            }
+        }
+        resolver.declare_with_linkage(&self.identifier);
+        resolver.resolve_type(&mut self.func_type, &self.identifier.location);
+
+        let body = match self.func_body.as_mut() {
+            Some(body) => body,
+            None => {
+                // Parameter names in a declaration don't mean anything, they just can't clash with each other
+                resolver.enter_scope();
+                resolve_parameters(&mut self.parameters, &self.open_paren, resolver);
+                resolver.exit_scope();
+                return;
+            }
+        };
+
+        resolver.enter_function();
+        // The parser makes sure every parameter of a definition has a name
+        resolve_parameters(&mut self.parameters, &self.open_paren, resolver);
+        // The body shares the scope of the parameters
+        for statement in body.statements.iter_mut() {
+            statement.resolve(resolver);
+        }
+        resolver.exit_function();
+    }
+}
+
+fn resolve_parameters(parameters: &mut [Parameter], open_paren: &LexerToken, resolver: &mut Resolver) {
+    for parameter in parameters.iter_mut() {
+        let location = parameter.identifier.as_ref().map_or(&open_paren.location, |identifier| &identifier.location);
+        resolver.resolve_type(&mut parameter.param_type, location);
+        if let Some(identifier) = parameter.identifier.as_mut() {
+            identifier.data = resolver.declare_variable(identifier);
+        }
+    }
+}
+
+impl Resolvable for Body {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        resolver.enter_scope();
+        for statement in self.statements.iter_mut() {
+            statement.resolve(resolver);
+        }
+        resolver.exit_scope();
+    }
+}
+
+impl Resolvable for VariableDeclaration {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        // File scope variables and extern ones keep their name so they can be linked against
+        let is_extern = self.storage_class.as_ref().is_some_and(|token| token.kind == SyntaxKind::ExternKeyword);
+        resolver.resolve_type(&mut self.var_type, &self.identifier.location);
+        if resolver.at_file_scope() || is_extern {
+            resolver.declare_with_linkage(&self.identifier);
+        } else {
+            self.identifier.data = resolver.declare_variable(&self.identifier);
+        }
+        // The variable is already in scope inside its own initializer
+        if let Some(initializer) = self.initializer.as_mut() {
+            initializer.resolve(resolver);
+        }
+    }
+}
+
+impl Resolvable for StructDeclaration {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        // Declared before the members so they can point back at the structure they're in
+        self.tag.data = resolver.declare_tag(&self.keyword, &self.tag);
+        for member in self.members.iter_mut().flatten() {
+            resolver.resolve_type(&mut member.member_type, &member.identifier.location);
+        }
+    }
+}
+
+// The enumerators were already folded into constants by the parser,
+// they're declared here so that nothing else in the same scope can take their names
+impl Resolvable for EnumDeclaration {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        if let Some(tag) = self.tag.as_mut() {
+            tag.data = resolver.define_enum_tag(&self.keyword, tag);
+        }
+        for enumerator in self.enumerators.iter_mut() {
+            enumerator.identifier.data = resolver.declare_variable(&enumerator.identifier);
+        }
+    }
+}
+
+impl Initializer {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        match self {
+            Initializer::Single(expression) => expression.resolve(resolver),
+            Initializer::Compound { elements, .. } => {
+                for element in elements.iter_mut() {
+                    element.resolve(resolver);
+                }
+            }
+            // Designators name members and indices, which the type checker looks up
+            Initializer::Designated { initializer, .. } => initializer.resolve(resolver),
+        }
+    }
+}
+
+impl Resolvable for ExpressionStatement {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        self.expression.resolve(resolver);
+    }
+}
+
+impl Resolvable for NullStatement {
+    fn resolve(&mut self, _resolver: &mut Resolver) {}
+}
+
+impl Resolvable for ReturnExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        if let Some(value) = self.value.as_mut() {
+            value.resolve(resolver);
+        }
+    }
+}
+
+impl Resolvable for SwitchStatement {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        self.condition.resolve(resolver);
+        self.label = resolver.enter_switch();
+        self.body.resolve(resolver);
+        self.cases = resolver.exit_switch();
+    }
+}
+
+impl Resolvable for CaseStatement {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        self.value.resolve(resolver);
+        let value = match self.value.constant_value() {
+            Some(constant) if constant.ty.is_integer() => constant.value,
+            _ => panic!("Case label does not reduce to an integer constant at {}", self.case_kw.location),
+        };
+        self.label = resolver.add_case(Some(value), &self.case_kw);
+        self.statement.resolve(resolver);
+    }
+}
+
+impl Resolvable for DefaultStatement {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        self.label = resolver.add_case(None, &self.default_kw);
+        self.statement.resolve(resolver);
+    }
+}
+
+impl Resolvable for LabeledStatement {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        resolver.declare_label(&self.label);
+        self.statement.resolve(resolver);
+    }
+}
+
+impl Resolvable for GotoStatement {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        resolver.add_goto(&self.label);
+    }
+}
+
+impl Resolvable for BreakStatement {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        self.label = resolver.break_label(&self.break_kw);
+    }
+}
+
+impl Resolvable for ConstantExpr {
+    fn resolve(&mut self, _resolver: &mut Resolver) {}
+}
+
+impl Resolvable for VariableExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        self.identifier.data = resolver.resolve_variable(&self.identifier);
+    }
+}
+
+impl Resolvable for UnaryExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        self.operand.resolve(resolver);
+    }
+}
+
+impl Resolvable for BinaryExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        self.left.resolve(resolver);
+        self.right.resolve(resolver);
+    }
+}
+
+impl Resolvable for AssignmentExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        if !self.left.is_lvalue() {
+            panic!("Invalid lvalue in assignment at {}", self.equal.location)
+        }
+        self.left.resolve(resolver);
+        self.right.resolve(resolver);
+    }
+}
+
+impl Resolvable for CompoundAssignmentExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        if !self.left.is_lvalue() {
+            panic!("Invalid lvalue in compound assignment at {}", self.operator.location)
+        }
+        self.left.resolve(resolver);
+        self.right.resolve(resolver);
+    }
+}
+
+impl Resolvable for IncrementExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        if !self.operand.is_lvalue() {
+            panic!("Invalid lvalue in '{}' at {}", self.operator.data, self.operator.location)
+        }
+        self.operand.resolve(resolver);
+    }
+}
+
+impl Resolvable for FunctionCallExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        self.function.resolve(resolver);
+        for argument in self.arguments.iter_mut() {
+            argument.resolve(resolver);
+        }
+    }
+}
+
+impl Resolvable for AddressOfExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        if !self.operand.is_lvalue() {
+            panic!("Can't take the address of a non-lvalue at {}", self.ampersand.location)
+        }
+        self.operand.resolve(resolver);
+    }
+}
+
+impl Resolvable for DereferenceExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        self.operand.resolve(resolver);
+    }
+}
+
+impl Resolvable for SubscriptExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        self.left.resolve(resolver);
+        self.index.resolve(resolver);
+    }
+}
+
+// Members are looked up by the type checker once it knows which structure they belong to
+impl Resolvable for MemberExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        self.structure.resolve(resolver);
+    }
+}
+
+impl Resolvable for SizeofExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        self.operand.resolve(resolver);
+    }
+}
+
+impl Resolvable for SizeofTypeExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        resolver.resolve_type(&mut self.target, &self.sizeof_kw.location);
+    }
+}
+
+impl Resolvable for CastExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        let location = self.location();
+        resolver.resolve_type(&mut self.target, &location);
+        self.expression.resolve(resolver);
+    }
+}
+
+impl Resolvable for VaBuiltinExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        for argument in self.arguments.iter_mut() {
+            argument.resolve(resolver);
+        }
+        if let Some(target) = self.target.as_mut() {
+            resolver.resolve_type(target, &self.identifier.location);
+        }
+    }
+}
+
+impl Resolvable for StringLiteralExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        self.name = resolver.unique_name("string");
+    }
+}
+
+impl Resolvable for CompoundLiteralExpr {
+    fn resolve(&mut self, resolver: &mut Resolver) {
+        resolver.resolve_type(&mut self.target, &self.open_paren.location);
+        self.initializer.resolve(resolver);
+        self.name = resolver.unique_name("literal");
+    }
+}
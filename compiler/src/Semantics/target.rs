@@ -0,0 +1,125 @@
+use crate::types::Type;
+
+// The machine we're compiling for. The sizes the checker gives types and the macros the preprocessor starts out
+// with both come from here, so `sizeof(long)` and `__SIZEOF_LONG__` can't disagree
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    // Spelled the way the macros spell them, `__x86_64__` and `__linux__`
+    pub arch: &'static str,
+    pub os: &'static str,
+    pub short_size: usize,
+    pub int_size: usize,
+    pub long_size: usize,
+    pub long_long_size: usize,
+    pub pointer_size: usize,
+    pub float_size: usize,
+    pub double_size: usize,
+    pub char_is_signed: bool,
+}
+
+impl Default for Target {
+    fn default() -> Self {
+        Target::X86_64_LINUX
+    }
+}
+
+impl Target {
+    // The System V ABI, the only one code generation knows
+    pub const X86_64_LINUX: Target = Target {
+        arch: "x86_64",
+        os: "linux",
+        short_size: 2,
+        int_size: 4,
+        long_size: 8,
+        long_long_size: 8,
+        pointer_size: 8,
+        float_size: 4,
+        double_size: 8,
+        char_is_signed: true,
+    };
+
+    // Constants are folded and code is generated with the sizes of x86_64 Linux, so the preprocessor and the type
+    // checker turn down any other target rather than describe a machine the code isn't for
+    pub fn check_supported(&self) {
+        if *self != Target::X86_64_LINUX {
+            panic!("Can't compile for {}-{} with these sizes, only the x86_64 Linux target is supported", self.arch, self.os)
+        }
+    }
+
+    // Size in bytes, only meaningful for complete object types other than structures and unions
+    pub fn size_of(&self, ty: &Type) -> usize {
+        match ty.unqualified() {
+            Type::Bool | Type::Char | Type::SChar | Type::UChar => 1,
+            Type::Short | Type::UShort => self.short_size,
+            Type::Int | Type::UInt => self.int_size,
+            Type::Long | Type::ULong => self.long_size,
+            Type::LongLong | Type::ULongLong => self.long_long_size,
+            Type::Float => self.float_size,
+            Type::Double => self.double_size,
+            Type::Pointer(_) => self.pointer_size,
+            Type::Array(element, count) => self.size_of(element) * count,
+            // Structures and unions only have a size once the symbol table knows their layout
+            Type::Void | Type::Function(_) | Type::Struct(_) | Type::Union(_) | Type::IncompleteArray(_) | Type::Qualified(..) => {
+                panic!("{} doesn't have a size", ty)
+            }
+        }
+    }
+
+    // Every scalar is aligned to its own size
+    pub fn alignment_of(&self, ty: &Type) -> usize {
+        match ty.unqualified() {
+            Type::Array(element, _) => self.alignment_of(element),
+            _ => self.size_of(ty),
+        }
+    }
+
+    // Written the way Preprocessor::define takes them, `NAME body`
+    pub fn predefined_macros(&self) -> Vec<String> {
+        let mut macros = Vec::new();
+        let arch = match self.arch {
+            "x86_64" => vec!["__x86_64__", "__x86_64", "__amd64__", "__amd64"],
+            arch => vec![arch],
+        };
+        let os = match self.os {
+            "linux" => vec!["__linux__", "__linux", "__gnu_linux__", "__unix__", "__unix", "__ELF__"],
+            os => vec![os],
+        };
+        macros.extend(arch.into_iter().chain(os).map(|name| format!("{} 1", name)));
+        if self.long_size == 8 && self.pointer_size == 8 {
+            macros.push("__LP64__ 1".to_string());
+            macros.push("_LP64 1".to_string());
+        }
+        if !self.char_is_signed {
+            macros.push("__CHAR_UNSIGNED__ 1".to_string());
+        }
+
+        macros.push("__CHAR_BIT__ 8".to_string());
+        let sizes = [
+            ("SHORT", self.short_size),
+            ("INT", self.int_size),
+            ("LONG", self.long_size),
+            ("LONG_LONG", self.long_long_size),
+            ("POINTER", self.pointer_size),
+            ("FLOAT", self.float_size),
+            ("DOUBLE", self.double_size),
+        ];
+        for (name, size) in sizes {
+            macros.push(format!("__SIZEOF_{}__ {}", name, size));
+        }
+        let limits = [
+            ("SCHAR", 1, ""),
+            ("SHRT", self.short_size, ""),
+            ("INT", self.int_size, ""),
+            ("LONG", self.long_size, "L"),
+            ("LONG_LONG", self.long_long_size, "LL"),
+        ];
+        for (name, size, suffix) in limits {
+            macros.push(format!("__{}_MAX__ {}{}", name, i64::MAX >> (64 - 8 * size), suffix));
+        }
+
+        macros.push("__ORDER_LITTLE_ENDIAN__ 1234".to_string());
+        macros.push("__ORDER_BIG_ENDIAN__ 4321".to_string());
+        macros.push("__BYTE_ORDER__ __ORDER_LITTLE_ENDIAN__".to_string());
+        macros
+    }
+}
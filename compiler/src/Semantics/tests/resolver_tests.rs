@@ -0,0 +1,230 @@
+#[cfg(test)]
+mod resolver_tests {
+    use crate::lexer::Lexer;
+    use crate::parsing::Parser;
+    use crate::resolver::Resolver;
+
+    fn resolve(str: &str) {
+        let mut lexer = Lexer::new(str);
+        lexer.lex();
+        let mut parser = Parser::new(lexer.tokens);
+        parser.start_parsing();
+        Resolver::new().resolve_program(&mut parser.parsed_tokens);
+    }
+
+    #[test]
+    fn resolving_switch() {
+        resolve(r#"
+        int main(void) {
+            int x = 1;
+            switch (x) {
+                case 1: {
+                    int x = 2;
+                    switch (x) { case 1: break; }
+                }
+                case -1:
+                default:
+                    break;
+            }
+            return x;
+        }
+        "#);
+    }
+
+    #[test]
+    #[should_panic(expected = "Multiple default labels in one switch")]
+    fn duplicate_default() {
+        resolve("int main(void) { switch (1) { default: return 0; default: return 1; } }");
+    }
+
+    #[test]
+    #[should_panic(expected = "'case' label not within a switch statement at 1, 18")]
+    fn case_outside_switch() {
+        resolve("int main(void) { case 1: return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "'default' label not within a switch statement")]
+    fn default_outside_switch() {
+        resolve("int main(void) { default: return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Case label does not reduce to an integer constant")]
+    fn non_constant_case() {
+        resolve("int main(void) { int x = 1; switch (x) { case x: return 0; } }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Break statement not within a loop or switch")]
+    fn break_outside_switch() {
+        resolve("int main(void) { break; }");
+    }
+
+    #[test]
+    fn resolving_labels() {
+        // Labels live in their own namespace and are visible across the whole function
+        resolve(r#"
+        int main(void) {
+            int done = 0;
+            goto done;
+            {
+                done: done = 1;
+            }
+            return done;
+        }
+        int other(void) {
+            done: return 0;
+        }
+        "#);
+    }
+
+    #[test]
+    #[should_panic(expected = "Use of undeclared label 'missing' at 1, 23")]
+    fn undeclared_label() {
+        resolve("int main(void) { goto missing; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Use of undeclared label 'end'")]
+    fn label_from_another_function() {
+        resolve("int foo(void) { end: return 0; } int main(void) { goto end; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate label 'top' at 1, 27")]
+    fn duplicate_label() {
+        resolve("int main(void) { top: ; { top: ; } return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Label '__switch_0_break' at 1, 18 is reserved for compiler generated labels")]
+    fn label_colliding_with_generated_label() {
+        resolve("int main(void) { __switch_0_break: switch (1) { default: break; } return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid lvalue in compound assignment at 1, 31")]
+    fn compound_assignment_to_constant() {
+        resolve("int main(void) { int x = 1; 2 += x; return x; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid lvalue in '++' at 1, 29")]
+    fn increment_of_non_lvalue() {
+        resolve("int main(void) { int x = 1; ++(x + 1); return x; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid lvalue in '--' at 1, 32")]
+    fn postfix_decrement_of_non_lvalue() {
+        resolve("int main(void) { int x = 1; x++--; return x; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Use of undeclared identifier 'y'")]
+    fn undeclared_variable() {
+        resolve("int main(void) { int x = y; return x; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Redeclaration of 'x'")]
+    fn redeclared_variable() {
+        resolve("int main(void) { int x = 1; int x = 2; return x; }");
+    }
+
+    #[test]
+    fn resolving_file_scope_variables() {
+        resolve(r#"
+        int x;
+        int main(void) {
+            int x = 1;
+            {
+                extern int x;
+                x = 2;
+            }
+            return x;
+        }
+        int x = 3;
+        "#);
+    }
+
+    #[test]
+    #[should_panic(expected = "Redeclaration of 'x' at 1, 36")]
+    fn extern_after_local() {
+        resolve("int main(void) { int x; extern int x; return x; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Function 'f' declared inside a block at 1, 29 can't be static")]
+    fn static_block_scope_function() {
+        resolve("int main(void) { static int f(void); return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't take the address of a non-lvalue at 1, 37")]
+    fn address_of_rvalue() {
+        resolve("int main(void) { int x = 1; return *&(x + 1); }");
+    }
+
+    #[test]
+    fn resolving_structure_tags() {
+        // A tag used before it's defined is the same one the definition completes
+        resolve(r#"
+        struct list *first;
+        struct list { struct list *next; int value; };
+        int main(void) {
+            struct list;
+            struct list *inner;
+            {
+                union list { int a; } shadow;
+            }
+            return 0;
+        }
+        "#);
+    }
+
+    #[test]
+    #[should_panic(expected = "'s' at 1, 17 was declared as a struct before")]
+    fn tag_with_wrong_kind() {
+        resolve("struct s; union s;");
+    }
+
+    #[test]
+    fn resolving_enums() {
+        // An inner scope can reuse the names of enumerators and tags from outside
+        resolve(r#"
+        enum color { RED, GREEN };
+        int main(void) {
+            int RED = GREEN;
+            struct color { int a; } shade;
+            enum { GREEN = 3 };
+            return RED + GREEN;
+        }
+        "#);
+    }
+
+    #[test]
+    #[should_panic(expected = "Redeclaration of 'A' at 1, 19")]
+    fn enumerator_redeclared_as_variable() {
+        resolve("enum e { A }; int A;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Redeclaration of 'A' at 1, 24")]
+    fn enumerator_in_two_enums() {
+        resolve("enum e { A }; enum f { A };");
+    }
+
+    #[test]
+    #[should_panic(expected = "Redefinition of 'enum e' at 1, 20")]
+    fn enum_defined_twice() {
+        resolve("enum e { A }; enum e { B };");
+    }
+
+    #[test]
+    #[should_panic(expected = "'s' at 1, 16 was declared as a struct before")]
+    fn enum_with_struct_tag() {
+        resolve("struct s; enum s { A };");
+    }
+}
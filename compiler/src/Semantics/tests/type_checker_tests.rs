@@ -0,0 +1,972 @@
+#[cfg(test)]
+mod type_checker_tests {
+    use crate::driver::compile;
+    use crate::lexer::Lexer;
+    use crate::parsing::Parser;
+    use crate::resolver::Resolver;
+    use crate::target::Target;
+    use crate::type_checker::{InitialValue, StaticInit, SymbolAttrs, SymbolTable, TypeChecker};
+    use crate::types::{Constant, Qualifiers, Type};
+
+    fn check(str: &str) -> SymbolTable {
+        check_for_target(str, Target::default())
+    }
+
+    fn check_for_target(str: &str, target: Target) -> SymbolTable {
+        let mut lexer = Lexer::new(str);
+        lexer.lex();
+        let mut parser = Parser::new(lexer.tokens);
+        parser.start_parsing();
+        Resolver::new().resolve_program(&mut parser.parsed_tokens);
+        let mut checker = TypeChecker::for_target(target);
+        checker.check_program(&mut parser.parsed_tokens);
+        checker.symbols
+    }
+
+    // A static initialized with a single value
+    fn initial(value: i64, ty: Type) -> InitialValue {
+        InitialValue::Initial(vec![StaticInit::Scalar(Constant::new(value, ty))])
+    }
+
+    #[test]
+    fn common_type() {
+        assert_eq!(Type::common_type(&Type::Char, &Type::Char), Type::Int);
+        assert_eq!(Type::common_type(&Type::Int, &Type::UInt), Type::UInt);
+        assert_eq!(Type::common_type(&Type::Long, &Type::UInt), Type::Long);
+        assert_eq!(Type::common_type(&Type::Long, &Type::ULong), Type::ULong);
+        assert_eq!(Type::common_type(&Type::Double, &Type::ULong), Type::Double);
+        assert_eq!(Type::common_type(&Type::Short, &Type::UShort), Type::Int);
+        assert_eq!(Type::common_type(&Type::Bool, &Type::Bool), Type::Int);
+        assert_eq!(Type::common_type(&Type::Long, &Type::LongLong), Type::LongLong);
+        assert_eq!(Type::common_type(&Type::LongLong, &Type::ULong), Type::ULongLong);
+        assert_eq!(Type::common_type(&Type::Float, &Type::ULongLong), Type::Float);
+        assert_eq!(Type::common_type(&Type::Float, &Type::Double), Type::Double);
+    }
+
+    #[test]
+    fn constant_conversions() {
+        assert_eq!(Constant::int(256).convert(&Type::Bool), Constant::new(1, Type::Bool));
+        assert_eq!(Constant::double(-0.0).convert(&Type::Bool), Constant::new(0, Type::Bool));
+        assert_eq!(Constant::int(70000).convert(&Type::Short).value, 4464);
+        assert_eq!(Constant::int(-1).convert(&Type::UShort).value, 65535);
+        assert_eq!(Constant::double(0.1).convert(&Type::Float), Constant::float(0.1));
+        assert_eq!(Constant::new(16777217, Type::Long).convert(&Type::Float).as_double(), 16777216.0);
+        assert_eq!(Constant::float(2.5).convert(&Type::Int).value, 2);
+    }
+
+    #[test]
+    fn default_argument_promotions() {
+        assert_eq!(Type::Float.promote_argument(), Type::Double);
+        assert_eq!(Type::UShort.promote_argument(), Type::Int);
+        assert_eq!(Type::Bool.promote(), Type::Int);
+        assert_eq!(Type::Float.promote(), Type::Float);
+    }
+
+    #[test]
+    fn function_symbols() {
+        let symbols = check("int f(int a, char b); int main(void) { return f(1, 2); } int f(int a, char b) { return a + b; }");
+        let f = symbols.get("f").unwrap();
+        assert_eq!(f.ty, Type::function(Type::Int, vec![Type::Int, Type::Char]));
+        assert!(symbols.is_defined("f"));
+
+        let symbols = check("int g(void); int main(void) { return g(); }");
+        assert!(!symbols.is_defined("g"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Function 'f' called with 1 arguments but takes 2 at 1, 46")]
+    fn wrong_argument_count() {
+        check("int f(int a, int b); int main(void) { return f(1); }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Called object of type 'int' at 1, 36 is not a function")]
+    fn calling_a_variable() {
+        check("int main(void) { int x = 3; return x(); }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Arithmetic on a pointer to incomplete type 'int()' at 1, 30")]
+    fn arithmetic_on_function() {
+        check("int main(void) { return main + 1; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Conflicting types for 'f'")]
+    fn conflicting_declarations() {
+        check("int f(int a); char f(int a); int main(void) { return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Redefinition of 'f'")]
+    fn function_defined_twice() {
+        check("int f(void) { return 1; } int f(void) { return 2; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Redeclaration of 'a'")]
+    fn duplicate_parameters() {
+        check("int f(int a, int a);");
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be defined inside another function")]
+    fn nested_function_definition() {
+        check("int main(void) { int f(void) { return 1; } return f(); }");
+    }
+
+    #[test]
+    fn file_scope_linkage() {
+        let symbols = check(r#"
+        static int hidden;
+        extern int hidden;
+        int tentative;
+        int tentative;
+        int set = 3;
+        extern int set;
+        extern int elsewhere;
+        char wrapped = 300;
+        static int helper(void);
+        int helper(void) { return 1; }
+        "#);
+        let attrs = |name: &str| symbols.get(name).unwrap().attrs.clone();
+        assert_eq!(attrs("hidden"), SymbolAttrs::Static { init: InitialValue::Tentative, global: false });
+        assert_eq!(attrs("tentative"), SymbolAttrs::Static { init: InitialValue::Tentative, global: true });
+        assert_eq!(attrs("set"), SymbolAttrs::Static { init: initial(3, Type::Int), global: true });
+        assert_eq!(attrs("elsewhere"), SymbolAttrs::Static { init: InitialValue::NoInitializer, global: true });
+        assert_eq!(attrs("wrapped"), SymbolAttrs::Static { init: initial(44, Type::Char), global: true });
+        assert_eq!(attrs("helper"), SymbolAttrs::Function { defined: true, global: false });
+    }
+
+    #[test]
+    #[should_panic(expected = "Conflicting linkage for 'x' at 1, 19")]
+    fn conflicting_variable_linkage() {
+        check("static int x; int x;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Static declaration of 'f' at 1, 25 follows a non-static one")]
+    fn static_function_after_global() {
+        check("int f(void); static int f(void) { return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Redefinition of 'x'")]
+    fn variable_defined_twice() {
+        check("int x = 1; int x = 2;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Initializer for 'x' at 1, 16 is not a constant")]
+    fn non_constant_static_initializer() {
+        check("int y = 1; int x = y;");
+    }
+
+    #[test]
+    #[should_panic(expected = "'x' at 1, 12 was declared as a variable before")]
+    fn variable_redeclared_as_function() {
+        check("int x; int x(void);");
+    }
+
+    #[test]
+    #[should_panic(expected = "can't have an initializer")]
+    fn local_extern_with_initializer() {
+        check("int main(void) { extern int x = 3; return x; }");
+    }
+
+    #[test]
+    fn static_local_symbols() {
+        let symbols = check("int main(void) { static int zeroed; static char set = 257; return zeroed + set; }");
+        let attrs: Vec<SymbolAttrs> = ["zeroed.0", "set.1"]
+            .iter()
+            .map(|name| symbols.get(name).unwrap().attrs.clone())
+            .collect();
+        assert_eq!(attrs[0], SymbolAttrs::Static { init: InitialValue::Initial(vec![StaticInit::Zero(4)]), global: false });
+        assert_eq!(attrs[1], SymbolAttrs::Static { init: initial(1, Type::Char), global: false });
+    }
+
+    #[test]
+    #[should_panic(expected = "Initializer for 'y' at 1, 40 is not a constant")]
+    fn non_constant_static_local() {
+        check("int main(void) { int x = 1; static int y = x + 1; return y; }");
+    }
+
+    #[test]
+    fn integer_specifier_types() {
+        let symbols = check("long int l; unsigned u = -1; unsigned long f(signed char c, long unsigned x);");
+        assert_eq!(symbols.type_of("l"), &Type::Long);
+        assert_eq!(symbols.type_of("u"), &Type::UInt);
+        assert_eq!(symbols.get("u").unwrap().attrs, SymbolAttrs::Static { init: initial(4294967295, Type::UInt), global: true });
+        assert_eq!(symbols.type_of("f").to_string(), "unsigned long(signed char, unsigned long)");
+    }
+
+    #[test]
+    fn double_static_initializers() {
+        let symbols = check("double d = 3; long l = 2.9; unsigned u = 1e9 * 4; double neg = -0.0;");
+        let init = |name: &str| match &symbols.get(name).unwrap().attrs {
+            SymbolAttrs::Static { init: InitialValue::Initial(inits), .. } => match inits.as_slice() {
+                [StaticInit::Scalar(constant)] => constant.value,
+                other => panic!("{:?}", other),
+            },
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(init("d"), 3.0f64.to_bits() as i64);
+        assert_eq!(init("l"), 2);
+        assert_eq!(init("u"), 4000000000);
+        assert_eq!(init("neg"), (-0.0f64).to_bits() as i64);
+    }
+
+    #[test]
+    #[should_panic(expected = "Operand of '%' at 1, 40 has type 'double' but must be an integer")]
+    fn remainder_of_double() {
+        check("int main(void) { double d = 1.0; d = d % 2; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Operand of 'switch' at 1, 18 has type 'double' but must be an integer")]
+    fn switch_on_double() {
+        check("int main(void) { switch (1.5) { default: return 0; } }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate case value 3 at 1, 49")]
+    fn duplicate_case_value() {
+        // Constant expressions are folded before they're compared
+        check("int main(void) { switch (1) { case 3: return 0; case 1 + 2: return 1; } }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate case value 4294967295 at 1, 56")]
+    fn duplicate_case_value_after_conversion() {
+        check("int main(void) { unsigned u = 0; switch (u) { case -1: case 4294967295: return 1; } return 0; }");
+    }
+
+    #[test]
+    fn cases_that_differ_after_conversion() {
+        check("int main(void) { long l = 0; switch (l) { case -1: case 4294967295: return 1; } return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Case label does not reduce to an integer constant at 1, 31")]
+    fn double_case_label() {
+        check("int main(void) { switch (1) { case 1.0: return 0; } }");
+    }
+
+    #[test]
+    fn pointer_types() {
+        let symbols = check("int **pp; int *null = 0; double *f(long *a); long g(int *a, int *b) { return a - b; }");
+        assert_eq!(symbols.type_of("pp"), &Type::Pointer(Box::new(Type::Pointer(Box::new(Type::Int)))));
+        assert_eq!(symbols.get("null").unwrap().attrs, SymbolAttrs::Static { init: initial(0, Type::Pointer(Box::new(Type::Int))), global: true });
+        assert_eq!(symbols.type_of("f").to_string(), "double *(long *)");
+    }
+
+    #[test]
+    #[should_panic(expected = "Comparison between distinct pointer types 'int *' and 'long *' at 1, 52")]
+    fn distinct_pointer_comparison() {
+        check("int main(void) { int *a = 0; long *b = 0; return a == b; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't convert 'int' to 'int *' at 1, 38")]
+    fn integer_to_pointer_conversion() {
+        check("int main(void) { int x = 1; int *p = x; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Initializer for 'p' at 1, 6 is not a null pointer constant")]
+    fn non_null_static_pointer() {
+        check("int *p = 1;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid operands of type 'int *' and 'int *' to '+' at 1, 49")]
+    fn adding_pointers() {
+        check("int main(void) { int x; int *p = &x; return *(p + p); }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't dereference a value of type 'int' at 1, 36")]
+    fn dereferencing_integer() {
+        check("int main(void) { int x = 1; return *x; }");
+    }
+
+    #[test]
+    fn array_types_and_static_initializers() {
+        let symbols = check("long grid[2][3] = {{1, 2}, {3}}; int f(int values[4]); double empty[2];");
+        assert_eq!(symbols.type_of("grid").to_string(), "long[2][3]");
+        assert_eq!(symbols.type_of("f").to_string(), "int(int *)");
+        let long = |value: i64| StaticInit::Scalar(Constant::new(value, Type::Long));
+        assert_eq!(symbols.get("grid").unwrap().attrs, SymbolAttrs::Static {
+            init: InitialValue::Initial(vec![long(1), long(2), StaticInit::Zero(8), long(3), StaticInit::Zero(16)]),
+            global: true,
+        });
+        assert_eq!(symbols.get("empty").unwrap().attrs, SymbolAttrs::Static { init: InitialValue::Tentative, global: true });
+    }
+
+    #[test]
+    fn initializers_without_inner_braces() {
+        let symbols = check("struct pt { int x; int y; }; struct line { struct pt a; struct pt b; int width; };
+            int m[2][2] = {1, 2, 3, 4}; struct line l = {1, 2, 3, 4, 5}; struct pt ps[2] = {{1}, 2, 3}; long a[] = {1, [3] = 2};");
+        let int = |value: i64| StaticInit::Scalar(Constant::int(value));
+        let initial = |name: &str| match &symbols.get(name).unwrap().attrs {
+            SymbolAttrs::Static { init: InitialValue::Initial(inits), .. } => inits.clone(),
+            attrs => panic!("{:?}", attrs),
+        };
+        assert_eq!(initial("m"), vec![int(1), int(2), int(3), int(4)]);
+        assert_eq!(initial("l"), vec![int(1), int(2), int(3), int(4), int(5)]);
+        assert_eq!(initial("ps"), vec![int(1), StaticInit::Zero(4), int(2), int(3)]);
+        assert_eq!(symbols.type_of("a").to_string(), "long[4]");
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many elements in initializer for 'int[2][2]' at 1, 15")]
+    fn too_many_elements_without_inner_braces() {
+        check("int m[2][2] = {1, 2, 3, 4, 5};");
+    }
+
+    #[test]
+    fn arrays_without_size() {
+        let symbols = check("extern int a[]; int a[3]; int t[]; extern long e[]; long (*p)[] = 0;
+            int main(void) { extern long e[]; long *first = e; return sizeof (int[]){1, 2}; }");
+        assert_eq!(symbols.type_of("a").to_string(), "int[3]");
+        assert_eq!(symbols.type_of("t").to_string(), "int[1]");
+        assert_eq!(symbols.type_of("e").to_string(), "long[]");
+        assert_eq!(symbols.type_of("p").to_string(), "long (*)[]");
+        assert_eq!(symbols.get("t").unwrap().attrs, SymbolAttrs::Static { init: InitialValue::Tentative, global: true });
+    }
+
+    #[test]
+    #[should_panic(expected = "Variable 'a' at 1, 22 has incomplete type 'int[]'")]
+    fn local_array_without_size() {
+        check("int main(void) { int a[]; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Variable 's' at 1, 12 has incomplete type 'int[]'")]
+    fn static_array_without_size() {
+        check("static int s[];");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't take the size of incomplete type 'int[]' at 1, 46")]
+    fn size_of_array_without_size() {
+        check("extern int a[]; int main(void) { return (int)sizeof a; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Conflicting types for 'a' at 1, 22, it was declared as 'int[2]' before")]
+    fn arrays_of_different_sizes() {
+        check("int a[2]; extern int a[3];");
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many elements in initializer for 'int[2]' at 1, 24")]
+    fn too_many_array_elements() {
+        check("int grid[3][2] = {{1}, {1, 2, 3}};");
+    }
+
+    #[test]
+    #[should_panic(expected = "Braced initializer for 'int' at 1, 30, which is not an array")]
+    fn braces_around_scalar() {
+        check("int main(void) { int a[2] = {{1}, 2}; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Array of type 'int[2]' at 1, 29 has to be initialized with a braced list")]
+    fn array_without_braces() {
+        check("int main(void) { int a[2] = 0; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't assign to array of type 'int[2]' at 1, 40")]
+    fn assigning_to_array() {
+        check("int main(void) { int a[2]; int b[2]; a = b; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid operands of type 'int' and 'int' to '[' at 1, 37")]
+    fn subscripting_integer() {
+        check("int main(void) { int a = 1; return a[0]; }");
+    }
+
+    #[test]
+    fn declarator_types() {
+        let symbols = check(r#"
+        int *(*fp)(int, long);
+        double (*rows)[3];
+        char *names[4];
+        int (*pick(int which))(long);
+        int count(char *argv[], long grid[][2], int callback(int));
+        "#);
+        assert_eq!(symbols.type_of("fp").to_string(), "int *(*)(int, long)");
+        assert_eq!(symbols.type_of("rows").to_string(), "double (*)[3]");
+        assert_eq!(symbols.type_of("names").to_string(), "char *[4]");
+        assert_eq!(symbols.type_of("pick").to_string(), "int (*(int))(long)");
+        assert_eq!(symbols.type_of("count").to_string(), "int(char **, long (*)[2], int (*)(int))");
+    }
+
+    #[test]
+    fn structure_layout() {
+        let symbols = check(r#"
+        struct inner { char c; double d; };
+        struct outer { char a; struct inner i; int b[3]; char z; };
+        union u { char c[5]; int i; };
+        "#);
+        let offsets = |tag: &str| -> Vec<usize> {
+            symbols.get_struct(tag).unwrap().members.iter().map(|member| member.offset).collect()
+        };
+        assert_eq!(offsets("inner.0"), vec![0, 8]);
+        assert_eq!(offsets("outer.1"), vec![0, 8, 24, 36]);
+        assert_eq!(offsets("u.2"), vec![0, 0]);
+        assert_eq!(symbols.size_of(&Type::Struct("inner.0".to_string())), 16);
+        assert_eq!(symbols.size_of(&Type::Struct("outer.1".to_string())), 40);
+        assert_eq!(symbols.alignment_of(&Type::Struct("outer.1".to_string())), 8);
+        assert_eq!(symbols.size_of(&Type::Union("u.2".to_string())), 8);
+    }
+
+    #[test]
+    fn structure_tags_in_inner_scopes() {
+        check(r#"
+        struct s { int a; };
+        int main(void) {
+            struct s outer;
+            outer.a = 1;
+            {
+                struct s { long b; } inner;
+                inner.b = 2;
+            }
+            return outer.a;
+        }
+        "#);
+    }
+
+    #[test]
+    fn static_structure_initializer() {
+        let symbols = check("struct s { char c; long l; int i; }; struct s g = {1, 2};");
+        let expected = InitialValue::Initial(vec![
+            StaticInit::Scalar(Constant::new(1, Type::Char)),
+            StaticInit::Zero(7),
+            StaticInit::Scalar(Constant::new(2, Type::Long)),
+            StaticInit::Zero(8),
+        ]);
+        assert!(matches!(&symbols.get("g").unwrap().attrs, SymbolAttrs::Static { init, .. } if *init == expected));
+    }
+
+    #[test]
+    #[should_panic(expected = "Redefinition of 'struct s' at 1, 29")]
+    fn redefined_structure() {
+        check("struct s { int a; }; struct s { int b; };");
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate member 'a' at 1, 24")]
+    fn duplicate_member() {
+        check("struct s { int a; long a; };");
+    }
+
+    #[test]
+    #[should_panic(expected = "Member 'inner' at 1, 21 has incomplete type 'struct s'")]
+    fn structure_containing_itself() {
+        check("struct s { struct s inner; };");
+    }
+
+    #[test]
+    #[should_panic(expected = "Variable 'x' at 1, 27 has incomplete type 'struct s'")]
+    fn incomplete_variable() {
+        check("int main(void) { struct s x; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't take the size of incomplete type 'struct s' at 1, 38")]
+    fn size_of_incomplete_structure() {
+        check("int main(void) { struct s *p; return sizeof *p; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "'struct s' has no member named 'b' at 1, 53")]
+    fn missing_member() {
+        check("struct s { int a; }; int main(void) { struct s x; x.b = 1; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid operand of type 'int' to '->' at 1, 37")]
+    fn arrow_on_non_pointer() {
+        check("int main(void) { int x = 0; return x->a; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many elements in initializer for 'union u' at 1, 58")]
+    fn too_many_union_initializers() {
+        check("union u { int a; long b; }; int main(void) { union u x = {1, 2}; return 0; }");
+    }
+
+    #[test]
+    fn casts_and_sizeof() {
+        let symbols = check(r#"
+        unsigned long size = sizeof(long[3]);
+        int *none = (int *)0;
+        struct s { char c; long l; };
+        int main(void) {
+            struct s value;
+            double d = (double)(int)3.7;
+            return (int)&value + sizeof(struct s) + sizeof value.c;
+        }
+        "#);
+        assert_eq!(symbols.get("size").unwrap().attrs, SymbolAttrs::Static { init: initial(24, Type::ULong), global: true });
+        assert!(matches!(&symbols.get("none").unwrap().attrs, SymbolAttrs::Static { init: InitialValue::Initial(_), .. }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't cast to 'int[2]' at 1, 35")]
+    fn cast_to_array() {
+        check("int main(void) { int a[2]; return (int[2])a; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't cast to 'struct s' at 1, 55")]
+    fn cast_to_structure() {
+        check("struct s { int a; }; int main(void) { struct s x; x = (struct s)x; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't cast 'struct s' to 'long' at 1, 58")]
+    fn cast_from_structure() {
+        check("struct s { int a; }; int main(void) { struct s x; return (long)x; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't cast 'double' to 'int *' at 1, 43")]
+    fn cast_double_to_pointer() {
+        check("int main(void) { double d = 1.0; int *p = (int *)d; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't take the size of a function at 1, 25")]
+    fn size_of_function_type() {
+        check("int main(void) { return sizeof(int(void)); }");
+    }
+
+    #[test]
+    fn void_pointers_convert_to_object_pointers() {
+        let symbols = check(r#"
+        void *malloc(unsigned long size);
+        void free(void *ptr);
+        int main(void) {
+            long *numbers = malloc(sizeof(long) * 4);
+            void *raw = numbers;
+            free(numbers);
+            return raw == numbers;
+        }
+        "#);
+        let Type::Function(malloc) = &symbols.get("malloc").unwrap().ty else { panic!("malloc isn't a function") };
+        assert_eq!(*malloc.return_type, Type::Pointer(Box::new(Type::Void)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't dereference a value of type 'void *' at 1, 31")]
+    fn dereference_void_pointer() {
+        check("int main(void) { void *p = 0; *p; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Arithmetic on a pointer to incomplete type 'void' at 1, 37")]
+    fn arithmetic_on_void_pointer() {
+        check("int main(void) { void *p = 0; p = p + 1; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Comparison between distinct pointer types 'void *' and 'int *' at 1, 52")]
+    fn relational_void_pointer_comparison() {
+        check("int main(void) { void *p = 0; int *q = 0; return p < q; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't convert 'void' to 'int' at 1, 40")]
+    fn using_a_void_value() {
+        check("void f(void); int main(void) { int x = f(); return x; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "'return' with a value at 1, 23 in a function returning void")]
+    fn return_value_from_void_function() {
+        check("void f(void) { return 1; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "'return' without a value at 1, 18 in a function returning 'int'")]
+    fn return_without_value() {
+        check("int main(void) { return; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Variable 'x' at 1, 23 can't have type void")]
+    fn void_variable() {
+        check("int main(void) { void x; return 0; }");
+    }
+
+    #[test]
+    fn qualifiers_on_declarations() {
+        let symbols = check(r#"
+        int main(void) {
+            int x = 1;
+            const int y = 2;
+            const int *p = &x;
+            int *const q = &x;
+            const int array[2] = {1, 2};
+            p = &y;
+            *q = y;
+            return *p + array[1];
+        }
+        "#);
+        let pointer = Type::Pointer(Box::new(Type::Int));
+        assert!(symbols.get("p.2").unwrap().ty.is_pointer());
+        assert_eq!(symbols.get("q.3").unwrap().ty, pointer.qualified(Qualifiers { constant: true, volatile: false }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Assignment to read-only location of type 'const int' at 1, 37")]
+    fn assignment_to_const() {
+        check("int main(void) { const int x = 1; x = 2; return x; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Assignment to read-only location of type 'const int' at 1, 36")]
+    fn increment_of_const() {
+        check("int main(void) { const int x = 1; x++; return x; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Assignment to read-only location of type 'const int' at 1, 47")]
+    fn assignment_through_pointer_to_const() {
+        check("int main(void) { int x; const int *p = &x; *p += 1; return x; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Conversion from 'const int *' to 'int *' at 1, 44 discards qualifiers")]
+    fn conversion_discards_qualifiers() {
+        check("int main(void) { const int a = 1; int *p = &a; return *p; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Assignment to read-only location of type 'struct p' at 1, 81")]
+    fn assignment_to_structure_with_const_member() {
+        check("struct p { const int x; }; int main(void) { struct p s = {1}; struct p t = s; s = t; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Assignment to read-only location of type 'const int' at 1, 67")]
+    fn assignment_to_member_of_const_structure() {
+        check("struct p { int x; }; int main(void) { const struct p s = {1}; s.x = 2; return 0; }");
+    }
+
+    #[test]
+    fn pointers_convert_to_bool() {
+        check("int main(void) { int x; _Bool b = &x; return b; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't cast 'float' to 'int *' at 1, 43")]
+    fn cast_float_to_pointer() {
+        check("int main(void) { float f = 1.0f; int *p = (int *)f; return 0; }");
+    }
+
+    #[test]
+    fn integer_literal_bases() {
+        let str = "long a = 010; long b = 0x1F; long c = 0xFFFFFFFF + 1; long d = 4294967295 + 1; long e = 0x8000000000000000 > 0;";
+        let symbols = check(str);
+        let value = |name: &str| symbols.get(name).unwrap().attrs.clone();
+        assert_eq!(value("a"), SymbolAttrs::Static { init: initial(8, Type::Long), global: true });
+        assert_eq!(value("b"), SymbolAttrs::Static { init: initial(31, Type::Long), global: true });
+        // 0xFFFFFFFF is an unsigned int, so adding one wraps around. The same value written in decimal is a long.
+        // Too big for a long, 0x8000000000000000 is an unsigned long and so not negative
+        assert_eq!(value("c"), SymbolAttrs::Static { init: initial(0, Type::Long), global: true });
+        assert_eq!(value("d"), SymbolAttrs::Static { init: initial(4294967296, Type::Long), global: true });
+        assert_eq!(value("e"), SymbolAttrs::Static { init: initial(1, Type::Long), global: true });
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid number '09' at 1, 9")]
+    fn invalid_octal_digit() {
+        check("int x = 09;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Number '18446744073709551616' at 1, 10 is too large for any integer type")]
+    fn integer_literal_too_large() {
+        check("long x = 18446744073709551616;");
+    }
+
+    #[test]
+    fn floating_literal_types() {
+        let symbols = check("float f = 1.5f; double d = 1.5; long long l = 2LL; unsigned long long u = 3ULL;");
+        assert_eq!(symbols.get("f").unwrap().attrs, SymbolAttrs::Static { init: initial(1.5f32.to_bits() as i64, Type::Float), global: true });
+        assert_eq!(symbols.get("l").unwrap().ty, Type::LongLong);
+        assert_eq!(symbols.get("u").unwrap().ty, Type::ULongLong);
+    }
+
+    #[test]
+    fn variadic_function_types() {
+        let symbols = check("int f(int a, ...); int main(void) { return f(1, 2.0f, 3); }");
+        let f = symbols.get("f").unwrap();
+        assert_eq!(f.ty, Type::variadic_function(Type::Int, vec![Type::Int]));
+        assert_eq!(f.ty.to_string(), "int(int, ...)");
+    }
+
+    #[test]
+    #[should_panic(expected = "Function 'f' called with 0 arguments but takes at least 1 at 1, 44")]
+    fn too_few_variadic_arguments() {
+        check("int f(int a, ...); int main(void) { return f(); }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Conflicting types for 'f' at 1, 19")]
+    fn variadic_conflicts_with_fixed() {
+        check("int f(int a); int f(int a, ...);");
+    }
+
+    #[test]
+    #[should_panic(expected = "'__builtin_va_arg' at 1, 51 reads 'float', which is passed as 'double'")]
+    fn va_arg_of_promoted_type() {
+        check("float f(int a, ...) { __builtin_va_list l; return __builtin_va_arg(l, float); }");
+    }
+
+    #[test]
+    #[should_panic(expected = "'__builtin_va_start' at 1, 38 used in a function without '...'")]
+    fn va_start_without_ellipsis() {
+        check("void f(int a) { __builtin_va_list l; __builtin_va_start(l, a); }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Argument of '__builtin_va_end' at 1, 46 has type 'int' but must be a va_list")]
+    fn va_end_of_something_else() {
+        check("void f(int a, ...) { int l; __builtin_va_end(l); }");
+    }
+
+    #[test]
+    fn stdarg_names_are_not_reserved() {
+        // Without <stdarg.h> they're ordinary identifiers, only the __builtin_ ones are there from the start
+        let symbols = check("int va_list = 3; int va_arg(int x) { return x; } int main(void) { return va_arg(va_list); }");
+        assert_eq!(symbols.get("va_list").unwrap().ty, Type::Int);
+    }
+
+    #[test]
+    fn designated_static_initializers() {
+        let symbols = check("struct s { char c; long l; }; struct s x = {.l = 3}; int a[4] = {[2] = 7, 8};");
+        let x = symbols.get("x").unwrap();
+        assert_eq!(x.attrs, SymbolAttrs::Static {
+            init: InitialValue::Initial(vec![
+                StaticInit::Zero(1),
+                StaticInit::Zero(7),
+                StaticInit::Scalar(Constant::new(3, Type::Long)),
+            ]),
+            global: true,
+        });
+        let a = symbols.get("a").unwrap();
+        assert_eq!(a.attrs, SymbolAttrs::Static {
+            init: InitialValue::Initial(vec![
+                StaticInit::Zero(4),
+                StaticInit::Zero(4),
+                StaticInit::Scalar(Constant::int(7)),
+                StaticInit::Scalar(Constant::int(8)),
+            ]),
+            global: true,
+        });
+    }
+
+    #[test]
+    fn designated_union_member() {
+        let symbols = check("union u { char c; double d; }; union u x = {.d = 1.5};");
+        let x = symbols.get("x").unwrap();
+        assert_eq!(x.attrs, SymbolAttrs::Static {
+            init: InitialValue::Initial(vec![StaticInit::Scalar(Constant::double(1.5))]),
+            global: true,
+        });
+    }
+
+    #[test]
+    fn elements_after_a_nested_designator() {
+        let str = "struct pt { int x; int y; }; struct two { struct pt a; struct pt b; }; \
+            struct two t = {.b.y = 4, .a = {1, 2}, 5}; struct two u = {.a.y = 1, 2, 3}; int m[2][2] = {[0][1] = 1, 2, 3};";
+        let symbols = check(str);
+        let int = |value| StaticInit::Scalar(Constant::new(value, Type::Int));
+        let values: Vec<&SymbolAttrs> = ["t", "u", "m"].iter().map(|name| &symbols.get(name).unwrap().attrs).collect();
+        assert_eq!(values, vec![
+            &SymbolAttrs::Static { init: InitialValue::Initial(vec![int(1), int(2), int(5), int(4)]), global: true },
+            &SymbolAttrs::Static { init: InitialValue::Initial(vec![StaticInit::Zero(4), int(1), int(2), int(3)]), global: true },
+            &SymbolAttrs::Static { init: InitialValue::Initial(vec![StaticInit::Zero(4), int(1), int(2), int(3)]), global: true },
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index 3 in designator at 1, 16 is out of bounds for 'int[3]'")]
+    fn designator_out_of_bounds() {
+        check("int a[3] = {1, [3] = 2};");
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many elements in initializer for 'int[3]' at 1, 12")]
+    fn positional_element_after_designator_out_of_bounds() {
+        check("int a[3] = {[2] = 1, 2};");
+    }
+
+    #[test]
+    #[should_panic(expected = "Element of 'int[3]' at 1, 22 is initialized more than once")]
+    fn overlapping_designators() {
+        check("int a[3] = {1, 2, 3, [1] = 4};");
+    }
+
+    #[test]
+    #[should_panic(expected = "Element of 'struct p' at 1, 73 is initialized more than once")]
+    fn designator_inside_initialized_member() {
+        check("struct q { int a; }; struct p { struct q q; }; struct p x = {.q = {1}, .q.a = 2};");
+    }
+
+    #[test]
+    #[should_panic(expected = "Element of 'union u' at 1, 51 is initialized more than once")]
+    fn two_members_of_a_union() {
+        check("union u { int i; long l; }; union u x = {.i = 1, .l = 2};");
+    }
+
+    #[test]
+    #[should_panic(expected = "'struct p' has no member named 'z' at 1, 37")]
+    fn designator_for_missing_member() {
+        check("struct p { int x; }; struct p v = {.z = 1};");
+    }
+
+    #[test]
+    #[should_panic(expected = "Index designator at 1, 38 for 'int', which is not an array")]
+    fn index_designator_for_scalar() {
+        check("struct p { int x; }; struct p v = {.x[0] = 1};");
+    }
+
+    #[test]
+    fn compound_literal_storage() {
+        let symbols = check("int size = sizeof (int[2]){1, 2}; int main(void) { return (int[1]){3}[0]; }");
+        let storage: Vec<&SymbolAttrs> = symbols
+            .iter()
+            .filter(|(name, _)| name.starts_with("literal."))
+            .map(|(_, symbol)| &symbol.attrs)
+            .collect();
+        assert_eq!(storage.len(), 2);
+        assert!(storage.contains(&&SymbolAttrs::Local));
+        assert!(storage.iter().any(|attrs| matches!(attrs, SymbolAttrs::Static { global: false, .. })));
+    }
+
+    #[test]
+    fn function_pointer_types() {
+        let symbols = check("int add(int a, int b); int (*op)(int, int) = add; int (*ops[2])(int, int) = {&add, 0};");
+        let pointer = Type::Pointer(Box::new(Type::function(Type::Int, vec![Type::Int, Type::Int])));
+        assert_eq!(symbols.get("op").unwrap().ty, pointer);
+        assert_eq!(symbols.get("op").unwrap().attrs, SymbolAttrs::Static {
+            init: InitialValue::Initial(vec![StaticInit::Address("add".to_string(), 0)]),
+            global: true,
+        });
+        assert_eq!(symbols.get("ops").unwrap().ty, Type::Array(Box::new(pointer), 2));
+    }
+
+    #[test]
+    fn static_addresses() {
+        let str = "struct pt { int x; long y; }; int x; long a[4]; struct pt s; \
+            int *p = &x; long *q = a + 2; long *r = &a[3] - 1; long *t = &s.y; void *v = &a; char *u = \"hi\" + 1; int *w = (int[2]){1, 2};";
+        let symbols = check(str);
+        let address = |name: &str| match &symbols.get(name).unwrap().attrs {
+            SymbolAttrs::Static { init: InitialValue::Initial(init), .. } => match init.as_slice() {
+                [StaticInit::Address(symbol, offset)] => (symbol.clone(), *offset),
+                init => panic!("{:?} isn't an address", init),
+            },
+            attrs => panic!("{:?} isn't initialized", attrs),
+        };
+        assert_eq!(address("p"), ("x".to_string(), 0));
+        assert_eq!(address("q"), ("a".to_string(), 16));
+        assert_eq!(address("r"), ("a".to_string(), 16));
+        assert_eq!(address("t"), ("s".to_string(), 8));
+        assert_eq!(address("v"), ("a".to_string(), 0));
+
+        // The string and the compound literal get arrays of their own
+        let (string, offset) = address("u");
+        assert_eq!(offset, 1);
+        assert_eq!(symbols.get(&string).unwrap().attrs, SymbolAttrs::Constant(vec![StaticInit::String(b"hi\0".to_vec())]));
+        let (literal, offset) = address("w");
+        assert_eq!(offset, 0);
+        assert!(matches!(symbols.get(&literal).unwrap().attrs, SymbolAttrs::Static { global: false, .. }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Initializer for 'p' at 1, 37 is not a constant")]
+    fn static_pointer_to_local() {
+        check("int main(void) { int x; static int *p = &x; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Conversion from 'const int *' to 'int *' at 1, 27 discards qualifiers")]
+    fn static_address_discarding_const() {
+        check("const int c = 1; int *p = &c;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't convert 'int (*)(int)' to 'int (*)(long)' at 1, 58")]
+    fn function_pointer_of_another_type() {
+        check("int f(int a); int main(void) { int (*fp)(long) = 0; fp = f; return 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Function of type 'int(int)' called with 2 arguments but takes 1 at 1, 46")]
+    fn wrong_argument_count_through_pointer() {
+        check("int main(void) { int (*fp)(int) = 0; return (*fp)(1, 2); }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't assign to function of type 'int(int)' at 1, 48")]
+    fn assigning_to_function() {
+        check("int f(int a); int g(int a); int main(void) { f = g; return 0; }");
+    }
+
+    #[test]
+    fn string_literals() {
+        let symbols = check("char s[] = \"ab\"; char t[4] = {\"x\"}; char u[2] = \"ab\"; long n = sizeof \"abc\";");
+        assert_eq!(symbols.get("s").unwrap().ty, Type::Array(Box::new(Type::Char), 3));
+        assert_eq!(symbols.get("t").unwrap().attrs, SymbolAttrs::Static {
+            init: InitialValue::Initial(vec![StaticInit::String(b"x".to_vec()), StaticInit::Zero(3)]),
+            global: true,
+        });
+        // There's no room for the null, so it's left off
+        assert_eq!(symbols.get("u").unwrap().attrs, SymbolAttrs::Static {
+            init: InitialValue::Initial(vec![StaticInit::String(b"ab".to_vec())]),
+            global: true,
+        });
+        assert_eq!(symbols.get("n").unwrap().attrs, SymbolAttrs::Static { init: initial(4, Type::Long), global: true });
+    }
+
+    #[test]
+    #[should_panic(expected = "String at 1, 13 is too long for 'char[2]'")]
+    fn string_too_long_for_array() {
+        check("char s[2] = \"abc\";");
+    }
+
+    #[test]
+    fn sizes_come_from_the_target() {
+        let str = "struct s { char c; long l; }; long a = sizeof(long); long b = sizeof(struct s); long c = sizeof(int *);";
+        let symbols = check(str);
+        let values: Vec<&SymbolAttrs> = ["a", "b", "c"].iter().map(|name| &symbols.get(name).unwrap().attrs).collect();
+        assert_eq!(values, vec![
+            &SymbolAttrs::Static { init: initial(8, Type::Long), global: true },
+            &SymbolAttrs::Static { init: initial(16, Type::Long), global: true },
+            &SymbolAttrs::Static { init: initial(8, Type::Long), global: true },
+        ]);
+
+        assert_eq!(symbols.target(), &Target::X86_64_LINUX);
+
+        // Code generation has to lay out the same sizes the checker worked out
+        let assembly = compile(str);
+        for (name, size) in [("a", 8), ("b", 16), ("c", 8)] {
+            assert!(assembly.contains(&format!("{}:\n    .quad {}\n", name, size)), "{}", assembly);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't compile for x86_64-linux with these sizes, only the x86_64 Linux target is supported")]
+    fn other_targets() {
+        let target = Target { long_size: 4, pointer_size: 4, ..Target::X86_64_LINUX };
+        check_for_target("long a = sizeof(long);", target);
+    }
+}
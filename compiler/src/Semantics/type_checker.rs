@@ -0,0 +1,1486 @@
+use crate::lexer::syntax_token::{Location, SyntaxKind};
+use crate::lexer::LexerToken;
+use crate::parsing::*;
+use crate::resolver::original_name;
+use crate::target::Target;
+use crate::types::{va_list_definition, Constant, Member, StructDef, Type, VA_LIST_TAG};
+use std::collections::{HashMap, LinkedList, VecDeque};
+
+// Every node in the tree knows how to check itself
+// Expressions record their type as they go and get wrapped in casts wherever C converts implicitly
+pub trait TypeCheckable {
+    fn type_check(&mut self, checker: &mut TypeChecker);
+}
+
+// What a variable with static storage starts out holding
+#[derive(Debug, Clone, PartialEq)]
+pub enum InitialValue {
+    // Declared without an initializer and without extern, becomes zero unless a definition turns up
+    Tentative,
+    Initial(Vec<StaticInit>),
+    // Only declared here, the definition lives somewhere else
+    NoInitializer,
+}
+
+// One piece of a static initializer, laid out one after the other in the data section
+#[derive(Debug, Clone, PartialEq)]
+pub enum StaticInit {
+    // Takes up as many bytes as its type
+    Scalar(Constant),
+    // Padding for whatever the initializer left out
+    Zero(usize),
+    // A pointer this many bytes into an object or function, the linker fills in where it ends up
+    Address(String, i64),
+    // The characters of a string, exactly these bytes and no terminating null unless it's one of them
+    String(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolAttrs {
+    // Global means the symbol has external linkage
+    Function { defined: bool, global: bool },
+    Static { init: InitialValue, global: bool },
+    // A string literal, which is static as well but can't be written to
+    Constant(Vec<StaticInit>),
+    // Lives in the stack frame of the function it's declared in
+    Local,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub ty: Type,
+    pub attrs: SymbolAttrs,
+}
+
+// Everything the later passes need to know about a name, keyed by the name the resolver gave it
+// Structure and union tags live in a namespace of their own
+#[derive(Debug, Clone)]
+pub struct SymbolTable {
+    symbols: HashMap<String, Symbol>,
+    structs: HashMap<String, StructDef>,
+    // Where the sizes of everything that isn't a structure or a union come from
+    target: Target,
+}
+
+impl Default for SymbolTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SymbolTable {
+    // The structure behind va_list is the only one that's there from the start
+    pub fn new() -> Self {
+        Self::for_target(Target::default())
+    }
+
+    pub fn for_target(target: Target) -> Self {
+        target.check_supported();
+        SymbolTable {
+            symbols: HashMap::new(),
+            structs: HashMap::from([(VA_LIST_TAG.to_string(), va_list_definition())]),
+            target,
+        }
+    }
+
+    pub fn target(&self) -> &Target {
+        &self.target
+    }
+
+    pub fn get_struct(&self, tag: &str) -> Option<&StructDef> {
+        self.structs.get(tag)
+    }
+
+    pub fn define_struct(&mut self, tag: String, definition: StructDef) {
+        self.structs.insert(tag, definition);
+    }
+
+    // Only structures and unions that have been declared but not defined yet are incomplete
+    pub fn is_complete(&self, ty: &Type) -> bool {
+        match ty.unqualified() {
+            Type::Struct(tag) | Type::Union(tag) => self.structs.contains_key(tag),
+            Type::Array(element, _) => self.is_complete(element),
+            Type::Void | Type::Function(_) | Type::IncompleteArray(_) => false,
+            _ => true,
+        }
+    }
+
+    // An array that's only missing its own size, which something else can give it
+    pub fn is_array_without_size(&self, ty: &Type) -> bool {
+        matches!(ty, Type::IncompleteArray(element) if self.is_complete(element))
+    }
+
+    fn struct_def(&self, tag: &str) -> &StructDef {
+        match self.structs.get(tag) {
+            Some(definition) => definition,
+            None => panic!("'{}' has no definition", original_name(tag)),
+        }
+    }
+
+    // The target's size, except it also knows how big structures and unions are
+    pub fn size_of(&self, ty: &Type) -> usize {
+        match ty.unqualified() {
+            Type::Struct(tag) | Type::Union(tag) => self.struct_def(tag).size,
+            Type::Array(element, count) => self.size_of(element) * count,
+            _ => self.target.size_of(ty),
+        }
+    }
+
+    pub fn alignment_of(&self, ty: &Type) -> usize {
+        match ty.unqualified() {
+            Type::Struct(tag) | Type::Union(tag) => self.struct_def(tag).alignment,
+            Type::Array(element, _) => self.alignment_of(element),
+            _ => self.target.alignment_of(ty),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.get(name)
+    }
+
+    pub fn insert(&mut self, name: String, symbol: Symbol) {
+        self.symbols.insert(name, symbol);
+    }
+
+    // The array a string literal lives in, which holds its characters and the null after them
+    pub fn insert_string(&mut self, name: &str, value: &[u8]) {
+        let mut init = value.to_vec();
+        init.push(0);
+        self.insert(name.to_string(), Symbol {
+            ty: Type::Array(Box::new(Type::Char), init.len()),
+            attrs: SymbolAttrs::Constant(vec![StaticInit::String(init)]),
+        });
+    }
+
+    pub fn type_of(&self, name: &str) -> &Type {
+        match self.symbols.get(name) {
+            Some(symbol) => &symbol.ty,
+            None => panic!("'{}' is missing from the symbol table", name),
+        }
+    }
+
+    // Whether a function is defined in this file
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.symbols
+            .get(name)
+            .is_some_and(|symbol| matches!(symbol.attrs, SymbolAttrs::Function { defined: true, .. }))
+    }
+
+    pub fn is_static(&self, name: &str) -> bool {
+        self.symbols
+            .get(name)
+            .is_some_and(|symbol| matches!(symbol.attrs, SymbolAttrs::Static { .. } | SymbolAttrs::Constant(_)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Symbol)> {
+        self.symbols.iter()
+    }
+}
+
+pub struct TypeChecker {
+    pub symbols: SymbolTable,
+    // The return type of the function we're inside of
+    return_type: Option<Type>,
+    // Whether that function takes a `...`, va_start only makes sense in one that does
+    variadic: bool,
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        Self::for_target(Target::default())
+    }
+
+    pub fn for_target(target: Target) -> Self {
+        TypeChecker {
+            symbols: SymbolTable::for_target(target),
+            return_type: None,
+            variadic: false,
+        }
+    }
+
+    // Outside of a function body there's no return type to check against
+    pub fn at_file_scope(&self) -> bool {
+        self.return_type.is_none()
+    }
+
+    pub fn check_program(&mut self, program: &mut LinkedList<Box<dyn Statement>>) {
+        for statement in program.iter_mut() {
+            statement.type_check(self);
+        }
+        // An array that's only ever had tentative definitions without a size ends up with one element, `int a[];`
+        for symbol in self.symbols.symbols.values_mut() {
+            if let (Type::IncompleteArray(element), SymbolAttrs::Static { init: InitialValue::Tentative, .. }) = (&symbol.ty, &symbol.attrs) {
+                symbol.ty = Type::Array(element.clone(), 1);
+            }
+        }
+    }
+}
+
+// Wraps the expression in a cast unless it already has the type we want
+pub fn convert_to(expression: &mut Box<dyn Expression>, target: &Type) {
+    if expression.get_type() == *target {
+        return;
+    }
+    let inner = expression.clone();
+    *expression = Box::new(CastExpr {
+        target: target.clone(),
+        expression: inner,
+        open_paren: None,
+    });
+}
+
+// An integer constant expression that comes out as zero can stand in for any pointer
+// Only looks at the folded value, so it works on initializers the checker hasn't been through
+pub fn is_null_pointer_constant(expression: &dyn Expression) -> bool {
+    expression
+        .constant_value()
+        .is_some_and(|constant| constant.ty.is_integer() && constant.value == 0)
+}
+
+// `void *` goes to and from any other object pointer without a cast
+fn converts_through_void(from: &Type, to: &Type) -> bool {
+    from.is_object_pointer() && to.is_object_pointer() && (from.is_void_pointer() || to.is_void_pointer())
+}
+
+// Both point at the same type, give or take its qualifiers
+fn same_pointee(left: &Type, right: &Type) -> bool {
+    matches!((left.unqualified(), right.unqualified()), (Type::Pointer(left), Type::Pointer(right)) if left.unqualified() == right.unqualified())
+}
+
+// The conversions that happen as if by assignment, for arguments, return values and initializers as well
+// Arithmetic types convert freely, pointers only come from the same pointer type, `void *` or a null pointer constant,
+// and any pointer can go into a _Bool.
+// A pointer can pick up qualifiers on what it points at, but dropping one takes a cast
+pub fn convert_by_assignment(expression: &mut Box<dyn Expression>, target: &Type) {
+    // The value being stored is never qualified, only the object it's stored into
+    let target = target.unqualified();
+    check_assignment(expression.as_ref(), &expression.get_type(), target);
+    convert_to(expression, target);
+}
+
+// Panics unless a value of type ty can be stored in an unqualified target, the expression is the value
+fn check_assignment(expression: &dyn Expression, ty: &Type, target: &Type) {
+    if let (Type::Pointer(from), Type::Pointer(to)) = (ty, target)
+        && (same_pointee(ty, target) || converts_through_void(ty, target))
+        && !to.qualifiers().contains(from.qualifiers())
+    {
+        panic!("Conversion from '{}' to '{}' at {} discards qualifiers", ty, target, expression.location())
+    }
+    let allowed = *ty == *target
+        || same_pointee(ty, target)
+        || (ty.is_arithmetic() && target.is_arithmetic())
+        || (ty.is_pointer() && *target == Type::Bool)
+        || converts_through_void(ty, target)
+        || (target.is_pointer() && is_null_pointer_constant(expression));
+    if !allowed {
+        panic!("Can't convert '{}' to '{}' at {}", ty, target, expression.location())
+    }
+}
+
+// Checks the expression and turns it into the value it holds
+fn check_decayed(expression: &mut Box<dyn Expression>, checker: &mut TypeChecker) -> Type {
+    expression.type_check(checker);
+    decay(expression)
+}
+
+// An array becomes a pointer to its first element and a function a pointer to itself, which is what happens to every
+// one that isn't the operand of & or sizeof. Anything else loses its qualifiers
+fn decay(expression: &mut Box<dyn Expression>) -> Type {
+    match expression.get_type() {
+        Type::Array(element, _) | Type::IncompleteArray(element) => convert_to(expression, &Type::Pointer(element)),
+        ty @ Type::Function(_) => convert_to(expression, &Type::Pointer(Box::new(ty))),
+        Type::Qualified(inner, _) => convert_to(expression, &inner),
+        _ => {}
+    }
+    expression.get_type()
+}
+
+// Checks the expression, then applies the integer promotions to it
+fn check_promoted(expression: &mut Box<dyn Expression>, checker: &mut TypeChecker) -> Type {
+    check_decayed(expression, checker);
+    let promoted = expression.get_type().promote();
+    convert_to(expression, &promoted);
+    promoted
+}
+
+fn expect_scalar(expression: &dyn Expression, operator: &LexerToken) {
+    let ty = expression.get_type();
+    if !ty.is_scalar() {
+        panic!("Invalid operand of type '{}' to '{}' at {}", ty, operator.data, operator.location)
+    }
+}
+
+fn expect_arithmetic(ty: &Type, operator: &LexerToken) {
+    if !ty.is_arithmetic() {
+        panic!("Invalid operand of type '{}' to '{}' at {}", ty, operator.data, operator.location)
+    }
+}
+
+fn expect_integer(ty: &Type, operator: &LexerToken) {
+    if !ty.is_integer() {
+        panic!("Operand of '{}' at {} has type '{}' but must be an integer", operator.data, operator.location, ty)
+    }
+}
+
+// Pointer arithmetic has to know how big the thing being pointed to is
+fn expect_complete_referenced(ty: &Type, symbols: &SymbolTable, operator: &LexerToken) {
+    if let Type::Pointer(referenced) = ty && !symbols.is_complete(referenced) {
+        panic!("Arithmetic on a pointer to incomplete type '{}' at {}", referenced, operator.location)
+    }
+}
+
+// A const member makes the whole structure read only, it can't be assigned to in one go
+fn is_read_only(ty: &Type, symbols: &SymbolTable) -> bool {
+    ty.is_const() || match ty.unqualified() {
+        Type::Struct(tag) | Type::Union(tag) => symbols
+            .get_struct(tag)
+            .is_some_and(|definition| definition.members.iter().any(|member| is_read_only(&member.ty, symbols))),
+        _ => false,
+    }
+}
+
+fn expect_modifiable(ty: &Type, symbols: &SymbolTable, operator: &LexerToken) {
+    if let Type::Function(_) = ty {
+        panic!("Can't assign to function of type '{}' at {}", ty, operator.location)
+    }
+    if is_read_only(ty, symbols) {
+        panic!("Assignment to read-only location of type '{}' at {}", ty, operator.location)
+    }
+}
+
+fn align_to(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+fn is_static_keyword(storage_class: &Option<LexerToken>) -> bool {
+    storage_class.as_ref().is_some_and(|token| token.kind == SyntaxKind::StaticKeyword)
+}
+
+fn is_extern_keyword(storage_class: &Option<LexerToken>) -> bool {
+    storage_class.as_ref().is_some_and(|token| token.kind == SyntaxKind::ExternKeyword)
+}
+
+// Two declarations of the same variable have to agree on its type, except one can leave out the size of an array the other gives
+fn composite_type(existing: &Type, ty: &Type) -> Option<Type> {
+    match (existing, ty) {
+        _ if existing == ty => Some(ty.clone()),
+        (Type::IncompleteArray(element), Type::Array(other, _)) if element == other => Some(ty.clone()),
+        (Type::Array(element, _), Type::IncompleteArray(other)) if element == other => Some(existing.clone()),
+        _ => None,
+    }
+}
+
+// Where an element of a braced list ends up while the list is being arranged
+enum Slot {
+    Empty,
+    Filled(Initializer),
+    // Taken from the list itself because its braces were left out or a designator went further in, it's been arranged already
+    Elided(Initializer),
+}
+
+// Puts every element of a braced list at the position it initializes, following its designators, and checks the list fits.
+// Braces go with arrays and structures and nothing else, with an empty list meaning zero for anything.
+// Afterwards a list has one element per position up to the last one initialized, with an empty list for every position
+// it skips, and no designators. A union only ever has one member initialized, the one at the last position.
+// The expressions have to be checked already, whether a structure's braces were left out depends on their types.
+// Gives back the type, which has its size once it's an array that was waiting on its initializer
+fn arrange_initializer(initializer: &mut Initializer, ty: &Type, symbols: &SymbolTable) -> Type {
+    // The null only goes in when there's room for it
+    if let Initializer::Single(expression) = &*initializer && initializes_characters(initializer, ty) {
+        let length = expression.string_literal().unwrap().len();
+        return match ty {
+            Type::IncompleteArray(element) => Type::Array(element.clone(), length + 1),
+            Type::Array(_, count) if length > *count => panic!("String at {} is too long for '{}'", expression.location(), ty),
+            _ => ty.clone(),
+        };
+    }
+    let (open_curly, elements) = match initializer {
+        // The braces around a string are optional, `char s[] = {"abc"}`
+        Initializer::Compound { elements, .. } if elements.len() == 1 && initializes_characters(&elements[0], ty) => {
+            *initializer = elements.remove(0);
+            return arrange_initializer(initializer, ty, symbols);
+        }
+        Initializer::Compound { open_curly, elements } => (open_curly.clone(), std::mem::take(elements)),
+        Initializer::Single(expression) if ty.is_array() => {
+            panic!("Array of type '{}' at {} has to be initialized with a braced list", ty, expression.location())
+        }
+        Initializer::Single(_) => return ty.clone(),
+        Initializer::Designated { .. } => unreachable!("Designators only appear in a braced list"),
+    };
+    let (ty, arranged) = arrange_list(&open_curly, &mut VecDeque::from(elements), ty, symbols, true);
+    *initializer = arranged;
+    ty
+}
+
+// Fills in ty from the front of the list. Without braces of its own it stops once ty is full or a designator
+// turns up, which always picks out something in the enclosing list. Only its first element can have one, what's left
+// of a designator that brought us into ty, and the elements after it carry on from there
+fn arrange_list(
+    open_curly: &LexerToken,
+    elements: &mut VecDeque<Initializer>,
+    ty: &Type,
+    symbols: &SymbolTable,
+    braced: bool,
+) -> (Type, Initializer) {
+    let (room, union) = match ty.unqualified() {
+        Type::Array(_, count) => (Some(*count), false),
+        Type::IncompleteArray(_) => (None, false),
+        Type::Struct(tag) => (Some(symbols.struct_def(tag).members.len()), false),
+        Type::Union(tag) => (Some(symbols.struct_def(tag).members.len()), true),
+        _ if elements.is_empty() => {
+            return (ty.clone(), Initializer::Compound { open_curly: open_curly.clone(), elements: Vec::new() })
+        }
+        _ => {
+            // A designator that goes too deep is what brought us here, `.x[0]` for an int
+            if let Some(Initializer::Designated { designators, .. }) = elements.front() {
+                designated_position(&designators[0], ty, symbols);
+            }
+            panic!("Braced initializer for '{}' at {}, which is not an array or structure", ty, open_curly.location)
+        }
+    };
+
+    let mut slots: Vec<Slot> = Vec::new();
+    let mut position = 0;
+    while let Some(next) = elements.front() {
+        let location = next.location();
+        let full = room.is_some_and(|room| position >= room) || (union && position > 0);
+        let designated = matches!(next, Initializer::Designated { .. });
+        if !braced && (full || (designated && !slots.is_empty())) {
+            break;
+        }
+        // Without a designator the element goes after the one before it, which only a union stops at its first member.
+        // The value is put back, it could be the first of an element whose braces were left out. So is whatever's
+        // left of a designator, `.a.b = 1` initializes a with `.b = 1` as if a's braces were left out
+        let (index, nested) = match elements.pop_front().unwrap() {
+            Initializer::Designated { mut designators, initializer } => {
+                let index = designated_position(&designators[0], ty, symbols);
+                designators.remove(0);
+                let nested = !designators.is_empty();
+                elements.push_front(if nested { Initializer::Designated { designators, initializer } } else { *initializer });
+                (index, nested)
+            }
+            _ if full => panic!("Too many elements in initializer for '{}' at {}", ty, open_curly.location),
+            element => {
+                elements.push_front(element);
+                (position, false)
+            }
+        };
+        let element_type = element_type(ty, index, symbols);
+        let slot = if nested || braces_elided(&elements[0], &element_type) {
+            Slot::Elided(arrange_list(open_curly, elements, &element_type, symbols, false).1)
+        } else {
+            Slot::Filled(elements.pop_front().unwrap())
+        };
+        let overlaps_union = union && slots.iter().enumerate().any(|(other, slot)| other != index && !matches!(slot, Slot::Empty));
+        if slots.len() <= index {
+            slots.resize_with(index + 1, || Slot::Empty);
+        }
+        match (&mut slots[index], slot) {
+            _ if overlaps_union => panic!("Element of '{}' at {} is initialized more than once", ty, location),
+            (existing @ Slot::Empty, slot) => *existing = slot,
+            (Slot::Elided(existing), Slot::Elided(value)) => {
+                if !merge_initializers(existing, value, &element_type, symbols) {
+                    panic!("Element of '{}' at {} is initialized more than once", ty, location)
+                }
+            }
+            _ => panic!("Element of '{}' at {} is initialized more than once", ty, location),
+        }
+        position = index + 1;
+    }
+
+    let ty = match ty {
+        Type::IncompleteArray(element) => Type::Array(element.clone(), slots.len()),
+        _ => ty.clone(),
+    };
+    let elements = slots
+        .into_iter()
+        .enumerate()
+        .map(|(index, slot)| match slot {
+            Slot::Empty => Initializer::Compound { open_curly: open_curly.clone(), elements: Vec::new() },
+            Slot::Elided(element) => element,
+            Slot::Filled(mut element) => {
+                arrange_initializer(&mut element, &element_type(&ty, index, symbols), symbols);
+                element
+            }
+        })
+        .collect();
+    (ty, Initializer::Compound { open_curly: open_curly.clone(), elements })
+}
+
+// Lays what one part of the list initialized in a subobject over what another part did, as long as they don't overlap.
+// `{.a.x = 1, .b = 2, .a.y = 3}` initializes a in two goes. Both have been arranged already
+fn merge_initializers(existing: &mut Initializer, value: Initializer, ty: &Type, symbols: &SymbolTable) -> bool {
+    let is_empty = |initializer: &Initializer| matches!(initializer, Initializer::Compound { elements, .. } if elements.is_empty());
+    if is_empty(&value) {
+        return true;
+    }
+    if is_empty(existing) {
+        *existing = value;
+        return true;
+    }
+    let (Initializer::Compound { elements: existing, .. }, Initializer::Compound { elements: values, .. }) = (existing, value) else {
+        return false;
+    };
+    // Two different members of a union can't both be initialized
+    if matches!(ty.unqualified(), Type::Union(_)) && existing.len() != values.len() {
+        return false;
+    }
+    for (index, value) in values.into_iter().enumerate() {
+        if index == existing.len() {
+            existing.push(value);
+        } else if !merge_initializers(&mut existing[index], value, &element_type(ty, index, symbols), symbols) {
+            return false;
+        }
+    }
+    true
+}
+
+// The type of whatever's at a position in a list for ty
+fn element_type(ty: &Type, position: usize, symbols: &SymbolTable) -> Type {
+    match ty.unqualified() {
+        Type::Array(element, _) | Type::IncompleteArray(element) => element.as_ref().clone(),
+        Type::Struct(tag) | Type::Union(tag) => symbols.struct_def(tag).members[position].ty.clone(),
+        _ => unreachable!(),
+    }
+}
+
+// An array or structure inside a list can leave out its braces and take its elements straight from the list,
+// a structure can still be given all at once by a value of its own type
+fn braces_elided(element: &Initializer, ty: &Type) -> bool {
+    let Initializer::Single(expression) = element else { return false };
+    match ty.unqualified() {
+        Type::Array(..) => !initializes_characters(element, ty),
+        Type::Struct(_) | Type::Union(_) => expression.get_type().unqualified() != ty.unqualified(),
+        _ => false,
+    }
+}
+
+// The position in the list a designator picks out, an index has to be inside the array
+fn designated_position(designator: &Designator, ty: &Type, symbols: &SymbolTable) -> usize {
+    match (designator, ty.unqualified()) {
+        (Designator::Index { open_bracket, index }, Type::Array(_, count)) => {
+            if index >= count {
+                panic!("Index {} in designator at {} is out of bounds for '{}'", index, open_bracket.location, ty)
+            }
+            *index
+        }
+        (Designator::Index { index, .. }, Type::IncompleteArray(_)) => *index,
+        (Designator::Member(member), Type::Struct(tag) | Type::Union(tag)) => {
+            match symbols.struct_def(tag).members.iter().position(|candidate| candidate.name == member.data) {
+                Some(position) => position,
+                None => panic!("'{}' has no member named '{}' at {}", ty, member.data, member.location),
+            }
+        }
+        (Designator::Index { open_bracket, .. }, _) => {
+            panic!("Index designator at {} for '{}', which is not an array", open_bracket.location, ty)
+        }
+        (Designator::Member(member), _) => {
+            panic!("Member designator '.{}' at {} for '{}', which is not a structure or union", member.data, member.location, ty)
+        }
+    }
+}
+
+// A string literal can stand in for the whole list of an array of characters
+fn initializes_characters(initializer: &Initializer, ty: &Type) -> bool {
+    let Initializer::Single(expression) = initializer else { return false };
+    let element = match ty {
+        Type::Array(element, _) | Type::IncompleteArray(element) => element.unqualified(),
+        _ => return false,
+    };
+    expression.string_literal().is_some() && matches!(element, Type::Char | Type::SChar | Type::UChar)
+}
+
+// Works out the value a static variable starts with, which has to be known at compile time
+fn static_initializer(declaration: &VariableDeclaration, ty: &Type, symbols: &mut SymbolTable) -> InitialValue {
+    let name = original_name(&declaration.identifier.data);
+    let location = &declaration.identifier.location;
+    match declaration.initializer.as_ref() {
+        Some(initializer) => InitialValue::Initial(static_init_list(name, location, initializer, ty, symbols)),
+        None if is_extern_keyword(&declaration.storage_class) => InitialValue::NoInitializer,
+        None => InitialValue::Tentative,
+    }
+}
+
+// A static pointer can only start out null, either from a null pointer constant or from zero cast to its own type
+fn is_static_null_pointer(expression: &dyn Expression, ty: &Type) -> bool {
+    is_null_pointer_constant(expression)
+        || expression.constant_value().is_some_and(|constant| constant.ty == *ty.unqualified() && constant.value == 0)
+}
+
+// Where a static pointer initialized with the expression points, if it's somewhere known before the program starts
+fn static_pointer(expression: &dyn Expression, symbols: &mut SymbolTable) -> Option<(String, i64)> {
+    match expression.string_literal() {
+        Some(_) => expression.static_object(symbols),
+        None => expression.static_address(symbols),
+    }
+}
+
+// Flattens an arranged initializer into the values that go into the data section, padding out whatever it leaves off with zeroes.
+// The name and location are of whatever's being initialized
+fn static_init_list(name: &str, location: &Location, initializer: &Initializer, ty: &Type, symbols: &mut SymbolTable) -> Vec<StaticInit> {
+    match (initializer, ty.unqualified()) {
+        (Initializer::Compound { elements, .. }, _) if elements.is_empty() => vec![StaticInit::Zero(symbols.size_of(ty))],
+        (Initializer::Compound { elements, .. }, Type::Array(element, count)) => {
+            let mut inits = Vec::new();
+            for initializer in elements {
+                inits.extend(static_init_list(name, location, initializer, element, symbols));
+            }
+            if elements.len() < *count {
+                inits.push(StaticInit::Zero((count - elements.len()) * symbols.size_of(element)));
+            }
+            inits
+        }
+        (Initializer::Compound { elements, .. }, Type::Union(tag)) => {
+            let definition = symbols.struct_def(tag).clone();
+            let member = &definition.members[elements.len() - 1];
+            let mut inits = static_init_list(name, location, &elements[elements.len() - 1], &member.ty, symbols);
+            let size = symbols.size_of(&member.ty);
+            if definition.size > size {
+                inits.push(StaticInit::Zero(definition.size - size));
+            }
+            inits
+        }
+        // Members go at their offsets, with zeroes for the padding in between and whatever's left at the end
+        (Initializer::Compound { elements, .. }, Type::Struct(tag)) => {
+            let definition = symbols.struct_def(tag).clone();
+            let mut inits = Vec::new();
+            let mut offset = 0;
+            for (initializer, member) in elements.iter().zip(definition.members.iter()) {
+                if member.offset > offset {
+                    inits.push(StaticInit::Zero(member.offset - offset));
+                }
+                inits.extend(static_init_list(name, location, initializer, &member.ty, symbols));
+                offset = member.offset + symbols.size_of(&member.ty);
+            }
+            if definition.size > offset {
+                inits.push(StaticInit::Zero(definition.size - offset));
+            }
+            inits
+        }
+        (Initializer::Single(expression), Type::Array(_, count)) => {
+            let value = expression.string_literal().expect("Only a string initializes an array without braces");
+            let length = value.len().min(*count);
+            let mut inits = vec![StaticInit::String(value[..length].to_vec())];
+            if length < *count {
+                inits.push(StaticInit::Zero(count - length));
+            }
+            inits
+        }
+        // A string is left as an array until here, so it points at its first character like any other array
+        (Initializer::Single(expression), _) if ty.is_pointer() && let Some((symbol, offset)) = static_pointer(expression.as_ref(), symbols) => {
+            let from = match expression.get_type() {
+                Type::Array(element, _) => Type::Pointer(element),
+                from => from,
+            };
+            check_assignment(expression.as_ref(), &from, ty.unqualified());
+            vec![StaticInit::Address(symbol, offset)]
+        }
+        (Initializer::Single(expression), _) if ty.is_pointer() && expression.constant_value().is_none() => {
+            panic!("Initializer for '{}' at {} is not a constant", name, location)
+        }
+        (Initializer::Single(expression), _) if ty.is_pointer() && !is_static_null_pointer(expression.as_ref(), ty) => {
+            panic!("Initializer for '{}' at {} is not a null pointer constant", name, location)
+        }
+        (Initializer::Single(expression), _) => match expression.constant_value() {
+            Some(constant) => vec![StaticInit::Scalar(constant.convert(ty))],
+            None => panic!("Initializer for '{}' at {} is not a constant", name, location),
+        },
+        _ => unreachable!(),
+    }
+}
+
+impl TypeChecker {
+    fn check_file_scope_variable(&mut self, declaration: &VariableDeclaration, mut ty: Type) {
+        let name = &declaration.identifier.data;
+        let mut init = static_initializer(declaration, &ty, &mut self.symbols);
+        let mut global = !is_static_keyword(&declaration.storage_class);
+
+        if let Some(existing) = self.symbols.get(name) {
+            let (old_init, old_global) = match &existing.attrs {
+                SymbolAttrs::Static { init, global } => (init.clone(), *global),
+                _ => panic!("'{}' at {} was declared as a function before", name, declaration.identifier.location),
+            };
+            ty = match composite_type(&existing.ty, &ty) {
+                Some(composite) => composite,
+                None => panic!("Conflicting types for '{}' at {}, it was declared as '{}' before", name, declaration.identifier.location, existing.ty),
+            };
+
+            // extern picks up whatever linkage was there before
+            if is_extern_keyword(&declaration.storage_class) {
+                global = old_global;
+            } else if global != old_global {
+                panic!("Conflicting linkage for '{}' at {}", name, declaration.identifier.location)
+            }
+
+            init = match (old_init, init) {
+                (InitialValue::Initial(_), InitialValue::Initial(_)) => {
+                    panic!("Redefinition of '{}' at {}", name, declaration.identifier.location)
+                }
+                (InitialValue::Initial(value), _) | (_, InitialValue::Initial(value)) => InitialValue::Initial(value),
+                (InitialValue::Tentative, _) | (_, InitialValue::Tentative) => InitialValue::Tentative,
+                _ => InitialValue::NoInitializer,
+            };
+        }
+
+        self.symbols.insert(name.clone(), Symbol {
+            ty,
+            attrs: SymbolAttrs::Static { init, global },
+        });
+    }
+
+    fn check_local_variable(&mut self, declaration: &mut VariableDeclaration, ty: Type) {
+        let name = declaration.identifier.data.clone();
+        // The resolver already gave the variable a name of its own, so it can sit next to the file scope ones
+        if is_static_keyword(&declaration.storage_class) {
+            let init = match static_initializer(declaration, &ty, &mut self.symbols) {
+                InitialValue::Initial(inits) => InitialValue::Initial(inits),
+                _ => InitialValue::Initial(vec![StaticInit::Zero(self.symbols.size_of(&ty))]),
+            };
+            self.symbols.insert(name, Symbol {
+                ty,
+                attrs: SymbolAttrs::Static { init, global: false },
+            });
+            return;
+        }
+
+        if is_extern_keyword(&declaration.storage_class) {
+            if declaration.initializer.is_some() {
+                panic!("Local extern declaration of '{}' at {} can't have an initializer", name, declaration.identifier.location)
+            }
+            match self.symbols.get(&name) {
+                Some(Symbol { attrs: SymbolAttrs::Function { .. }, .. }) => {
+                    panic!("'{}' at {} was declared as a function before", name, declaration.identifier.location)
+                }
+                Some(existing) => match composite_type(&existing.ty, &ty) {
+                    Some(composite) => {
+                        let attrs = existing.attrs.clone();
+                        self.symbols.insert(name, Symbol { ty: composite, attrs });
+                    }
+                    None => {
+                        panic!("Conflicting types for '{}' at {}, it was declared as '{}' before", name, declaration.identifier.location, existing.ty)
+                    }
+                },
+                None => self.symbols.insert(name, Symbol {
+                    ty,
+                    attrs: SymbolAttrs::Static { init: InitialValue::NoInitializer, global: true },
+                }),
+            }
+            return;
+        }
+
+        self.symbols.insert(name, Symbol {
+            ty: ty.clone(),
+            attrs: SymbolAttrs::Local,
+        });
+        if let Some(initializer) = declaration.initializer.as_mut() {
+            self.convert_initializer(initializer, &ty);
+        }
+    }
+
+    // Every value in an initializer gets checked in the order it's written before the list is arranged.
+    // A string stays an array until we know whether it's the contents of one
+    fn check_initializer_values(&mut self, initializer: &mut Initializer) {
+        match initializer {
+            Initializer::Single(expression) if expression.string_literal().is_some() => expression.type_check(self),
+            Initializer::Single(expression) => {
+                check_decayed(expression, self);
+            }
+            Initializer::Compound { elements, .. } => {
+                for element in elements.iter_mut() {
+                    self.check_initializer_values(element);
+                }
+            }
+            Initializer::Designated { initializer, .. } => self.check_initializer_values(initializer),
+        }
+    }
+
+    // Initializing isn't assigning, so a const object gets its value here just like any other.
+    // Static values are worked out at compile time and converted as constants instead
+    fn convert_initializer(&mut self, initializer: &mut Initializer, ty: &Type) {
+        match (initializer, ty.unqualified()) {
+            (Initializer::Compound { elements, .. }, _) if elements.is_empty() => {}
+            (Initializer::Compound { elements, .. }, Type::Array(element, _)) => {
+                for initializer in elements.iter_mut() {
+                    self.convert_initializer(initializer, element);
+                }
+            }
+            (Initializer::Compound { elements, .. }, Type::Struct(tag) | Type::Union(tag)) => {
+                let members = self.symbols.struct_def(tag).members.clone();
+                for (initializer, member) in elements.iter_mut().zip(members.iter()) {
+                    self.convert_initializer(initializer, &member.ty);
+                }
+            }
+            (Initializer::Single(_), Type::Array(..)) => {}
+            (Initializer::Single(expression), _) => {
+                decay(expression);
+                convert_by_assignment(expression, ty.unqualified());
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn is_shift(kind: &SyntaxKind) -> bool {
+    matches!(kind, SyntaxKind::LessLess | SyntaxKind::GreaterGreater)
+}
+
+// Operators that only make sense on integers
+fn needs_integers(kind: &SyntaxKind) -> bool {
+    matches!(kind, SyntaxKind::Percent | SyntaxKind::Ampersand | SyntaxKind::Pipe | SyntaxKind::Caret) || is_shift(kind)
+}
+
+fn is_relational(kind: &SyntaxKind) -> bool {
+    is_equality(kind) || matches!(kind,
+        SyntaxKind::Less | SyntaxKind::LessEqual |
+        SyntaxKind::Greater | SyntaxKind::GreaterEqual)
+}
+
+fn is_equality(kind: &SyntaxKind) -> bool {
+    matches!(kind, SyntaxKind::EqualEqual | SyntaxKind::BangEqual)
+}
+
+fn invalid_operands(left: &Type, right: &Type, operator: &LexerToken) -> ! {
+    panic!("Invalid operands of type '{}' and '{}' to '{}' at {}", left, right, operator.data, operator.location)
+}
+
+impl BinaryExpr {
+    // Pointers can move by an integer, be subtracted from each other and be compared, nothing else
+    fn check_pointer_operands(&mut self, left: Type, right: Type, symbols: &SymbolTable) -> Type {
+        let kind = self.operator.kind.clone();
+        if matches!(kind, SyntaxKind::Plus | SyntaxKind::Minus) {
+            expect_complete_referenced(&left, symbols, &self.operator);
+            expect_complete_referenced(&right, symbols, &self.operator);
+        }
+        match kind {
+            SyntaxKind::Plus | SyntaxKind::Minus if left.is_pointer() && right.is_integer() => {
+                convert_to(&mut self.right, &Type::Long);
+                left
+            }
+            SyntaxKind::Plus if left.is_integer() && right.is_pointer() => {
+                convert_to(&mut self.left, &Type::Long);
+                right
+            }
+            // The difference counts elements, not bytes
+            SyntaxKind::Minus if same_pointee(&left, &right) => Type::Long,
+            _ if is_relational(&kind) => {
+                let right_is_null = is_null_pointer_constant(self.right.as_ref());
+                let common = if left == right || same_pointee(&left, &right) || (is_equality(&kind) && left.is_pointer() && right_is_null) {
+                    left
+                } else if is_equality(&kind) && right.is_pointer() && is_null_pointer_constant(self.left.as_ref()) {
+                    right
+                } else if is_equality(&kind) && converts_through_void(&left, &right) {
+                    Type::Pointer(Box::new(Type::Void))
+                } else if left.is_pointer() && right.is_pointer() {
+                    panic!("Comparison between distinct pointer types '{}' and '{}' at {}", left, right, self.operator.location)
+                } else {
+                    invalid_operands(&left, &right, &self.operator)
+                };
+                convert_to(&mut self.left, &common);
+                convert_to(&mut self.right, &common);
+                Type::Int
+            }
+            _ => invalid_operands(&left, &right, &self.operator),
+        }
+    }
+}
+
+impl TypeCheckable for FunctionStatement {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        let return_type = self.func_type.clone();
+        let params: Vec<Type> = self.parameters
+            .iter()
+            .map(|parameter| parameter.param_type.clone())
+            .collect();
+        let ty = if self.variadic {
+            Type::variadic_function(return_type.clone(), params.clone())
+        } else {
+            Type::function(return_type.clone(), params.clone())
+        };
+        let name = &self.identifier.data;
+
+        let mut defined = self.func_body.is_some();
+        let mut global = !is_static_keyword(&self.storage_class);
+        if let Some(existing) = checker.symbols.get(name) {
+            let (old_defined, old_global) = match existing.attrs {
+                SymbolAttrs::Function { defined, global } => (defined, global),
+                _ => panic!("'{}' at {} was declared as a variable before", name, self.identifier.location),
+            };
+            if existing.ty != ty {
+                panic!("Conflicting types for '{}' at {}, it was declared as '{}' before", name, self.identifier.location, existing.ty)
+            }
+            if old_defined && defined {
+                panic!("Redefinition of '{}' at {}", name, self.identifier.location)
+            }
+            if old_global && !global {
+                panic!("Static declaration of '{}' at {} follows a non-static one", name, self.identifier.location)
+            }
+            defined |= old_defined;
+            global = old_global;
+        }
+        checker.symbols.insert(name.clone(), Symbol {
+            ty,
+            attrs: SymbolAttrs::Function { defined, global },
+        });
+
+        let body = match self.func_body.as_mut() {
+            Some(body) => body,
+            None => return,
+        };
+        // A definition has to know how big everything it's passed and returns is
+        if return_type.is_structure() && !checker.symbols.is_complete(&return_type) {
+            panic!("Function '{}' at {} returns incomplete type '{}'", name, self.identifier.location, return_type)
+        }
+        for (parameter, ty) in self.parameters.iter().zip(params) {
+            let Some(identifier) = parameter.identifier.as_ref() else { continue };
+            if !checker.symbols.is_complete(&ty) {
+                panic!("Parameter '{}' at {} has incomplete type '{}'", original_name(&identifier.data), identifier.location, ty)
+            }
+            checker.symbols.insert(identifier.data.clone(), Symbol {
+                ty,
+                attrs: SymbolAttrs::Local,
+            });
+        }
+        checker.return_type = Some(return_type);
+        checker.variadic = self.variadic;
+        body.type_check(checker);
+        checker.return_type = None;
+    }
+}
+
+impl TypeCheckable for Body {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        for statement in self.statements.iter_mut() {
+            statement.type_check(checker);
+        }
+    }
+}
+
+impl TypeCheckable for VariableDeclaration {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        let mut ty = self.var_type.clone();
+        if *ty.unqualified() == Type::Void {
+            panic!("Variable '{}' at {} can't have type void", original_name(&self.identifier.data), self.identifier.location)
+        }
+        // Only a declaration can get away with not knowing the size. An array can still get it from its initializer,
+        // or from a later declaration when this one is a tentative definition
+        let waits_for_size = checker.symbols.is_array_without_size(&ty)
+            && (self.initializer.is_some() || (checker.at_file_scope() && !is_static_keyword(&self.storage_class)));
+        if !is_extern_keyword(&self.storage_class) && !waits_for_size && !checker.symbols.is_complete(&ty) {
+            panic!("Variable '{}' at {} has incomplete type '{}'", original_name(&self.identifier.data), self.identifier.location, ty)
+        }
+        let automatic = !checker.at_file_scope() && !is_static_keyword(&self.storage_class) && !is_extern_keyword(&self.storage_class);
+        if let Some(initializer) = self.initializer.as_mut() {
+            // A local is in scope in its own initializer, `long size = sizeof size`
+            if automatic {
+                checker.symbols.insert(self.identifier.data.clone(), Symbol {
+                    ty: ty.clone(),
+                    attrs: SymbolAttrs::Local,
+                });
+            }
+            checker.check_initializer_values(initializer);
+            ty = arrange_initializer(initializer, &ty, &checker.symbols);
+            self.var_type = ty.clone();
+        }
+        if checker.at_file_scope() {
+            checker.check_file_scope_variable(self, ty);
+        } else {
+            checker.check_local_variable(self, ty);
+        }
+    }
+}
+
+// Enumerations are plain ints, there's nothing left to check
+impl TypeCheckable for EnumDeclaration {
+    fn type_check(&mut self, _checker: &mut TypeChecker) {}
+}
+
+// Lays out the members in order, each at the next offset that suits its alignment, and pads the end
+// so that arrays of the structure keep every element aligned. The members of a union all start at zero.
+impl TypeCheckable for StructDeclaration {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        let Some(declarations) = self.members.as_ref() else { return };
+        let tag = &self.tag.data;
+        if checker.symbols.get_struct(tag).is_some() {
+            panic!("Redefinition of '{} {}' at {}", self.keyword.data, original_name(tag), self.tag.location)
+        }
+
+        let union = self.keyword.kind == SyntaxKind::UnionKeyword;
+        let mut members: Vec<Member> = Vec::new();
+        let mut size = 0;
+        let mut alignment = 1;
+        for declaration in declarations.iter() {
+            let name = &declaration.identifier.data;
+            if members.iter().any(|member| member.name == *name) {
+                panic!("Duplicate member '{}' at {}", name, declaration.identifier.location)
+            }
+            let ty = declaration.member_type.clone();
+            if !checker.symbols.is_complete(&ty) {
+                panic!("Member '{}' at {} has incomplete type '{}'", name, declaration.identifier.location, ty)
+            }
+
+            let member_alignment = checker.symbols.alignment_of(&ty);
+            let member_size = checker.symbols.size_of(&ty);
+            alignment = alignment.max(member_alignment);
+            let offset = if union { 0 } else { align_to(size, member_alignment) };
+            size = size.max(offset + member_size);
+            members.push(Member {
+                name: name.clone(),
+                ty,
+                offset,
+            });
+        }
+
+        checker.symbols.define_struct(tag.clone(), StructDef {
+            size: align_to(size, alignment),
+            alignment,
+            members,
+        });
+    }
+}
+
+impl TypeCheckable for ExpressionStatement {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        check_decayed(&mut self.expression, checker);
+    }
+}
+
+impl TypeCheckable for NullStatement {
+    fn type_check(&mut self, _checker: &mut TypeChecker) {}
+}
+
+impl TypeCheckable for ReturnExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        let return_type = checker.return_type
+            .clone()
+            .expect("Return statement outside of a function");
+        match (self.value.as_mut(), return_type) {
+            (Some(value), Type::Void) => {
+                panic!("'return' with a value at {} in a function returning void", value.location())
+            }
+            (Some(value), return_type) => {
+                check_decayed(value, checker);
+                convert_by_assignment(value, &return_type);
+            }
+            (None, Type::Void) => {}
+            (None, return_type) => {
+                panic!("'return' without a value at {} in a function returning '{}'", self.return_kw.location, return_type)
+            }
+        }
+    }
+}
+
+impl TypeCheckable for SwitchStatement {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        let ty = check_promoted(&mut self.condition, checker);
+        expect_integer(&ty, &self.switch_kw);
+        // Case values are compared against the condition, so they take on its type. They were
+        // folded to their exact value already, and every integer value fits in a long.
+        // Cases are only compared once they have been converted, in a switch on an unsigned int -1 and 4294967295 are the same case
+        for case in self.cases.iter_mut() {
+            case.value = case.value.map(|value| Constant::new(value, Type::Long).convert(&ty).value);
+        }
+        for (index, case) in self.cases.iter().enumerate() {
+            if let Some(value) = case.value && self.cases[..index].iter().any(|earlier| earlier.value == Some(value)) {
+                panic!("Duplicate case value {} at {}", value, case.location)
+            }
+        }
+        self.body.type_check(checker);
+    }
+}
+
+impl TypeCheckable for CaseStatement {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        self.value.type_check(checker);
+        self.statement.type_check(checker);
+    }
+}
+
+impl TypeCheckable for DefaultStatement {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        self.statement.type_check(checker);
+    }
+}
+
+impl TypeCheckable for LabeledStatement {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        self.statement.type_check(checker);
+    }
+}
+
+impl TypeCheckable for GotoStatement {
+    fn type_check(&mut self, _checker: &mut TypeChecker) {}
+}
+
+impl TypeCheckable for BreakStatement {
+    fn type_check(&mut self, _checker: &mut TypeChecker) {}
+}
+
+// The parser already worked out the type from the literal
+impl TypeCheckable for ConstantExpr {
+    fn type_check(&mut self, _checker: &mut TypeChecker) {}
+}
+
+impl TypeCheckable for VariableExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        self.ty = Some(checker.symbols.type_of(&self.identifier.data).clone());
+    }
+}
+
+impl TypeCheckable for UnaryExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        if self.operator.kind == SyntaxKind::Bang {
+            check_decayed(&mut self.operand, checker);
+            expect_scalar(self.operand.as_ref(), &self.operator);
+            self.ty = Some(Type::Int);
+            return;
+        }
+
+        let ty = check_promoted(&mut self.operand, checker);
+        expect_arithmetic(&ty, &self.operator);
+        if self.operator.kind == SyntaxKind::Tilde {
+            expect_integer(&ty, &self.operator);
+        }
+        self.ty = Some(ty);
+    }
+}
+
+impl TypeCheckable for BinaryExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        let kind = &self.operator.kind;
+        if let SyntaxKind::AmpersandAmpersand | SyntaxKind::PipePipe = kind {
+            check_decayed(&mut self.left, checker);
+            check_decayed(&mut self.right, checker);
+            expect_scalar(self.left.as_ref(), &self.operator);
+            expect_scalar(self.right.as_ref(), &self.operator);
+            self.ty = Some(Type::Int);
+            return;
+        }
+
+        let left = check_promoted(&mut self.left, checker);
+        let right = check_promoted(&mut self.right, checker);
+        if left.is_pointer() || right.is_pointer() {
+            self.ty = Some(self.check_pointer_operands(left, right, &checker.symbols));
+            return;
+        }
+        expect_arithmetic(&left, &self.operator);
+        expect_arithmetic(&right, &self.operator);
+        if needs_integers(kind) {
+            expect_integer(&left, &self.operator);
+            expect_integer(&right, &self.operator);
+        }
+
+        // The result of a shift has the type of its left operand, the right one is only a count
+        if is_shift(kind) {
+            self.ty = Some(left);
+            return;
+        }
+
+        let common = Type::common_type(&left, &right);
+        convert_to(&mut self.left, &common);
+        convert_to(&mut self.right, &common);
+        self.ty = if is_relational(kind) {
+            Some(Type::Int)
+        } else {
+            Some(common)
+        };
+    }
+}
+
+impl TypeCheckable for AssignmentExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        self.left.type_check(checker);
+        check_decayed(&mut self.right, checker);
+        let ty = self.left.get_type();
+        if ty.is_array() {
+            panic!("Can't assign to array of type '{}' at {}", ty, self.equal.location)
+        }
+        expect_modifiable(&ty, &checker.symbols, &self.equal);
+        let ty = ty.unqualified().clone();
+        convert_by_assignment(&mut self.right, &ty);
+        self.ty = Some(ty);
+    }
+}
+
+impl TypeCheckable for CompoundAssignmentExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        let operator = SyntaxKind::compound_operator(&self.operator.kind).unwrap();
+        self.left.type_check(checker);
+        let ty = self.left.get_type();
+        expect_modifiable(&ty, &checker.symbols, &self.operator);
+        let ty = ty.unqualified().clone();
+        let right = check_promoted(&mut self.right, checker);
+        // Pointers can only move by an integer number of elements
+        if ty.is_pointer() {
+            if !matches!(operator, SyntaxKind::Plus | SyntaxKind::Minus) || !right.is_integer() {
+                invalid_operands(&ty, &right, &self.operator)
+            }
+            expect_complete_referenced(&ty, &checker.symbols, &self.operator);
+            convert_to(&mut self.right, &Type::Long);
+            self.ty = Some(ty);
+            return;
+        }
+        expect_arithmetic(&ty, &self.operator);
+        expect_arithmetic(&right, &self.operator);
+        if needs_integers(&operator) {
+            expect_integer(&ty, &self.operator);
+            expect_integer(&right, &self.operator);
+        }
+
+        // The operation happens in the common type and the result is converted back on the way into the left side,
+        // lowering works out the common type again from the right operand
+        if !is_shift(&operator) {
+            let common = Type::common_type(&ty, &right);
+            convert_to(&mut self.right, &common);
+        }
+        self.ty = Some(ty);
+    }
+}
+
+impl TypeCheckable for IncrementExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        self.operand.type_check(checker);
+        let ty = self.operand.get_type();
+        expect_modifiable(&ty, &checker.symbols, &self.operator);
+        let ty = ty.unqualified().clone();
+        if !ty.is_pointer() {
+            expect_arithmetic(&ty, &self.operator);
+        }
+        expect_complete_referenced(&ty, &checker.symbols, &self.operator);
+        self.ty = Some(ty);
+    }
+}
+
+// A function called by name is called directly, anything else has to be a pointer to a function once it decays
+impl TypeCheckable for FunctionCallExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        self.function.type_check(checker);
+        let ty = match self.function.designated_function() {
+            Some(_) => self.function.get_type(),
+            None => decay(&mut self.function),
+        };
+        let function = match &ty {
+            Type::Function(function) => function.clone(),
+            Type::Pointer(referenced) if let Type::Function(function) = referenced.as_ref() => function.clone(),
+            ty => panic!("Called object of type '{}' at {} is not a function", ty, self.function.location()),
+        };
+        let callee = match self.function.designated_function() {
+            Some(name) => format!("Function '{}'", name),
+            None => format!("Function of type '{}'", Type::Function(function.clone())),
+        };
+        let count_matches = if function.variadic {
+            self.arguments.len() >= function.params.len()
+        } else {
+            self.arguments.len() == function.params.len()
+        };
+        if !count_matches {
+            let at_least = if function.variadic { "at least " } else { "" };
+            panic!("{} called with {} arguments but takes {}{} at {}",
+                   callee, self.arguments.len(), at_least, function.params.len(), self.function.location())
+        }
+
+        for (argument, param) in self.arguments.iter_mut().zip(function.params.iter()) {
+            check_decayed(argument, checker);
+            convert_by_assignment(argument, param);
+        }
+        // Whatever the ellipsis takes has no parameter type, so it only gets the default argument promotions
+        for argument in self.arguments.iter_mut().skip(function.params.len()) {
+            let ty = check_decayed(argument, checker);
+            if ty == Type::Void || !checker.symbols.is_complete(&ty) {
+                panic!("Argument at {} has incomplete type '{}'", argument.location(), ty)
+            }
+            convert_to(argument, &ty.promote_argument());
+        }
+        if *function.return_type != Type::Void && !checker.symbols.is_complete(&function.return_type) {
+            panic!("{} called at {} returns incomplete type '{}'", callee, self.function.location(), function.return_type)
+        }
+        self.ty = Some(*function.return_type);
+    }
+}
+
+impl TypeCheckable for AddressOfExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        self.operand.type_check(checker);
+        self.ty = Some(Type::Pointer(Box::new(self.operand.get_type())));
+    }
+}
+
+impl TypeCheckable for DereferenceExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        match check_decayed(&mut self.operand, checker) {
+            Type::Pointer(referenced) if *referenced.unqualified() != Type::Void => self.ty = Some(*referenced),
+            ty => panic!("Can't dereference a value of type '{}' at {}", ty, self.star.location),
+        }
+    }
+}
+
+impl TypeCheckable for SubscriptExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        let left = check_decayed(&mut self.left, checker);
+        let index = check_promoted(&mut self.index, checker);
+        let ty = match (&left, &index) {
+            (Type::Pointer(referenced), index) if index.is_integer() => {
+                convert_to(&mut self.index, &Type::Long);
+                *referenced.clone()
+            }
+            (left, Type::Pointer(referenced)) if left.is_integer() => {
+                convert_to(&mut self.left, &Type::Long);
+                *referenced.clone()
+            }
+            _ => invalid_operands(&left, &index, &self.open_bracket),
+        };
+        if !checker.symbols.is_complete(&ty) {
+            panic!("Subscript of a pointer to incomplete type '{}' at {}", ty, self.open_bracket.location)
+        }
+        self.ty = Some(ty);
+    }
+}
+
+impl TypeCheckable for MemberExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        // `p->member` looks through the pointer, `s.member` needs the structure itself
+        let structure = if self.operator.kind == SyntaxKind::Arrow {
+            match check_decayed(&mut self.structure, checker) {
+                Type::Pointer(referenced) if referenced.is_structure() => *referenced,
+                ty => panic!("Invalid operand of type '{}' to '->' at {}", ty, self.operator.location),
+            }
+        } else {
+            self.structure.type_check(checker);
+            self.structure.get_type()
+        };
+        let (Type::Struct(tag) | Type::Union(tag)) = structure.unqualified() else {
+            panic!("Invalid operand of type '{}' to '.' at {}", structure, self.operator.location)
+        };
+        let Some(definition) = checker.symbols.get_struct(tag) else {
+            panic!("Member access into incomplete type '{}' at {}", structure, self.operator.location)
+        };
+        // A member of a const structure is just as const
+        match definition.member(&self.member.data) {
+            Some(member) => self.ty = Some(member.ty.clone().qualified(structure.qualifiers())),
+            None => panic!("'{}' has no member named '{}' at {}", structure, self.member.data, self.member.location),
+        }
+    }
+}
+
+// Both forms of sizeof come down to the size of a type, which has to be a complete object type
+fn size_for_sizeof(ty: &Type, symbols: &SymbolTable, sizeof_kw: &LexerToken) -> usize {
+    if let Type::Function(_) = ty {
+        panic!("Can't take the size of a function at {}", sizeof_kw.location)
+    }
+    if !symbols.is_complete(ty) {
+        panic!("Can't take the size of incomplete type '{}' at {}", ty, sizeof_kw.location)
+    }
+    symbols.size_of(ty)
+}
+
+impl TypeCheckable for SizeofExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        // No decay here, the size of an array is the size of the whole thing
+        self.operand.type_check(checker);
+        self.size = Some(size_for_sizeof(&self.operand.get_type(), &checker.symbols, &self.sizeof_kw));
+        self.ty = Some(Type::ULong);
+    }
+}
+
+impl TypeCheckable for SizeofTypeExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        self.size = Some(size_for_sizeof(&self.target, &checker.symbols, &self.sizeof_kw));
+        self.ty = Some(Type::ULong);
+    }
+}
+
+// Only scalars can be cast, and only to other scalars. Floating values and pointers don't convert into each other.
+// Anything at all can be cast to void, which throws the value away
+impl TypeCheckable for CastExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        let Some(open_paren) = self.open_paren.as_ref() else {
+            self.expression.type_check(checker);
+            return;
+        };
+        let from = check_decayed(&mut self.expression, checker);
+        if self.target == Type::Void {
+            return;
+        }
+        let target = &self.target;
+        if !target.is_scalar() {
+            panic!("Can't cast to '{}' at {}", target, open_paren.location)
+        }
+        let between_floating_and_pointer = (from.is_floating() && target.is_pointer()) || (from.is_pointer() && target.is_floating());
+        if !from.is_scalar() || between_floating_and_pointer {
+            panic!("Can't cast '{}' to '{}' at {}", from, target, open_paren.location)
+        }
+    }
+}
+
+// Every one of these takes the va_list first, which has decayed to a pointer by the time we see it
+impl TypeCheckable for VaBuiltinExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        let name = &self.identifier.data;
+        let location = &self.identifier.location;
+        let va_list = Type::Pointer(Box::new(Type::Struct(VA_LIST_TAG.to_string())));
+        for (index, argument) in self.arguments.iter_mut().enumerate() {
+            let ty = check_decayed(argument, checker);
+            // The second argument of va_start is the last named parameter, which is only there for show
+            if self.builtin == VaBuiltin::Start && index == 1 {
+                continue;
+            }
+            if ty != va_list {
+                panic!("Argument of '{}' at {} has type '{}' but must be a va_list", name, argument.location(), ty)
+            }
+        }
+        if self.builtin == VaBuiltin::Start && !checker.variadic {
+            panic!("'{}' at {} used in a function without '...'", name, location)
+        }
+        let ty = match self.target.as_ref() {
+            Some(target) => {
+                if !checker.symbols.is_complete(target) {
+                    panic!("'{}' at {} reads incomplete type '{}'", name, location, target)
+                }
+                // Nothing that narrow is ever passed through a `...`, the default argument promotions have widened it
+                let promoted = target.promote_argument();
+                if promoted != *target.unqualified() {
+                    panic!("'{}' at {} reads '{}', which is passed as '{}'", name, location, target, promoted)
+                }
+                target.unqualified().clone()
+            }
+            None => Type::Void,
+        };
+        self.ty = Some(ty);
+    }
+}
+
+// The type comes with the literal, the constant it lives in is only made once something uses it as an array
+impl TypeCheckable for StringLiteralExpr {
+    fn type_check(&mut self, _checker: &mut TypeChecker) {}
+}
+
+// A compound literal is a variable without a name, with static storage outside of a function like any other
+impl TypeCheckable for CompoundLiteralExpr {
+    fn type_check(&mut self, checker: &mut TypeChecker) {
+        // Like a variable, an array gets its size from the initializer when it doesn't have one, `(int[]){1, 2}`
+        if !checker.symbols.is_complete(&self.target) && !checker.symbols.is_array_without_size(&self.target) {
+            panic!("Compound literal at {} has incomplete type '{}'", self.open_paren.location, self.target)
+        }
+        checker.check_initializer_values(&mut self.initializer);
+        let ty = arrange_initializer(&mut self.initializer, &self.target, &checker.symbols);
+        self.target = ty.clone();
+        if checker.at_file_scope() {
+            let inits = static_init_list("compound literal", &self.open_paren.location, &self.initializer, &ty, &mut checker.symbols);
+            checker.symbols.insert(self.name.clone(), Symbol {
+                ty,
+                attrs: SymbolAttrs::Static { init: InitialValue::Initial(inits), global: false },
+            });
+            return;
+        }
+        checker.symbols.insert(self.name.clone(), Symbol {
+            ty: ty.clone(),
+            attrs: SymbolAttrs::Local,
+        });
+        checker.convert_initializer(&mut self.initializer, &ty);
+    }
+}
@@ -0,0 +1,459 @@
+use crate::resolver::original_name;
+use crate::target::Target;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionType {
+    pub return_type: Box<Type>,
+    pub params: Vec<Type>,
+    // Takes any number of arguments after the declared ones, `int printf(char *, ...)`
+    pub variadic: bool,
+}
+
+// const and volatile, which can go on any level of a type
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Qualifiers {
+    pub constant: bool,
+    pub volatile: bool,
+}
+
+impl Qualifiers {
+    pub fn is_empty(&self) -> bool {
+        !self.constant && !self.volatile
+    }
+
+    pub fn union(self, other: Qualifiers) -> Qualifiers {
+        Qualifiers {
+            constant: self.constant || other.constant,
+            volatile: self.volatile || other.volatile,
+        }
+    }
+
+    // Converting to a pointer can add qualifiers to what it points at, never take them away
+    pub fn contains(&self, other: Qualifiers) -> bool {
+        self.union(other) == *self
+    }
+}
+
+impl fmt::Display for Qualifiers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.constant, self.volatile) {
+            (true, true) => write!(f, "const volatile"),
+            (true, false) => write!(f, "const"),
+            (false, true) => write!(f, "volatile"),
+            (false, false) => Ok(()),
+        }
+    }
+}
+
+// The types of C, as opposed to the keywords that spell them
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Bool,
+    Char,
+    SChar,
+    UChar,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Long,
+    ULong,
+    LongLong,
+    ULongLong,
+    Float,
+    Double,
+    Void,
+    Pointer(Box<Type>),
+    Array(Box<Type>, usize),
+    // An array whose size isn't known yet, `int a[]`, which an initializer can complete
+    IncompleteArray(Box<Type>),
+    Function(FunctionType),
+    // Structures and unions are looked up by their tag, which the resolver makes unique
+    Struct(String),
+    Union(String),
+    // Never wraps another qualified type or an array, see Type::qualified
+    Qualified(Box<Type>, Qualifiers),
+}
+
+// A member of a structure or union, the offset counts from the start of the whole thing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub name: String,
+    pub ty: Type,
+    pub offset: usize,
+}
+
+// How a structure or union is laid out in memory, every member of a union starts at offset zero
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDef {
+    pub size: usize,
+    pub alignment: usize,
+    pub members: Vec<Member>,
+}
+
+impl StructDef {
+    pub fn member(&self, name: &str) -> Option<&Member> {
+        self.members.iter().find(|member| member.name == name)
+    }
+}
+
+// The structure behind va_list, which is an array of one of these so it's passed to other functions by reference.
+// It's built in, the name is reserved so nothing from the source can clash with it
+pub const VA_LIST_TAG: &str = "__va_list_tag";
+
+// Where va_arg is up to in the registers the caller used and the arguments it pushed, see the System V ABI
+pub fn va_list_definition() -> StructDef {
+    let void_pointer = Type::Pointer(Box::new(Type::Void));
+    let members = [
+        ("gp_offset", Type::UInt, 0),
+        ("fp_offset", Type::UInt, 4),
+        ("overflow_arg_area", void_pointer.clone(), 8),
+        ("reg_save_area", void_pointer, 16),
+    ];
+    StructDef {
+        size: 24,
+        alignment: 8,
+        members: members
+            .into_iter()
+            .map(|(name, ty, offset)| Member { name: name.to_string(), ty, offset })
+            .collect(),
+    }
+}
+
+impl Type {
+    pub fn va_list() -> Type {
+        Type::Array(Box::new(Type::Struct(VA_LIST_TAG.to_string())), 1)
+    }
+
+    // Qualifiers on the return type or on a parameter itself don't change which function it is
+    pub fn function(return_type: Type, params: Vec<Type>) -> Type {
+        Type::Function(FunctionType {
+            return_type: Box::new(return_type.unqualified().clone()),
+            params: params.iter().map(|param| param.unqualified().clone()).collect(),
+            variadic: false,
+        })
+    }
+
+    pub fn variadic_function(return_type: Type, params: Vec<Type>) -> Type {
+        let Type::Function(function) = Type::function(return_type, params) else { unreachable!() };
+        Type::Function(FunctionType { variadic: true, ..function })
+    }
+
+    // Qualifying an array qualifies its elements, and qualifying twice keeps every qualifier
+    pub fn qualified(self, qualifiers: Qualifiers) -> Type {
+        if qualifiers.is_empty() {
+            return self;
+        }
+        match self {
+            Type::Array(element, count) => Type::Array(Box::new(element.qualified(qualifiers)), count),
+            Type::IncompleteArray(element) => Type::IncompleteArray(Box::new(element.qualified(qualifiers))),
+            Type::Qualified(inner, existing) => Type::Qualified(inner, existing.union(qualifiers)),
+            ty => Type::Qualified(Box::new(ty), qualifiers),
+        }
+    }
+
+    // The type without its outermost qualifiers, which is what reading a value of this type gives
+    pub fn unqualified(&self) -> &Type {
+        match self {
+            Type::Qualified(inner, _) => inner,
+            _ => self,
+        }
+    }
+
+    // An array is as qualified as its elements
+    pub fn qualifiers(&self) -> Qualifiers {
+        match self {
+            Type::Qualified(_, qualifiers) => *qualifiers,
+            Type::Array(element, _) | Type::IncompleteArray(element) => element.qualifiers(),
+            _ => Qualifiers::default(),
+        }
+    }
+
+    pub fn is_const(&self) -> bool {
+        self.qualifiers().constant
+    }
+
+    pub fn is_volatile(&self) -> bool {
+        self.qualifiers().volatile
+    }
+
+    // Size in bytes on x86_64 Linux, the only target there is. The preprocessor and the type checker refuse any other,
+    // so their sizes are always these
+    pub fn size(&self) -> usize {
+        Target::X86_64_LINUX.size_of(self)
+    }
+
+    pub fn alignment(&self) -> usize {
+        Target::X86_64_LINUX.alignment_of(self)
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self.unqualified(), Type::Bool | Type::Char | Type::SChar | Type::UChar | Type::Short | Type::UShort
+            | Type::Int | Type::UInt | Type::Long | Type::ULong | Type::LongLong | Type::ULongLong)
+    }
+
+    pub fn is_floating(&self) -> bool {
+        matches!(self.unqualified(), Type::Float | Type::Double)
+    }
+
+    pub fn is_arithmetic(&self) -> bool {
+        self.is_integer() || self.is_floating()
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        matches!(self.unqualified(), Type::Pointer(_))
+    }
+
+    pub fn is_void_pointer(&self) -> bool {
+        matches!(self.unqualified(), Type::Pointer(referenced) if *referenced.unqualified() == Type::Void)
+    }
+
+    // Functions aren't objects, so a pointer to one is the only pointer that isn't an object pointer
+    pub fn is_object_pointer(&self) -> bool {
+        matches!(self.unqualified(), Type::Pointer(referenced) if !matches!(**referenced, Type::Function(_)))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, Type::Array(..) | Type::IncompleteArray(_))
+    }
+
+    pub fn is_structure(&self) -> bool {
+        matches!(self.unqualified(), Type::Struct(_) | Type::Union(_))
+    }
+
+    // What an array is made of once every dimension is peeled off, the type itself for anything else
+    pub fn scalar_element(&self) -> &Type {
+        match self {
+            Type::Array(element, _) | Type::IncompleteArray(element) => element.scalar_element(),
+            _ => self.unqualified(),
+        }
+    }
+
+    // Anything that can be tested against zero
+    pub fn is_scalar(&self) -> bool {
+        self.is_arithmetic() || self.is_pointer()
+    }
+
+    // Plain char is signed on x86_64 Linux, _Bool counts as unsigned
+    pub fn is_signed(&self) -> bool {
+        match self.unqualified() {
+            Type::Char => Target::X86_64_LINUX.char_is_signed,
+            ty => matches!(ty, Type::SChar | Type::Short | Type::Int | Type::Long | Type::LongLong),
+        }
+    }
+
+    // The integer conversion rank, a signed type and its unsigned counterpart share one
+    fn rank(&self) -> usize {
+        match self.unqualified() {
+            Type::Bool => 0,
+            Type::Char | Type::SChar | Type::UChar => 1,
+            Type::Short | Type::UShort => 2,
+            Type::Int | Type::UInt => 3,
+            Type::Long | Type::ULong => 4,
+            Type::LongLong | Type::ULongLong => 5,
+            _ => panic!("'{}' isn't an integer type", self),
+        }
+    }
+
+    fn to_unsigned(&self) -> Type {
+        match self.unqualified() {
+            Type::Char | Type::SChar => Type::UChar,
+            Type::Short => Type::UShort,
+            Type::Int => Type::UInt,
+            Type::Long => Type::ULong,
+            Type::LongLong => Type::ULongLong,
+            ty => ty.clone(),
+        }
+    }
+
+    // Anything ranked below int gets promoted to an int before we do arithmetic on it, every value of those fits
+    pub fn promote(&self) -> Type {
+        if self.is_integer() && self.rank() < Type::Int.rank() {
+            Type::Int
+        } else {
+            self.unqualified().clone()
+        }
+    }
+
+    // The default argument promotions, for arguments that don't have a parameter type to be converted to
+    pub fn promote_argument(&self) -> Type {
+        if *self.unqualified() == Type::Float {
+            Type::Double
+        } else {
+            self.promote()
+        }
+    }
+
+    // The usual arithmetic conversions, the type both operands of a binary operator end up as
+    pub fn common_type(left: &Type, right: &Type) -> Type {
+        let left = left.promote();
+        let right = right.promote();
+        if left == right {
+            return left;
+        }
+        for floating in [Type::Double, Type::Float] {
+            if left == floating || right == floating {
+                return floating;
+            }
+        }
+        if left.is_signed() == right.is_signed() {
+            return if left.rank() > right.rank() { left } else { right };
+        }
+        // The unsigned side wins unless the signed one can hold all of its values,
+        // and if neither can hold the other's they meet in the unsigned version of the signed type
+        let (signed, unsigned) = if left.is_signed() { (left, right) } else { (right, left) };
+        if unsigned.rank() >= signed.rank() {
+            unsigned
+        } else if signed.size() > unsigned.size() {
+            signed
+        } else {
+            signed.to_unsigned()
+        }
+    }
+}
+
+// A value that's known at compile time, along with its type
+// Floating values keep their bit pattern in value, four bytes of it for a float,
+// so everything downstream can carry constants around as an i64
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constant {
+    pub value: i64,
+    pub ty: Type,
+}
+
+impl Constant {
+    pub fn new(value: i64, ty: Type) -> Constant {
+        Constant {
+            value: convert_constant(value, &ty),
+            ty,
+        }
+    }
+
+    pub fn int(value: i64) -> Constant {
+        Constant::new(value, Type::Int)
+    }
+
+    pub fn double(value: f64) -> Constant {
+        Constant {
+            value: value.to_bits() as i64,
+            ty: Type::Double,
+        }
+    }
+
+    pub fn float(value: f32) -> Constant {
+        Constant {
+            value: value.to_bits() as i64,
+            ty: Type::Float,
+        }
+    }
+
+    // Only meaningful when the constant is a float or a double, every float is exactly a double as well
+    pub fn as_double(&self) -> f64 {
+        if *self.ty.unqualified() == Type::Float {
+            f32::from_bits(self.value as u32) as f64
+        } else {
+            f64::from_bits(self.value as u64)
+        }
+    }
+
+    // Negative zero is still zero, even though its bits aren't
+    pub fn is_zero(&self) -> bool {
+        if self.ty.is_floating() {
+            self.as_double() == 0.0
+        } else {
+            self.value == 0
+        }
+    }
+
+    // Integers go straight to a float rather than through a double, so they're only rounded once
+    pub fn convert(&self, ty: &Type) -> Constant {
+        let ty = ty.unqualified();
+        let float = *ty == Type::Float;
+        match (self.ty.is_floating(), ty.is_floating()) {
+            // Anything that isn't zero is true, NaN included
+            _ if *ty == Type::Bool => Constant::new(!self.is_zero() as i64, Type::Bool),
+            (false, false) => Constant::new(self.value, ty.clone()),
+            (false, true) if float && self.ty.is_signed() => Constant::float(self.value as f32),
+            (false, true) if float => Constant::float(self.value as u64 as f32),
+            (false, true) if self.ty.is_signed() => Constant::double(self.value as f64),
+            (false, true) => Constant::double(self.value as u64 as f64),
+            // Conversions to an integer round towards zero, values that don't fit are undefined so we saturate like Rust does
+            (true, false) if ty.is_signed() => Constant::new(self.as_double() as i64, ty.clone()),
+            (true, false) => Constant::new(self.as_double() as u64 as i64, ty.clone()),
+            (true, true) if float => Constant::float(self.as_double() as f32),
+            (true, true) => Constant::double(self.as_double()),
+        }
+    }
+}
+
+// What a value turns into once it's stored in something of type ty
+// Unsigned values are kept zero extended and signed ones sign extended
+pub fn convert_constant(value: i64, ty: &Type) -> i64 {
+    if *ty.unqualified() == Type::Bool {
+        return (value != 0) as i64;
+    }
+    match (ty.size(), ty.is_signed()) {
+        (1, true) => value as i8 as i64,
+        (1, false) => value as u8 as i64,
+        (2, true) => value as i16 as i64,
+        (2, false) => value as u16 as i64,
+        (4, true) => value as i32 as i64,
+        (4, false) => value as u32 as i64,
+        _ => value,
+    }
+}
+
+// Derived types are written the way C declares them, inside out around the spot the name would go,
+// so a pointer to a function comes out as `int *(*)(int, long)`
+fn spell(ty: &Type, inner: String) -> String {
+    // Suffixes bind tighter than the star, a pointer has to be bracketed to get one
+    let bracketed = |inner: String| if inner.starts_with('*') { format!("({})", inner) } else { inner };
+    match ty {
+        Type::Pointer(referenced) => spell(referenced, format!("*{}", inner)),
+        // Qualifiers on the pointer itself go after its star, `int *const`
+        Type::Qualified(pointer, qualifiers) if pointer.is_pointer() => {
+            let Type::Pointer(referenced) = pointer.as_ref() else { unreachable!() };
+            let inner = if inner.is_empty() { inner } else { format!(" {}", inner) };
+            spell(referenced, format!("*{}{}", qualifiers, inner))
+        }
+        Type::Array(element, count) => spell(element, format!("{}[{}]", bracketed(inner), count)),
+        Type::IncompleteArray(element) => spell(element, format!("{}[]", bracketed(inner))),
+        Type::Function(function) => {
+            let mut params: Vec<String> = function.params.iter().map(|param| param.to_string()).collect();
+            if function.variadic {
+                params.push("...".to_string());
+            }
+            spell(&function.return_type, format!("{}({})", bracketed(inner), params.join(", ")))
+        }
+        // A space keeps the star off the base type, `int *` rather than `int*`
+        _ if inner.starts_with('*') || inner.starts_with("(*") => format!("{} {}", ty, inner),
+        _ => format!("{}{}", ty, inner),
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Bool => write!(f, "_Bool"),
+            Type::Char => write!(f, "char"),
+            Type::SChar => write!(f, "signed char"),
+            Type::UChar => write!(f, "unsigned char"),
+            Type::Short => write!(f, "short"),
+            Type::UShort => write!(f, "unsigned short"),
+            Type::Int => write!(f, "int"),
+            Type::UInt => write!(f, "unsigned int"),
+            Type::Long => write!(f, "long"),
+            Type::ULong => write!(f, "unsigned long"),
+            Type::LongLong => write!(f, "long long"),
+            Type::ULongLong => write!(f, "unsigned long long"),
+            Type::Float => write!(f, "float"),
+            Type::Double => write!(f, "double"),
+            Type::Void => write!(f, "void"),
+            Type::Struct(tag) => write!(f, "struct {}", original_name(tag)),
+            Type::Union(tag) => write!(f, "union {}", original_name(tag)),
+            Type::Qualified(inner, qualifiers) if !inner.is_pointer() => write!(f, "{} {}", qualifiers, inner),
+            _ => write!(f, "{}", spell(self, String::new())),
+        }
+    }
+}
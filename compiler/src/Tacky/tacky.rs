@@ -0,0 +1,1201 @@
+use crate::lexer::syntax_token::SyntaxKind;
+use crate::parsing::*;
+use crate::resolver::GENERATED_LABEL_PREFIX;
+use crate::type_checker::{InitialValue, StaticInit, Symbol, SymbolAttrs, SymbolTable};
+use crate::types::{convert_constant, Constant, Member, Type};
+use std::collections::{HashMap, LinkedList};
+
+// TACKY is the three address code that sits between the tree and the assembly
+// Every operation takes its operands from values and writes its result into a variable
+#[derive(Debug, Clone, PartialEq)]
+pub enum Val {
+    Constant(i64),
+    Var(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnaryOp {
+    Complement,
+    Negate,
+    Not,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessOrEqual,
+    GreaterThan,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    // A void function returns without a value
+    Return(Option<Val>),
+    Unary { op: UnaryOp, src: Val, dst: Val },
+    Binary { op: BinaryOp, src1: Val, src2: Val, dst: Val },
+    Copy { src: Val, dst: Val },
+    // Conversions between integers of different sizes
+    SignExtend { src: Val, dst: Val },
+    ZeroExtend { src: Val, dst: Val },
+    Truncate { src: Val, dst: Val },
+    // Conversions between the floating types and the integer types, the integer side is always four or eight bytes
+    // and the floating side is whichever of float and double the other operand is
+    IntToDouble { src: Val, dst: Val },
+    DoubleToInt { src: Val, dst: Val },
+    UIntToDouble { src: Val, dst: Val },
+    DoubleToUInt { src: Val, dst: Val },
+    FloatToDouble { src: Val, dst: Val },
+    DoubleToFloat { src: Val, dst: Val },
+    // Calls to void functions have nowhere to put a result
+    FunCall { name: String, args: Vec<Val>, dst: Option<Val> },
+    // Calls whatever function the pointer in function points to
+    IndirectCall { function: Val, args: Vec<Val>, dst: Option<Val> },
+    GetAddress { src: Val, dst: Val },
+    // Reads through the pointer in src_ptr and writes through the one in dst_ptr
+    Load { src_ptr: Val, dst: Val },
+    Store { src: Val, dst_ptr: Val },
+    // dst = ptr + index * scale, where scale is the size of what the pointer points to
+    AddPtr { ptr: Val, index: Val, scale: i64, dst: Val },
+    // Writes into the variable offset bytes from its start, for filling in arrays piece by piece
+    CopyToOffset { src: Val, dst: String, offset: i64 },
+    // And reads back out of it, for members of structures
+    CopyFromOffset { src: String, offset: i64, dst: Val },
+    Jump(String),
+    JumpIfZero(Val, String),
+    JumpIfNotZero(Val, String),
+    // Jumps to targets[index], the index has to already be known to be in range
+    JumpTable { index: Val, table: String, targets: Vec<String> },
+    Label(String),
+    // Points the va_list that list points to at the first argument behind the `...`
+    VaStart { list: Val },
+    // Reads the next of those arguments into dst, which decides its type, and moves the va_list on past it
+    VaArg { list: Val, dst: Val },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub global: bool,
+    pub params: Vec<String>,
+    pub instructions: Vec<Instruction>,
+}
+
+// A variable with static storage, it gets its own spot in the data section instead of on the stack
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticVariable {
+    pub name: String,
+    pub global: bool,
+    pub ty: Type,
+    pub init: Vec<StaticInit>,
+}
+
+// A string literal the code uses, which goes with the variables except that nothing can write to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticConstant {
+    pub name: String,
+    pub ty: Type,
+    pub init: Vec<StaticInit>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub functions: Vec<Function>,
+    pub static_variables: Vec<StaticVariable>,
+    pub static_constants: Vec<StaticConstant>,
+    // Things that are allowed but probably wrong, the driver prints them
+    pub warnings: Vec<String>,
+}
+
+// A switch needs at least this many cases before an indirect jump beats a chain of compares
+const JUMP_TABLE_MIN_CASES: usize = 4;
+// Every case can pay for this many slots in the table, anything sparser becomes a compare chain
+const JUMP_TABLE_SLOTS_PER_CASE: i64 = 3;
+
+pub trait LowerStatement {
+    fn emit_tacky(&self, generator: &mut TackyGenerator);
+}
+
+// Where an lvalue lives, either a variable of its own, a piece of one or whatever a pointer points to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lvalue {
+    Variable(Val),
+    // A member of a structure that's in a variable, offset bytes from its start
+    SubObject { base: String, offset: i64 },
+    Dereferenced(Val),
+}
+
+pub trait LowerExpression {
+    // Hands back where the result of the expression ended up
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val;
+
+    // Only called on lvalues, most of which are simply a variable
+    fn emit_lvalue(&self, generator: &mut TackyGenerator) -> Lvalue {
+        Lvalue::Variable(self.emit_tacky(generator))
+    }
+}
+
+pub struct TackyGenerator {
+    instructions: Vec<Instruction>,
+    functions: Vec<Function>,
+    // Temporaries get added to the symbol table so codegen knows how big they are
+    symbols: SymbolTable,
+    counter: usize,
+    warnings: Vec<String>,
+}
+
+impl TackyGenerator {
+    pub fn new(symbols: SymbolTable) -> Self {
+        TackyGenerator {
+            instructions: Vec::new(),
+            functions: Vec::new(),
+            symbols,
+            counter: 0,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn generate(mut self, program: &LinkedList<Box<dyn Statement>>) -> (Program, SymbolTable) {
+        for statement in program.iter() {
+            statement.emit_tacky(&mut self);
+        }
+        let static_variables = self.static_variables();
+        let static_constants = self.static_constants();
+        let program = Program {
+            functions: self.functions,
+            static_variables,
+            static_constants,
+            warnings: self.warnings,
+        };
+        (program, self.symbols)
+    }
+
+    fn static_constants(&self) -> Vec<StaticConstant> {
+        let mut constants: Vec<StaticConstant> = self.symbols
+            .iter()
+            .filter_map(|(name, symbol)| match &symbol.attrs {
+                SymbolAttrs::Constant(init) => Some(StaticConstant {
+                    name: name.clone(),
+                    ty: symbol.ty.clone(),
+                    init: init.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+        constants.sort_by(|left, right| left.name.cmp(&right.name));
+        constants
+    }
+
+    // Everything with static storage that this file defines, tentative definitions end up as zero
+    fn static_variables(&self) -> Vec<StaticVariable> {
+        let mut variables: Vec<StaticVariable> = self.symbols
+            .iter()
+            .filter_map(|(name, symbol)| {
+                let (init, global) = match &symbol.attrs {
+                    SymbolAttrs::Static { init, global } => (init, *global),
+                    _ => return None,
+                };
+                let init = match init {
+                    InitialValue::Initial(inits) => inits.clone(),
+                    InitialValue::Tentative => vec![StaticInit::Zero(self.symbols.size_of(&symbol.ty))],
+                    InitialValue::NoInitializer => return None,
+                };
+                Some(StaticVariable {
+                    name: name.clone(),
+                    global,
+                    ty: symbol.ty.clone(),
+                    init,
+                })
+            })
+            .collect();
+        // The symbol table doesn't keep things in order, this keeps the output the same from run to run
+        variables.sort_by(|left, right| left.name.cmp(&right.name));
+        variables
+    }
+
+    pub fn emit(&mut self, instruction: Instruction) {
+        self.instructions.push(instruction);
+    }
+
+    // Temporaries have a dot in their name so they can never clash with a variable from the source
+    // They only ever hold values, which aren't qualified
+    pub fn make_temporary(&mut self, ty: &Type) -> Val {
+        let name = format!("tmp.{}", self.counter);
+        self.counter += 1;
+        self.symbols.insert(name.clone(), Symbol {
+            ty: ty.unqualified().clone(),
+            attrs: SymbolAttrs::Local,
+        });
+        Val::Var(name)
+    }
+
+    // Converts a value that has type `from` into one of type `to`
+    pub fn emit_cast(&mut self, val: Val, from: &Type, to: &Type) -> Val {
+        let (from, to) = (from.unqualified(), to.unqualified());
+        if from == to {
+            return val;
+        }
+        if let Val::Constant(value) = val {
+            return Val::Constant(Constant::new(value, from.clone()).convert(to).value);
+        }
+        // Anything that isn't zero becomes 1, which is just what comparing against zero gives
+        if *to == Type::Bool {
+            let dst = self.make_temporary(to);
+            self.emit(Instruction::Binary { op: BinaryOp::NotEqual, src1: val, src2: Val::Constant(0), dst: dst.clone() });
+            return dst;
+        }
+        if from.is_floating() || to.is_floating() {
+            return self.emit_floating_cast(val, from, to);
+        }
+
+        let dst = self.make_temporary(to);
+        let instruction = if to.size() < from.size() {
+            Instruction::Truncate { src: val, dst: dst.clone() }
+        } else if to.size() == from.size() {
+            // Same bits, it's only how we look at them that changes
+            Instruction::Copy { src: val, dst: dst.clone() }
+        } else if from.is_signed() {
+            Instruction::SignExtend { src: val, dst: dst.clone() }
+        } else {
+            Instruction::ZeroExtend { src: val, dst: dst.clone() }
+        };
+        self.emit(instruction);
+        dst
+    }
+
+    // The SSE conversions only work on 32 and 64 bit integers, so anything narrower goes through an int on the way
+    fn emit_floating_cast(&mut self, val: Val, from: &Type, to: &Type) -> Val {
+        if from.size() < 4 || to.size() < 4 {
+            let val = self.emit_cast(val, from, &Type::Int);
+            return self.emit_cast(val, &Type::Int, to);
+        }
+
+        let dst = self.make_temporary(to);
+        if from.is_floating() && to.is_floating() {
+            let instruction = match to {
+                Type::Double => Instruction::FloatToDouble { src: val, dst: dst.clone() },
+                _ => Instruction::DoubleToFloat { src: val, dst: dst.clone() },
+            };
+            self.emit(instruction);
+            return dst;
+        }
+        let instruction = match (from.is_floating(), from.is_signed() || to.is_signed()) {
+            (false, true) => Instruction::IntToDouble { src: val, dst: dst.clone() },
+            (false, false) => Instruction::UIntToDouble { src: val, dst: dst.clone() },
+            (true, true) => Instruction::DoubleToInt { src: val, dst: dst.clone() },
+            (true, false) => Instruction::DoubleToUInt { src: val, dst: dst.clone() },
+        };
+        self.emit(instruction);
+        dst
+    }
+
+    // Gets the current value out of an lvalue
+    pub fn read(&mut self, lvalue: &Lvalue, ty: &Type) -> Val {
+        match lvalue {
+            Lvalue::Variable(val) => val.clone(),
+            Lvalue::SubObject { base, offset } => {
+                let dst = self.make_temporary(ty);
+                self.emit(Instruction::CopyFromOffset { src: base.clone(), offset: *offset, dst: dst.clone() });
+                dst
+            }
+            Lvalue::Dereferenced(ptr) => {
+                let dst = self.make_temporary(ty);
+                self.emit(Instruction::Load { src_ptr: ptr.clone(), dst: dst.clone() });
+                dst
+            }
+        }
+    }
+
+    // Stores a value of type ty into an lvalue and hands back the value the assignment expression has
+    pub fn write(&mut self, lvalue: &Lvalue, src: Val, ty: &Type) -> Val {
+        match lvalue {
+            Lvalue::Variable(val) => {
+                self.emit(Instruction::Copy { src, dst: val.clone() });
+                val.clone()
+            }
+            Lvalue::SubObject { base, offset } => {
+                self.copy_to_offset(src.clone(), base, *offset, ty);
+                src
+            }
+            Lvalue::Dereferenced(ptr) => {
+                self.emit(Instruction::Store { src: src.clone(), dst_ptr: ptr.clone() });
+                src
+            }
+        }
+    }
+
+    // Codegen goes by the destination for how wide a constant is, which only works when it's made of one scalar type
+    // Constants going into a structure get a temporary of their own type instead
+    pub fn copy_to_offset(&mut self, src: Val, dst: &str, offset: i64, ty: &Type) {
+        let src = match src {
+            Val::Constant(_) if !self.symbols.type_of(dst).scalar_element().is_scalar() => {
+                let temporary = self.make_temporary(ty);
+                self.emit(Instruction::Copy { src, dst: temporary.clone() });
+                temporary
+            }
+            src => src,
+        };
+        self.emit(Instruction::CopyToOffset {
+            src,
+            dst: dst.to_string(),
+            offset,
+        });
+    }
+
+    // &x for a variable, and &*p is just p
+    pub fn address_of(&mut self, lvalue: Lvalue, ty: &Type) -> Val {
+        match lvalue {
+            Lvalue::Variable(src) => {
+                let dst = self.make_temporary(ty);
+                self.emit(Instruction::GetAddress { src, dst: dst.clone() });
+                dst
+            }
+            Lvalue::SubObject { base, offset } => {
+                let address = self.make_temporary(ty);
+                self.emit(Instruction::GetAddress { src: Val::Var(base), dst: address.clone() });
+                self.emit_byte_offset(address, offset, ty)
+            }
+            Lvalue::Dereferenced(ptr) => ptr,
+        }
+    }
+
+    // A pointer of type ty that's offset bytes on from ptr, always a new temporary so it has the type it points with
+    pub fn emit_byte_offset(&mut self, ptr: Val, offset: i64, ty: &Type) -> Val {
+        let dst = self.make_temporary(ty);
+        self.emit(Instruction::AddPtr { ptr, index: Val::Constant(offset), scale: 1, dst: dst.clone() });
+        dst
+    }
+
+    // Where a member starts inside its structure or union
+    pub fn member_offset(&self, ty: &Type, member: &str) -> i64 {
+        let definition = match ty.unqualified() {
+            Type::Struct(tag) | Type::Union(tag) => self.symbols.get_struct(tag),
+            _ => None,
+        };
+        match definition.and_then(|definition| definition.member(member)) {
+            Some(member) => member.offset as i64,
+            None => panic!("'{}' has no member named '{}'", ty, member),
+        }
+    }
+
+    // Moves a pointer of type ty by index elements, backwards if negate is set
+    pub fn emit_pointer_offset(&mut self, ptr: Val, index: Val, negate: bool, ty: &Type) -> Val {
+        let scale = match ty.unqualified() {
+            Type::Pointer(referenced) => self.symbols.size_of(referenced) as i64,
+            _ => panic!("{} is not a pointer", ty),
+        };
+        let index = match (index, negate) {
+            (Val::Constant(value), true) => Val::Constant(value.wrapping_neg()),
+            (index, true) => {
+                let negated = self.make_temporary(&Type::Long);
+                self.emit(Instruction::Unary { op: UnaryOp::Negate, src: index, dst: negated.clone() });
+                negated
+            }
+            (index, false) => index,
+        };
+        let dst = self.make_temporary(ty);
+        self.emit(Instruction::AddPtr { ptr, index, scale, dst: dst.clone() });
+        dst
+    }
+
+    pub fn make_label(&mut self, purpose: &str) -> String {
+        let label = format!("{}{}_{}", GENERATED_LABEL_PREFIX, purpose, self.counter);
+        self.counter += 1;
+        label
+    }
+}
+
+impl UnaryOp {
+    pub fn from_kind(kind: &SyntaxKind) -> UnaryOp {
+        match kind {
+            SyntaxKind::Minus => UnaryOp::Negate,
+            SyntaxKind::Tilde => UnaryOp::Complement,
+            SyntaxKind::Bang => UnaryOp::Not,
+            _ => panic!("{:?} is not a unary operator", kind),
+        }
+    }
+}
+
+impl BinaryOp {
+    pub fn from_kind(kind: &SyntaxKind) -> BinaryOp {
+        match kind {
+            SyntaxKind::Plus => BinaryOp::Add,
+            SyntaxKind::Minus => BinaryOp::Subtract,
+            SyntaxKind::Star => BinaryOp::Multiply,
+            SyntaxKind::Slash => BinaryOp::Divide,
+            SyntaxKind::Percent => BinaryOp::Remainder,
+            SyntaxKind::Ampersand => BinaryOp::BitwiseAnd,
+            SyntaxKind::Pipe => BinaryOp::BitwiseOr,
+            SyntaxKind::Caret => BinaryOp::BitwiseXor,
+            SyntaxKind::LessLess => BinaryOp::ShiftLeft,
+            SyntaxKind::GreaterGreater => BinaryOp::ShiftRight,
+            SyntaxKind::EqualEqual => BinaryOp::Equal,
+            SyntaxKind::BangEqual => BinaryOp::NotEqual,
+            SyntaxKind::Less => BinaryOp::LessThan,
+            SyntaxKind::LessEqual => BinaryOp::LessOrEqual,
+            SyntaxKind::Greater => BinaryOp::GreaterThan,
+            SyntaxKind::GreaterEqual => BinaryOp::GreaterOrEqual,
+            _ => panic!("{:?} is not a binary operator", kind),
+        }
+    }
+}
+
+// Whether control can run off the bottom of the instructions without hitting a return
+pub fn falls_off_end(instructions: &[Instruction]) -> bool {
+    let labels: HashMap<&String, usize> = instructions
+        .iter()
+        .enumerate()
+        .filter_map(|(index, instruction)| match instruction {
+            Instruction::Label(label) => Some((label, index)),
+            _ => None,
+        })
+        .collect();
+
+    let mut visited = vec![false; instructions.len() + 1];
+    let mut pending = vec![0];
+    while let Some(index) = pending.pop() {
+        if visited[index] {
+            continue;
+        }
+        visited[index] = true;
+        if index == instructions.len() {
+            return true;
+        }
+
+        let mut jump = |label: &String| pending.push(labels[label]);
+        match &instructions[index] {
+            Instruction::Return(_) => continue,
+            Instruction::Jump(label) => {
+                jump(label);
+                continue;
+            }
+            Instruction::JumpTable { targets, .. } => {
+                targets.iter().for_each(jump);
+                continue;
+            }
+            Instruction::JumpIfZero(_, label) | Instruction::JumpIfNotZero(_, label) => jump(label),
+            _ => {}
+        }
+        pending.push(index + 1);
+    }
+    false
+}
+
+// Dense switches jump straight to their case through a table, sparse ones compare against every case
+pub fn uses_jump_table(values: &[i64]) -> bool {
+    if values.len() < JUMP_TABLE_MIN_CASES {
+        return false;
+    }
+    let min = values.iter().min().unwrap();
+    let max = values.iter().max().unwrap();
+    max - min < values.len() as i64 * JUMP_TABLE_SLOTS_PER_CASE
+}
+
+impl LowerStatement for FunctionStatement {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) {
+        // Declarations don't produce any code
+        let body = match self.func_body.as_ref() {
+            Some(body) => body,
+            None => return,
+        };
+
+        generator.instructions.clear();
+        body.emit_tacky(generator);
+        if falls_off_end(&generator.instructions) {
+            // Falling off the end of main returns 0. Anywhere else there's no value to hand back, which is
+            // fine as long as the caller doesn't use it. The path might never be taken, after a call to abort say,
+            // so it's only a warning
+            if self.identifier.data == "main" && self.func_type != Type::Void {
+                generator.emit(Instruction::Return(Some(Val::Constant(0))));
+            } else {
+                if self.func_type != Type::Void {
+                    generator.warnings.push(format!(
+                        "Control reaches the end of non-void function '{}' at {}",
+                        self.identifier.data, self.identifier.location
+                    ));
+                }
+                generator.emit(Instruction::Return(None));
+            }
+        }
+
+        let instructions = std::mem::take(&mut generator.instructions);
+        let global = match generator.symbols.get(&self.identifier.data) {
+            Some(Symbol { attrs: SymbolAttrs::Function { global, .. }, .. }) => *global,
+            _ => panic!("Function '{}' is missing from the symbol table", self.identifier.data),
+        };
+        generator.functions.push(Function {
+            name: self.identifier.data.clone(),
+            global,
+            params: self.parameters
+                .iter()
+                .map(|parameter| match parameter.identifier.as_ref() {
+                    Some(identifier) => identifier.data.clone(),
+                    None => panic!("Parameter of '{}' has no name", self.identifier.data),
+                })
+                .collect(),
+            instructions,
+        });
+    }
+}
+
+impl LowerStatement for Body {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) {
+        for statement in self.statements.iter() {
+            statement.emit_tacky(generator);
+        }
+    }
+}
+
+impl LowerStatement for VariableDeclaration {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) {
+        // Static variables are initialized before the program starts, not when we get to them
+        if generator.symbols.is_static(&self.identifier.data) {
+            return;
+        }
+        if let Some(initializer) = self.initializer.as_ref() {
+            emit_initializer(generator, &self.identifier.data, initializer, &self.var_type);
+        }
+    }
+}
+
+fn emit_initializer(generator: &mut TackyGenerator, name: &str, initializer: &Initializer, ty: &Type) {
+    match initializer {
+        Initializer::Single(expression) if !ty.is_array() => {
+            let src = expression.emit_tacky(generator);
+            generator.emit(Instruction::Copy {
+                src,
+                dst: Val::Var(name.to_string()),
+            });
+        }
+        initializer => emit_compound_initializer(generator, name, initializer, ty, 0),
+    }
+}
+
+// The members a braced list fills in, in order. Only the first member of a union gets a value.
+fn initialized_members(generator: &TackyGenerator, ty: &Type) -> Vec<Member> {
+    match ty.unqualified() {
+        Type::Struct(tag) => generator.symbols.get_struct(tag).map(|definition| definition.members.clone()),
+        Type::Union(tag) => generator.symbols.get_struct(tag).map(|definition| definition.members[..1].to_vec()),
+        _ => None,
+    }
+    .unwrap_or_else(|| panic!("'{}' has no definition", ty))
+}
+
+// Stores every element of an automatic array or structure where it belongs, the elements the list leaves off are set to zero
+// The type checker has arranged the list, so an element's position says what it initializes
+fn emit_compound_initializer(generator: &mut TackyGenerator, name: &str, initializer: &Initializer, ty: &Type, offset: i64) {
+    match (initializer, ty.unqualified()) {
+        (Initializer::Compound { elements, .. }, _) if elements.is_empty() => emit_zero(generator, name, ty, offset),
+        (Initializer::Compound { elements, .. }, Type::Array(element, count)) => {
+            let size = generator.symbols.size_of(element) as i64;
+            for index in 0..*count {
+                let offset = offset + index as i64 * size;
+                match elements.get(index) {
+                    Some(initializer) => emit_compound_initializer(generator, name, initializer, element, offset),
+                    None => emit_zero(generator, name, element, offset),
+                }
+            }
+        }
+        // The last element is for the member the list picked
+        (Initializer::Compound { elements, .. }, Type::Union(tag)) => {
+            let member = generator.symbols.get_struct(tag).expect("Unions are complete by the time they're initialized").members[elements.len() - 1].clone();
+            emit_compound_initializer(generator, name, &elements[elements.len() - 1], &member.ty, offset);
+        }
+        (Initializer::Compound { elements, .. }, _) => {
+            for (index, member) in initialized_members(generator, ty).iter().enumerate() {
+                let offset = offset + member.offset as i64;
+                match elements.get(index) {
+                    Some(initializer) => emit_compound_initializer(generator, name, initializer, &member.ty, offset),
+                    None => emit_zero(generator, name, &member.ty, offset),
+                }
+            }
+        }
+        // The characters of a string, and zeroes for whatever room is left after them
+        (Initializer::Single(expression), Type::Array(element, count)) => {
+            let value = expression.string_literal().expect("Only a string initializes an array without braces");
+            for index in 0..*count {
+                let byte = value.get(index).map_or(0, |byte| convert_constant(*byte as i64, element));
+                generator.copy_to_offset(Val::Constant(byte), name, offset + index as i64, element);
+            }
+        }
+        (Initializer::Single(expression), _) => {
+            let src = expression.emit_tacky(generator);
+            generator.copy_to_offset(src, name, offset, ty);
+        }
+        (Initializer::Designated { .. }, _) => unreachable!("The type checker takes out every designator"),
+    }
+}
+
+fn emit_zero(generator: &mut TackyGenerator, name: &str, ty: &Type, offset: i64) {
+    match ty.unqualified() {
+        Type::Array(element, count) => {
+            let size = generator.symbols.size_of(element) as i64;
+            for index in 0..*count as i64 {
+                emit_zero(generator, name, element, offset + index * size);
+            }
+        }
+        Type::Struct(_) | Type::Union(_) => {
+            for member in initialized_members(generator, ty).iter() {
+                emit_zero(generator, name, &member.ty, offset + member.offset as i64);
+            }
+        }
+        _ => generator.copy_to_offset(Val::Constant(0), name, offset, ty),
+    }
+}
+
+// Only the type checker cares about structures, they're laid out by the time we get here
+impl LowerStatement for StructDeclaration {
+    fn emit_tacky(&self, _generator: &mut TackyGenerator) {}
+}
+
+impl LowerStatement for EnumDeclaration {
+    fn emit_tacky(&self, _generator: &mut TackyGenerator) {}
+}
+
+impl LowerStatement for ExpressionStatement {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) {
+        self.expression.emit_tacky(generator);
+    }
+}
+
+impl LowerStatement for NullStatement {
+    fn emit_tacky(&self, _generator: &mut TackyGenerator) {}
+}
+
+impl LowerStatement for ReturnExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) {
+        let value = self.value.as_ref().map(|value| value.emit_tacky(generator));
+        generator.emit(Instruction::Return(value));
+    }
+}
+
+impl LowerStatement for SwitchStatement {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) {
+        let value = self.condition.emit_tacky(generator);
+        let break_label = format!("{}_break", self.label);
+        // Anything that doesn't match a case goes to default, or skips the body entirely
+        let fallback = self.cases
+            .iter()
+            .find(|case| case.value.is_none())
+            .map(|case| case.label.clone())
+            .unwrap_or(break_label.clone());
+        let cases: Vec<&SwitchCase> = self.cases.iter().filter(|case| case.value.is_some()).collect();
+        let values: Vec<i64> = cases.iter().filter_map(|case| case.value).collect();
+
+        if uses_jump_table(&values) {
+            let min = *values.iter().min().unwrap();
+            let max = *values.iter().max().unwrap();
+
+            let ty = self.condition.get_type();
+            let index = generator.make_temporary(&ty);
+            generator.emit(Instruction::Binary {
+                op: BinaryOp::Subtract,
+                src1: value,
+                src2: Val::Constant(min),
+                dst: index.clone(),
+            });
+            let below = generator.make_temporary(&Type::Int);
+            generator.emit(Instruction::Binary {
+                op: BinaryOp::LessThan,
+                src1: index.clone(),
+                src2: Val::Constant(0),
+                dst: below.clone(),
+            });
+            generator.emit(Instruction::JumpIfNotZero(below, fallback.clone()));
+            let above = generator.make_temporary(&Type::Int);
+            generator.emit(Instruction::Binary {
+                op: BinaryOp::GreaterThan,
+                src1: index.clone(),
+                src2: Val::Constant(max - min),
+                dst: above.clone(),
+            });
+            generator.emit(Instruction::JumpIfNotZero(above, fallback.clone()));
+
+            // The holes between cases behave as if the value didn't match anything
+            let targets = (min..=max)
+                .map(|slot| cases
+                    .iter()
+                    .find(|case| case.value == Some(slot))
+                    .map(|case| case.label.clone())
+                    .unwrap_or(fallback.clone()))
+                .collect();
+            generator.emit(Instruction::JumpTable {
+                index,
+                table: format!("{}_table", self.label),
+                targets,
+            });
+        } else {
+            for case in cases.iter() {
+                let matches = generator.make_temporary(&Type::Int);
+                generator.emit(Instruction::Binary {
+                    op: BinaryOp::Equal,
+                    src1: value.clone(),
+                    src2: Val::Constant(case.value.unwrap()),
+                    dst: matches.clone(),
+                });
+                generator.emit(Instruction::JumpIfNotZero(matches, case.label.clone()));
+            }
+            generator.emit(Instruction::Jump(fallback));
+        }
+
+        self.body.emit_tacky(generator);
+        generator.emit(Instruction::Label(break_label));
+    }
+}
+
+impl LowerStatement for CaseStatement {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) {
+        generator.emit(Instruction::Label(self.label.clone()));
+        self.statement.emit_tacky(generator);
+    }
+}
+
+impl LowerStatement for DefaultStatement {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) {
+        generator.emit(Instruction::Label(self.label.clone()));
+        self.statement.emit_tacky(generator);
+    }
+}
+
+impl LowerStatement for LabeledStatement {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) {
+        generator.emit(Instruction::Label(self.label.data.clone()));
+        self.statement.emit_tacky(generator);
+    }
+}
+
+impl LowerStatement for GotoStatement {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) {
+        generator.emit(Instruction::Jump(self.label.data.clone()));
+    }
+}
+
+impl LowerStatement for BreakStatement {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) {
+        generator.emit(Instruction::Jump(format!("{}_break", self.label)));
+    }
+}
+
+impl LowerExpression for ConstantExpr {
+    fn emit_tacky(&self, _generator: &mut TackyGenerator) -> Val {
+        Val::Constant(convert_constant(self.value, &self.get_type()))
+    }
+}
+
+impl LowerExpression for VariableExpr {
+    fn emit_tacky(&self, _generator: &mut TackyGenerator) -> Val {
+        Val::Var(self.identifier.data.clone())
+    }
+}
+
+impl LowerExpression for UnaryExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val {
+        // Codegen can't tell the type of a constant, so operations on nothing but constants get done here
+        if let Some(constant) = self.constant_value() {
+            return Val::Constant(constant.value);
+        }
+        let src = self.operand.emit_tacky(generator);
+        let dst = generator.make_temporary(&self.get_type());
+        generator.emit(Instruction::Unary {
+            op: UnaryOp::from_kind(&self.operator.kind),
+            src,
+            dst: dst.clone(),
+        });
+        dst
+    }
+}
+
+// Codegen tests constants against zero bit by bit, which gets negative zero wrong, so those get decided here
+fn emit_condition(expression: &dyn Expression, generator: &mut TackyGenerator) -> Val {
+    match expression.constant_value() {
+        Some(constant) => Val::Constant(!constant.is_zero() as i64),
+        None => expression.emit_tacky(generator),
+    }
+}
+
+// && and || only evaluate their right operand when the left one hasn't already decided the result
+fn emit_short_circuit(expression: &BinaryExpr, generator: &mut TackyGenerator) -> Val {
+    let is_and = expression.operator.kind == SyntaxKind::AmpersandAmpersand;
+    let (purpose, short_circuit_value) = if is_and {
+        ("and_false", 0)
+    } else {
+        ("or_true", 1)
+    };
+    let short_circuit = generator.make_label(purpose);
+    let end = generator.make_label(if is_and { "and_end" } else { "or_end" });
+    let jump = |val: Val, label: String| if is_and {
+        Instruction::JumpIfZero(val, label)
+    } else {
+        Instruction::JumpIfNotZero(val, label)
+    };
+
+    let left = emit_condition(expression.left.as_ref(), generator);
+    generator.emit(jump(left, short_circuit.clone()));
+    let right = emit_condition(expression.right.as_ref(), generator);
+    generator.emit(jump(right, short_circuit.clone()));
+
+    let dst = generator.make_temporary(&Type::Int);
+    generator.emit(Instruction::Copy {
+        src: Val::Constant(1 - short_circuit_value),
+        dst: dst.clone(),
+    });
+    generator.emit(Instruction::Jump(end.clone()));
+    generator.emit(Instruction::Label(short_circuit));
+    generator.emit(Instruction::Copy {
+        src: Val::Constant(short_circuit_value),
+        dst: dst.clone(),
+    });
+    generator.emit(Instruction::Label(end));
+    dst
+}
+
+impl LowerExpression for BinaryExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val {
+        if let Some(constant) = self.constant_value() {
+            return Val::Constant(constant.value);
+        }
+        if let SyntaxKind::AmpersandAmpersand | SyntaxKind::PipePipe = self.operator.kind {
+            return emit_short_circuit(self, generator);
+        }
+
+        let src1 = self.left.emit_tacky(generator);
+        let src2 = self.right.emit_tacky(generator);
+        let left = self.left.get_type();
+        let right = self.right.get_type();
+        let is_additive = matches!(self.operator.kind, SyntaxKind::Plus | SyntaxKind::Minus);
+        if is_additive && left.is_pointer() && right.is_integer() {
+            let negate = self.operator.kind == SyntaxKind::Minus;
+            return generator.emit_pointer_offset(src1, src2, negate, &left);
+        }
+        if is_additive && right.is_pointer() && left.is_integer() {
+            return generator.emit_pointer_offset(src2, src1, false, &right);
+        }
+
+        let dst = generator.make_temporary(&self.get_type());
+        generator.emit(Instruction::Binary {
+            op: BinaryOp::from_kind(&self.operator.kind),
+            src1,
+            src2,
+            dst: dst.clone(),
+        });
+        // Subtracting pointers gives the distance in bytes, which then gets turned into a count of elements
+        if let (Type::Pointer(referenced), SyntaxKind::Minus) = (&left, &self.operator.kind) {
+            let elements = generator.make_temporary(&Type::Long);
+            generator.emit(Instruction::Binary {
+                op: BinaryOp::Divide,
+                src1: dst,
+                src2: Val::Constant(generator.symbols.size_of(referenced) as i64),
+                dst: elements.clone(),
+            });
+            return elements;
+        }
+        dst
+    }
+}
+
+impl LowerExpression for AssignmentExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val {
+        let src = self.right.emit_tacky(generator);
+        let dst = self.left.emit_lvalue(generator);
+        generator.write(&dst, src, &self.get_type())
+    }
+}
+
+impl LowerExpression for CompoundAssignmentExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val {
+        // The left side is only evaluated once, it's both an operand and where the result goes
+        let dst = self.left.emit_lvalue(generator);
+        let src2 = self.right.emit_tacky(generator);
+        let operator = SyntaxKind::compound_operator(&self.operator.kind).unwrap();
+        let ty = self.get_type();
+        let current = generator.read(&dst, &ty);
+
+        if ty.is_pointer() {
+            let result = generator.emit_pointer_offset(current, src2, operator == SyntaxKind::Minus, &ty);
+            return generator.write(&dst, result, &ty);
+        }
+
+        // The type checker already converted the right side to the type the operation happens in,
+        // except for shifts where it's only a count
+        let operation_type = match operator {
+            SyntaxKind::LessLess | SyntaxKind::GreaterGreater => ty.promote(),
+            _ => self.right.get_type(),
+        };
+        let src1 = generator.emit_cast(current, &ty, &operation_type);
+
+        let result = generator.make_temporary(&operation_type);
+        generator.emit(Instruction::Binary {
+            op: BinaryOp::from_kind(&operator),
+            src1,
+            src2,
+            dst: result.clone(),
+        });
+        let result = generator.emit_cast(result, &operation_type, &ty);
+        generator.write(&dst, result, &ty)
+    }
+}
+
+impl LowerExpression for IncrementExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val {
+        let dst = self.operand.emit_lvalue(generator);
+        let op = match self.operator.kind {
+            SyntaxKind::PlusPlus => BinaryOp::Add,
+            _ => BinaryOp::Subtract,
+        };
+        let ty = self.get_type();
+        let current = generator.read(&dst, &ty);
+
+        // Postfix hands back a copy of the value from before the update
+        let old = if self.postfix {
+            let old = generator.make_temporary(&ty);
+            generator.emit(Instruction::Copy {
+                src: current.clone(),
+                dst: old.clone(),
+            });
+            Some(old)
+        } else {
+            None
+        };
+
+        let result = if ty.is_pointer() {
+            generator.emit_pointer_offset(current, Val::Constant(1), op == BinaryOp::Subtract, &ty)
+        } else {
+            let operation_type = ty.promote();
+            let src1 = generator.emit_cast(current, &ty, &operation_type);
+            let result = generator.make_temporary(&operation_type);
+            generator.emit(Instruction::Binary {
+                op,
+                src1,
+                src2: Val::Constant(Constant::int(1).convert(&operation_type).value),
+                dst: result.clone(),
+            });
+            generator.emit_cast(result, &operation_type, &ty)
+        };
+        let updated = generator.write(&dst, result, &ty);
+        old.unwrap_or(updated)
+    }
+}
+
+impl LowerExpression for FunctionCallExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val {
+        let callee = self.function.get_type();
+        let Type::Function(function) = (match &callee {
+            Type::Pointer(referenced) => referenced.as_ref(),
+            ty => ty,
+        }).clone() else {
+            panic!("Call to '{}', which is not a function", callee)
+        };
+        // Anything but a function named directly is called through the pointer it comes to, which codegen needs
+        // in a variable to know the function's type
+        let pointer = match self.function.designated_function() {
+            Some(_) => None,
+            None => match self.function.emit_tacky(generator) {
+                val @ Val::Constant(_) => {
+                    let temporary = generator.make_temporary(&callee);
+                    generator.emit(Instruction::Copy { src: val, dst: temporary.clone() });
+                    Some(temporary)
+                }
+                val => Some(val),
+            },
+        };
+        let args = self.arguments
+            .iter()
+            .enumerate()
+            .map(|(index, argument)| {
+                let val = argument.emit_tacky(generator);
+                // Codegen goes by the parameter type for how wide an argument is, a constant past the `...` doesn't have one
+                match val {
+                    Val::Constant(_) if index >= function.params.len() => {
+                        let temporary = generator.make_temporary(&argument.get_type());
+                        generator.emit(Instruction::Copy { src: val, dst: temporary.clone() });
+                        temporary
+                    }
+                    val => val,
+                }
+            })
+            .collect();
+        // A void call has no value, the checker makes sure nothing tries to use the one returned here
+        let ty = self.get_type();
+        let dst = (ty != Type::Void).then(|| generator.make_temporary(&ty));
+        generator.emit(match (pointer, self.function.designated_function()) {
+            (None, Some(name)) => Instruction::FunCall { name: name.to_string(), args, dst: dst.clone() },
+            (function, _) => Instruction::IndirectCall {
+                function: function.expect("Only a function called by name has no pointer"),
+                args,
+                dst: dst.clone(),
+            },
+        });
+        dst.unwrap_or(Val::Constant(0))
+    }
+}
+
+impl LowerExpression for AddressOfExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val {
+        let lvalue = self.operand.emit_lvalue(generator);
+        generator.address_of(lvalue, &self.get_type())
+    }
+}
+
+impl LowerExpression for DereferenceExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val {
+        let lvalue = self.emit_lvalue(generator);
+        generator.read(&lvalue, &self.get_type())
+    }
+
+    fn emit_lvalue(&self, generator: &mut TackyGenerator) -> Lvalue {
+        Lvalue::Dereferenced(self.operand.emit_tacky(generator))
+    }
+}
+
+impl LowerExpression for SubscriptExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val {
+        let lvalue = self.emit_lvalue(generator);
+        generator.read(&lvalue, &self.get_type())
+    }
+
+    fn emit_lvalue(&self, generator: &mut TackyGenerator) -> Lvalue {
+        let left = self.left.emit_tacky(generator);
+        let index = self.index.emit_tacky(generator);
+        let (ptr, index, ty) = match self.left.get_type() {
+            Type::Pointer(_) => (left, index, self.left.get_type()),
+            _ => (index, left, self.index.get_type()),
+        };
+        Lvalue::Dereferenced(generator.emit_pointer_offset(ptr, index, false, &ty))
+    }
+}
+
+impl LowerExpression for MemberExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val {
+        let lvalue = self.emit_lvalue(generator);
+        generator.read(&lvalue, &self.get_type())
+    }
+
+    // A member of a variable stays a piece of that variable, anything reached through a pointer gets a pointer of its own
+    fn emit_lvalue(&self, generator: &mut TackyGenerator) -> Lvalue {
+        let member_pointer = Type::Pointer(Box::new(self.get_type()));
+        if self.operator.kind == SyntaxKind::Arrow {
+            let Type::Pointer(structure) = self.structure.get_type() else {
+                panic!("'->' on something that isn't a pointer")
+            };
+            let offset = generator.member_offset(&structure, &self.member.data);
+            let ptr = self.structure.emit_tacky(generator);
+            return Lvalue::Dereferenced(generator.emit_byte_offset(ptr, offset, &member_pointer));
+        }
+
+        let offset = generator.member_offset(&self.structure.get_type(), &self.member.data);
+        match self.structure.emit_lvalue(generator) {
+            Lvalue::Variable(Val::Var(base)) => Lvalue::SubObject { base, offset },
+            Lvalue::SubObject { base, offset: start } => Lvalue::SubObject { base, offset: start + offset },
+            Lvalue::Dereferenced(ptr) => Lvalue::Dereferenced(generator.emit_byte_offset(ptr, offset, &member_pointer)),
+            Lvalue::Variable(Val::Constant(_)) => unreachable!("Structures are never constants"),
+        }
+    }
+}
+
+impl LowerExpression for SizeofExpr {
+    fn emit_tacky(&self, _generator: &mut TackyGenerator) -> Val {
+        Val::Constant(self.constant_value().unwrap().value)
+    }
+}
+
+impl LowerExpression for SizeofTypeExpr {
+    fn emit_tacky(&self, _generator: &mut TackyGenerator) -> Val {
+        Val::Constant(self.constant_value().unwrap().value)
+    }
+}
+
+impl LowerExpression for CastExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val {
+        // A cast to void is only there for the side effects, the value is thrown away
+        if self.target == Type::Void {
+            if self.expression.get_type().is_array() {
+                self.expression.emit_lvalue(generator);
+            } else {
+                self.expression.emit_tacky(generator);
+            }
+            return Val::Constant(0);
+        }
+        // An array decaying into a pointer, which is the address of its first element, or a function into its address
+        if self.expression.get_type().is_array() || matches!(self.expression.get_type(), Type::Function(_)) {
+            let lvalue = self.expression.emit_lvalue(generator);
+            return generator.address_of(lvalue, &self.target);
+        }
+        let from = self.expression.get_type();
+        let mut val = self.expression.emit_tacky(generator);
+        // Nothing later on drops or merges accesses, but reading a volatile variable by name doesn't produce any
+        // instruction at all. Copying it out makes the read happen even when nobody uses the value.
+        if from.is_volatile() && let Val::Var(_) = val {
+            let copy = generator.make_temporary(&from);
+            generator.emit(Instruction::Copy { src: val, dst: copy.clone() });
+            val = copy;
+        }
+        generator.emit_cast(val, &from, &self.target)
+    }
+}
+
+impl LowerExpression for VaBuiltinExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val {
+        let list = self.arguments[0].emit_tacky(generator);
+        match self.builtin {
+            VaBuiltin::Start => generator.emit(Instruction::VaStart { list }),
+            VaBuiltin::Arg => {
+                let dst = generator.make_temporary(&self.get_type());
+                generator.emit(Instruction::VaArg { list, dst: dst.clone() });
+                return dst;
+            }
+            // Nothing was set up that needs undoing
+            VaBuiltin::End => {}
+            VaBuiltin::Copy => {
+                let src = self.arguments[1].emit_tacky(generator);
+                let Type::Pointer(structure) = self.arguments[1].get_type() else {
+                    panic!("va_copy of something that isn't a va_list")
+                };
+                let copy = generator.make_temporary(&structure);
+                generator.emit(Instruction::Load { src_ptr: src, dst: copy.clone() });
+                generator.emit(Instruction::Store { src: copy, dst_ptr: list });
+            }
+        }
+        Val::Constant(0)
+    }
+}
+
+// The array only exists once something refers to it, a string that initializes an array of characters never does
+impl LowerExpression for StringLiteralExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val {
+        let lvalue = self.emit_lvalue(generator);
+        generator.read(&lvalue, &self.get_type())
+    }
+
+    fn emit_lvalue(&self, generator: &mut TackyGenerator) -> Lvalue {
+        generator.symbols.insert_string(&self.name, &self.value);
+        Lvalue::Variable(Val::Var(self.name.clone()))
+    }
+}
+
+// A compound literal with static storage was initialized before the program started, any other one is initialized
+// every time we get to it
+impl LowerExpression for CompoundLiteralExpr {
+    fn emit_tacky(&self, generator: &mut TackyGenerator) -> Val {
+        let lvalue = self.emit_lvalue(generator);
+        generator.read(&lvalue, &self.get_type())
+    }
+
+    fn emit_lvalue(&self, generator: &mut TackyGenerator) -> Lvalue {
+        if !generator.symbols.is_static(&self.name) {
+            emit_initializer(generator, &self.name, &self.initializer, &self.target);
+        }
+        Lvalue::Variable(Val::Var(self.name.clone()))
+    }
+}
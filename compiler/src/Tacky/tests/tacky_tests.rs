@@ -0,0 +1,301 @@
+#[cfg(test)]
+mod tacky_tests {
+    use crate::driver::compile_to_tacky;
+    use crate::tacky::*;
+    use crate::types::Type;
+
+    fn has_jump_table(str: &str) -> bool {
+        let (program, _) = compile_to_tacky(str);
+        program.functions[0]
+            .instructions
+            .iter()
+            .any(|instruction| matches!(instruction, Instruction::JumpTable { .. }))
+    }
+
+    #[test]
+    fn logical_operators_lower_to_jumps() {
+        let (program, _) = compile_to_tacky("int main(void) { int a = 1; int b = 0; return a && b; }");
+        let instructions = &program.functions[0].instructions;
+        assert!(instructions.iter().all(|instruction| !matches!(instruction, Instruction::Binary { .. })));
+        let jumps = instructions
+            .iter()
+            .filter(|instruction| matches!(instruction, Instruction::JumpIfZero(..)))
+            .count();
+        assert_eq!(jumps, 2);
+
+        let (program, _) = compile_to_tacky("int main(void) { int a = 1; int b = 0; return a || b; }");
+        let jumps = program.functions[0]
+            .instructions
+            .iter()
+            .filter(|instruction| matches!(instruction, Instruction::JumpIfNotZero(..)))
+            .count();
+        assert_eq!(jumps, 2);
+    }
+
+    #[test]
+    fn jump_table_density() {
+        assert!(!uses_jump_table(&[]));
+        assert!(!uses_jump_table(&[1, 2, 3]));
+        assert!(uses_jump_table(&[1, 2, 3, 4]));
+        assert!(uses_jump_table(&[0, 3, 6, 11]));
+        assert!(!uses_jump_table(&[0, 3, 6, 12]));
+        assert!(!uses_jump_table(&[1, 100, 1000, 10000]));
+    }
+
+    #[test]
+    fn dense_switch_lowers_to_jump_table() {
+        let str = r#"
+        int main(void) {
+            int x = 2;
+            switch (x) {
+                case 0: return 10;
+                case 1: return 11;
+                case 2: return 12;
+                case 4: return 14;
+                default: return 0;
+            }
+        }
+        "#;
+        assert!(has_jump_table(str));
+
+        let (program, _) = compile_to_tacky(str);
+        let targets = program.functions[0]
+            .instructions
+            .iter()
+            .find_map(|instruction| match instruction {
+                Instruction::JumpTable { targets, .. } => Some(targets.clone()),
+                _ => None,
+            })
+            .unwrap();
+        // The hole at 3 goes to the default case
+        assert_eq!(targets.len(), 5);
+        assert!(targets[3].ends_with("_default"));
+    }
+
+    #[test]
+    fn sparse_switch_lowers_to_compare_chain() {
+        let str = r#"
+        int main(void) {
+            int x = 2;
+            switch (x) {
+                case 1: return 1;
+                case 100: return 2;
+                case 1000: return 3;
+                case 10000: return 4;
+            }
+            return 0;
+        }
+        "#;
+        assert!(!has_jump_table(str));
+
+        let (program, _) = compile_to_tacky(str);
+        let compares = program.functions[0]
+            .instructions
+            .iter()
+            .filter(|instruction| matches!(instruction, Instruction::Binary { op: BinaryOp::Equal, .. }))
+            .count();
+        assert_eq!(compares, 4);
+    }
+
+    #[test]
+    fn implicit_conversions() {
+        let (program, symbols) = compile_to_tacky("int main(void) { char c = 1; int i = c + 1; c = i; return i; }");
+        let instructions = &program.functions[0].instructions;
+        assert!(instructions.iter().any(|instruction| matches!(instruction, Instruction::SignExtend { .. })));
+        assert!(instructions.iter().any(|instruction| matches!(instruction, Instruction::Truncate { .. })));
+
+        // The constant is converted at compile time rather than truncated at runtime
+        let copies_constant = instructions
+            .iter()
+            .any(|instruction| matches!(instruction, Instruction::Copy { src: Val::Constant(1), .. }));
+        assert!(copies_constant);
+
+        // Temporaries end up in the symbol table with the type of the expression they hold
+        let sum = instructions.iter().find_map(|instruction| match instruction {
+            Instruction::Binary { dst: Val::Var(name), .. } => Some(name),
+            _ => None,
+        });
+        assert_eq!(symbols.get(sum.unwrap()).unwrap().ty, Type::Int);
+    }
+
+    #[test]
+    fn double_conversions() {
+        let str = r#"
+        int main(void) {
+            int i = 3;
+            unsigned int u = 4;
+            char c = 5;
+            double d = i;
+            d = d + u;
+            d = d + c;
+            i = d;
+            u = d;
+            c = d;
+            return 0;
+        }
+        "#;
+        let (program, _) = compile_to_tacky(str);
+        let count = |matches: fn(&Instruction) -> bool| program.functions[0].instructions.iter().filter(|instruction| matches(instruction)).count();
+        // Characters are widened to an int before they turn into a double, and narrowed from one on the way back
+        assert_eq!(count(|instruction| matches!(instruction, Instruction::IntToDouble { .. })), 2);
+        assert_eq!(count(|instruction| matches!(instruction, Instruction::UIntToDouble { .. })), 1);
+        assert_eq!(count(|instruction| matches!(instruction, Instruction::DoubleToInt { .. })), 2);
+        assert_eq!(count(|instruction| matches!(instruction, Instruction::DoubleToUInt { .. })), 1);
+        assert_eq!(count(|instruction| matches!(instruction, Instruction::Truncate { .. })), 1);
+
+        // Constants are converted at compile time
+        let (program, _) = compile_to_tacky("int main(void) { double d = 2; return d; }");
+        assert_eq!(program.functions[0].instructions[0], Instruction::Copy {
+            src: Val::Constant(2.0f64.to_bits() as i64),
+            dst: Val::Var("d.0".to_string()),
+        });
+    }
+
+    #[test]
+    fn missing_return() {
+        // Every path returns, even though the last statement isn't a return
+        compile_to_tacky(r#"
+        int sign(int x) {
+            switch (x > 0) {
+                case 1: return 1;
+                default: return -1;
+            }
+        }
+        int main(void) { return sign(3); }
+        "#);
+
+        let (program, _) = compile_to_tacky("int main(void) { }");
+        assert_eq!(program.functions[0].instructions, vec![Instruction::Return(Some(Val::Constant(0)))]);
+    }
+
+    #[test]
+    fn falling_off_non_void_function() {
+        // Only using the value would be wrong, and the default case never comes back
+        let str = "void abort(void); int f(int x) { switch (x) { case 1: return 1; default: abort(); } } int main(void) { return f(1); }";
+        let (program, _) = compile_to_tacky(str);
+        assert_eq!(program.functions[0].instructions.last(), Some(&Instruction::Return(None)));
+        assert_eq!(program.warnings, vec!["Control reaches the end of non-void function 'f' at 1, 23".to_string()]);
+
+        // main returns 0 and a void function has nothing to return, so neither is worth a warning
+        let (program, _) = compile_to_tacky("void g(void) { } int main(void) { g(); }");
+        assert!(program.warnings.is_empty());
+    }
+
+    #[test]
+    fn pointer_arithmetic_scales_by_element() {
+        let (program, _) = compile_to_tacky("int main(void) { double d; double *p = &d; long n = 2; p = p - n; return p - &d; }");
+        let instructions = &program.functions[0].instructions;
+        let scales: Vec<i64> = instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::AddPtr { scale, .. } => Some(*scale),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(scales, vec![8]);
+        // The difference in bytes gets divided back down to a count of elements
+        assert!(instructions.iter().any(|instruction| matches!(instruction,
+            Instruction::Binary { op: BinaryOp::Divide, src2: Val::Constant(8), .. })));
+    }
+
+    #[test]
+    fn array_initializer_fills_in_zeroes() {
+        let (program, _) = compile_to_tacky("int main(void) { long a[2][2] = {{7}}; return a[1][1]; }");
+        let copies: Vec<(Val, i64)> = program.functions[0]
+            .instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::CopyToOffset { src, offset, .. } => Some((src.clone(), *offset)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(copies, vec![
+            (Val::Constant(7), 0),
+            (Val::Constant(0), 8),
+            (Val::Constant(0), 16),
+            (Val::Constant(0), 24),
+        ]);
+    }
+
+    #[test]
+    fn members_are_offsets_into_their_variable() {
+        let (program, _) = compile_to_tacky("struct s { int a; long b; }; int main(void) { struct s x; x.b = 3; return x.a; }");
+        let instructions = &program.functions[0].instructions;
+        // The constant gets a temporary so codegen knows it's a long
+        assert!(instructions.iter().any(|instruction| matches!(instruction,
+            Instruction::CopyToOffset { src: Val::Var(_), offset: 8, .. })));
+        assert!(instructions.iter().any(|instruction| matches!(instruction,
+            Instruction::CopyFromOffset { offset: 0, .. })));
+    }
+
+    #[test]
+    fn void_functions_return_nothing() {
+        let (program, _) = compile_to_tacky("void f(void) {} int main(void) { f(); return 0; }");
+        assert_eq!(program.functions[0].instructions, vec![Instruction::Return(None)]);
+        assert!(program.functions[1].instructions.iter().any(|instruction| matches!(instruction,
+            Instruction::FunCall { dst: None, .. })));
+    }
+
+    #[test]
+    fn volatile_reads_are_kept() {
+        let (program, _) = compile_to_tacky("int main(void) { volatile int x = 1; x; return 0; }");
+        let instructions = &program.functions[0].instructions;
+        assert!(instructions.iter().any(|instruction| matches!(instruction,
+            Instruction::Copy { src: Val::Var(name), .. } if name.starts_with("x."))));
+    }
+
+    #[test]
+    fn conversion_to_bool_compares_with_zero() {
+        let (program, _) = compile_to_tacky("int main(void) { long x = 4; _Bool b = x; return b; }");
+        let instructions = &program.functions[0].instructions;
+        assert!(instructions.iter().any(|instruction| matches!(instruction,
+            Instruction::Binary { op: BinaryOp::NotEqual, src2: Val::Constant(0), .. })));
+    }
+
+    #[test]
+    fn floats_widen_to_double() {
+        let (program, _) = compile_to_tacky("int main(void) { float f = 1.0f; double d = f; short s = f; return d + s; }");
+        let instructions = &program.functions[0].instructions;
+        assert!(instructions.iter().any(|instruction| matches!(instruction, Instruction::FloatToDouble { .. })));
+        // There's no SSE conversion to a short, it goes through an int
+        assert!(instructions.iter().any(|instruction| matches!(instruction, Instruction::DoubleToInt { .. })));
+        assert!(instructions.iter().any(|instruction| matches!(instruction, Instruction::Truncate { .. })));
+    }
+
+    #[test]
+    fn variadic_constants_get_temporaries() {
+        let (program, symbols) = compile_to_tacky("int f(int a, ...); int main(void) { return f(1, 2, 3L); }");
+        let args = program.functions[0]
+            .instructions
+            .iter()
+            .find_map(|instruction| match instruction {
+                Instruction::FunCall { args, .. } => Some(args.clone()),
+                _ => None,
+            })
+            .unwrap();
+        // The named parameter says how wide its argument is, the rest have to say it themselves
+        assert_eq!(args[0], Val::Constant(1));
+        let types: Vec<Type> = args[1..]
+            .iter()
+            .map(|arg| match arg {
+                Val::Var(name) => symbols.type_of(name).clone(),
+                Val::Constant(_) => panic!("Constant passed through '...'"),
+            })
+            .collect();
+        assert_eq!(types, vec![Type::Int, Type::Long]);
+    }
+
+    #[test]
+    fn calls_through_pointers_are_indirect() {
+        let (program, _) = compile_to_tacky("int f(int a); int main(void) { int (*fp)(int) = f; return f(1) + fp(2) + (*fp)(3); }");
+        let calls: Vec<&Instruction> = program.functions[0]
+            .instructions
+            .iter()
+            .filter(|instruction| matches!(instruction, Instruction::FunCall { .. } | Instruction::IndirectCall { .. }))
+            .collect();
+        assert_eq!(calls.len(), 3);
+        assert!(matches!(calls[0], Instruction::FunCall { name, .. } if name == "f"));
+        assert!(matches!(calls[1], Instruction::IndirectCall { function: Val::Var(name), .. } if name.starts_with("fp.")));
+        assert!(matches!(calls[2], Instruction::IndirectCall { function: Val::Var(name), .. } if name.starts_with("fp.")));
+    }
+}
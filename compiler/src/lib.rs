@@ -1,3 +1,6 @@
+// Every test file wraps its tests in a module named after the file
+#![allow(clippy::module_inception)]
+
 // Lexing
 
 #[path= "Lexing/lexer.rs"]
@@ -7,10 +10,48 @@ pub mod lexer_tests;
 #[path= "Lexing/tests/syntax_token_tests.rs"]
 pub mod syntax_token_tests;
 
+// Preprocessing
+#[path= "Preprocessing/preprocessor.rs"]
+pub mod preprocessor;
+#[path= "Preprocessing/tests/preprocessor_tests.rs"]
+pub mod preprocessor_tests;
+
 // Parsing
 #[path= "Parsing/parser.rs"]
 pub mod parsing;
 
 
 #[path= "Parsing/tests/parsing_tests.rs"]
-pub mod parsing_tests;
\ No newline at end of file
+pub mod parsing_tests;
+
+// Semantics
+#[path= "Semantics/resolver.rs"]
+pub mod resolver;
+#[path= "Semantics/tests/resolver_tests.rs"]
+pub mod resolver_tests;
+#[path= "Semantics/types.rs"]
+pub mod types;
+#[path= "Semantics/target.rs"]
+pub mod target;
+#[path= "Semantics/type_checker.rs"]
+pub mod type_checker;
+#[path= "Semantics/tests/type_checker_tests.rs"]
+pub mod type_checker_tests;
+
+// TACKY
+#[path= "Tacky/tacky.rs"]
+pub mod tacky;
+#[path= "Tacky/tests/tacky_tests.rs"]
+pub mod tacky_tests;
+
+// Codegen
+#[path= "Codegen/assembly.rs"]
+pub mod assembly;
+#[path= "Codegen/emitter.rs"]
+pub mod emitter;
+
+// Driver
+#[path= "Driver/driver.rs"]
+pub mod driver;
+#[path= "Driver/tests/driver_tests.rs"]
+pub mod driver_tests;
@@ -0,0 +1,38 @@
+use compiler::driver::{self, Options, Stage};
+use std::path::PathBuf;
+
+fn usage() -> ! {
+    eprintln!("Usage: compiler [-S | -c] [-o <output>] [-I <directory>] <file.c | file.s | file.o>...");
+    std::process::exit(1);
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut options = Options {
+        inputs: Vec::new(),
+        include_paths: Vec::new(),
+        stage: Stage::Executable,
+        output: None,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // -S stops after writing out the assembly, -c after assembling it
+            "-S" => options.stage = Stage::Assembly,
+            "-c" => options.stage = Stage::Object,
+            "-o" => options.output = Some(args.next().map(PathBuf::from).unwrap_or_else(|| usage())),
+            // -I adds a directory to look for includes in, either right after it or as the next argument
+            "-I" => options.include_paths.push(args.next().map(PathBuf::from).unwrap_or_else(|| usage())),
+            _ if arg.starts_with("-I") => options.include_paths.push(PathBuf::from(&arg[2..])),
+            _ if !arg.starts_with('-') => options.inputs.push(PathBuf::from(arg)),
+            _ => {
+                eprintln!("Unknown option {}", arg);
+                usage();
+            }
+        }
+    }
+    if options.inputs.is_empty() {
+        usage();
+    }
+
+    driver::build(&options);
+}